@@ -0,0 +1,48 @@
+//
+// RGBA color.
+//
+
+/// Color with red, green, blue, and alpha components in [0, 1].
+#[repr(C)]
+#[derive(Copy, Clone, PartialOrd, PartialEq, Debug)]
+pub struct Rgba {
+  pub r: f32,
+  pub g: f32,
+  pub b: f32,
+  pub a: f32,
+}
+
+impl Rgba {
+  pub const WHITE: Rgba = Rgba::new(1.0, 1.0, 1.0, 1.0);
+  pub const BLACK: Rgba = Rgba::new(0.0, 0.0, 0.0, 1.0);
+  pub const TRANSPARENT: Rgba = Rgba::new(0.0, 0.0, 0.0, 0.0);
+  pub const RED: Rgba = Rgba::new(1.0, 0.0, 0.0, 1.0);
+  pub const GREEN: Rgba = Rgba::new(0.0, 1.0, 0.0, 1.0);
+  pub const BLUE: Rgba = Rgba::new(0.0, 0.0, 1.0, 1.0);
+  pub const YELLOW: Rgba = Rgba::new(1.0, 1.0, 0.0, 1.0);
+
+  #[inline]
+  pub const fn new(r: f32, g: f32, b: f32, a: f32) -> Self { Self { r, g, b, a } }
+
+  #[inline]
+  pub const fn rgb(r: f32, g: f32, b: f32) -> Self { Self::new(r, g, b, 1.0) }
+
+  /// Packs into 8-bit unsigned normalized components, with red in the lowest byte. Matches the memory layout of the
+  /// `R8G8B8A8_UNORM` format on little-endian machines.
+  #[inline]
+  pub fn to_rgba8_packed(&self) -> u32 {
+    #[inline]
+    fn unorm8(c: f32) -> u32 { (c.max(0.0).min(1.0) * 255.0).round() as u32 }
+    unorm8(self.r) | unorm8(self.g) << 8 | unorm8(self.b) << 16 | unorm8(self.a) << 24
+  }
+}
+
+impl Default for Rgba {
+  #[inline]
+  fn default() -> Self { Rgba::WHITE }
+}
+
+impl From<Rgba> for [f32; 4] {
+  #[inline]
+  fn from(c: Rgba) -> Self { [c.r, c.g, c.b, c.a] }
+}
@@ -1,2 +1,4 @@
 pub mod screen;
+pub mod color;
+pub mod rotor;
 pub mod prelude;
@@ -1,2 +1,7 @@
+pub use ultraviolet::{Isometry2, Mat4, Rotor2, Vec2, Vec3, Vec4};
+pub use ultraviolet::projection;
+
+pub use crate::color::Rgba;
+pub use crate::rotor::{rotor2_angle, rotor2_slerp, wrap_angle};
 pub use crate::screen::{LogicalPosition, LogicalSize, PhysicalPosition, PhysicalSize, Scale, ScreenPosition, ScreenSize};
 
@@ -0,0 +1,86 @@
+//
+// Rotor helpers.
+//
+
+use std::f32::consts::PI;
+
+use ultraviolet::Rotor2;
+
+/// Gets the counter-clockwise angle in radians that `rotor` rotates by, in [-π, π]. Inverse of [Rotor2::from_angle].
+#[inline]
+pub fn rotor2_angle(rotor: Rotor2) -> f32 {
+  // Rotors `r` and `-r` represent the same rotation; pick the one with a non-negative scalar part, so that the half
+  // angle is in [-π/2, π/2].
+  let (s, xy) = if rotor.s < 0.0 { (-rotor.s, -rotor.bv.xy) } else { (rotor.s, rotor.bv.xy) };
+  2.0 * (-xy).atan2(s)
+}
+
+/// Wraps `angle` in radians into [-π, π].
+#[inline]
+pub fn wrap_angle(angle: f32) -> f32 {
+  let wrapped = (angle + PI).rem_euclid(2.0 * PI) - PI;
+  // `rem_euclid` may round up to exactly 2π for inputs just below a multiple of it.
+  if wrapped > PI { wrapped - 2.0 * PI } else { wrapped }
+}
+
+/// Spherically interpolates from `a` at `t` = 0 to `b` at `t` = 1 along the shortest arc, at constant angular velocity.
+#[inline]
+pub fn rotor2_slerp(a: Rotor2, b: Rotor2, t: f32) -> Rotor2 {
+  let angle_a = rotor2_angle(a);
+  let delta = wrap_angle(rotor2_angle(b) - angle_a);
+  Rotor2::from_angle(angle_a + delta * t)
+}
+
+#[cfg(test)]
+mod tests {
+  use std::f32::consts::{FRAC_PI_2, PI};
+
+  use ultraviolet::{Bivec2, Rotor2};
+
+  use super::{rotor2_angle, rotor2_slerp, wrap_angle};
+
+  const EPSILON: f32 = 1e-5;
+
+  fn assert_angle_eq(actual: f32, expected: f32) {
+    assert!((actual - expected).abs() < EPSILON, "Expected angle {}, got {}", expected, actual);
+  }
+
+  #[test]
+  fn rotor2_angle_inverts_from_angle() {
+    for &angle in &[0.0, 0.5, FRAC_PI_2, -FRAC_PI_2, 3.0, -3.0] {
+      assert_angle_eq(rotor2_angle(Rotor2::from_angle(angle)), angle);
+    }
+  }
+
+  #[test]
+  fn rotor2_angle_is_same_for_negated_rotor() {
+    let rotor = Rotor2::from_angle(1.0);
+    let negated = Rotor2::new(-rotor.s, Bivec2::new(-rotor.bv.xy));
+    assert_angle_eq(rotor2_angle(negated), 1.0);
+  }
+
+  #[test]
+  fn wrap_angle_wraps_into_range() {
+    assert_angle_eq(wrap_angle(3.0 * PI / 2.0), -FRAC_PI_2);
+    assert_angle_eq(wrap_angle(-3.0 * PI / 2.0), FRAC_PI_2);
+    assert_angle_eq(wrap_angle(0.25), 0.25);
+  }
+
+  #[test]
+  fn rotor2_slerp_interpolates_at_constant_angular_velocity() {
+    let a = Rotor2::from_angle(0.0);
+    let b = Rotor2::from_angle(FRAC_PI_2);
+    assert_angle_eq(rotor2_angle(rotor2_slerp(a, b, 0.0)), 0.0);
+    assert_angle_eq(rotor2_angle(rotor2_slerp(a, b, 0.5)), FRAC_PI_2 / 2.0);
+    assert_angle_eq(rotor2_angle(rotor2_slerp(a, b, 1.0)), FRAC_PI_2);
+  }
+
+  #[test]
+  fn rotor2_slerp_takes_shortest_arc() {
+    // From 170° to -170° is 20° counter-clockwise through 180°, not 340° clockwise through 0°.
+    let a = Rotor2::from_angle(170f32.to_radians());
+    let b = Rotor2::from_angle(-170f32.to_radians());
+    assert_angle_eq(rotor2_angle(rotor2_slerp(a, b, 0.25)), 175f32.to_radians());
+    assert_angle_eq(rotor2_angle(rotor2_slerp(a, b, 0.75)), -175f32.to_radians());
+  }
+}
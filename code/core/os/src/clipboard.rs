@@ -0,0 +1,41 @@
+use log::warn;
+use thiserror::Error;
+
+/// System clipboard. Unavailable when there is no clipboard, e.g. when running headless.
+pub struct Clipboard {
+  clipboard: Option<arboard::Clipboard>,
+}
+
+#[derive(Debug, Error)]
+pub enum ClipboardError {
+  #[error("Clipboard is unavailable")]
+  Unavailable,
+  #[error("Failed to set clipboard text: {0}")]
+  SetFail(arboard::Error),
+}
+
+impl Clipboard {
+  pub fn new() -> Self {
+    let clipboard = match arboard::Clipboard::new() {
+      Ok(clipboard) => Some(clipboard),
+      Err(e) => {
+        warn!("Clipboard is unavailable: {}", e);
+        None
+      }
+    };
+    Self { clipboard }
+  }
+
+  #[inline]
+  pub fn is_available(&self) -> bool { self.clipboard.is_some() }
+
+  /// Gets the text on the clipboard, or `None` if the clipboard is unavailable or does not contain text.
+  pub fn get_text(&mut self) -> Option<String> {
+    self.clipboard.as_mut()?.get_text().ok()
+  }
+
+  pub fn set_text(&mut self, text: &str) -> Result<(), ClipboardError> {
+    let clipboard = self.clipboard.as_mut().ok_or(ClipboardError::Unavailable)?;
+    clipboard.set_text(text.to_owned()).map_err(ClipboardError::SetFail)
+  }
+}
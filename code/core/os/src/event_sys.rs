@@ -1,7 +1,8 @@
 use std::sync::mpsc::{channel, Receiver, Sender};
 
+use log::error;
 use winit::dpi::LogicalPosition as WinitLogicalPosition;
-use winit::event::{ElementState as WinitElementState, Event, KeyboardInput, MouseButton as WinitMouseButton, MouseScrollDelta, WindowEvent};
+use winit::event::{DeviceEvent, ElementState as WinitElementState, Event, KeyboardInput, MouseButton as WinitMouseButton, MouseScrollDelta, WindowEvent};
 use winit::event_loop::ControlFlow;
 use winit::platform::desktop::EventLoopExtDesktop;
 use winit::window::WindowId;
@@ -10,22 +11,36 @@ use math::screen::{PhysicalPosition, PhysicalSize, Scale, ScreenSize};
 
 use crate::context::OsContext;
 use crate::screen_ext::*;
-use crate::window::Window;
+use crate::window::{CursorIcon, Window};
 
 pub struct OsEventSys {
   input_event_tx: Sender<OsInputEvent>,
   os_event_tx: Sender<OsEvent>,
+  command_tx: Sender<OsCommand>,
+  command_rx: Receiver<OsCommand>,
   window_id: WindowId,
   scale_factor: Scale,
   inner_size: PhysicalSize,
+  secondary_windows: Vec<SecondaryWindow>,
+}
+
+/// Window besides the main window, for example for debugging, whose input events are ignored.
+struct SecondaryWindow {
+  window: Window,
+  scale_factor: Scale,
+  inner_size: PhysicalSize,
 }
 
 #[derive(Clone, Copy, PartialEq, Debug)]
 pub enum OsInputEvent {
   MouseInput { button: MouseButton, state: ElementState },
+  /// Mouse cursor moved to a position in physical pixels, relative to the top-left of the window. Convert to logical
+  /// pixels with the window scale factor only where needed, such as in the UI.
   MouseMoved(PhysicalPosition),
   // TODO: distinguish line and pixel delta.
   MouseWheelMoved { x_delta: f64, y_delta: f64 },
+  /// Raw relative mouse motion, unaffected by pointer acceleration and window bounds.
+  RawMouseMotion(f64, f64),
   // TODO: this contains a winit item, but it's pretty big to copy...
   KeyboardInput(KeyboardInput),
   CharacterInput(char),
@@ -35,6 +50,23 @@ pub enum OsInputEvent {
 pub enum OsEvent {
   TerminateRequested,
   WindowResized(ScreenSize),
+  /// Secondary window with given index was resized.
+  SecondaryWindowResized(usize, ScreenSize),
+  /// Secondary window with given index was closed by the user, and is now hidden. It stays alive until the event system
+  /// is dropped, so that surfaces created for it can be destroyed first.
+  SecondaryWindowClosed(usize),
+}
+
+/// Command to the OS-event thread, for window actions that must run on that thread.
+#[derive(Clone, PartialEq, Debug)]
+pub enum OsCommand {
+  SetTitle(String),
+  SetFullscreen(bool),
+  SetCursorGrab(bool),
+  SetCursorVisible(bool),
+  SetCursorIcon(CursorIcon),
+  /// Stops the OS event loop, for example when the game thread finished on its own.
+  Exit,
 }
 
 #[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
@@ -76,30 +108,52 @@ impl OsEventSys {
   pub fn new(window: &Window) -> (OsEventSys, Receiver<OsInputEvent>, Receiver<OsEvent>) {
     let (input_event_tx, input_event_rx) = channel::<OsInputEvent>();
     let (os_event_tx, os_event_rx) = channel::<OsEvent>();
+    let (command_tx, command_rx) = channel::<OsCommand>();
     let os_event_sys = OsEventSys {
       input_event_tx,
       os_event_tx,
+      command_tx,
+      command_rx,
       window_id: window.winit_window_id(),
       scale_factor: window.window_scale_factor(),
       inner_size: window.window_inner_physical_size(),
+      secondary_windows: Vec::new(),
     };
     (os_event_sys, input_event_rx, os_event_rx, )
   }
 
-  pub fn run(mut self, os_context: OsContext) {
+  /// Adds a secondary window, which the event system takes ownership of, and returns its index in secondary window
+  /// events. Only resize and close events are handled for secondary windows. Create the surface for the window before
+  /// adding it.
+  pub fn add_secondary_window(&mut self, window: Window) -> usize {
+    let scale_factor = window.window_scale_factor();
+    let inner_size = window.window_inner_physical_size();
+    self.secondary_windows.push(SecondaryWindow { window, scale_factor, inner_size });
+    self.secondary_windows.len() - 1
+  }
+
+  /// Gets a sender for sending commands to the OS-event thread, which are processed while the event loop runs.
+  pub fn command_sender(&self) -> Sender<OsCommand> {
+    self.command_tx.clone()
+  }
+
+  pub fn run(mut self, os_context: OsContext, window: Window) {
     os_context.event_loop.run(move |event, _, control_flow| {
-      self.event_loop(event, control_flow);
+      self.event_loop(event, control_flow, &window);
     });
   }
 
-  pub fn run_return(&mut self, os_context: &mut OsContext) {
+  pub fn run_return(&mut self, os_context: &mut OsContext, window: &Window) {
     os_context.event_loop.run_return(|event, _, control_flow| {
-      self.event_loop(event, control_flow);
+      self.event_loop(event, control_flow, window);
     });
   }
 
-  fn event_loop(&mut self, event: Event<()>, control_flow: &mut ControlFlow) {
+  fn event_loop(&mut self, event: Event<()>, control_flow: &mut ControlFlow, window: &Window) {
     match event {
+      Event::MainEventsCleared => {
+        self.process_commands(control_flow, window);
+      }
       Event::WindowEvent { event, window_id, .. } if window_id == self.window_id => {
         match event {
           WindowEvent::MouseInput { state, button, .. } => {
@@ -148,7 +202,51 @@ impl OsEventSys {
           _ => {}
         }
       }
+      Event::WindowEvent { event, window_id, .. } => {
+        if let Some(index) = self.secondary_windows.iter().position(|w| w.window.winit_window_id() == window_id) {
+          self.secondary_window_event(index, event, control_flow);
+        }
+      }
+      Event::DeviceEvent { event: DeviceEvent::MouseMotion { delta: (x, y) }, .. } => {
+        self.input_event_tx.send(OsInputEvent::RawMouseMotion(x, y))
+          .unwrap_or_else(|_| *control_flow = ControlFlow::Exit);
+      }
       _ => {}
     }
   }
+  fn secondary_window_event(&mut self, index: usize, event: WindowEvent, control_flow: &mut ControlFlow) {
+    let secondary_window = &mut self.secondary_windows[index];
+    let os_event = match event {
+      WindowEvent::CloseRequested => {
+        secondary_window.window.set_visible(false);
+        OsEvent::SecondaryWindowClosed(index)
+      }
+      WindowEvent::Resized(inner_size) => {
+        secondary_window.inner_size = inner_size.into_util();
+        OsEvent::SecondaryWindowResized(index, ScreenSize::from_physical_scale(secondary_window.inner_size, secondary_window.scale_factor))
+      }
+      WindowEvent::ScaleFactorChanged { scale_factor, .. } => {
+        secondary_window.scale_factor = scale_factor.into();
+        OsEvent::SecondaryWindowResized(index, ScreenSize::from_physical_scale(secondary_window.inner_size, secondary_window.scale_factor))
+      }
+      _ => return,
+    };
+    self.os_event_tx.send(os_event)
+      .unwrap_or_else(|_| *control_flow = ControlFlow::Exit);
+  }
+
+  fn process_commands(&mut self, control_flow: &mut ControlFlow, window: &Window) {
+    for command in self.command_rx.try_iter() {
+      match command {
+        OsCommand::SetTitle(title) => window.set_title(&title),
+        OsCommand::SetFullscreen(fullscreen) => window.set_fullscreen(fullscreen),
+        OsCommand::SetCursorGrab(grab) => if let Err(e) = window.set_cursor_grab(grab) {
+          error!("Failed to set cursor grab: {}", e);
+        }
+        OsCommand::SetCursorVisible(visible) => window.set_cursor_visible(visible),
+        OsCommand::SetCursorIcon(cursor_icon) => window.set_cursor_icon(cursor_icon),
+        OsCommand::Exit => *control_flow = ControlFlow::Exit,
+      }
+    }
+  }
 }
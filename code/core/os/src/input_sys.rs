@@ -5,11 +5,13 @@ use winit::event::{ElementState as WinitElementState, KeyboardInput, VirtualKeyC
 
 use math::screen::{PhysicalDelta, PhysicalPosition};
 
+use crate::clipboard::Clipboard;
 use crate::event_sys::{ElementState, MouseButton, OsInputEvent};
 
 pub struct OsInputSys {
   input_event_rx: Receiver<OsInputEvent>,
   prev_state: Option<RawInput>,
+  clipboard: Clipboard,
 }
 
 impl OsInputSys {
@@ -17,6 +19,7 @@ impl OsInputSys {
     return OsInputSys {
       input_event_rx,
       prev_state: None,
+      clipboard: Clipboard::new(),
     };
   }
 
@@ -46,12 +49,22 @@ impl OsInputSys {
           input_state.mouse_wheel_delta.x += x_delta;
           input_state.mouse_wheel_delta.y += y_delta;
         }
+        OsInputEvent::RawMouseMotion(x, y) => {
+          input_state.raw_mouse_delta.x += x;
+          input_state.raw_mouse_delta.y += y;
+        }
         OsInputEvent::KeyboardInput(KeyboardInput { virtual_keycode, state, .. }) => {
           if let Some(virtual_keycode) = virtual_keycode {
             match state {
               WinitElementState::Pressed => {
                 input_state.keyboard_buttons.insert(virtual_keycode);
                 input_state.keyboard_buttons_pressed.insert(virtual_keycode);
+                if virtual_keycode == VirtualKeyCode::V && input_state.is_control_down() {
+                  // Paste clipboard text into the character input.
+                  if let Some(text) = self.clipboard.get_text() {
+                    input_state.characters.extend(text.chars().filter(|c| *c != '\r'));
+                  }
+                }
               }
               WinitElementState::Released => {
                 input_state.keyboard_buttons.remove(&virtual_keycode);
@@ -74,15 +87,21 @@ impl OsInputSys {
     self.prev_state = Some(input_state.clone());
     return input_state;
   }
+
+  #[inline]
+  pub fn clipboard(&mut self) -> &mut Clipboard { &mut self.clipboard }
 }
 
 
 #[derive(Clone, Debug, Default)]
 pub struct RawInput {
   pub mouse_buttons: MouseButtons,
+  /// Mouse position in physical pixels, relative to the top-left of the window.
   pub mouse_pos: PhysicalPosition,
+  /// Mouse position delta since the previous frame, in physical pixels.
   pub mouse_pos_delta: PhysicalDelta,
   pub mouse_wheel_delta: MouseWheelDelta,
+  pub raw_mouse_delta: RawMouseDelta,
   pub keyboard_buttons: HashSet<VirtualKeyCode>,
   pub keyboard_buttons_pressed: HashSet<VirtualKeyCode>,
   pub keyboard_buttons_released: HashSet<VirtualKeyCode>,
@@ -99,6 +118,9 @@ impl RawInput {
   pub fn is_key_released(&self, key: VirtualKeyCode) -> bool {
     self.keyboard_buttons_released.contains(&key)
   }
+  pub fn is_control_down(&self) -> bool {
+    self.is_key_down(VirtualKeyCode::LControl) || self.is_key_down(VirtualKeyCode::RControl)
+  }
 
 
   pub fn remove_mouse_input(&mut self) {
@@ -107,6 +129,7 @@ impl RawInput {
     self.mouse_buttons.middle = false;
     self.mouse_pos_delta = PhysicalDelta::default();
     self.mouse_wheel_delta = MouseWheelDelta::default();
+    self.raw_mouse_delta = RawMouseDelta::default();
   }
 
   pub fn remove_keyboard_input(&mut self) {
@@ -120,6 +143,7 @@ impl RawInput {
   fn clear_deltas(&mut self) {
     self.mouse_pos_delta = PhysicalDelta::default();
     self.mouse_wheel_delta = MouseWheelDelta::default();
+    self.raw_mouse_delta = RawMouseDelta::default();
     self.keyboard_buttons_pressed.clear();
     self.keyboard_buttons_released.clear();
     self.characters.clear();
@@ -143,3 +167,14 @@ pub struct MouseWheelDelta {
 impl MouseWheelDelta {
   pub fn new(x: f64, y: f64) -> MouseWheelDelta { MouseWheelDelta { x, y } }
 }
+
+/// Raw relative mouse motion in device-specific units, unaffected by pointer acceleration and window bounds.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RawMouseDelta {
+  pub x: f64,
+  pub y: f64,
+}
+
+impl RawMouseDelta {
+  pub fn new(x: f64, y: f64) -> RawMouseDelta { RawMouseDelta { x, y } }
+}
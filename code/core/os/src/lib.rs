@@ -1,6 +1,8 @@
 pub mod screen_ext;
 pub mod context;
 pub mod window;
+pub mod monitor;
 pub mod event_sys;
 pub mod input_sys;
+pub mod clipboard;
 pub mod prelude;
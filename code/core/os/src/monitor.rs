@@ -0,0 +1,44 @@
+use winit::monitor::MonitorHandle;
+
+use math::screen::{PhysicalPosition, PhysicalSize, Scale};
+
+use crate::context::OsContext;
+use crate::screen_ext::*;
+
+#[derive(Clone, Debug)]
+pub struct MonitorInfo {
+  pub name: Option<String>,
+  pub size: PhysicalSize,
+  pub position: PhysicalPosition,
+  pub scale: Scale,
+  /// Highest refresh rate in Hz of the video modes with the size of the monitor, or `None` if it has no such modes.
+  pub refresh_rate: Option<u16>,
+  handle: MonitorHandle,
+}
+
+impl MonitorInfo {
+  pub(crate) fn from_winit(handle: MonitorHandle) -> Self {
+    let size = handle.size();
+    let refresh_rate = handle.video_modes()
+      .filter(|m| m.size() == size)
+      .map(|m| m.refresh_rate())
+      .max();
+    Self {
+      name: handle.name(),
+      size: size.into_util(),
+      position: handle.position().into_util(),
+      scale: handle.scale_factor().into(),
+      refresh_rate,
+      handle,
+    }
+  }
+
+  pub fn winit_monitor_handle(&self) -> &MonitorHandle {
+    &self.handle
+  }
+}
+
+/// Gets all available monitors, or an empty list when there are none (e.g. when running headless).
+pub fn available_monitors(os_context: &OsContext) -> Vec<MonitorInfo> {
+  os_context.event_loop.available_monitors().map(MonitorInfo::from_winit).collect()
+}
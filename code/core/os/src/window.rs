@@ -1,11 +1,14 @@
 use raw_window_handle::{HasRawWindowHandle, RawWindowHandle};
 use thiserror::Error;
-use winit::error::OsError;
-use winit::window::{Window as WinitWindow, WindowBuilder, WindowId};
+use winit::error::{ExternalError, OsError};
+use winit::window::{BadIcon, Fullscreen, Icon, Window as WinitWindow, WindowBuilder as WinitWindowBuilder, WindowId};
+pub use winit::window::CursorIcon;
 
-use math::screen::{LogicalSize, PhysicalSize, Scale, ScreenSize};
+use math::screen::{LogicalPosition, LogicalSize, PhysicalPosition, PhysicalSize, Scale, ScreenSize};
+use util::image::{Components, ImageData};
 
 use crate::context::OsContext;
+use crate::monitor::MonitorInfo;
 use crate::screen_ext::*;
 
 pub struct Window {
@@ -16,6 +19,16 @@ pub struct Window {
 #[error("Could not create Window")]
 pub struct WindowCreateError(#[from] OsError);
 
+#[derive(Debug, Error)]
+pub enum WindowIconError {
+  #[error("Icon image is empty")]
+  Empty,
+  #[error("Icon image has {0} components instead of 4 (RGBA)")]
+  NotRgba(u8),
+  #[error("Icon image was rejected")]
+  BadIcon(#[from] BadIcon),
+}
+
 impl Window {
   pub fn new<S: Into<String>>(
     os_context: &OsContext,
@@ -23,12 +36,9 @@ impl Window {
     min_inner_size: LogicalSize,
     title: S,
   ) -> Result<Self, WindowCreateError> {
-    let window = WindowBuilder::new()
-      .with_inner_size(inner_size.into_winit())
-      .with_min_inner_size(min_inner_size.into_winit())
-      .with_title(title)
-      .build(&os_context.event_loop)?;
-    Ok(Self { window })
+    WindowBuilder::new(inner_size, title)
+      .with_min_inner_size(min_inner_size)
+      .build(os_context)
   }
 
 
@@ -47,6 +57,80 @@ impl Window {
   }
 
 
+  /// Sets the window icon from RGBA image data.
+  pub fn set_icon(&self, image: &ImageData) -> Result<(), WindowIconError> {
+    let dimensions = image.dimensions;
+    if dimensions.width == 0 || dimensions.height == 0 { return Err(WindowIconError::Empty); }
+    if dimensions.components != Components::Components4 { return Err(WindowIconError::NotRgba(dimensions.components.into())); }
+    let icon = Icon::from_rgba(image.data_slice().to_vec(), dimensions.width, dimensions.height)?;
+    self.window.set_window_icon(Some(icon));
+    Ok(())
+  }
+
+  pub fn set_cursor_icon(&self, cursor_icon: CursorIcon) {
+    self.window.set_cursor_icon(cursor_icon);
+  }
+
+  pub fn set_cursor_visible(&self, visible: bool) {
+    self.window.set_cursor_visible(visible);
+  }
+
+  /// Sets whether the cursor is confined to the window.
+  pub fn set_cursor_grab(&self, grab: bool) -> Result<(), ExternalError> {
+    self.window.set_cursor_grab(grab)
+  }
+
+  pub fn set_title(&self, title: &str) {
+    self.window.set_title(title);
+  }
+
+  /// Sets whether the window is visible. Hidden windows stay alive, so surfaces created for them stay valid.
+  pub fn set_visible(&self, visible: bool) {
+    self.window.set_visible(visible);
+  }
+
+  /// Sets whether the window is borderless fullscreen on its current monitor.
+  pub fn set_fullscreen(&self, fullscreen: bool) {
+    let fullscreen = if fullscreen { Some(Fullscreen::Borderless(self.window.current_monitor())) } else { None };
+    self.window.set_fullscreen(fullscreen);
+  }
+
+
+  /// Gets the monitor that the window is currently on.
+  pub fn current_monitor(&self) -> MonitorInfo {
+    MonitorInfo::from_winit(self.window.current_monitor())
+  }
+
+
+  /// Gets the position of the top-left corner of the window, including decorations, or `None` if not supported by the
+  /// platform.
+  pub fn outer_position(&self) -> Option<PhysicalPosition> {
+    self.window.outer_position().ok().map(|p| p.into_util())
+  }
+
+  /// Gets the size of the window, including decorations.
+  pub fn outer_size(&self) -> PhysicalSize {
+    self.window.outer_size().into_util()
+  }
+
+  /// Sets the position of the top-left corner of the window, including decorations. If the window would not overlap any
+  /// monitor, it is instead clamped onto the nearest monitor.
+  pub fn set_outer_position(&self, position: PhysicalPosition) {
+    let monitors: Vec<_> = self.window.available_monitors()
+      .map(|m| (m.position().into_util(), m.size().into_util()))
+      .collect();
+    let position = clamp_onto_monitors(position, self.outer_size(), &monitors);
+    self.window.set_outer_position(position.into_winit());
+  }
+
+  /// Centers the window on the monitor it is currently on.
+  pub fn center_on_monitor(&self) {
+    let monitor = self.window.current_monitor();
+    let position = center_on(monitor.position().into_util(), monitor.size().into_util(), self.outer_size());
+    self.set_outer_position(position);
+  }
+
+
   pub fn winit_window(&self) -> &WinitWindow {
     &self.window
   }
@@ -59,3 +143,130 @@ impl Window {
     self.window.raw_window_handle()
   }
 }
+
+// Positioning
+
+/// Gets the position that centers a window of `size` on the monitor at `monitor_position` with `monitor_size`.
+fn center_on(monitor_position: PhysicalPosition, monitor_size: PhysicalSize, size: PhysicalSize) -> PhysicalPosition {
+  let x = monitor_position.x + (monitor_size.width as i32 - size.width as i32) / 2;
+  let y = monitor_position.y + (monitor_size.height as i32 - size.height as i32) / 2;
+  PhysicalPosition::new(x, y)
+}
+
+/// Gets `position` if a window of `size` at that position overlaps any of `monitors`, given as position and size.
+/// Otherwise, gets the position clamped onto the nearest monitor, keeping the window inside that monitor as far as it
+/// fits. Gets `position` as-is if there are no monitors.
+fn clamp_onto_monitors(position: PhysicalPosition, size: PhysicalSize, monitors: &[(PhysicalPosition, PhysicalSize)]) -> PhysicalPosition {
+  let (x, y) = (position.x as i64, position.y as i64);
+  let (width, height) = (size.width as i64, size.height as i64);
+  let overlaps = |(monitor_position, monitor_size): &(PhysicalPosition, PhysicalSize)| {
+    let (mx, my) = (monitor_position.x as i64, monitor_position.y as i64);
+    x < mx + monitor_size.width as i64 && mx < x + width && y < my + monitor_size.height as i64 && my < y + height
+  };
+  if monitors.is_empty() || monitors.iter().any(overlaps) {
+    return position;
+  }
+  // Distance from the window to a monitor, squared, along the axes in which they do not overlap.
+  let distance_squared = |(monitor_position, monitor_size): &(PhysicalPosition, PhysicalSize)| {
+    let (mx, my) = (monitor_position.x as i64, monitor_position.y as i64);
+    let dx = (mx - (x + width)).max(x - (mx + monitor_size.width as i64)).max(0);
+    let dy = (my - (y + height)).max(y - (my + monitor_size.height as i64)).max(0);
+    dx * dx + dy * dy
+  };
+  // CORRECTNESS: safe to `unwrap` - `monitors` is not empty.
+  let (monitor_position, monitor_size) = monitors.iter().min_by_key(|m| distance_squared(m)).unwrap();
+  let clamp = |value: i64, min: i64, length: i64, window_length: i64| value.min(min + length - window_length).max(min);
+  PhysicalPosition::new(
+    clamp(x, monitor_position.x as i64, monitor_size.width as i64, width) as i32,
+    clamp(y, monitor_position.y as i64, monitor_size.height as i64, height) as i32,
+  )
+}
+
+// Builder
+
+pub struct WindowBuilder {
+  title: String,
+  inner_size: LogicalSize,
+  min_inner_size: Option<LogicalSize>,
+  position: Option<LogicalPosition>,
+  centered: bool,
+  resizable: bool,
+  decorations: bool,
+  maximized: bool,
+  always_on_top: bool,
+}
+
+impl WindowBuilder {
+  pub fn new<S: Into<String>>(inner_size: LogicalSize, title: S) -> Self {
+    Self {
+      title: title.into(),
+      inner_size,
+      min_inner_size: None,
+      position: None,
+      centered: false,
+      resizable: true,
+      decorations: true,
+      maximized: false,
+      always_on_top: false,
+    }
+  }
+
+  pub fn with_min_inner_size(mut self, min_inner_size: LogicalSize) -> Self {
+    self.min_inner_size = Some(min_inner_size);
+    self
+  }
+
+  /// Sets the initial position of the top-left corner of the window, including decorations.
+  pub fn with_position(mut self, position: LogicalPosition) -> Self {
+    self.position = Some(position);
+    self
+  }
+
+  /// Sets whether to initially center the window on its monitor. Ignored when a [position](Self::with_position) is set.
+  pub fn with_centered(mut self, centered: bool) -> Self {
+    self.centered = centered;
+    self
+  }
+
+  pub fn with_resizable(mut self, resizable: bool) -> Self {
+    self.resizable = resizable;
+    self
+  }
+
+  pub fn with_decorations(mut self, decorations: bool) -> Self {
+    self.decorations = decorations;
+    self
+  }
+
+  pub fn with_maximized(mut self, maximized: bool) -> Self {
+    self.maximized = maximized;
+    self
+  }
+
+  pub fn with_always_on_top(mut self, always_on_top: bool) -> Self {
+    self.always_on_top = always_on_top;
+    self
+  }
+
+  pub fn build(self, os_context: &OsContext) -> Result<Window, WindowCreateError> {
+    let mut builder = WinitWindowBuilder::new()
+      .with_inner_size(self.inner_size.into_winit())
+      .with_title(self.title)
+      .with_resizable(self.resizable)
+      .with_decorations(self.decorations)
+      .with_maximized(self.maximized)
+      .with_always_on_top(self.always_on_top);
+    if let Some(min_inner_size) = self.min_inner_size {
+      builder = builder.with_min_inner_size(min_inner_size.into_winit());
+    }
+    let window = builder.build(&os_context.event_loop)?;
+    // Initial position cannot be set on the winit builder, so set it right after creation.
+    let window = Window { window };
+    if let Some(position) = self.position {
+      window.window.set_outer_position(position.into_winit());
+    } else if self.centered {
+      window.center_on_monitor();
+    }
+    Ok(window)
+  }
+}
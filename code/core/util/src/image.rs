@@ -1,4 +1,5 @@
 use std::ffi::CStr;
+use std::ops::Range;
 use std::os::raw::{c_char, c_int, c_void};
 use std::slice::{
   from_raw_parts,
@@ -66,11 +67,34 @@ impl ImageData {
   }
 
   pub fn from_vec(dimensions: Dimensions, data: Vec<u8>) -> ImageData {
+    assert_eq!(data.len(), dimensions.num_bytes(), "Data of {} bytes does not match dimensions {:?}", data.len(), dimensions);
     let storage = Box::new(VecStorage { data });
     ImageData { dimensions, storage }
   }
 
 
+  /// Converts this image into an image with 4 components. Grayscale components are replicated into the red, green, and
+  /// blue components, and alpha is set to 255 if the source has no alpha component.
+  pub fn to_rgba(&self) -> ImageData {
+    let dimensions = Dimensions { components: Components::Components4, ..self.dimensions };
+    let src = self.storage.as_slice();
+    let mut data = Vec::with_capacity(dimensions.num_bytes());
+    match self.dimensions.components {
+      Components::Components1 => for &g in src {
+        data.extend_from_slice(&[g, g, g, 255]);
+      },
+      Components::Components2 => for ga in src.chunks_exact(2) {
+        data.extend_from_slice(&[ga[0], ga[0], ga[0], ga[1]]);
+      },
+      Components::Components3 => for rgb in src.chunks_exact(3) {
+        data.extend_from_slice(&[rgb[0], rgb[1], rgb[2], 255]);
+      },
+      Components::Components4 => data.extend_from_slice(src),
+    }
+    ImageData::from_vec(dimensions, data)
+  }
+
+
   pub fn size(&self) -> usize { self.dimensions.num_bytes() }
   pub fn data_slice(&self) -> &[u8] { self.storage.as_slice() }
   pub fn data_slice_mut(&mut self) -> &mut [u8] { self.storage.as_slice_mut() }
@@ -78,6 +102,27 @@ impl ImageData {
   pub fn data_ptr_mut(&mut self) -> *mut u8 { self.storage.as_ptr_mut() }
 
 
+  /// Returns the components of the pixel at `x`, `y`. Panics if `x` or `y` is out of range.
+  pub fn pixel(&self, x: u32, y: u32) -> &[u8] {
+    let range = self.pixel_range(x, y);
+    &self.storage.as_slice()[range]
+  }
+
+  /// Returns the mutable components of the pixel at `x`, `y`. Panics if `x` or `y` is out of range.
+  pub fn pixel_mut(&mut self, x: u32, y: u32) -> &mut [u8] {
+    let range = self.pixel_range(x, y);
+    &mut self.storage.as_slice_mut()[range]
+  }
+
+  fn pixel_range(&self, x: u32, y: u32) -> Range<usize> {
+    let Dimensions { width, height, components } = self.dimensions;
+    assert!(x < width && y < height, "Pixel ({}, {}) is out of range for image of {}x{} pixels", x, y, width, height);
+    let components = u8::from(components) as usize;
+    let start = (x as usize + y as usize * width as usize) * components;
+    start..start + components
+  }
+
+
   pub fn subdivide_into_tiles(&self, tile_width: u32, tile_height: u32) -> Vec<ImageData> {
     let dimensions = self.dimensions;
     let width = dimensions.width;
@@ -200,3 +245,54 @@ impl Storage for VecStorage {
   fn as_ptr(&self) -> *const u8 { self.data.as_ptr() }
   fn as_ptr_mut(&mut self) -> *mut u8 { self.data.as_mut_ptr() }
 }
+
+#[cfg(test)]
+mod tests {
+  use super::{Components, Dimensions, ImageData};
+
+  fn image(width: u32, height: u32, components: Components, data: Vec<u8>) -> ImageData {
+    ImageData::from_vec(Dimensions::new(width, height, components), data)
+  }
+
+  #[test]
+  fn to_rgba_replicates_gray_with_opaque_alpha() {
+    let rgba = image(2, 1, Components::Components1, vec![10, 20]).to_rgba();
+    assert_eq!(rgba.dimensions.components, Components::Components4);
+    assert_eq!(rgba.data_slice(), &[10, 10, 10, 255, 20, 20, 20, 255]);
+  }
+
+  #[test]
+  fn to_rgba_replicates_gray_and_keeps_alpha() {
+    let rgba = image(2, 1, Components::Components2, vec![10, 100, 20, 200]).to_rgba();
+    assert_eq!(rgba.data_slice(), &[10, 10, 10, 100, 20, 20, 20, 200]);
+  }
+
+  #[test]
+  fn to_rgba_adds_opaque_alpha_to_rgb() {
+    let rgba = image(1, 2, Components::Components3, vec![1, 2, 3, 4, 5, 6]).to_rgba();
+    assert_eq!((rgba.dimensions.width, rgba.dimensions.height), (1, 2));
+    assert_eq!(rgba.data_slice(), &[1, 2, 3, 255, 4, 5, 6, 255]);
+  }
+
+  #[test]
+  fn pixel_reads_and_writes_components() {
+    let mut image = image(3, 2, Components::Components2, (0..12).collect());
+    // Pixel (1, 1) starts at (1 + 1 * 3) * 2 = 8.
+    assert_eq!(image.pixel(1, 1), &[8, 9]);
+    image.pixel_mut(1, 1).copy_from_slice(&[42, 43]);
+    assert_eq!(image.pixel(1, 1), &[42, 43]);
+    assert_eq!(&image.data_slice()[6..10], &[6, 7, 42, 43]);
+  }
+
+  #[test]
+  #[should_panic(expected = "out of range")]
+  fn pixel_panics_at_width() {
+    image(3, 2, Components::Components1, vec![0; 6]).pixel(3, 0);
+  }
+
+  #[test]
+  #[should_panic(expected = "out of range")]
+  fn pixel_mut_panics_at_height() {
+    image(3, 2, Components::Components1, vec![0; 6]).pixel_mut(0, 2);
+  }
+}
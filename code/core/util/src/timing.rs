@@ -1,3 +1,4 @@
+use std::fmt::{self, Display, Formatter};
 use std::time::{Duration, Instant};
 
 pub struct Timer {
@@ -25,3 +26,219 @@ impl Timer {
     Time { elapsed, delta }
   }
 }
+
+
+
+/// Conversion and formatting extensions for [Duration].
+pub trait DurationExt {
+  /// Returns the number of milliseconds contained by this duration as `f64`, including the fractional part.
+  fn millis_f64(&self) -> f64;
+
+  /// Returns the number of times this duration fits in one second (e.g. frames per second for a frame time). Returns
+  /// infinity for a zero duration.
+  fn as_fps(&self) -> f64;
+
+  /// Returns a wrapper that displays this duration in ns, µs, ms, or s, whichever unit fits best.
+  fn display(&self) -> DisplayDuration;
+}
+
+impl DurationExt for Duration {
+  #[inline]
+  fn millis_f64(&self) -> f64 { self.as_secs_f64() * 1_000.0 }
+
+  #[inline]
+  fn as_fps(&self) -> f64 { 1.0 / self.as_secs_f64() }
+
+  #[inline]
+  fn display(&self) -> DisplayDuration { DisplayDuration(*self) }
+}
+
+/// Displays a [Duration] with an automatically selected unit. Uses the precision of the formatter, or 2 decimals if no
+/// precision is given. Durations below one microsecond are displayed as whole nanoseconds.
+#[derive(Copy, Clone, Debug)]
+pub struct DisplayDuration(pub Duration);
+
+impl Display for DisplayDuration {
+  fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+    let nanos = self.0.as_nanos();
+    let precision = f.precision().unwrap_or(2);
+    if nanos < 1_000 {
+      write!(f, "{} ns", nanos)
+    } else if nanos < 1_000_000 {
+      write!(f, "{:.*} µs", precision, nanos as f64 / 1_000.0)
+    } else if nanos < 1_000_000_000 {
+      write!(f, "{:.*} ms", precision, nanos as f64 / 1_000_000.0)
+    } else {
+      write!(f, "{:.*} s", precision, self.0.as_secs_f64())
+    }
+  }
+}
+
+
+/// Fixed-timestep accumulator: accumulates real time with [advance](FixedTimestep::advance), and consumes it in fixed
+/// steps with [step](FixedTimestep::step) while [should_tick](FixedTimestep::should_tick) returns true. Accumulated
+/// time is clamped to `max_steps_per_advance` steps, dropping the excess.
+#[derive(Copy, Clone, Debug)]
+pub struct FixedTimestep {
+  time_step: Duration,
+  max_steps_per_advance: u32,
+  accumulated: Duration,
+  dropped: Duration,
+}
+
+impl FixedTimestep {
+  pub fn new(time_step: Duration, max_steps_per_advance: u32) -> FixedTimestep {
+    debug_assert!(time_step > Duration::default(), "Time step must be larger than 0");
+    debug_assert!(max_steps_per_advance > 0, "Maximum steps per advance must be larger than 0");
+    FixedTimestep {
+      time_step,
+      max_steps_per_advance,
+      accumulated: Duration::default(),
+      dropped: Duration::default(),
+    }
+  }
+
+
+  /// Adds `real_dt` to the accumulated time. Returns the time that was dropped due to clamping, which is zero if the
+  /// accumulated time was not clamped.
+  pub fn advance(&mut self, real_dt: Duration) -> Duration {
+    self.accumulated += real_dt;
+    let max_accumulated = self.time_step * self.max_steps_per_advance;
+    if self.accumulated > max_accumulated {
+      let dropped = self.accumulated - max_accumulated;
+      self.accumulated = max_accumulated;
+      self.dropped += dropped;
+      dropped
+    } else {
+      Duration::default()
+    }
+  }
+
+  /// Discards the accumulated time, without counting it as dropped.
+  #[inline]
+  pub fn reset(&mut self) {
+    self.accumulated = Duration::default();
+  }
+
+  #[inline]
+  pub fn should_tick(&self) -> bool {
+    self.accumulated >= self.time_step
+  }
+
+  /// Consumes one time step from the accumulated time.
+  #[inline]
+  pub fn step(&mut self) {
+    debug_assert!(self.should_tick(), "Cannot step; accumulated time {:?} is less than time step {:?}", self.accumulated, self.time_step);
+    self.accumulated -= self.time_step;
+  }
+
+  #[inline]
+  pub fn num_upcoming_steps(&self) -> u64 {
+    (self.accumulated.as_nanos() / self.time_step.as_nanos()) as u64
+  }
+
+  /// Fraction of a time step that has been accumulated but not yet consumed, in the range [0, 1) after stepping.
+  #[inline]
+  pub fn extrapolation(&self) -> f64 {
+    self.accumulated.as_secs_f64() / self.time_step.as_secs_f64()
+  }
+
+
+  #[inline]
+  pub fn time_step(&self) -> Duration { self.time_step }
+
+  /// Sets the time step, rescaling the accumulated time so that the [extrapolation](Self::extrapolation) and number of
+  /// upcoming steps are preserved. Switching to a shorter time step thus does not cause a burst of steps.
+  pub fn set_time_step(&mut self, time_step: Duration) {
+    debug_assert!(time_step > Duration::default(), "Time step must be larger than 0");
+    let extrapolation = self.extrapolation();
+    self.time_step = time_step;
+    self.accumulated = time_step.mul_f64(extrapolation).min(time_step * self.max_steps_per_advance);
+  }
+
+  #[inline]
+  pub fn max_steps_per_advance(&self) -> u32 { self.max_steps_per_advance }
+
+  #[inline]
+  pub fn set_max_steps_per_advance(&mut self, max_steps_per_advance: u32) {
+    debug_assert!(max_steps_per_advance > 0, "Maximum steps per advance must be larger than 0");
+    self.max_steps_per_advance = max_steps_per_advance;
+  }
+
+  #[inline]
+  pub fn accumulated(&self) -> Duration { self.accumulated }
+
+  /// Total time that was dropped because the accumulated time exceeded `max_steps_per_advance` steps.
+  #[inline]
+  pub fn dropped(&self) -> Duration { self.dropped }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::time::Duration;
+
+  use super::{DurationExt, FixedTimestep};
+
+  const STEP: Duration = Duration::from_millis(16);
+
+  fn count_ticks(fixed_timestep: &mut FixedTimestep) -> u32 {
+    let mut ticks = 0;
+    while fixed_timestep.should_tick() {
+      fixed_timestep.step();
+      ticks += 1;
+    }
+    ticks
+  }
+
+  #[test]
+  fn fixed_timestep_consumes_whole_steps() {
+    let mut fixed_timestep = FixedTimestep::new(STEP, 10);
+    assert_eq!(fixed_timestep.advance(Duration::from_millis(100)), Duration::default());
+    assert_eq!(fixed_timestep.num_upcoming_steps(), 6);
+    assert_eq!(count_ticks(&mut fixed_timestep), 6);
+    assert_eq!(fixed_timestep.accumulated(), Duration::from_millis(4));
+    assert!((fixed_timestep.extrapolation() - 0.25).abs() < 1e-9);
+  }
+
+  #[test]
+  fn fixed_timestep_clamps_and_drops_excess() {
+    let mut fixed_timestep = FixedTimestep::new(STEP, 4);
+    assert_eq!(fixed_timestep.advance(Duration::from_millis(100)), Duration::from_millis(36));
+    assert_eq!(fixed_timestep.dropped(), Duration::from_millis(36));
+    assert_eq!(count_ticks(&mut fixed_timestep), 4);
+  }
+
+  #[test]
+  fn fixed_timestep_set_time_step_does_not_burst() {
+    let mut fixed_timestep = FixedTimestep::new(STEP, 10);
+    fixed_timestep.advance(Duration::from_millis(24));
+    assert_eq!(count_ticks(&mut fixed_timestep), 1);
+    // Half a step is accumulated; a four times shorter step must keep that at half a step instead of ticking twice.
+    fixed_timestep.set_time_step(Duration::from_millis(4));
+    assert!((fixed_timestep.extrapolation() - 0.5).abs() < 1e-9);
+    assert_eq!(count_ticks(&mut fixed_timestep), 0);
+    fixed_timestep.advance(Duration::from_millis(10));
+    assert_eq!(count_ticks(&mut fixed_timestep), 3);
+  }
+
+  #[test]
+  fn display_duration_selects_unit() {
+    assert_eq!(Duration::from_nanos(999).display().to_string(), "999 ns");
+    assert_eq!(Duration::from_nanos(1_500).display().to_string(), "1.50 µs");
+    assert_eq!(Duration::from_micros(1_500).display().to_string(), "1.50 ms");
+    assert_eq!(Duration::from_millis(2_250).display().to_string(), "2.25 s");
+  }
+
+  #[test]
+  fn display_duration_uses_formatter_precision() {
+    assert_eq!(format!("{:.1}", Duration::from_micros(1_500).display()), "1.5 ms");
+    assert_eq!(format!("{:.0}", Duration::from_millis(2_250).display()), "2 s");
+  }
+
+  #[test]
+  fn duration_conversions() {
+    assert_eq!(Duration::from_micros(1_500).millis_f64(), 1.5);
+    assert_eq!(Duration::from_millis(20).as_fps(), 50.0);
+    assert!(Duration::default().as_fps().is_infinite());
+  }
+}
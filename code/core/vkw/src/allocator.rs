@@ -1,8 +1,9 @@
 use core::ptr;
 use std::mem::size_of;
-use std::ops::Deref;
+use std::ops::{Bound, Deref, Range, RangeBounds};
 
-use ash::vk::{self, Buffer, BufferUsageFlags, DeviceSize, Image, ImageCreateInfo};
+use ash::version::InstanceV1_0;
+use ash::vk::{self, Buffer, BufferUsageFlags, DeviceSize, Image, ImageCreateInfo, MemoryPropertyFlags};
 use log::debug;
 use thiserror::Error;
 use vk_mem::{Allocation, AllocationCreateFlags, AllocationCreateInfo, AllocationInfo, Allocator as VkMemAllocator, AllocatorCreateInfo, Error as VkMemError, MemoryUsage};
@@ -12,10 +13,22 @@ use crate::instance::Instance;
 
 // Wrapper
 
+/// Memory allocator. `Send` and `Sync`: VMA synchronizes allocation and deallocation internally, as it is not created
+/// with `EXTERNALLY_SYNCHRONIZED`. Mapping, flushing, and invalidating the same allocation concurrently must still be
+/// synchronized by the caller.
 pub struct Allocator {
-  pub wrapped: VkMemAllocator
+  pub wrapped: VkMemAllocator,
+  /// Property flags per memory type index of the physical device.
+  memory_type_flags: Vec<MemoryPropertyFlags>,
+  non_coherent_atom_size: DeviceSize,
 }
 
+// CORRECTNESS: VMA allocators are internally synchronized, see above. The remaining fields are plain data.
+unsafe impl Send for Allocator {}
+unsafe impl Sync for Allocator {}
+
+crate::assert_send_sync!(Allocator);
+
 // Creation
 
 #[derive(Error, Debug)]
@@ -26,13 +39,18 @@ impl Device {
   pub unsafe fn create_allocator(&self, instance: &Instance) -> Result<Allocator, AllocatorCreateError> {
     let create_info = AllocatorCreateInfo {
       physical_device: self.physical_device,
-      device: self.wrapped.clone(),
-      instance: instance.wrapped.clone(),
+      device: self.raw().clone(),
+      instance: instance.raw().clone(),
       ..AllocatorCreateInfo::default()
     };
     let allocator = VkMemAllocator::new(&create_info)?;
+    let memory_properties = instance.raw().get_physical_device_memory_properties(self.physical_device);
+    let memory_type_flags = memory_properties.memory_types[..memory_properties.memory_type_count as usize].iter()
+      .map(|t| t.property_flags)
+      .collect();
+    let non_coherent_atom_size = instance.raw().get_physical_device_properties(self.physical_device).limits.non_coherent_atom_size;
     debug!("Created allocator");
-    Ok(Allocator { wrapped: allocator })
+    Ok(Allocator { wrapped: allocator, memory_type_flags, non_coherent_atom_size })
   }
 }
 
@@ -44,14 +62,34 @@ impl Allocator {
   }
 }
 
+// Memory properties
+
+impl Allocator {
+  /// Whether the memory of allocation `info` is host-coherent, in which case it never needs to be flushed or
+  /// invalidated.
+  pub fn is_coherent(&self, info: &AllocationInfo) -> bool {
+    self.memory_type_flags.get(info.get_memory_type() as usize)
+      .map_or(false, |flags| flags.contains(MemoryPropertyFlags::HOST_COHERENT))
+  }
+
+  #[inline]
+  pub fn non_coherent_atom_size(&self) -> DeviceSize { self.non_coherent_atom_size }
+}
+
 // Buffer creation
 
+/// Buffer with its allocated memory. `Send` and `Sync`, as [AllocationInfo] only holds a pointer to mapped memory that
+/// is not dereferenced without going through the (unsafe) [MappedMemory] API.
 pub struct BufferAllocation {
   pub buffer: Buffer,
   pub allocation: Allocation,
   pub info: AllocationInfo,
 }
 
+// CORRECTNESS: the mapped data and user data pointers in `info` are never dereferenced by this type, see above.
+unsafe impl Send for BufferAllocation {}
+unsafe impl Sync for BufferAllocation {}
+
 #[derive(Error, Debug)]
 #[error("Failed to allocate buffer: {0:?}")]
 pub struct BufferAllocationError(#[from] VkMemError);
@@ -77,6 +115,18 @@ impl Allocator {
     Ok(BufferAllocation { buffer, allocation, info })
   }
 
+  /// Creates a buffer like [create_buffer](Self::create_buffer), with `BufferUsageFlags::SHADER_DEVICE_ADDRESS_EXT` added
+  /// to `buffer_usage` for getting its [device address](Device::get_buffer_device_address).
+  pub unsafe fn create_device_address_buffer(
+    &self,
+    size: usize,
+    buffer_usage: BufferUsageFlags,
+    memory_usage: MemoryUsage,
+    flags: AllocationCreateFlags,
+  ) -> Result<BufferAllocation, BufferAllocationError> {
+    self.create_buffer(size, buffer_usage | BufferUsageFlags::SHADER_DEVICE_ADDRESS_EXT, memory_usage, flags)
+  }
+
 
   pub unsafe fn create_staging_buffer(&self, size: usize) -> Result<BufferAllocation, BufferAllocationError> {
     self.create_buffer(size, BufferUsageFlags::TRANSFER_SRC, MemoryUsage::CpuOnly, AllocationCreateFlags::NONE)
@@ -86,6 +136,11 @@ impl Allocator {
     self.create_buffer(size, BufferUsageFlags::TRANSFER_SRC, MemoryUsage::CpuOnly, AllocationCreateFlags::MAPPED)
   }
 
+  /// Creates a mapped buffer that the GPU copies into for reading back on the CPU.
+  pub unsafe fn create_readback_buffer_mapped(&self, size: usize) -> Result<BufferAllocation, BufferAllocationError> {
+    self.create_buffer(size, BufferUsageFlags::TRANSFER_DST, MemoryUsage::GpuToCpu, AllocationCreateFlags::MAPPED)
+  }
+
 
   pub unsafe fn create_gpu_vertex_buffer(&self, size: usize) -> Result<BufferAllocation, BufferAllocationError> {
     self.create_buffer(size, BufferUsageFlags::TRANSFER_DST | BufferUsageFlags::VERTEX_BUFFER, MemoryUsage::GpuOnly, AllocationCreateFlags::NONE)
@@ -124,6 +179,32 @@ impl Allocator {
   pub unsafe fn create_cpugpu_uniform_buffer_mapped(&self, size: usize) -> Result<BufferAllocation, BufferAllocationError> {
     self.create_buffer(size, BufferUsageFlags::UNIFORM_BUFFER, MemoryUsage::CpuToGpu, AllocationCreateFlags::MAPPED)
   }
+
+
+  pub unsafe fn create_gpu_storage_buffer(&self, size: usize) -> Result<BufferAllocation, BufferAllocationError> {
+    self.create_buffer(size, BufferUsageFlags::TRANSFER_DST | BufferUsageFlags::STORAGE_BUFFER, MemoryUsage::GpuOnly, AllocationCreateFlags::NONE)
+  }
+
+  pub unsafe fn create_gpu_storage_vertex_buffer(&self, size: usize) -> Result<BufferAllocation, BufferAllocationError> {
+    self.create_buffer(size, BufferUsageFlags::STORAGE_BUFFER | BufferUsageFlags::VERTEX_BUFFER, MemoryUsage::GpuOnly, AllocationCreateFlags::NONE)
+  }
+
+  pub unsafe fn create_cpugpu_storage_buffer(&self, size: usize) -> Result<BufferAllocation, BufferAllocationError> {
+    self.create_buffer(size, BufferUsageFlags::STORAGE_BUFFER, MemoryUsage::CpuToGpu, AllocationCreateFlags::NONE)
+  }
+
+  pub unsafe fn create_cpugpu_storage_buffer_mapped(&self, size: usize) -> Result<BufferAllocation, BufferAllocationError> {
+    self.create_buffer(size, BufferUsageFlags::STORAGE_BUFFER, MemoryUsage::CpuToGpu, AllocationCreateFlags::MAPPED)
+  }
+
+
+  pub unsafe fn create_gpu_indirect_buffer(&self, size: usize) -> Result<BufferAllocation, BufferAllocationError> {
+    self.create_buffer(size, BufferUsageFlags::STORAGE_BUFFER | BufferUsageFlags::INDIRECT_BUFFER, MemoryUsage::GpuOnly, AllocationCreateFlags::NONE)
+  }
+
+  pub unsafe fn create_gpucpu_indirect_buffer_mapped(&self, size: usize) -> Result<BufferAllocation, BufferAllocationError> {
+    self.create_buffer(size, BufferUsageFlags::STORAGE_BUFFER | BufferUsageFlags::INDIRECT_BUFFER, MemoryUsage::GpuToCpu, AllocationCreateFlags::MAPPED)
+  }
 }
 
 // Staging buffer creation
@@ -133,7 +214,9 @@ pub enum StagingBufferAllocationError {
   #[error(transparent)]
   BufferAllocationFail(#[from] BufferAllocationError),
   #[error(transparent)]
-  MemoryMapFail(#[from] MemoryMapError)
+  MemoryMapFail(#[from] MemoryMapError),
+  #[error(transparent)]
+  MemoryFlushFail(#[from] MemoryFlushError),
 }
 
 impl Allocator {
@@ -143,6 +226,7 @@ impl Allocator {
     {
       let mapped = buffer_allocation.map(self)?;
       mapped.copy_from_slice(slice);
+      mapped.flush(..)?;
     }
     Ok(buffer_allocation)
   }
@@ -160,12 +244,19 @@ impl BufferAllocation {
 
 // Image creation
 
+/// Image with its allocated memory. `Send` and `Sync` for the same reasons as [BufferAllocation].
 pub struct ImageAllocation {
   pub image: Image,
   pub allocation: Allocation,
   pub info: AllocationInfo,
 }
 
+// CORRECTNESS: the mapped data and user data pointers in `info` are never dereferenced by this type.
+unsafe impl Send for ImageAllocation {}
+unsafe impl Sync for ImageAllocation {}
+
+crate::assert_send_sync!(BufferAllocation, ImageAllocation);
+
 #[derive(Error, Debug)]
 #[error("Failed to allocate image: {0:?}")]
 pub struct ImageAllocationError(#[from] VkMemError);
@@ -202,15 +293,71 @@ impl ImageAllocation {
 #[error("Failed to map memory: {0:?}")]
 pub struct MemoryMapError(#[from] VkMemError);
 
+#[derive(Error, Debug)]
+#[error("Failed to flush mapped memory: {0:?}")]
+pub struct MemoryFlushError(#[from] VkMemError);
+
+#[derive(Error, Debug)]
+#[error("Failed to invalidate mapped memory: {0:?}")]
+pub struct MemoryInvalidateError(#[from] VkMemError);
+
+/// Mapped memory of an allocation, unmapped on drop if it was not persistently mapped. Neither `Send` nor `Sync`, as
+/// it holds a raw pointer to the mapped memory; map memory on the thread that writes or reads it.
 pub struct MappedMemory<'a> {
   ptr: *mut u8,
-  unmap: Option<(&'a Allocator, &'a Allocation)>,
+  allocator: &'a Allocator,
+  allocation: &'a Allocation,
+  info: &'a AllocationInfo,
+  unmap: bool,
 }
 
 impl MappedMemory<'_> {
   #[inline]
   pub fn ptr(&self) -> *mut u8 { self.ptr }
 
+  /// Flushes host writes to the bytes in `range` of the mapped memory, making them visible to the device. The range is
+  /// widened to multiples of the non-coherent atom size. Does nothing on host-coherent memory.
+  pub unsafe fn flush<R: RangeBounds<usize>>(&self, range: R) -> Result<(), MemoryFlushError> {
+    if let Some(range) = self.non_coherent_range(range) {
+      self.allocator.flush_allocation(self.allocation, range.start, range.end - range.start)?;
+    }
+    Ok(())
+  }
+
+  /// Invalidates the bytes in `range` of the mapped memory, making device writes visible to the host. The range is
+  /// widened to multiples of the non-coherent atom size. Does nothing on host-coherent memory.
+  pub unsafe fn invalidate<R: RangeBounds<usize>>(&self, range: R) -> Result<(), MemoryInvalidateError> {
+    if let Some(range) = self.non_coherent_range(range) {
+      self.allocator.invalidate_allocation(self.allocation, range.start, range.end - range.start)?;
+    }
+    Ok(())
+  }
+
+  /// Gets `range` aligned to the non-coherent atom size, or `None` if the memory is coherent or the range is empty.
+  fn non_coherent_range<R: RangeBounds<usize>>(&self, range: R) -> Option<Range<usize>> {
+    if self.allocator.is_coherent(self.info) {
+      return None;
+    }
+    let size = self.info.get_size();
+    let start = match range.start_bound() {
+      Bound::Included(start) => *start,
+      Bound::Excluded(start) => *start + 1,
+      Bound::Unbounded => 0,
+    };
+    let end = match range.end_bound() {
+      Bound::Included(end) => *end + 1,
+      Bound::Excluded(end) => *end,
+      Bound::Unbounded => size,
+    }.min(size);
+    if start >= end {
+      return None;
+    }
+    // Align in terms of the offset into device memory, as that is what the atom size applies to.
+    let offset = self.info.get_offset();
+    let range = align_range(offset + start..offset + end, self.allocator.non_coherent_atom_size as usize);
+    Some(range.start.max(offset) - offset..range.end.min(offset + size) - offset)
+  }
+
   pub unsafe fn copy_zeroes(&self, count: usize) {
     std::ptr::write_bytes(self.ptr, 0, count);
   }
@@ -250,22 +397,28 @@ impl MappedMemory<'_> {
 
 impl BufferAllocation {
   /// Returns a pointer to the mapped data if memory is persistently mapped, `None` otherwise.
-  pub unsafe fn get_mapped_data(&self) -> Option<MappedMemory> {
+  pub unsafe fn get_mapped_data<'a>(&'a self, allocator: &'a Allocator) -> Option<MappedMemory<'a>> {
     let ptr = self.info.get_mapped_data();
     if ptr == ptr::null_mut() {
       None
     } else {
-      Some(MappedMemory { ptr, unmap: None })
+      Some(MappedMemory { ptr, allocator, allocation: &self.allocation, info: &self.info, unmap: false })
     }
   }
 
   pub unsafe fn map<'a>(&'a self, allocator: &'a Allocator) -> Result<MappedMemory<'a>, MemoryMapError> {
     let allocation = &self.allocation;
     let ptr = allocator.map_memory(allocation)?;
-    Ok(MappedMemory { ptr, unmap: Some((allocator, allocation)) })
+    Ok(MappedMemory { ptr, allocator, allocation, info: &self.info, unmap: true })
   }
 }
 
+/// Widens `range` to start and end at multiples of `atom_size`, which must be a power of two.
+pub fn align_range(range: Range<usize>, atom_size: usize) -> Range<usize> {
+  let mask = atom_size.max(1) - 1;
+  (range.start & !mask)..((range.end + mask) & !mask)
+}
+
 
 // Implementations
 
@@ -278,9 +431,32 @@ impl Deref for Allocator {
 
 impl<'a> Drop for MappedMemory<'a> {
   fn drop(&mut self) {
-    if let Some((allocator, allocation)) = self.unmap {
+    if self.unmap {
       // CORRECTNESS: safe to `ok` - `unmap_memory` never fails.
-      allocator.wrapped.unmap_memory(allocation).ok();
+      self.allocator.wrapped.unmap_memory(self.allocation).ok();
     }
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use super::align_range;
+
+  #[test]
+  fn align_range_widens_to_atom_size() {
+    assert_eq!(align_range(3..70, 64), 0..128);
+    assert_eq!(align_range(64..65, 64), 64..128);
+  }
+
+  #[test]
+  fn align_range_keeps_aligned_ranges() {
+    assert_eq!(align_range(0..128, 64), 0..128);
+    assert_eq!(align_range(64..64, 64), 64..64);
+  }
+
+  #[test]
+  fn align_range_treats_zero_atom_size_as_one() {
+    assert_eq!(align_range(3..70, 0), 3..70);
+    assert_eq!(align_range(3..70, 1), 3..70);
+  }
+}
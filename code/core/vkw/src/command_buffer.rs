@@ -1,9 +1,12 @@
 use ash::version::DeviceV1_0;
-use ash::vk::{self, CommandBuffer, Fence, PipelineStageFlags, Result as VkError, Semaphore};
+use std::mem::size_of;
+
+use ash::vk::{self, Buffer, CommandBuffer, DeviceSize, DrawIndexedIndirectCommand, Fence, PipelineStageFlags, Queue, Result as VkError, Semaphore};
 use log::trace;
 use thiserror::Error;
 
 use crate::device::Device;
+use crate::device::draw_indirect_count_extension::DrawIndirectCount;
 
 // Beginning/ending command buffers
 
@@ -22,7 +25,7 @@ impl Device {
     let begin_info = vk::CommandBufferBeginInfo::builder()
       .flags(flags)
       ;
-    self.wrapped.begin_command_buffer(command_buffer, &begin_info)?;
+    self.raw().begin_command_buffer(command_buffer, &begin_info)?;
     trace!("Begun recording for command buffer {:?}", command_buffer);
     Ok(())
   }
@@ -35,7 +38,7 @@ pub struct CommandBufferEndError(#[from] VkError);
 impl Device {
   pub unsafe fn end_command_buffer(&self, command_buffer: CommandBuffer) -> Result<(), CommandBufferEndError> {
     trace!("Ending recording for command buffer {:?}", command_buffer);
-    Ok(self.wrapped.end_command_buffer(command_buffer)?)
+    Ok(self.raw().end_command_buffer(command_buffer)?)
   }
 }
 
@@ -46,8 +49,12 @@ impl Device {
 pub struct CommandBufferSubmitError(#[from] VkError);
 
 impl Device {
-  pub unsafe fn submit_command_buffers(
+  /// Submits `command_buffers` to `queue`, which must be one of the queues of this device and must be externally
+  /// synchronized. `signal_semaphores` may be waited on by submissions or presentation on other queues; see the
+  /// [module documentation](crate::device) for synchronizing graphics and present queues.
+  pub unsafe fn submit_command_buffers_to_queue(
     &self,
+    queue: Queue,
     command_buffers: &[CommandBuffer],
     wait_semaphores: &[Semaphore],
     wait_dst_stage_mask: &[PipelineStageFlags],
@@ -61,13 +68,25 @@ impl Device {
       .signal_semaphores(signal_semaphores)
       .build()
     ];
-    // TODO: don't assume that command pools are always submitted to the graphics queue.
     // CORRECTNESS: slices are taken by pointer but are alive until `queue_submit` is called.
-    self.wrapped.queue_submit(self.graphics_queue, &submits, fence)?;
-    trace!("Submitted command buffers {:?}", command_buffers);
+    self.raw().queue_submit(queue, &submits, fence)?;
+    trace!("Submitted command buffers {:?} to queue {:?}", command_buffers, queue);
     Ok(())
   }
 
+  /// Submits `command_buffers` to the graphics queue, which all command pools created by this device submit to.
+  pub unsafe fn submit_command_buffers(
+    &self,
+    command_buffers: &[CommandBuffer],
+    wait_semaphores: &[Semaphore],
+    wait_dst_stage_mask: &[PipelineStageFlags],
+    signal_semaphores: &[Semaphore],
+    fence: Fence,
+  ) -> Result<(), CommandBufferSubmitError> {
+    self.submit_command_buffers_to_queue(self.graphics_queue, command_buffers, wait_semaphores, wait_dst_stage_mask, signal_semaphores, fence)
+  }
+
+  /// Submits `command_buffer` to the graphics queue, see [submit_command_buffers](Self::submit_command_buffers).
   pub unsafe fn submit_command_buffer(
     &self,
     command_buffer: CommandBuffer,
@@ -79,3 +98,35 @@ impl Device {
     self.submit_command_buffers(&[command_buffer], wait_semaphores, wait_dst_stage_mask, signal_semaphores, fence.unwrap_or_default())
   }
 }
+
+// Indirect draw
+
+impl Device {
+  /// Draws `draw_count` tightly packed [DrawIndexedIndirectCommand]s from `buffer`, starting at `offset`.
+  pub unsafe fn cmd_draw_indexed_indirect(
+    &self,
+    command_buffer: CommandBuffer,
+    buffer: Buffer,
+    offset: DeviceSize,
+    draw_count: u32,
+  ) {
+    self.raw().cmd_draw_indexed_indirect(command_buffer, buffer, offset, draw_count, size_of::<DrawIndexedIndirectCommand>() as u32);
+  }
+
+  /// Draws tightly packed [DrawIndexedIndirectCommand]s from `buffer`, starting at `offset`, where the number of draws
+  /// is read from the `u32` in `count_buffer` at `count_buffer_offset`, and is at most `max_draw_count`. Requires the
+  /// `VK_KHR_draw_indirect_count` extension.
+  pub unsafe fn cmd_draw_indexed_indirect_count(
+    &self,
+    draw_indirect_count: &DrawIndirectCount,
+    command_buffer: CommandBuffer,
+    buffer: Buffer,
+    offset: DeviceSize,
+    count_buffer: Buffer,
+    count_buffer_offset: DeviceSize,
+    max_draw_count: u32,
+  ) {
+    let stride = size_of::<DrawIndexedIndirectCommand>() as u32;
+    draw_indirect_count.fp.cmd_draw_indexed_indirect_count_khr(command_buffer, buffer, offset, count_buffer, count_buffer_offset, max_draw_count, stride);
+  }
+}
@@ -1,9 +1,9 @@
 use ash::version::DeviceV1_0;
-use ash::vk::{self, CommandBuffer, CommandPool, Result as VkError};
+use ash::vk::{self, AccessFlags, BufferCopy, BufferMemoryBarrier, CommandBuffer, CommandPool, DependencyFlags, PipelineStageFlags, Result as VkError};
 use log::trace;
 use thiserror::Error;
 
-use crate::allocator::{Allocator, BufferAllocation};
+use crate::allocator::{Allocator, BufferAllocation, BufferAllocationError, MemoryInvalidateError};
 use crate::command_buffer::{CommandBufferBeginError, CommandBufferEndError, CommandBufferSubmitError};
 use crate::device::Device;
 use crate::sync::{FenceCreateError, FenceWaitError};
@@ -29,14 +29,14 @@ impl Device {
       // TODO: don't assume that command pools are always created for the graphics queue.
       .queue_family_index(self.graphics_queue_index)
       ;
-    let command_pool = self.wrapped.create_command_pool(&create_info, None)?;
+    let command_pool = self.raw().create_command_pool(&create_info, None)?;
     trace!("Created command pool {:?}", command_pool);
     Ok(command_pool)
   }
 
   pub unsafe fn destroy_command_pool(&self, command_pool: CommandPool) {
     trace!("Destroying command pool {:?}", command_pool);
-    self.wrapped.destroy_command_pool(command_pool, None)
+    self.raw().destroy_command_pool(command_pool, None)
   }
 }
 
@@ -54,7 +54,7 @@ impl Device {
       if release_resources { flags |= CommandPoolResetFlags::RELEASE_RESOURCES }
       flags
     };
-    self.wrapped.reset_command_pool(command_pool, flags)?;
+    self.raw().reset_command_pool(command_pool, flags)?;
     trace!("Reset command pool {:?}", command_pool);
     Ok(())
   }
@@ -75,7 +75,7 @@ impl Device {
       .level(level)
       .command_buffer_count(count)
       ;
-    let command_buffers = self.wrapped.allocate_command_buffers(&create_info)?;
+    let command_buffers = self.raw().allocate_command_buffers(&create_info)?;
     trace!("Allocated command buffers from {:?}", command_buffers);
     Ok(command_buffers)
   }
@@ -86,7 +86,7 @@ impl Device {
 
   pub unsafe fn free_command_buffers(&self, command_pool: CommandPool, command_buffers: &[CommandBuffer]) {
     trace!("Freeing command buffers {:?}", command_buffers);
-    self.wrapped.free_command_buffers(command_pool, command_buffers);
+    self.raw().free_command_buffers(command_pool, command_buffers);
   }
 
   pub unsafe fn free_command_buffer(&self, command_pool: CommandPool, command_buffer: CommandBuffer) {
@@ -177,3 +177,63 @@ impl<T> RecordedResource<T> for RecordedStagingBuffer<T> {
     self.result
   }
 }
+
+// Buffer readback
+
+#[derive(Error, Debug)]
+pub enum BufferReadError {
+  #[error(transparent)]
+  BufferAllocationFail(#[from] BufferAllocationError),
+  #[error(transparent)]
+  CopyFail(#[from] AllocateRecordSubmitWaitError),
+  #[error(transparent)]
+  MemoryInvalidateFail(#[from] MemoryInvalidateError),
+}
+
+impl Device {
+  /// Reads back the first `size` bytes of `buffer`, which can be GPU-only but must have been created with
+  /// `BufferUsageFlags::TRANSFER_SRC`, by copying it into a temporary readback buffer with a command buffer from
+  /// `command_pool` and waiting for the copy. Waits for all prior writes to `buffer` submitted to the queue. Intended
+  /// for debugging and testing GPU passes, as it stalls the CPU.
+  pub unsafe fn read_buffer(
+    &self,
+    allocator: &Allocator,
+    command_pool: CommandPool,
+    buffer: &BufferAllocation,
+    size: usize,
+  ) -> Result<Vec<u8>, BufferReadError> {
+    let readback = allocator.create_readback_buffer_mapped(size)?;
+    let result = (|| -> Result<Vec<u8>, BufferReadError> {
+      self.allocate_record_submit_wait(command_pool, |command_buffer| {
+        // Make prior writes to the buffer, by any command, available to the copy.
+        let src_barrier = BufferMemoryBarrier::builder()
+          .src_access_mask(AccessFlags::MEMORY_WRITE)
+          .dst_access_mask(AccessFlags::TRANSFER_READ)
+          .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+          .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+          .buffer(buffer.buffer)
+          .size(vk::WHOLE_SIZE)
+          .build();
+        self.cmd_pipeline_barrier(command_buffer, PipelineStageFlags::ALL_COMMANDS, PipelineStageFlags::TRANSFER, DependencyFlags::empty(), &[], &[src_barrier], &[]);
+        self.cmd_copy_buffer(command_buffer, buffer.buffer, readback.buffer, &[BufferCopy::builder().size(size as u64).build()]);
+        // Make the copy visible to the host.
+        let dst_barrier = BufferMemoryBarrier::builder()
+          .src_access_mask(AccessFlags::TRANSFER_WRITE)
+          .dst_access_mask(AccessFlags::HOST_READ)
+          .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+          .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+          .buffer(readback.buffer)
+          .size(vk::WHOLE_SIZE)
+          .build();
+        self.cmd_pipeline_barrier(command_buffer, PipelineStageFlags::TRANSFER, PipelineStageFlags::HOST, DependencyFlags::empty(), &[], &[dst_barrier], &[]);
+        Ok(())
+      })?;
+      // CORRECTNESS: safe to `unwrap` - the readback buffer is created persistently mapped.
+      let mapped = readback.get_mapped_data(allocator).unwrap();
+      mapped.invalidate(..size)?;
+      Ok(std::slice::from_raw_parts(mapped.ptr() as *const u8, size).to_vec())
+    })();
+    readback.destroy(allocator);
+    result
+  }
+}
@@ -0,0 +1,35 @@
+use ash::version::DeviceV1_0;
+use ash::vk::{ComputePipelineCreateInfo, Pipeline, PipelineCache, Result as VkError};
+use log::debug;
+use thiserror::Error;
+
+use crate::device::Device;
+
+// Compute pipeline creation. Destroy with `Device::destroy_pipeline`.
+
+#[derive(Error, Debug)]
+#[error("Failed to create compute pipeline: {0:?}")]
+pub struct ComputePipelineCreateError(#[from] VkError);
+
+impl Device {
+  pub unsafe fn create_compute_pipelines(
+    &self,
+    pipeline_cache: PipelineCache,
+    create_infos: &[ComputePipelineCreateInfo]
+  ) -> Result<Vec<Pipeline>, ComputePipelineCreateError> {
+    let pipelines = match self.raw().create_compute_pipelines(pipeline_cache, create_infos, None) {
+      Err((_, e)) => Err(e),
+      Ok(v) => Ok(v),
+    }?;
+    debug!("Created compute pipelines {:?}", pipelines);
+    Ok(pipelines)
+  }
+
+  pub unsafe fn create_compute_pipeline(
+    &self,
+    pipeline_cache: PipelineCache,
+    create_info: &ComputePipelineCreateInfo
+  ) -> Result<Pipeline, ComputePipelineCreateError> {
+    Ok(self.create_compute_pipelines(pipeline_cache, &[*create_info])?[0])
+  }
+}
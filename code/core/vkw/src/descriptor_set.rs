@@ -29,6 +29,10 @@ pub fn uniform_layout_binding(binding: u32, count: u32, stage_flags: ShaderStage
   layout_binding(binding, DescriptorType::UNIFORM_BUFFER, count, stage_flags)
 }
 
+pub fn storage_buffer_layout_binding(binding: u32, count: u32, stage_flags: ShaderStageFlags) -> DescriptorSetLayoutBinding {
+  layout_binding(binding, DescriptorType::STORAGE_BUFFER, count, stage_flags)
+}
+
 pub fn sampler_layout_binding(binding: u32, count: u32) -> DescriptorSetLayoutBinding {
   layout_binding(binding, DescriptorType::COMBINED_IMAGE_SAMPLER, count, ShaderStageFlags::FRAGMENT)
 }
@@ -52,14 +56,14 @@ impl Device {
       .bindings(bindings)
       .push_next(&mut ext)
       ;
-    let descriptor_set_layout = self.wrapped.create_descriptor_set_layout(&create_info, None)?;
+    let descriptor_set_layout = self.raw().create_descriptor_set_layout(&create_info, None)?;
     debug!("Created descriptor set layout {:?}", descriptor_set_layout);
     Ok(descriptor_set_layout)
   }
 
   pub unsafe fn destroy_descriptor_set_layout(&self, layout: DescriptorSetLayout) {
     debug!("Destroying descriptor set layout {:?}", layout);
-    self.wrapped.destroy_descriptor_set_layout(layout, None)
+    self.raw().destroy_descriptor_set_layout(layout, None)
   }
 }
 
@@ -73,6 +77,10 @@ pub fn uniform_pool_size(count: u32) -> DescriptorPoolSize {
   pool_size(DescriptorType::UNIFORM_BUFFER, count)
 }
 
+pub fn storage_buffer_pool_size(count: u32) -> DescriptorPoolSize {
+  pool_size(DescriptorType::STORAGE_BUFFER, count)
+}
+
 pub fn sampler_pool_size(count: u32) -> DescriptorPoolSize {
   pool_size(DescriptorType::COMBINED_IMAGE_SAMPLER, count)
 }
@@ -84,19 +92,44 @@ pub fn sampler_pool_size(count: u32) -> DescriptorPoolSize {
 pub struct DescriptorPoolCreateError(#[from] VkError);
 
 impl Device {
-  pub unsafe fn create_descriptor_pool(&self, max_sets: u32, pool_sizes: &[DescriptorPoolSize]) -> Result<DescriptorPool, DescriptorPoolCreateError> {
+  /// Creates a descriptor pool. Descriptor sets can only be [freed](Self::free_descriptor_sets) individually if
+  /// `free_individual_sets` is true, but can always be freed all at once by [resetting](Self::reset_descriptor_pool) the
+  /// pool.
+  pub unsafe fn create_descriptor_pool(
+    &self,
+    max_sets: u32,
+    pool_sizes: &[DescriptorPoolSize],
+    free_individual_sets: bool,
+  ) -> Result<DescriptorPool, DescriptorPoolCreateError> {
+    use vk::DescriptorPoolCreateFlags;
+    let flags = if free_individual_sets { DescriptorPoolCreateFlags::FREE_DESCRIPTOR_SET } else { DescriptorPoolCreateFlags::empty() };
     let create_info = vk::DescriptorPoolCreateInfo::builder()
+      .flags(flags)
       .max_sets(max_sets)
       .pool_sizes(&pool_sizes)
       ;
-    let descriptor_pool = self.wrapped.create_descriptor_pool(&create_info, None)?;
+    let descriptor_pool = self.raw().create_descriptor_pool(&create_info, None)?;
     debug!("Created descriptor pool {:?}", descriptor_pool);
     Ok(descriptor_pool)
   }
 
   pub unsafe fn destroy_descriptor_pool(&self, pool: DescriptorPool) {
     debug!("Destroying descriptor pool {:?}", pool);
-    self.wrapped.destroy_descriptor_pool(pool, None);
+    self.raw().destroy_descriptor_pool(pool, None);
+  }
+}
+
+// Descriptor pool reset
+
+#[derive(Error, Debug)]
+#[error("Failed to reset descriptor pool: {0:?}")]
+pub struct DescriptorPoolResetError(#[from] VkError);
+
+impl Device {
+  /// Frees all descriptor sets allocated from `pool`, which must not be in use.
+  pub unsafe fn reset_descriptor_pool(&self, pool: DescriptorPool) -> Result<(), DescriptorPoolResetError> {
+    self.raw().reset_descriptor_pool(pool, vk::DescriptorPoolResetFlags::empty())?;
+    Ok(())
   }
 }
 
@@ -113,7 +146,7 @@ impl Device {
       .descriptor_pool(pool)
       .set_layouts(&set_layouts)
       ;
-    let descriptor_sets = self.wrapped.allocate_descriptor_sets(&create_info)?;
+    let descriptor_sets = self.raw().allocate_descriptor_sets(&create_info)?;
     debug!("Created descriptor sets {:?}", descriptor_sets);
     Ok(descriptor_sets)
   }
@@ -122,8 +155,9 @@ impl Device {
     Ok(self.allocate_descriptor_sets(pool, layout, 1)?[0])
   }
 
+  /// Frees `descriptor_sets` allocated from `pool`, which must have been created with `free_individual_sets`.
   pub unsafe fn free_descriptor_sets(&self, pool: DescriptorPool, descriptor_sets: &[DescriptorSet]) {
-    self.wrapped.free_descriptor_sets(pool, descriptor_sets);
+    self.raw().free_descriptor_sets(pool, descriptor_sets);
   }
 
   pub unsafe fn free_descriptor_set(&self, pool: DescriptorPool, descriptor_set: DescriptorSet) {
@@ -131,6 +165,72 @@ impl Device {
   }
 }
 
+// Descriptor allocator
+
+/// Allocates descriptor sets from a chain of descriptor pools, creating a new pool when the current pool is exhausted.
+/// Descriptor sets are not freed individually, but all at once by [resetting](Self::reset) the allocator.
+pub struct DescriptorAllocator {
+  sets_per_pool: u32,
+  pool_sizes: Vec<DescriptorPoolSize>,
+  /// Pools in creation order, the last being the pool that is allocated from.
+  pools: Vec<DescriptorPool>,
+  /// Pools that were reset, which are reused before creating new pools.
+  free_pools: Vec<DescriptorPool>,
+}
+
+#[derive(Error, Debug)]
+pub enum DescriptorAllocateError {
+  #[error(transparent)]
+  PoolCreateFail(#[from] DescriptorPoolCreateError),
+  #[error(transparent)]
+  AllocateFail(#[from] DescriptorSetsAllocateError),
+}
+
+impl DescriptorAllocator {
+  /// Creates an allocator that creates pools of `sets_per_pool` descriptor sets with descriptor counts `pool_sizes`.
+  /// No pools are created until the first allocation.
+  pub fn new(sets_per_pool: u32, pool_sizes: &[DescriptorPoolSize]) -> Self {
+    Self { sets_per_pool, pool_sizes: pool_sizes.to_vec(), pools: Vec::new(), free_pools: Vec::new() }
+  }
+
+  /// Allocates a descriptor set with `layout`, from a new pool if the current pool is exhausted.
+  pub unsafe fn allocate(&mut self, device: &Device, layout: DescriptorSetLayout) -> Result<DescriptorSet, DescriptorAllocateError> {
+    if let Some(pool) = self.pools.last() {
+      match device.allocate_descriptor_set(*pool, layout) {
+        Ok(descriptor_set) => return Ok(descriptor_set),
+        Err(DescriptorSetsAllocateError(VkError::ERROR_OUT_OF_POOL_MEMORY)) |
+        Err(DescriptorSetsAllocateError(VkError::ERROR_FRAGMENTED_POOL)) => {}
+        Err(e) => return Err(e.into()),
+      }
+    }
+    let pool = match self.free_pools.pop() {
+      Some(pool) => pool,
+      None => device.create_descriptor_pool(self.sets_per_pool, &self.pool_sizes, false)?,
+    };
+    self.pools.push(pool);
+    Ok(device.allocate_descriptor_set(pool, layout)?)
+  }
+
+  /// Number of pools that descriptor sets have been allocated from since creation or the last reset.
+  #[inline]
+  pub fn pool_count(&self) -> usize { self.pools.len() }
+
+  /// Frees all allocated descriptor sets, keeping the pools for reuse. The descriptor sets must not be in use.
+  pub unsafe fn reset(&mut self, device: &Device) -> Result<(), DescriptorPoolResetError> {
+    for pool in self.pools.drain(..) {
+      device.reset_descriptor_pool(pool)?;
+      self.free_pools.push(pool);
+    }
+    Ok(())
+  }
+
+  pub unsafe fn destroy(&mut self, device: &Device) {
+    for pool in self.pools.drain(..).chain(self.free_pools.drain(..)) {
+      device.destroy_descriptor_pool(pool);
+    }
+  }
+}
+
 // Descriptor set update
 
 #[derive(Default)]
@@ -176,9 +276,35 @@ impl DescriptorSetUpdateBuilder {
     self.add_buffer_write(dst_set, dst_binding, dst_array_element, DescriptorType::UNIFORM_BUFFER, buffer, buffer_offset, buffer_range)
   }
 
+  pub fn add_storage_buffer_write(
+    self,
+    dst_set: DescriptorSet,
+    dst_binding: u32,
+    dst_array_element: u32,
+    buffer: Buffer,
+    buffer_offset: DeviceSize,
+    buffer_range: DeviceSize
+  ) -> Self {
+    self.add_buffer_write(dst_set, dst_binding, dst_array_element, DescriptorType::STORAGE_BUFFER, buffer, buffer_offset, buffer_range)
+  }
+
+  /// Adds a single write of combined image samplers `images` to consecutive array elements of `dst_binding`, starting
+  /// at `dst_array_element`.
+  pub fn add_image_array_write(
+    self,
+    dst_set: DescriptorSet,
+    dst_binding: u32,
+    dst_array_element: u32,
+    images: &[(Sampler, ImageView, ImageLayout)],
+  ) -> Self {
+    let write = WriteDescriptorSetBuilder::new(dst_set, dst_binding, dst_array_element, DescriptorType::COMBINED_IMAGE_SAMPLER)
+      .image_infos(images.iter().map(|&(sampler, image_view, image_layout)| DescriptorImageInfo { sampler, image_view, image_layout }).collect());
+    self.add_write(write)
+  }
+
   pub unsafe fn do_update(&self, device: &Device) {
     let writes: Vec<_> = self.writes.iter().map(|w| w.build()).collect();
-    device.wrapped.update_descriptor_sets(&writes, &[]);
+    device.raw().update_descriptor_sets(&writes, &[]);
   }
 }
 
@@ -0,0 +1,66 @@
+use std::ffi::CStr;
+use std::mem;
+
+use ash::version::InstanceV1_0;
+use ash::vk::{self, DeviceAddress, ExtBufferDeviceAddressFn, PhysicalDeviceBufferAddressFeaturesEXT};
+use byte_strings::c_str;
+
+use crate::allocator::BufferAllocation;
+use crate::device::{Device, DeviceFeatures, DeviceFeaturesQuery};
+use crate::device::feature_chain::FeatureStruct;
+
+// Wrapper
+
+/// Function pointers of the `VK_EXT_buffer_device_address` extension. Only create when the extension is enabled.
+pub struct BufferDeviceAddress {
+  pub fp: ExtBufferDeviceAddressFn,
+}
+
+impl BufferDeviceAddress {
+  pub fn new(instance: &ash::Instance, device: &ash::Device) -> Self {
+    let fp = ExtBufferDeviceAddressFn::load(|name| unsafe {
+      mem::transmute(instance.get_device_proc_addr(device.handle(), name.as_ptr()))
+    });
+    Self { fp }
+  }
+}
+
+unsafe impl FeatureStruct for PhysicalDeviceBufferAddressFeaturesEXT {}
+
+// API
+
+impl DeviceFeatures {
+  pub fn is_buffer_device_address_enabled(&self) -> bool {
+    self.is_extension_enabled(self::BUFFER_DEVICE_ADDRESS_EXTENSION_NAME) &&
+      self.get_enabled_extension_features::<PhysicalDeviceBufferAddressFeaturesEXT>()
+        .map_or(false, |f| f.buffer_device_address == vk::TRUE)
+  }
+}
+
+impl DeviceFeaturesQuery {
+  /// Requires the `VK_EXT_buffer_device_address` extension and its `bufferDeviceAddress` feature, for getting device
+  /// addresses of buffers created with `BufferUsageFlags::SHADER_DEVICE_ADDRESS_EXT`.
+  pub fn require_buffer_device_address(&mut self) {
+    self.require_extension(self::BUFFER_DEVICE_ADDRESS_EXTENSION_NAME);
+    self.require_extension_features(PhysicalDeviceBufferAddressFeaturesEXT::builder()
+      .buffer_device_address(true)
+      .build()
+    );
+  }
+}
+
+impl Device {
+  /// Gets the device address of `buffer`, which must have been created with
+  /// `BufferUsageFlags::SHADER_DEVICE_ADDRESS_EXT`. Panics if buffer device addresses are not
+  /// [enabled](DeviceFeaturesQuery::require_buffer_device_address).
+  pub unsafe fn get_buffer_device_address(&self, buffer: &BufferAllocation) -> DeviceAddress {
+    let buffer_device_address = self.buffer_device_address.as_ref()
+      .expect("Attempted to get buffer device address, but buffer device addresses are not enabled");
+    let info = vk::BufferDeviceAddressInfoEXT::builder().buffer(buffer.buffer);
+    buffer_device_address.fp.get_buffer_device_address_ext(self.raw().handle(), &*info)
+  }
+}
+
+// Extension name
+
+pub const BUFFER_DEVICE_ADDRESS_EXTENSION_NAME: &'static CStr = c_str!("VK_EXT_buffer_device_address");
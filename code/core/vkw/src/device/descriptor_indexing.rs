@@ -23,7 +23,7 @@ impl DeviceFeaturesQuery {
   }
 
   pub fn require_descriptor_indexing_features(&mut self, required_features: PhysicalDeviceDescriptorIndexingFeaturesEXT) {
-    self.descriptor_indexing_features = required_features;
+    self.require_extension_features(required_features);
   }
 }
 
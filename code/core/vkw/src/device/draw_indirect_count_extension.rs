@@ -0,0 +1,46 @@
+use std::ffi::CStr;
+use std::mem;
+
+use ash::version::InstanceV1_0;
+use ash::vk::KhrDrawIndirectCountFn;
+use byte_strings::c_str;
+
+use crate::device::{Device, DeviceFeatures, DeviceFeaturesQuery};
+
+// Wrapper
+
+/// Function pointers of the `VK_KHR_draw_indirect_count` extension. Only create when the extension is enabled.
+pub struct DrawIndirectCount {
+  pub fp: KhrDrawIndirectCountFn,
+}
+
+impl DrawIndirectCount {
+  pub fn new(device: &Device) -> Self {
+    let fp = KhrDrawIndirectCountFn::load(|name| unsafe {
+      mem::transmute(device.instance.get_device_proc_addr(device.raw().handle(), name.as_ptr()))
+    });
+    Self { fp }
+  }
+}
+
+// API
+
+impl DeviceFeatures {
+  pub fn is_draw_indirect_count_extension_enabled(&self) -> bool {
+    self.is_extension_enabled(self::DRAW_INDIRECT_COUNT_EXTENSION_NAME)
+  }
+}
+
+impl DeviceFeaturesQuery {
+  pub fn want_draw_indirect_count_extension(&mut self) {
+    self.want_extension(self::DRAW_INDIRECT_COUNT_EXTENSION_NAME);
+  }
+
+  pub fn require_draw_indirect_count_extension(&mut self) {
+    self.require_extension(self::DRAW_INDIRECT_COUNT_EXTENSION_NAME);
+  }
+}
+
+// Extension name
+
+pub const DRAW_INDIRECT_COUNT_EXTENSION_NAME: &'static CStr = c_str!("VK_KHR_draw_indirect_count");
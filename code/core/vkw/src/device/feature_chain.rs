@@ -0,0 +1,95 @@
+use std::any::{Any, TypeId};
+use std::fmt::{self, Debug, Formatter};
+use std::os::raw::c_void;
+use std::ptr;
+
+use ash::vk::{self, BaseOutStructure};
+
+// Feature struct
+
+/// Extension feature structs that can be chained into device creation through their `p_next` pointers.
+///
+/// # Safety
+///
+/// Implementors must be `#[repr(C)]` Vulkan structs that start with `s_type` and `p_next` fields (i.e., have the layout
+/// of [BaseOutStructure]), and that extend `VkDeviceCreateInfo`.
+pub unsafe trait FeatureStruct: Copy + 'static {}
+
+unsafe impl FeatureStruct for vk::PhysicalDeviceDescriptorIndexingFeaturesEXT {}
+
+unsafe impl FeatureStruct for vk::PhysicalDevice16BitStorageFeatures {}
+
+unsafe impl FeatureStruct for vk::PhysicalDeviceMultiviewFeatures {}
+
+unsafe impl FeatureStruct for vk::PhysicalDeviceSamplerYcbcrConversionFeatures {}
+
+// Feature chain
+
+/// Owned chain of extension feature structs, at most one per type, which are linked through their `p_next` pointers
+/// only while creating a device.
+#[derive(Default)]
+pub struct FeatureChain {
+  structs: Vec<(Box<dyn Any>, &'static str)>,
+}
+
+// CORRECTNESS: `p_next` pointers of the structs are only set while creating a device, during which the chain is not
+// shared, so it is safe to be Sent. Shared references only allow reading the structs, so it is also safe to be Synced.
+unsafe impl Send for FeatureChain {}
+unsafe impl Sync for FeatureChain {}
+
+impl FeatureChain {
+  pub fn new() -> Self { Self::default() }
+
+  /// Adds `features` to the chain, replacing the struct of the same type if one was added before.
+  pub fn add<T: FeatureStruct>(&mut self, mut features: T) {
+    unsafe { (*(&mut features as *mut T as *mut BaseOutStructure)).p_next = ptr::null_mut(); }
+    let features: Box<dyn Any> = Box::new(features);
+    if let Some(index) = self.index_of::<T>() {
+      self.structs[index].0 = features;
+    } else {
+      self.structs.push((features, std::any::type_name::<T>()));
+    }
+  }
+
+  /// Gets the struct of type `T` in the chain, if any.
+  pub fn get<T: FeatureStruct>(&self) -> Option<&T> {
+    self.structs.iter().find_map(|(s, _)| s.downcast_ref::<T>())
+  }
+
+  #[inline]
+  pub fn len(&self) -> usize { self.structs.len() }
+
+  #[inline]
+  pub fn is_empty(&self) -> bool { self.structs.is_empty() }
+
+  /// Links the structs through their `p_next` pointers, returning a pointer to the first struct, or null if the chain
+  /// is empty. The pointer is valid until the chain is modified or dropped, and the chain must be
+  /// [unlinked](Self::unlink) after use.
+  pub(crate) unsafe fn link(&mut self) -> *mut c_void {
+    let mut next = ptr::null_mut();
+    for (s, _) in self.structs.iter_mut().rev() {
+      let base = &mut **s as *mut dyn Any as *mut BaseOutStructure;
+      (*base).p_next = next;
+      next = base;
+    }
+    next as *mut c_void
+  }
+
+  /// Resets the `p_next` pointers of the structs to null.
+  pub(crate) fn unlink(&mut self) {
+    for (s, _) in self.structs.iter_mut() {
+      let base = &mut **s as *mut dyn Any as *mut BaseOutStructure;
+      unsafe { (*base).p_next = ptr::null_mut(); }
+    }
+  }
+
+  fn index_of<T: FeatureStruct>(&self) -> Option<usize> {
+    self.structs.iter().position(|(s, _)| (**s).type_id() == TypeId::of::<T>())
+  }
+}
+
+impl Debug for FeatureChain {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    f.debug_list().entries(self.structs.iter().map(|(_, name)| name)).finish()
+  }
+}
@@ -3,11 +3,32 @@
 //! Safe usage prohibits:
 //!
 //! * Calling methods or getting fields of [`Device`] when its creating [`Instance`] has been destroyed.
-//! * Calling methods or getting fields of [`Device`] after it has been [destroyed](Device::destroy).
+//! * Calling methods or getting fields of [`Device`] after it has been [destroyed](Device::destroy). In debug builds,
+//!   calling device functions, through its methods, [`Device::raw`], or [`Deref`], or destroying again panics instead.
 //!
 //! # Destruction
 //!
 //! A [`Device`] must be manually destroyed with [`Device::destroy`].
+//!
+//! # Thread safety
+//!
+//! A [`Device`] is `Send` and `Sync`, as Vulkan device functions may be called from any thread, and its caches and
+//! trackers are behind mutexes. Vulkan requires external synchronization of some objects, such as queues, command pools,
+//! and descriptor pools, when used through a shared [`Device`]; the (unsafe) functions using them must be synchronized
+//! by the caller.
+//!
+//! # Queues
+//!
+//! Command pools are created for, and command buffers are submitted to, the graphics queue, unless submitted to an
+//! explicit queue with [`Device::submit_command_buffers_to_queue`]. Presentation uses the present queue, which may be
+//! of a different queue family than the graphics queue ([`Device::is_present_queue_separate`]). In that case:
+//!
+//! * Swapchain images are created with concurrent sharing between both queue families, so no queue family ownership
+//!   transfers are needed.
+//! * Rendering and presentation are ordered by a semaphore that the graphics submission signals and presentation waits
+//!   on. This is the same handshake as with a single queue; semaphores work across queues of the same device.
+//! * Resources other than swapchain images that are used on both queues must be created with concurrent sharing, or
+//!   transferred with queue family ownership transfer barriers.
 
 use std::borrow::Borrow;
 use std::collections::HashSet;
@@ -23,56 +44,82 @@ use ash::{
   },
   vk::{self, PhysicalDevice as VkPhysicalDevice, PhysicalDeviceFeatures, Queue, QueueFlags, Result as VkError},
 };
-use ash::vk::PhysicalDeviceDescriptorIndexingFeaturesEXT;
 use log::debug;
 use thiserror::Error;
 
+use crate::device::buffer_device_address_extension::BufferDeviceAddress;
+use crate::device::feature_chain::{FeatureChain, FeatureStruct};
+use crate::graphics_pipeline::{PipelineLayoutCache, RenderPassTracker};
 use crate::instance::Instance;
 use crate::instance::surface_extension::Surface;
+use crate::util::DestroyedFlag;
 
 pub mod swapchain_extension;
 pub mod descriptor_indexing;
+pub mod draw_indirect_count_extension;
+pub mod buffer_device_address_extension;
+pub mod feature_chain;
 
 // Wrapper
 
 pub struct Device {
   pub instance: VkInstance,
   pub physical_device: VkPhysicalDevice,
-  pub wrapped: VkDevice,
+  wrapped: VkDevice,
   pub graphics_queue_index: u32,
   pub graphics_queue: Queue,
   pub present_queue_index: u32,
   pub present_queue: Queue,
   pub features: DeviceFeatures,
+  /// Function pointers for getting buffer device addresses, if enabled.
+  buffer_device_address: Option<BufferDeviceAddress>,
+  pub(crate) pipeline_layout_cache: PipelineLayoutCache,
+  pub(crate) render_pass_tracker: RenderPassTracker,
+  destroyed: DestroyedFlag,
 }
 
+crate::assert_send_sync!(Device);
+
 #[derive(Debug)]
 pub struct DeviceFeatures {
   pub enabled_extensions: HashSet<CString>,
   pub enabled_features: PhysicalDeviceFeatures,
-  pub enabled_descriptor_indexing_features: PhysicalDeviceDescriptorIndexingFeaturesEXT
+  /// Extension feature structs that were requested at creation.
+  pub enabled_feature_chain: FeatureChain,
 }
 
 impl DeviceFeatures {
   fn new(
     enabled_extensions: HashSet<CString>,
     enabled_features: PhysicalDeviceFeatures,
-    enabled_descriptor_indexing_features: PhysicalDeviceDescriptorIndexingFeaturesEXT
+    enabled_feature_chain: FeatureChain,
   ) -> Self {
     Self {
       enabled_extensions,
       enabled_features,
-      enabled_descriptor_indexing_features
+      enabled_feature_chain,
     }
   }
 
   pub fn is_extension_enabled<B: Borrow<CStr> + ?Sized>(&self, extension_name: &B) -> bool {
     self.enabled_extensions.contains(extension_name.borrow())
   }
-}
 
-// CORRECTNESS: *mut c_void in PhysicalDeviceDescriptorIndexingFeaturesEXT is not used, so it is safe to be Sent.
-unsafe impl Send for DeviceFeatures {}
+  /// Whether non-solid polygon modes (e.g. `PolygonMode::LINE`) are enabled.
+  pub fn is_fill_mode_non_solid_enabled(&self) -> bool {
+    self.enabled_features.fill_mode_non_solid == vk::TRUE
+  }
+
+  /// Whether line widths other than 1.0 are enabled.
+  pub fn is_wide_lines_enabled(&self) -> bool {
+    self.enabled_features.wide_lines == vk::TRUE
+  }
+
+  /// Gets the extension feature struct of type `T` that was requested at creation, if any.
+  pub fn get_enabled_extension_features<T: FeatureStruct>(&self) -> Option<&T> {
+    self.enabled_feature_chain.get()
+  }
+}
 
 // Creation and destruction
 
@@ -81,7 +128,9 @@ pub struct DeviceFeaturesQuery {
   wanted_extensions: HashSet<CString>,
   required_extensions: HashSet<CString>,
   required_features: PhysicalDeviceFeatures,
-  descriptor_indexing_features: PhysicalDeviceDescriptorIndexingFeaturesEXT,
+  wanted_fill_mode_non_solid: bool,
+  wanted_wide_lines: bool,
+  feature_chain: FeatureChain,
 }
 
 impl DeviceFeaturesQuery {
@@ -98,6 +147,22 @@ impl DeviceFeaturesQuery {
   pub fn require_features(&mut self, required_features: PhysicalDeviceFeatures) {
     self.required_features = required_features;
   }
+
+  /// Enables non-solid polygon modes if the physical device supports them.
+  pub fn want_fill_mode_non_solid(&mut self) {
+    self.wanted_fill_mode_non_solid = true;
+  }
+
+  /// Enables line widths other than 1.0 if the physical device supports them.
+  pub fn want_wide_lines(&mut self) {
+    self.wanted_wide_lines = true;
+  }
+
+  /// Requires the features in extension feature struct `features`, replacing previously required features of the same
+  /// type. The extension or Vulkan version that provides the struct must be enabled.
+  pub fn require_extension_features<T: FeatureStruct>(&mut self, features: T) {
+    self.feature_chain.add(features);
+  }
 }
 
 /*
@@ -132,7 +197,9 @@ impl Device {
       wanted_extensions,
       required_extensions,
       required_features,
-      mut descriptor_indexing_features,
+      wanted_fill_mode_non_solid,
+      wanted_wide_lines,
+      mut feature_chain,
     } = features_query;
 
     let physical_devices = unsafe { instance.enumerate_physical_devices() }
@@ -149,7 +216,18 @@ impl Device {
         }
       };
 
-      // TODO: check features
+      // TODO: check required features
+      let enabled_features = {
+        let mut enabled_features = required_features;
+        let supported_features = unsafe { instance.get_physical_device_features(physical_device) };
+        if wanted_fill_mode_non_solid && enabled_features.fill_mode_non_solid != vk::TRUE {
+          enabled_features.fill_mode_non_solid = supported_features.fill_mode_non_solid;
+        }
+        if wanted_wide_lines && enabled_features.wide_lines != vk::TRUE {
+          enabled_features.wide_lines = supported_features.wide_lines;
+        }
+        enabled_features
+      };
 
       let (graphics_queue_index, present_queue_index) = {
         let mut graphics = None;
@@ -193,23 +271,28 @@ impl Device {
         }
         infos
       };
-      // Create a copy of descriptor_indexing_features for usage in DeviceFeatures, where the p_next pointer is 0 and unused.
-      let descriptor_indexing_features_copy = descriptor_indexing_features;
       let mut create_info = DeviceCreateInfo::builder()
         .queue_create_infos(&queue_create_infos)
         .enabled_extension_names(&enabled_extensions_raw)
-        .enabled_features(&required_features)
-        ;
-      create_info = create_info.push_next(&mut descriptor_indexing_features);
-      // CORRECTNESS: `queue_priorities` is taken by pointer but is alive until `create_device` is called.
-      let device = unsafe { instance.create_device(physical_device, &create_info, None) }
-        .map_err(|e| DeviceCreateFail(e))?;
+        .enabled_features(&enabled_features)
+        .build();
+      create_info.p_next = unsafe { feature_chain.link() };
+      // CORRECTNESS: `queue_priorities` and `feature_chain` are taken by pointer but are alive until `create_device` is
+      // called.
+      let device = unsafe { instance.create_device(physical_device, &create_info, None) };
+      feature_chain.unlink();
+      let device = device.map_err(|e| DeviceCreateFail(e))?;
       debug!("Created device {:?}", device.handle());
       let graphics_queue = unsafe { device.get_device_queue(graphics_queue_index, 0) };
       let present_queue = unsafe { device.get_device_queue(present_queue_index, 0) };
-      let features = DeviceFeatures::new(enabled_extensions, required_features, descriptor_indexing_features_copy);
+      let features = DeviceFeatures::new(enabled_extensions, enabled_features, feature_chain);
+      let buffer_device_address = if features.is_buffer_device_address_enabled() {
+        Some(BufferDeviceAddress::new(instance.raw(), &device))
+      } else {
+        None
+      };
       return Ok(Self {
-        instance: instance.wrapped.clone(),
+        instance: instance.raw().clone(),
         physical_device,
         wrapped: device,
         graphics_queue_index,
@@ -217,22 +300,50 @@ impl Device {
         present_queue_index,
         present_queue,
         features,
+        buffer_device_address,
+        pipeline_layout_cache: PipelineLayoutCache::default(),
+        render_pass_tracker: RenderPassTracker::default(),
+        destroyed: DestroyedFlag::default(),
       });
     }
     Err(NoSuitablePhysicalDeviceFound)
   }
 
   pub unsafe fn destroy(&mut self) {
+    self.destroyed.assert_not_destroyed("Device");
+    self.destroy_pipeline_layout_cache();
     debug!("Destroying device {:?}", self.wrapped.handle());
     self.wrapped.destroy_device(None);
+    self.destroyed.set();
   }
 }
 
+// Raw access
+
+impl Device {
+  /// Gets the wrapped ash device. In debug builds, panics if this device has been destroyed.
+  #[inline]
+  #[track_caller]
+  pub fn raw(&self) -> &VkDevice {
+    self.destroyed.assert_not_destroyed("Device");
+    &self.wrapped
+  }
+}
+
+// Queues
+
+impl Device {
+  /// Whether the present queue is of a different queue family than the graphics queue.
+  #[inline]
+  pub fn is_present_queue_separate(&self) -> bool { self.present_queue_index != self.graphics_queue_index }
+}
+
 // Implementations
 
 impl Deref for Device {
   type Target = VkDevice;
 
   #[inline]
-  fn deref(&self) -> &Self::Target { &self.wrapped }
+  #[track_caller]
+  fn deref(&self) -> &Self::Target { self.raw() }
 }
@@ -3,11 +3,18 @@
 //! Safe usage prohibits:
 //!
 //! * Calling methods or getting fields of [`Swapchain`] when its creating [`Instance`], [`Device`], or [`Surface`] has been destroyed.
-//! * Calling methods or getting fields of [`Swapchain`] after it has been [destroyed](Swapchain::destroy).
+//! * Calling methods or getting fields of [`Swapchain`] after it has been [destroyed](Swapchain::destroy). In debug
+//!   builds, calling its methods or dereferencing it panics instead.
 //!
 //! # Destruction
 //!
 //! A [`Swapchain`] must be manually destroyed with [`Swapchain::destroy`].
+//!
+//! # Thread safety
+//!
+//! A [`Swapchain`] is `Send` and `Sync`, as it only holds handles and function pointers. Acquiring images and presenting
+//! require external synchronization of the swapchain and present queue, which the caller must provide, for example by
+//! presenting from a single thread through a [`SwapchainPresentLoader`].
 
 use std::ffi::CStr;
 use std::num::NonZeroU32;
@@ -24,6 +31,7 @@ use crate::image::view::ImageViewCreateError;
 use crate::instance::Instance;
 use crate::instance::surface_extension::{Surface, SurfaceFormatError};
 use crate::timeout::Timeout;
+use crate::util::DestroyedFlag;
 
 // Wrapper
 
@@ -34,23 +42,65 @@ pub struct Swapchain {
   pub extent: Extent2D,
   pub features_query: SwapchainFeaturesQuery,
   pub features: SwapchainFeatures,
+  destroyed: DestroyedFlag,
 }
 
+crate::assert_send_sync!(Swapchain);
+
 #[derive(Debug)]
 pub struct SwapchainFeatures {
+  /// Minimum number of images requested at creation: the wanted image count clamped to the surface capabilities.
   pub min_image_count: u32,
+  /// Number of images actually created, which can be higher than `min_image_count`.
+  pub image_count: u32,
   pub surface_format: SurfaceFormatKHR,
   pub sharing_mode: SharingMode,
+  /// Transforms that the surface supports as pre-transform.
+  pub supported_transforms: SurfaceTransformFlagsKHR,
+  /// Transform of the surface relative to its natural orientation at creation, for example when the display is rotated.
+  pub current_transform: SurfaceTransformFlagsKHR,
+  /// Transform that the presentation engine applies to images before presenting. Images must be rendered with content
+  /// pre-rotated by this transform, relative to how they should appear, which [Swapchain::display_extent] and
+  /// [pre_rotation](Self::pre_rotation) help with.
   pub pre_transform: SurfaceTransformFlagsKHR,
+  /// How the alpha channel of images is composited with other surfaces.
+  pub composite_alpha: CompositeAlphaFlagsKHR,
   pub present_mode: PresentModeKHR,
 }
 
+impl SwapchainFeatures {
+  /// Gets the clockwise rotation in degrees (0, 90, 180, or 270) of the [pre-transform](Self::pre_transform), or `None`
+  /// if it is mirrored or inherited, which are not supported.
+  pub fn pre_rotation(&self) -> Option<u32> { transform_rotation(self.pre_transform) }
+
+  /// Whether the [pre-transform](Self::pre_transform) rotates by 90 or 270 degrees, swapping width and height.
+  pub fn is_pre_rotated_sideways(&self) -> bool {
+    match self.pre_rotation() {
+      Some(90) | Some(270) => true,
+      _ => false,
+    }
+  }
+}
+
+/// Gets the clockwise rotation in degrees (0, 90, 180, or 270) of `transform`, or `None` if it is mirrored or inherited.
+pub fn transform_rotation(transform: SurfaceTransformFlagsKHR) -> Option<u32> {
+  match transform {
+    SurfaceTransformFlagsKHR::IDENTITY => Some(0),
+    SurfaceTransformFlagsKHR::ROTATE_90 => Some(90),
+    SurfaceTransformFlagsKHR::ROTATE_180 => Some(180),
+    SurfaceTransformFlagsKHR::ROTATE_270 => Some(270),
+    _ => None,
+  }
+}
+
 // Creation and destruction
 
 #[derive(Clone, Debug)]
 pub struct SwapchainFeaturesQuery {
   wanted_image_count: NonZeroU32,
   wanted_present_modes_ord: Vec<PresentModeKHR>,
+  wanted_pre_transform: Option<SurfaceTransformFlagsKHR>,
+  wanted_composite_alphas_ord: Vec<CompositeAlphaFlagsKHR>,
 }
 
 impl SwapchainFeaturesQuery {
@@ -61,11 +111,35 @@ impl SwapchainFeaturesQuery {
   pub fn want_present_mode(&mut self, present_modes_ord: Vec<PresentModeKHR>) {
     self.wanted_present_modes_ord = present_modes_ord;
   }
+
+  /// Wants `pre_transform` as pre-transform if the surface supports it. Otherwise, or if not called, `IDENTITY` is
+  /// preferred, falling back to the current transform of the surface. Wanting the current transform of the surface
+  /// avoids the presentation engine rotating images on rotated displays, at the cost of rendering pre-rotated content.
+  pub fn want_pre_transform(&mut self, pre_transform: SurfaceTransformFlagsKHR) {
+    self.wanted_pre_transform = Some(pre_transform);
+  }
+
+  /// Wants the first composite alpha mode of `composite_alphas_ord` that the surface supports. Defaults to `OPAQUE`,
+  /// then `INHERIT`, then `PRE_MULTIPLIED`, then `POST_MULTIPLIED`. Creation fails if none of the wanted modes are
+  /// supported.
+  pub fn want_composite_alpha(&mut self, composite_alphas_ord: Vec<CompositeAlphaFlagsKHR>) {
+    self.wanted_composite_alphas_ord = composite_alphas_ord;
+  }
 }
 
 impl Default for SwapchainFeaturesQuery {
   fn default() -> Self {
-    Self { wanted_image_count: unsafe { NonZeroU32::new_unchecked(1) }, wanted_present_modes_ord: Vec::new() }
+    Self {
+      wanted_image_count: unsafe { NonZeroU32::new_unchecked(1) },
+      wanted_present_modes_ord: Vec::new(),
+      wanted_pre_transform: None,
+      wanted_composite_alphas_ord: vec![
+        CompositeAlphaFlagsKHR::OPAQUE,
+        CompositeAlphaFlagsKHR::INHERIT,
+        CompositeAlphaFlagsKHR::PRE_MULTIPLIED,
+        CompositeAlphaFlagsKHR::POST_MULTIPLIED,
+      ],
+    }
   }
 }
 
@@ -75,7 +149,7 @@ pub enum SwapchainCreateError {
   SurfaceFormatFail(#[from] SurfaceFormatError),
   #[error("Failed to get surface capabilities: {0:?}")]
   SurfaceCapabilitiesFail(#[source] VkError),
-  #[error("Failed to find support composite alpha mode")]
+  #[error("Failed to find supported composite alpha mode")]
   NoCompositeAlphaModeFound,
   #[error("Failed to get surface present modes: {0:?}")]
   SurfacePresentModesFail(#[source] VkError),
@@ -97,16 +171,18 @@ impl Swapchain {
     features_query: SwapchainFeaturesQuery,
     surface_extent: Extent2D,
   ) -> Result<Self, SwapchainCreateError> {
-    let loader = SwapchainLoader::new(&instance.wrapped, &device.wrapped);
+    let loader = SwapchainLoader::new(instance.raw(), device.raw());
     Self::new_internal(loader, device, surface, features_query, surface_extent, None)
   }
 
   pub unsafe fn destroy(&mut self, device: &Device) {
+    self.destroyed.assert_not_destroyed("Swapchain");
     debug!("Destroying swapchain {:?}", self.wrapped);
     for image_view in &self.image_views {
       device.destroy_image_view(*image_view);
     }
     self.loader.destroy_swapchain(self.wrapped, None);
+    self.destroyed.set();
   }
 
   fn new_internal(
@@ -128,10 +204,8 @@ impl Swapchain {
       max_image_count => max(capabilities.min_image_count, min(wanted_image_count, max_image_count)),
     };
     let surface_format = unsafe { surface.get_suitable_surface_format(device.physical_device) }?;
-    let extent = match (capabilities.current_extent.width, capabilities.current_extent.height) {
-      (std::u32::MAX, std::u32::MAX) => surface_extent,
-      _ => capabilities.current_extent,
-    };
+    let pre_transform = Self::select_pre_transform(capabilities.supported_transforms, capabilities.current_transform, features_query.wanted_pre_transform);
+    let extent = Self::select_extent(capabilities.current_extent, surface_extent, pre_transform);
     let extent = {
       let Extent2D { width, height } = extent;
       let (min_width, min_height) = {
@@ -154,16 +228,8 @@ impl Swapchain {
         (SharingMode::CONCURRENT, vec![graphics, present])
       }
     };
-    let pre_transform = if capabilities.supported_transforms.contains(SurfaceTransformFlagsKHR::IDENTITY) {
-      SurfaceTransformFlagsKHR::IDENTITY
-    } else {
-      capabilities.current_transform
-    };
-    let composite_alpha = if capabilities.supported_composite_alpha.contains(CompositeAlphaFlagsKHR::OPAQUE) {
-      CompositeAlphaFlagsKHR::OPAQUE
-    } else {
-      return Err(NoCompositeAlphaModeFound)
-    };
+    let composite_alpha = Self::select_composite_alpha(capabilities.supported_composite_alpha, &features_query.wanted_composite_alphas_ord)
+      .ok_or(NoCompositeAlphaModeFound)?;
     let present_mode = {
       let available_present_modes = unsafe { surface.get_present_modes(device.physical_device) }
         .map_err(|e| SurfacePresentModesFail(e))?;
@@ -195,6 +261,7 @@ impl Swapchain {
 
     let images = unsafe { loader.get_swapchain_images(swapchain) }
       .map_err(|e| SwapchainImagesFail(e))?;
+    let image_count = images.len() as u32;
     let image_views = {
       let image_views: Result<Vec<_>, _> = images
         .into_iter()
@@ -207,9 +274,13 @@ impl Swapchain {
 
     let features = SwapchainFeatures {
       min_image_count,
+      image_count,
       surface_format,
       sharing_mode,
+      supported_transforms: capabilities.supported_transforms,
+      current_transform: capabilities.current_transform,
       pre_transform,
+      composite_alpha,
       present_mode,
     };
 
@@ -219,10 +290,35 @@ impl Swapchain {
       image_views,
       extent,
       features_query,
-      features
+      features,
+      destroyed: DestroyedFlag::default(),
     })
   }
 
+  fn select_pre_transform(supported_transforms: SurfaceTransformFlagsKHR, current_transform: SurfaceTransformFlagsKHR, wanted_pre_transform: Option<SurfaceTransformFlagsKHR>) -> SurfaceTransformFlagsKHR {
+    match wanted_pre_transform {
+      Some(wanted) if supported_transforms.contains(wanted) => wanted,
+      _ if supported_transforms.contains(SurfaceTransformFlagsKHR::IDENTITY) => SurfaceTransformFlagsKHR::IDENTITY,
+      _ => current_transform,
+    }
+  }
+
+  /// Selects the image extent before clamping to the surface capabilities.
+  fn select_extent(current_extent: Extent2D, surface_extent: Extent2D, pre_transform: SurfaceTransformFlagsKHR) -> Extent2D {
+    match (current_extent.width, current_extent.height) {
+      // Surface extent is as displayed, while images are in the natural orientation of the surface.
+      (std::u32::MAX, std::u32::MAX) => match transform_rotation(pre_transform) {
+        Some(90) | Some(270) => Extent2D { width: surface_extent.height, height: surface_extent.width },
+        _ => surface_extent,
+      },
+      _ => current_extent,
+    }
+  }
+
+  fn select_composite_alpha(supported_composite_alpha: CompositeAlphaFlagsKHR, wanted_composite_alphas_ord: &[CompositeAlphaFlagsKHR]) -> Option<CompositeAlphaFlagsKHR> {
+    wanted_composite_alphas_ord.iter().copied().find(|wanted| !wanted.is_empty() && supported_composite_alpha.contains(*wanted))
+  }
+
   fn select_present_mode(available_present_modes: Vec<PresentModeKHR>, wanted_present_modes_ord: Vec<PresentModeKHR>) -> Option<PresentModeKHR> {
     for wanted_mode in &wanted_present_modes_ord {
       for available_mode in &available_present_modes {
@@ -242,12 +338,23 @@ impl Swapchain {
 // API
 
 impl Swapchain {
+  /// Gets the extent of images as displayed: the image extent with width and height swapped if the pre-transform rotates
+  /// by 90 or 270 degrees.
+  pub fn display_extent(&self) -> Extent2D {
+    if self.features.is_pre_rotated_sideways() {
+      Extent2D { width: self.extent.height, height: self.extent.width }
+    } else {
+      self.extent
+    }
+  }
+
   pub unsafe fn recreate(
     &mut self,
     device: &Device,
     surface: &Surface,
     surface_extent: Extent2D
   ) -> Result<(), SwapchainCreateError> {
+    self.destroyed.assert_not_destroyed("Swapchain");
     debug!("Recreating swapchain");
     let mut new_swapchain = Self::new_internal(
       self.loader.clone(),
@@ -265,10 +372,11 @@ impl Swapchain {
 
 #[derive(Error, Debug)]
 #[error("Failed to acquire next image from swapchain: {0:?}")]
-pub struct AcquireNextImageError(#[from] VkError);
+pub struct AcquireNextImageError(#[from] pub(crate) VkError);
 
 impl Swapchain {
   pub unsafe fn acquire_next_image(&self, timeout: Timeout, semaphore: Option<Semaphore>, fence: Option<Fence>) -> Result<(u32, bool), AcquireNextImageError> {
+    self.destroyed.assert_not_destroyed("Swapchain");
     Ok(self.loader.acquire_next_image(self.wrapped, timeout.into(), semaphore.unwrap_or_default(), fence.unwrap_or_default())?)
   }
 }
@@ -279,11 +387,32 @@ pub struct QueuePresentError(#[from] VkError);
 
 impl Swapchain {
   pub unsafe fn queue_present(&self, queue: Queue, create_info: &vk::PresentInfoKHR) -> Result<bool, QueuePresentError> {
-    let result = self.loader.queue_present(queue, create_info);
-    match result {
-      Err(VkError::ERROR_OUT_OF_DATE_KHR) => Ok(true),
-      result => Ok(result?)
-    }
+    self.destroyed.assert_not_destroyed("Swapchain");
+    queue_present(&self.loader, queue, create_info)
+  }
+
+  /// Creates a loader that can present to this swapchain, or to swapchains recreated from it, from another thread.
+  pub fn present_loader(&self) -> SwapchainPresentLoader {
+    SwapchainPresentLoader(self.loader.clone())
+  }
+}
+
+/// Swapchain function loader that can be sent to another thread for presenting. Presenting requires external
+/// synchronization of the queue and the swapchains presented to.
+#[derive(Clone)]
+pub struct SwapchainPresentLoader(SwapchainLoader);
+
+impl SwapchainPresentLoader {
+  pub unsafe fn queue_present(&self, queue: Queue, create_info: &vk::PresentInfoKHR) -> Result<bool, QueuePresentError> {
+    queue_present(&self.0, queue, create_info)
+  }
+}
+
+unsafe fn queue_present(loader: &SwapchainLoader, queue: Queue, create_info: &vk::PresentInfoKHR) -> Result<bool, QueuePresentError> {
+  let result = loader.queue_present(queue, create_info);
+  match result {
+    Err(VkError::ERROR_OUT_OF_DATE_KHR) => Ok(true),
+    result => Ok(result?)
   }
 }
 
@@ -309,9 +438,90 @@ impl Deref for Swapchain {
   type Target = SwapchainKHR;
 
   #[inline]
-  fn deref(&self) -> &Self::Target { &self.wrapped }
+  fn deref(&self) -> &Self::Target {
+    self.destroyed.assert_not_destroyed("Swapchain");
+    &self.wrapped
+  }
 }
 
 // Extension name
 
 pub const SWAPCHAIN_EXTENSION_NAME: &'static CStr = c_str!("VK_KHR_swapchain");
+
+#[cfg(test)]
+mod tests {
+  use ash::vk::{CompositeAlphaFlagsKHR as CompositeAlpha, Extent2D, SurfaceTransformFlagsKHR as Transform};
+
+  use super::{Swapchain, SwapchainFeaturesQuery, transform_rotation};
+
+  const NO_EXTENT: Extent2D = Extent2D { width: std::u32::MAX, height: std::u32::MAX };
+
+  fn extent(width: u32, height: u32) -> Extent2D { Extent2D { width, height } }
+
+  fn assert_extent_eq(actual: Extent2D, expected: Extent2D) {
+    assert_eq!((actual.width, actual.height), (expected.width, expected.height));
+  }
+
+  #[test]
+  fn transform_rotation_of_rotations_only() {
+    assert_eq!(transform_rotation(Transform::IDENTITY), Some(0));
+    assert_eq!(transform_rotation(Transform::ROTATE_90), Some(90));
+    assert_eq!(transform_rotation(Transform::ROTATE_180), Some(180));
+    assert_eq!(transform_rotation(Transform::ROTATE_270), Some(270));
+    assert_eq!(transform_rotation(Transform::HORIZONTAL_MIRROR), None);
+    assert_eq!(transform_rotation(Transform::INHERIT), None);
+  }
+
+  #[test]
+  fn select_pre_transform_prefers_wanted_then_identity() {
+    let supported = Transform::IDENTITY | Transform::ROTATE_90;
+    assert_eq!(Swapchain::select_pre_transform(supported, Transform::ROTATE_90, Some(Transform::ROTATE_90)), Transform::ROTATE_90);
+    assert_eq!(Swapchain::select_pre_transform(supported, Transform::ROTATE_90, None), Transform::IDENTITY);
+    assert_eq!(Swapchain::select_pre_transform(supported, Transform::ROTATE_90, Some(Transform::ROTATE_180)), Transform::IDENTITY);
+  }
+
+  #[test]
+  fn select_pre_transform_falls_back_to_current_transform_on_rotated_surface() {
+    // A surface that only supports its current, rotated, transform.
+    assert_eq!(Swapchain::select_pre_transform(Transform::ROTATE_270, Transform::ROTATE_270, None), Transform::ROTATE_270);
+    assert_eq!(Swapchain::select_pre_transform(Transform::ROTATE_270, Transform::ROTATE_270, Some(Transform::IDENTITY)), Transform::ROTATE_270);
+  }
+
+  #[test]
+  fn select_extent_swaps_surface_extent_when_rotated_sideways() {
+    let surface_extent = extent(1920, 1080);
+    assert_extent_eq(Swapchain::select_extent(NO_EXTENT, surface_extent, Transform::IDENTITY), extent(1920, 1080));
+    assert_extent_eq(Swapchain::select_extent(NO_EXTENT, surface_extent, Transform::ROTATE_90), extent(1080, 1920));
+    assert_extent_eq(Swapchain::select_extent(NO_EXTENT, surface_extent, Transform::ROTATE_180), extent(1920, 1080));
+    assert_extent_eq(Swapchain::select_extent(NO_EXTENT, surface_extent, Transform::ROTATE_270), extent(1080, 1920));
+  }
+
+  #[test]
+  fn select_extent_uses_fixed_surface_extent() {
+    // A fixed extent is already in the natural orientation of the surface.
+    assert_extent_eq(Swapchain::select_extent(extent(1080, 1920), extent(1920, 1080), Transform::ROTATE_90), extent(1080, 1920));
+  }
+
+  #[test]
+  fn select_composite_alpha_selects_inherit_when_only_supported() {
+    let query = SwapchainFeaturesQuery::default();
+    assert_eq!(Swapchain::select_composite_alpha(CompositeAlpha::INHERIT, &query.wanted_composite_alphas_ord), Some(CompositeAlpha::INHERIT));
+  }
+
+  #[test]
+  fn select_composite_alpha_follows_preference_order() {
+    let supported = CompositeAlpha::OPAQUE | CompositeAlpha::PRE_MULTIPLIED;
+    let query = SwapchainFeaturesQuery::default();
+    assert_eq!(Swapchain::select_composite_alpha(supported, &query.wanted_composite_alphas_ord), Some(CompositeAlpha::OPAQUE));
+    let wanted = [CompositeAlpha::POST_MULTIPLIED, CompositeAlpha::PRE_MULTIPLIED, CompositeAlpha::OPAQUE];
+    assert_eq!(Swapchain::select_composite_alpha(supported, &wanted), Some(CompositeAlpha::PRE_MULTIPLIED));
+  }
+
+  #[test]
+  fn select_composite_alpha_fails_without_supported_wanted_mode() {
+    assert_eq!(Swapchain::select_composite_alpha(CompositeAlpha::INHERIT, &[CompositeAlpha::OPAQUE]), None);
+    assert_eq!(Swapchain::select_composite_alpha(CompositeAlpha::OPAQUE, &[]), None);
+    // An empty wanted mode is contained in any supported modes, but is not a mode.
+    assert_eq!(Swapchain::select_composite_alpha(CompositeAlpha::OPAQUE, &[CompositeAlpha::empty()]), None);
+  }
+}
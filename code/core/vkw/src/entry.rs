@@ -1,6 +1,10 @@
+use std::ffi::CStr;
 use std::ops::Deref;
+use std::ptr;
 
 use ash::{Entry as VkEntry, LoadingError};
+use ash::version::EntryV1_0;
+use ash::vk::{ExtensionProperties, Result as VkError};
 use thiserror::Error;
 
 use crate::version::VkVersion;
@@ -36,6 +40,23 @@ impl Entry {
       Err(_) => None,
     }
   }
+
+  /// Enumerates the instance extensions provided by layer `layer_name`, which are not included in
+  /// `enumerate_instance_extension_properties`.
+  pub fn enumerate_layer_extension_properties(&self, layer_name: &CStr) -> Result<Vec<ExtensionProperties>, VkError> {
+    let enumerate = self.wrapped.fp_v1_0().enumerate_instance_extension_properties;
+    unsafe {
+      let mut count = 0;
+      let result = enumerate(layer_name.as_ptr(), &mut count, ptr::null_mut());
+      if result != VkError::SUCCESS { return Err(result); }
+      let mut properties = Vec::with_capacity(count as usize);
+      let result = enumerate(layer_name.as_ptr(), &mut count, properties.as_mut_ptr());
+      if result != VkError::SUCCESS { return Err(result); }
+      // CORRECTNESS: `count` is at most the capacity, and that many properties were written.
+      properties.set_len(count as usize);
+      Ok(properties)
+    }
+  }
 }
 
 // Implementations
@@ -13,13 +13,13 @@ pub struct FramebufferCreateError(#[from] VkError);
 
 impl Device {
   pub unsafe fn create_framebuffer(&self, create_info: &FramebufferCreateInfo) -> Result<Framebuffer, FramebufferCreateError> {
-    let framebuffer = self.wrapped.create_framebuffer(create_info, None)?;
+    let framebuffer = self.raw().create_framebuffer(create_info, None)?;
     debug!("Created framebuffer {:?}", framebuffer);
     Ok(framebuffer)
   }
 
   pub unsafe fn destroy_framebuffer(&self, framebuffer: Framebuffer) {
     debug!("Destroying framebuffer {:?}", framebuffer);
-    self.wrapped.destroy_framebuffer(framebuffer, None)
+    self.raw().destroy_framebuffer(framebuffer, None)
   }
 }
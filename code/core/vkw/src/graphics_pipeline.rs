@@ -1,6 +1,9 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
 use ash::version::DeviceV1_0;
-use ash::vk::{self, DescriptorSetLayout, GraphicsPipelineCreateInfo, Pipeline, PipelineCache, PipelineLayout, PushConstantRange, Result as VkError};
-use log::debug;
+use ash::vk::{self, CommandBuffer, DescriptorSetLayout, GraphicsPipelineCreateInfo, Pipeline, PipelineCache, PipelineLayout, PushConstantRange, RenderPass, Result as VkError};
+use log::{debug, warn};
 use thiserror::Error;
 
 use crate::device::Device;
@@ -21,14 +24,85 @@ impl Device {
       .set_layouts(descriptor_set_layouts)
       .push_constant_ranges(push_constant_ranges)
       ;
-    let pipeline_layout = self.wrapped.create_pipeline_layout(&create_info, None)?;
+    let pipeline_layout = self.raw().create_pipeline_layout(&create_info, None)?;
     debug!("Created pipeline layout {:?}", pipeline_layout);
     Ok(pipeline_layout)
   }
 
   pub unsafe fn destroy_pipeline_layout(&self, pipeline_layout: PipelineLayout) {
     debug!("Destroying pipeline layout {:?}", pipeline_layout);
-    self.wrapped.destroy_pipeline_layout(pipeline_layout, None);
+    self.raw().destroy_pipeline_layout(pipeline_layout, None);
+  }
+}
+
+// Pipeline layout cache.
+
+/// Pipeline layouts shared between users that request identical layouts, reference counted per layout.
+#[derive(Default)]
+pub(crate) struct PipelineLayoutCache {
+  layouts: Mutex<HashMap<PipelineLayoutKey, (PipelineLayout, usize)>>,
+}
+
+#[derive(Clone, Eq, PartialEq, Hash, Debug)]
+struct PipelineLayoutKey {
+  descriptor_set_layouts: Vec<DescriptorSetLayout>,
+  /// Push constant ranges as (stage flags, offset, size).
+  push_constant_ranges: Vec<(u32, u32, u32)>,
+}
+
+impl PipelineLayoutKey {
+  fn new(descriptor_set_layouts: &[DescriptorSetLayout], push_constant_ranges: &[PushConstantRange]) -> Self {
+    Self {
+      descriptor_set_layouts: descriptor_set_layouts.to_vec(),
+      push_constant_ranges: push_constant_ranges.iter().map(|r| (r.stage_flags.as_raw(), r.offset, r.size)).collect(),
+    }
+  }
+}
+
+impl Device {
+  /// Gets a pipeline layout with `descriptor_set_layouts` and `push_constant_ranges`, shared with other users that
+  /// requested an identical layout. Each acquired layout must be released with
+  /// [release_pipeline_layout](Self::release_pipeline_layout) instead of being destroyed.
+  pub unsafe fn acquire_pipeline_layout(
+    &self,
+    descriptor_set_layouts: &[DescriptorSetLayout],
+    push_constant_ranges: &[PushConstantRange],
+  ) -> Result<PipelineLayout, PipelineLayoutCreateError> {
+    let key = PipelineLayoutKey::new(descriptor_set_layouts, push_constant_ranges);
+    let mut layouts = self.pipeline_layout_cache.layouts.lock().unwrap();
+    if let Some((pipeline_layout, count)) = layouts.get_mut(&key) {
+      *count += 1;
+      return Ok(*pipeline_layout);
+    }
+    let pipeline_layout = self.create_pipeline_layout(descriptor_set_layouts, push_constant_ranges)?;
+    layouts.insert(key, (pipeline_layout, 1));
+    Ok(pipeline_layout)
+  }
+
+  /// Releases a pipeline layout acquired with [acquire_pipeline_layout](Self::acquire_pipeline_layout), destroying it
+  /// when it is released by its last user.
+  pub unsafe fn release_pipeline_layout(&self, pipeline_layout: PipelineLayout) {
+    let mut layouts = self.pipeline_layout_cache.layouts.lock().unwrap();
+    let key = layouts.iter().find(|(_, (l, _))| *l == pipeline_layout).map(|(k, _)| k.clone());
+    if let Some(key) = key {
+      let count = &mut layouts.get_mut(&key).unwrap().1;
+      *count -= 1;
+      if *count == 0 {
+        layouts.remove(&key);
+        self.destroy_pipeline_layout(pipeline_layout);
+      }
+    } else {
+      warn!("Attempted to release pipeline layout {:?} which was not acquired from the cache", pipeline_layout);
+    }
+  }
+
+  /// Destroys pipeline layouts that were not released.
+  pub(crate) unsafe fn destroy_pipeline_layout_cache(&self) {
+    let mut layouts = self.pipeline_layout_cache.layouts.lock().unwrap();
+    for (_, (pipeline_layout, count)) in layouts.drain() {
+      warn!("Pipeline layout {:?} was not released by {} user(s)", pipeline_layout, count);
+      self.destroy_pipeline_layout(pipeline_layout);
+    }
   }
 }
 
@@ -41,14 +115,14 @@ pub struct PipelineCacheCreateError(#[from] VkError);
 impl Device {
   pub unsafe fn create_pipeline_cache(&self) -> Result<PipelineCache, PipelineCacheCreateError> {
     let create_info = vk::PipelineCacheCreateInfo::builder();
-    let pipeline_cache = self.wrapped.create_pipeline_cache(&create_info, None)?;
+    let pipeline_cache = self.raw().create_pipeline_cache(&create_info, None)?;
     debug!("Created pipeline cache {:?}", pipeline_cache);
     Ok(pipeline_cache)
   }
 
   pub unsafe fn destroy_pipeline_cache(&self, pipeline_cache: PipelineCache) {
     debug!("Destroying pipeline cache {:?}", pipeline_cache);
-    self.wrapped.destroy_pipeline_cache(pipeline_cache, None);
+    self.raw().destroy_pipeline_cache(pipeline_cache, None);
   }
 }
 
@@ -64,11 +138,17 @@ impl Device {
     pipeline_cache: PipelineCache,
     create_infos: &[GraphicsPipelineCreateInfo]
   ) -> Result<Vec<Pipeline>, GraphicsPipelineCreateError> {
-    let pipelines = match self.wrapped.create_graphics_pipelines(pipeline_cache, create_infos, None) {
+    let pipelines = match self.raw().create_graphics_pipelines(pipeline_cache, create_infos, None) {
       Err((_, e)) => Err(e),
       Ok(v) => Ok(v),
     }?;
     debug!("Created graphics pipelines {:?}", pipelines);
+    #[cfg(debug_assertions)] {
+      let mut render_passes = self.render_pass_tracker.pipeline_render_passes.lock().unwrap();
+      for (pipeline, create_info) in pipelines.iter().zip(create_infos) {
+        render_passes.insert(*pipeline, create_info.render_pass);
+      }
+    }
     Ok(pipelines)
   }
 
@@ -80,8 +160,82 @@ impl Device {
     Ok(self.create_graphics_pipelines(pipeline_cache, &[*create_info])?[0])
   }
 
+  /// Creates a graphics pipeline that derives from `base_pipeline`, which drivers may use to speed up creation of
+  /// pipelines that only differ in a few states. `base_pipeline` must have been created with
+  /// `PipelineCreateFlags::ALLOW_DERIVATIVES`.
+  pub unsafe fn create_graphics_pipeline_derivative(
+    &self,
+    pipeline_cache: PipelineCache,
+    base_pipeline: Pipeline,
+    create_info: &GraphicsPipelineCreateInfo
+  ) -> Result<Pipeline, GraphicsPipelineCreateError> {
+    let mut create_info = *create_info;
+    create_info.flags |= vk::PipelineCreateFlags::DERIVATIVE;
+    create_info.base_pipeline_handle = base_pipeline;
+    create_info.base_pipeline_index = -1;
+    self.create_graphics_pipeline(pipeline_cache, &create_info)
+  }
+
   pub unsafe fn destroy_pipeline(&self, pipeline: Pipeline) {
     debug!("Destroying pipeline {:?}", pipeline);
-    self.wrapped.destroy_pipeline(pipeline, None);
+    #[cfg(debug_assertions)] {
+      self.render_pass_tracker.pipeline_render_passes.lock().unwrap().remove(&pipeline);
+    }
+    self.raw().destroy_pipeline(pipeline, None);
+  }
+}
+
+// Render pass compatibility checking.
+
+/// Tracks the render passes that graphics pipelines were created against, and the render pass that is active in each
+/// command buffer, to check that bound pipelines match the active render pass. Only tracks in debug builds.
+#[derive(Default)]
+pub(crate) struct RenderPassTracker {
+  #[cfg(debug_assertions)]
+  pipeline_render_passes: Mutex<HashMap<Pipeline, RenderPass>>,
+  #[cfg(debug_assertions)]
+  active_render_passes: Mutex<HashMap<CommandBuffer, RenderPass>>,
+}
+
+impl RenderPassTracker {
+  #[allow(unused_variables)]
+  pub(crate) fn begin(&self, command_buffer: CommandBuffer, render_pass: RenderPass) {
+    #[cfg(debug_assertions)] {
+      self.active_render_passes.lock().unwrap().insert(command_buffer, render_pass);
+    }
+  }
+
+  #[allow(unused_variables)]
+  pub(crate) fn end(&self, command_buffer: CommandBuffer) {
+    #[cfg(debug_assertions)] {
+      self.active_render_passes.lock().unwrap().remove(&command_buffer);
+    }
+  }
+}
+
+impl Device {
+  /// Binds graphics `pipeline` in `command_buffer`. In debug builds, panics if `pipeline` was created against a
+  /// different render pass than the one active in `command_buffer`, which is undefined behaviour if the render passes
+  /// are incompatible.
+  pub unsafe fn cmd_bind_graphics_pipeline(&self, command_buffer: CommandBuffer, pipeline: Pipeline) {
+    #[cfg(debug_assertions)] {
+      let created_render_pass = self.render_pass_tracker.pipeline_render_passes.lock().unwrap().get(&pipeline).copied();
+      let active_render_pass = self.render_pass_tracker.active_render_passes.lock().unwrap().get(&command_buffer).copied();
+      if let (Some(created_render_pass), Some(active_render_pass)) = (created_render_pass, active_render_pass) {
+        assert_eq!(created_render_pass, active_render_pass, "Graphics pipeline {:?} was created against render pass {:?}, but is bound inside render pass {:?}", pipeline, created_render_pass, active_render_pass);
+      }
+    }
+    self.raw().cmd_bind_pipeline(command_buffer, vk::PipelineBindPoint::GRAPHICS, pipeline);
+  }
+}
+
+// Dynamic state
+
+impl Device {
+  /// Sets the dynamic line width of the bound graphics pipeline. Line widths other than 1.0 require the `wideLines`
+  /// feature; 1.0 is used instead if it is not enabled.
+  pub unsafe fn cmd_set_line_width(&self, command_buffer: CommandBuffer, line_width: f32) {
+    let line_width = if self.features.is_wide_lines_enabled() { line_width } else { 1.0 };
+    self.raw().cmd_set_line_width(command_buffer, line_width);
   }
 }
@@ -7,7 +7,7 @@ use crate::instance::Instance;
 
 impl Instance {
   pub unsafe fn get_format_properties(&self, physical_device: PhysicalDevice, format: Format) -> FormatProperties {
-    self.wrapped.get_physical_device_format_properties(physical_device, format)
+    self.raw().get_physical_device_format_properties(physical_device, format)
   }
 }
 
@@ -33,3 +33,79 @@ impl Device {
     Err(FormatFindError)
   }
 }
+
+
+// Block-compressed formats
+
+/// Returns the block width, block height, and number of bytes per block of block-compressed (BC, ETC2, or EAC)
+/// `format`, or `None` if `format` is not block-compressed.
+pub fn compressed_block_info(format: Format) -> Option<(u32, u32, usize)> {
+  match format {
+    Format::BC1_RGB_UNORM_BLOCK | Format::BC1_RGB_SRGB_BLOCK | Format::BC1_RGBA_UNORM_BLOCK | Format::BC1_RGBA_SRGB_BLOCK |
+    Format::BC4_UNORM_BLOCK | Format::BC4_SNORM_BLOCK |
+    Format::ETC2_R8G8B8_UNORM_BLOCK | Format::ETC2_R8G8B8_SRGB_BLOCK |
+    Format::ETC2_R8G8B8A1_UNORM_BLOCK | Format::ETC2_R8G8B8A1_SRGB_BLOCK |
+    Format::EAC_R11_UNORM_BLOCK | Format::EAC_R11_SNORM_BLOCK => Some((4, 4, 8)),
+    Format::BC2_UNORM_BLOCK | Format::BC2_SRGB_BLOCK | Format::BC3_UNORM_BLOCK | Format::BC3_SRGB_BLOCK |
+    Format::BC5_UNORM_BLOCK | Format::BC5_SNORM_BLOCK | Format::BC6H_UFLOAT_BLOCK | Format::BC6H_SFLOAT_BLOCK |
+    Format::BC7_UNORM_BLOCK | Format::BC7_SRGB_BLOCK |
+    Format::ETC2_R8G8B8A8_UNORM_BLOCK | Format::ETC2_R8G8B8A8_SRGB_BLOCK |
+    Format::EAC_R11G11_UNORM_BLOCK | Format::EAC_R11G11_SNORM_BLOCK => Some((4, 4, 16)),
+    _ => None,
+  }
+}
+
+/// Returns the number of bytes of a `width` by `height` image in block-compressed `format`, where partial blocks at the
+/// edges are stored as full blocks. Returns `None` if `format` is not block-compressed.
+pub fn compressed_size(format: Format, width: u32, height: u32) -> Option<usize> {
+  let (block_width, block_height, block_size) = compressed_block_info(format)?;
+  let blocks_x = ((width + block_width - 1) / block_width) as usize;
+  let blocks_y = ((height + block_height - 1) / block_height) as usize;
+  Some(blocks_x * blocks_y * block_size)
+}
+
+impl Device {
+  pub unsafe fn is_format_supported(&self, format: Format, tiling: ImageTiling, features: FormatFeatureFlags) -> bool {
+    let properties = self.get_format_properties(format);
+    match tiling {
+      ImageTiling::OPTIMAL => properties.optimal_tiling_features.contains(features),
+      ImageTiling::LINEAR => properties.linear_tiling_features.contains(features),
+      _ => false,
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use ash::vk::Format;
+
+  use super::{compressed_block_info, compressed_size};
+
+  #[test]
+  fn compressed_block_info_of_block_sizes() {
+    assert_eq!(compressed_block_info(Format::BC1_RGBA_SRGB_BLOCK), Some((4, 4, 8)));
+    assert_eq!(compressed_block_info(Format::ETC2_R8G8B8_UNORM_BLOCK), Some((4, 4, 8)));
+    assert_eq!(compressed_block_info(Format::BC7_UNORM_BLOCK), Some((4, 4, 16)));
+    assert_eq!(compressed_block_info(Format::R8G8B8A8_UNORM), None);
+  }
+
+  #[test]
+  fn compressed_size_rounds_up_partial_blocks() {
+    // 5x3 texels cover 2x1 blocks.
+    assert_eq!(compressed_size(Format::BC7_SRGB_BLOCK, 5, 3), Some(2 * 16));
+    assert_eq!(compressed_size(Format::BC7_SRGB_BLOCK, 8, 8), Some(4 * 16));
+    assert_eq!(compressed_size(Format::BC7_SRGB_BLOCK, 1, 1), Some(16));
+  }
+
+  #[test]
+  fn compressed_size_of_8_byte_block_formats() {
+    assert_eq!(compressed_size(Format::BC1_RGB_UNORM_BLOCK, 5, 3), Some(2 * 8));
+    assert_eq!(compressed_size(Format::BC1_RGB_UNORM_BLOCK, 16, 16), Some(16 * 8));
+    assert_eq!(compressed_size(Format::ETC2_R8G8B8_SRGB_BLOCK, 6, 9), Some(2 * 3 * 8));
+  }
+
+  #[test]
+  fn compressed_size_of_uncompressed_format_is_none() {
+    assert_eq!(compressed_size(Format::R8G8B8A8_UNORM, 4, 4), None);
+  }
+}
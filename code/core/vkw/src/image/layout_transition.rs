@@ -26,6 +26,9 @@ impl Device {
       (ImageLayout::TRANSFER_DST_OPTIMAL, ImageLayout::SHADER_READ_ONLY_OPTIMAL) => (
         AccessFlags::TRANSFER_WRITE, AccessFlags::SHADER_READ, PipelineStageFlags::TRANSFER, PipelineStageFlags::FRAGMENT_SHADER
       ),
+      (ImageLayout::SHADER_READ_ONLY_OPTIMAL, ImageLayout::TRANSFER_DST_OPTIMAL) => (
+        AccessFlags::SHADER_READ, AccessFlags::TRANSFER_WRITE, PipelineStageFlags::FRAGMENT_SHADER, PipelineStageFlags::TRANSFER
+      ),
       (ImageLayout::UNDEFINED, ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL) => (
         AccessFlags::empty(), AccessFlags::DEPTH_STENCIL_ATTACHMENT_READ | AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE, PipelineStageFlags::TOP_OF_PIPE, PipelineStageFlags::EARLY_FRAGMENT_TESTS
       ),
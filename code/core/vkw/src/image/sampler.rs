@@ -5,6 +5,21 @@ use thiserror::Error;
 
 use crate::device::Device;
 
+// Level of detail
+
+/// Level of detail settings of a sampler. A negative `mip_lod_bias` selects sharper mipmap levels when minifying, and
+/// `max_lod` clamps the mipmap levels that are selected ([vk::LOD_CLAMP_NONE] for no clamping).
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct SamplerLod {
+  pub mip_lod_bias: f32,
+  pub max_lod: f32,
+}
+
+impl Default for SamplerLod {
+  /// No bias, and only sampling the base mipmap level.
+  fn default() -> Self { Self { mip_lod_bias: 0.0, max_lod: 0.0 } }
+}
+
 // Creation and destruction
 
 #[derive(Error, Debug)]
@@ -13,27 +28,34 @@ pub struct SamplerCreateError(#[from] VkError);
 
 impl Device {
   pub unsafe fn create_sampler(&self, create_info: &SamplerCreateInfo) -> Result<Sampler, SamplerCreateError> {
-    let sampler = self.wrapped.create_sampler(create_info, None)?;
+    let sampler = self.raw().create_sampler(create_info, None)?;
     trace!("Created image sampler: {:?}", sampler);
     Ok(sampler)
   }
 
   pub unsafe fn create_default_sampler(&self) -> Result<Sampler, SamplerCreateError> {
+    self.create_filtered_sampler(vk::Filter::NEAREST, SamplerLod::default())
+  }
+
+  /// Creates a sampler like the [default sampler](Self::create_default_sampler), but with `filter` for magnification,
+  /// minification, and mipmapping, and with level of detail settings `lod`.
+  pub unsafe fn create_filtered_sampler(&self, filter: vk::Filter, lod: SamplerLod) -> Result<Sampler, SamplerCreateError> {
     use vk::{Filter, SamplerMipmapMode, SamplerAddressMode, CompareOp, BorderColor};
+    let mipmap_mode = if filter == Filter::NEAREST { SamplerMipmapMode::NEAREST } else { SamplerMipmapMode::LINEAR };
     self.create_sampler(&SamplerCreateInfo::builder()
-      .mag_filter(Filter::NEAREST)
-      .min_filter(Filter::NEAREST)
-      .mipmap_mode(SamplerMipmapMode::NEAREST)
+      .mag_filter(filter)
+      .min_filter(filter)
+      .mipmap_mode(mipmap_mode)
       .address_mode_u(SamplerAddressMode::REPEAT)
       .address_mode_v(SamplerAddressMode::REPEAT)
       .address_mode_w(SamplerAddressMode::REPEAT)
-      .mip_lod_bias(0.0)
+      .mip_lod_bias(lod.mip_lod_bias)
       .anisotropy_enable(false)
       .max_anisotropy(1.0)
       .compare_enable(false)
       .compare_op(CompareOp::NEVER)
       .min_lod(0.0)
-      .max_lod(0.0)
+      .max_lod(lod.max_lod)
       .border_color(BorderColor::FLOAT_OPAQUE_WHITE)
       .unnormalized_coordinates(false)
     )
@@ -41,6 +63,6 @@ impl Device {
 
   pub unsafe fn destroy_sampler(&self, sampler: Sampler) {
     trace!("Destroying image sampler: {:?}", sampler);
-    self.wrapped.destroy_sampler(sampler, None);
+    self.raw().destroy_sampler(sampler, None);
   }
 }
@@ -1,12 +1,13 @@
 use ash::version::DeviceV1_0;
-use ash::vk::{self, CommandBuffer, Format};
+use ash::vk::{self, CommandBuffer, Format, Image};
 use thiserror::Error;
 
 use util::image::{Components, Dimensions, ImageData};
 
-use crate::allocator::{Allocator, BufferAllocationError, ImageAllocationError, MemoryMapError};
+use crate::allocator::{Allocator, BufferAllocation, BufferAllocationError, ImageAllocationError, MemoryFlushError, MemoryMapError, StagingBufferAllocationError};
 use crate::command_pool::RecordedStagingBuffer;
 use crate::device::Device;
+use crate::image::format::compressed_size;
 use crate::image::layout_transition::LayoutTransitionError;
 use crate::image::sampler::SamplerCreateError;
 use crate::image::texture::Texture;
@@ -24,6 +25,8 @@ pub enum AllocateRecordCopyTextureArrayError {
   StagingBufferAllocateFail(#[from] BufferAllocationError),
   #[error("Failed to memory map staging buffer")]
   StagingBufferMemoryMapFail(#[from] MemoryMapError),
+  #[error("Failed to flush staging buffer memory")]
+  StagingBufferMemoryFlushFail(#[from] MemoryFlushError),
   #[error(transparent)]
   ImageAllocateFail(#[from] ImageAllocationError),
   #[error(transparent)]
@@ -43,7 +46,6 @@ impl Device {
     command_buffer: CommandBuffer,
   ) -> Result<RecordedStagingBuffer<Texture>, AllocateRecordCopyTextureArrayError> {
     use AllocateRecordCopyTextureArrayError::*;
-    use vk::{Extent3D, ImageAspectFlags, ImageUsageFlags, ImageLayout};
 
     if images_data.is_empty() {
       return Err(NoImageDataGiven);
@@ -70,12 +72,190 @@ impl Device {
         map.copy_from_bytes_offset_ptr(image_data.data_ptr(), dst_offset, size);
         dst_offset += size as isize;
       }
+      map.flush(..)?;
     }
 
+    let texture = self.record_texture_array_upload::<AllocateRecordCopyTextureArrayError>(&staging_buffer, size, layer_count, dimensions.width, dimensions.height, allocator, format, command_buffer)?;
+    Ok(RecordedStagingBuffer::new(staging_buffer, texture))
+  }
+}
+
+#[derive(Debug, Error)]
+pub enum AllocateRecordCopyCompressedTextureArrayError {
+  #[error("No image data was given")]
+  NoImageDataGiven,
+  #[error("Format {0:?} is not a block-compressed format")]
+  NotACompressedFormat(Format),
+  #[error("Format {0:?} does not support being sampled or transferred to with optimal tiling on this device")]
+  UnsupportedFormat(Format),
+  #[error("Image data of layer {0} has size {1}, but size {2} is required")]
+  IncorrectSize(usize, usize, usize),
+  #[error("Failed to allocate staging buffer")]
+  StagingBufferAllocateFail(#[from] BufferAllocationError),
+  #[error("Failed to memory map staging buffer")]
+  StagingBufferMemoryMapFail(#[from] MemoryMapError),
+  #[error("Failed to flush staging buffer memory")]
+  StagingBufferMemoryFlushFail(#[from] MemoryFlushError),
+  #[error(transparent)]
+  ImageAllocateFail(#[from] ImageAllocationError),
+  #[error(transparent)]
+  ImageLayoutTransitionFail(#[from] LayoutTransitionError),
+  #[error(transparent)]
+  ImageViewCreateFail(#[from] ImageViewCreateError),
+  #[error(transparent)]
+  SamplerCreateFail(#[from] SamplerCreateError),
+}
+
+impl Device {
+  /// Records an upload of pre-compressed layers in block-compressed (BC, ETC2, or EAC) `format` into a new texture
+  /// array. Each layer must contain exactly the block-aligned number of bytes for a `width` by `height` image.
+  pub unsafe fn allocate_record_copy_compressed_texture_array(
+    &self,
+    layers_data: &[&[u8]],
+    width: u32,
+    height: u32,
+    allocator: &Allocator,
+    format: Format,
+    command_buffer: CommandBuffer,
+  ) -> Result<RecordedStagingBuffer<Texture>, AllocateRecordCopyCompressedTextureArrayError> {
+    use AllocateRecordCopyCompressedTextureArrayError::*;
+    use vk::{FormatFeatureFlags, ImageTiling};
+
+    if layers_data.is_empty() {
+      return Err(NoImageDataGiven);
+    }
+    let size = compressed_size(format, width, height).ok_or(NotACompressedFormat(format))?;
+    if !self.is_format_supported(format, ImageTiling::OPTIMAL, FormatFeatureFlags::SAMPLED_IMAGE | FormatFeatureFlags::TRANSFER_DST) {
+      return Err(UnsupportedFormat(format));
+    }
+    for (layer, layer_data) in layers_data.iter().enumerate() {
+      if layer_data.len() != size {
+        return Err(IncorrectSize(layer, layer_data.len(), size));
+      }
+    }
+    let layer_count = layers_data.len();
+
+    let staging_buffer = allocator.create_staging_buffer(size * layer_count)?;
+    {
+      let map = staging_buffer.map(allocator)?;
+      let mut dst_offset = 0;
+      for layer_data in layers_data {
+        map.copy_from_bytes_offset_ptr(layer_data.as_ptr(), dst_offset, size);
+        dst_offset += size as isize;
+      }
+      map.flush(..)?;
+    }
+
+    let texture = self.record_texture_array_upload::<AllocateRecordCopyCompressedTextureArrayError>(&staging_buffer, size, layer_count, width, height, allocator, format, command_buffer)?;
+    Ok(RecordedStagingBuffer::new(staging_buffer, texture))
+  }
+}
+
+#[derive(Debug, Error)]
+pub enum AllocateRecordCopyTextureArrayLayerError {
+  #[error("Layer {0} is out of bounds for texture array with {1} layers")]
+  LayerOutOfBounds(u32, u32),
+  #[error("Image data has {0} components, but 4 components are required")]
+  IncorrectComponentCount(u8),
+  #[error(transparent)]
+  StagingBufferAllocateFail(#[from] StagingBufferAllocationError),
+  #[error(transparent)]
+  ImageLayoutTransitionFail(#[from] LayoutTransitionError),
+}
+
+impl Device {
+  /// Records a copy of `image_data` into layer `layer` of texture array `image`, which must be in the
+  /// `SHADER_READ_ONLY_OPTIMAL` layout. The image must not be in use by the device when the recorded commands execute.
+  /// `image_data` must have the same dimensions as the layers of the texture array.
+  pub unsafe fn allocate_record_copy_texture_array_layer(
+    &self,
+    image_data: &ImageData,
+    image: Image,
+    layer: u32,
+    layer_count: u32,
+    allocator: &Allocator,
+    format: Format,
+    command_buffer: CommandBuffer,
+  ) -> Result<RecordedStagingBuffer<()>, AllocateRecordCopyTextureArrayLayerError> {
+    use AllocateRecordCopyTextureArrayLayerError::*;
+    use vk::{Extent3D, ImageAspectFlags, ImageLayout};
+
+    if layer >= layer_count {
+      return Err(LayerOutOfBounds(layer, layer_count));
+    }
+    let dimensions = image_data.dimensions;
+    if dimensions.components != Components::Components4 {
+      return Err(IncorrectComponentCount(dimensions.components.into()));
+    }
+
+    let staging_buffer = allocator.create_staging_buffer_from_slice(image_data.data_slice())?;
+
+    self.record_images_layout_transition(
+      std::iter::once(image),
+      format,
+      ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+      ImageLayout::TRANSFER_DST_OPTIMAL,
+      layer_count,
+      command_buffer,
+    )?;
+
+    self.cmd_copy_buffer_to_image(
+      command_buffer,
+      staging_buffer.buffer,
+      image,
+      ImageLayout::TRANSFER_DST_OPTIMAL,
+      &[vk::BufferImageCopy::builder()
+        .buffer_offset(0)
+        .buffer_row_length(0)
+        .buffer_image_height(0)
+        .image_subresource(vk::ImageSubresourceLayers::builder()
+          .aspect_mask(ImageAspectFlags::COLOR)
+          .mip_level(0)
+          .base_array_layer(layer)
+          .layer_count(1)
+          .build()
+        )
+        .image_offset(vk::Offset3D { x: 0, y: 0, z: 0 })
+        .image_extent(Extent3D { width: dimensions.width, height: dimensions.height, depth: 1 })
+        .build()
+      ],
+    );
+
+    self.record_images_layout_transition(
+      std::iter::once(image),
+      format,
+      ImageLayout::TRANSFER_DST_OPTIMAL,
+      ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+      layer_count,
+      command_buffer,
+    )?;
+
+    Ok(RecordedStagingBuffer::new(staging_buffer, ()))
+  }
+}
+
+// Shared texture array upload
+
+impl Device {
+  unsafe fn record_texture_array_upload<E>(
+    &self,
+    staging_buffer: &BufferAllocation,
+    layer_size: usize,
+    layer_count: usize,
+    width: u32,
+    height: u32,
+    allocator: &Allocator,
+    format: Format,
+    command_buffer: CommandBuffer,
+  ) -> Result<Texture, E> where
+    E: From<ImageAllocationError> + From<LayoutTransitionError> + From<ImageViewCreateError> + From<SamplerCreateError>
+  {
+    use vk::{Extent3D, ImageAspectFlags, ImageUsageFlags, ImageLayout};
+
     let image_info = vk::ImageCreateInfo::builder()
       .image_type(vk::ImageType::TYPE_2D)
       .format(format)
-      .extent(Extent3D { width: dimensions.width, height: dimensions.height, depth: 1 })
+      .extent(Extent3D { width, height, depth: 1 })
       .mip_levels(1)
       .array_layers(layer_count as u32)
       .samples(vk::SampleCountFlags::TYPE_1)
@@ -97,7 +277,7 @@ impl Device {
 
     let regions: Vec<_> = (0..layer_count).into_iter()
       .map(|i| {
-        let buffer_offset = i * size;
+        let buffer_offset = i * layer_size;
         vk::BufferImageCopy::builder()
           .buffer_offset(buffer_offset as u64)
           .buffer_row_length(0)
@@ -110,7 +290,7 @@ impl Device {
             .build()
           )
           .image_offset(vk::Offset3D { x: 0, y: 0, z: 0 })
-          .image_extent(Extent3D { width: dimensions.width, height: dimensions.height, depth: 1 })
+          .image_extent(Extent3D { width, height, depth: 1 })
           .build()
       })
       .collect();
@@ -133,7 +313,6 @@ impl Device {
 
     let view = self.create_image_view(image_allocation.image, format, vk::ImageViewType::TYPE_2D_ARRAY, ImageAspectFlags::COLOR, layer_count as u32)?;
     let sampler = self.create_default_sampler()?;
-    let texture = Texture { allocation: image_allocation, view, sampler };
-    Ok(RecordedStagingBuffer::new(staging_buffer, texture))
+    Ok(Texture { allocation: image_allocation, view, sampler })
   }
 }
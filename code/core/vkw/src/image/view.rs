@@ -40,14 +40,14 @@ impl Device {
         .build()
       )
       ;
-    let image_view = self.wrapped.create_image_view(&create_info, None)?;
+    let image_view = self.raw().create_image_view(&create_info, None)?;
     trace!("Created image view {:?}", image_view);
     Ok(image_view)
   }
 
   pub unsafe fn destroy_image_view(&self, image_view: ImageView) {
     trace!("Destroying image view {:?}", image_view);
-    self.wrapped.destroy_image_view(image_view, None);
+    self.raw().destroy_image_view(image_view, None);
   }
 }
 
@@ -65,12 +65,12 @@ impl Device {
       .offset(offset)
       .range(range)
       ;
-    let buffer_view = self.wrapped.create_buffer_view(&create_info, None)?;
+    let buffer_view = self.raw().create_buffer_view(&create_info, None)?;
     trace!("Created buffer view {:?}", buffer_view);
     Ok(buffer_view)
   }
 
   pub unsafe fn destroy_buffer_view(&self, buffer_view: BufferView) {
-    self.wrapped.destroy_buffer_view(buffer_view, None);
+    self.raw().destroy_buffer_view(buffer_view, None);
   }
 }
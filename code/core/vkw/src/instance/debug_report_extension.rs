@@ -43,7 +43,7 @@ impl DebugReport {
       .flags(flags)
       .pfn_callback(Some(vulkan_debug_callback))
       ;
-    let loader = VkDebugReport::new(&instance.entry.wrapped, &instance.wrapped);
+    let loader = VkDebugReport::new(&instance.entry.wrapped, instance.raw());
     let callback = unsafe { loader.create_debug_report_callback(&info, None) }?;
     debug!("Created debug report callback {:?}", callback);
     Ok(Self { loader, callback })
@@ -2,7 +2,9 @@
 //!
 //! Safe usage prohibits:
 //!
-//! * Calling methods or getting fields of [`Instance`] after it has been [destroyed](Instance::destroy).
+//! * Calling methods or getting fields of [`Instance`] after it has been [destroyed](Instance::destroy). In debug
+//!   builds, calling instance functions, through its methods, [`Instance::raw`], or [`Deref`], or destroying again
+//!   panics instead.
 //!
 //! # Destruction
 //!
@@ -20,6 +22,8 @@ use log::debug;
 use thiserror::Error;
 
 use crate::entry::Entry;
+use crate::instance::validation_layer::{VALIDATION_FEATURES_EXTENSION_NAME, ValidationFeatures};
+use crate::util::DestroyedFlag;
 use crate::version::VkVersion;
 
 pub mod validation_layer;
@@ -30,19 +34,21 @@ pub mod surface_extension;
 
 pub struct Instance {
   pub entry: Entry,
-  pub wrapped: VkInstance,
+  wrapped: VkInstance,
   pub features: InstanceFeatures,
+  destroyed: DestroyedFlag,
 }
 
 #[derive(Debug)]
 pub struct InstanceFeatures {
   pub enabled_layers: HashSet<CString>,
   pub enabled_extensions: HashSet<CString>,
+  validation_features: ValidationFeatures,
 }
 
 impl InstanceFeatures {
-  fn new(enabled_layers: HashSet<CString>, enabled_extensions: HashSet<CString>) -> Self {
-    Self { enabled_layers, enabled_extensions }
+  fn new(enabled_layers: HashSet<CString>, enabled_extensions: HashSet<CString>, validation_features: ValidationFeatures) -> Self {
+    Self { enabled_layers, enabled_extensions, validation_features }
   }
 
   pub fn is_layer_enabled<B: Borrow<CStr> + ?Sized>(&self, layer_name: &B) -> bool {
@@ -62,6 +68,7 @@ pub struct InstanceFeaturesQuery {
   required_layers: HashSet<CString>,
   wanted_extensions: HashSet<CString>,
   required_extensions: HashSet<CString>,
+  validation_features: ValidationFeatures,
 }
 
 impl InstanceFeaturesQuery {
@@ -131,7 +138,8 @@ impl Instance {
       wanted_layers,
       required_layers,
       wanted_extensions,
-      required_extensions
+      required_extensions,
+      validation_features,
     } = features_query;
     let (enabled_layers, enabled_layers_raw) = {
       let available = entry.enumerate_instance_layer_properties()
@@ -142,30 +150,63 @@ impl Instance {
         .map_err(|e| RequiredLayersMissing(e.0))?
     };
     let (enabled_extensions, enabled_extensions_raw) = {
-      let available = entry.enumerate_instance_extension_properties()
-        .map_err(|e| EnumerateExtensionFail(e))?
+      let mut available = entry.enumerate_instance_extension_properties()
+        .map_err(|e| EnumerateExtensionFail(e))?;
+      // Extensions provided by enabled layers, such as validation features by the validation layer, are only listed
+      // when enumerating the extensions of the layer.
+      for layer in &enabled_layers {
+        available.extend(entry.enumerate_layer_extension_properties(layer).map_err(|e| EnumerateExtensionFail(e))?);
+      }
+      let available = available
         .into_iter()
         .map(|p| unsafe { CStr::from_ptr(p.extension_name.as_ptr()) }.to_owned());
       get_enabled_or_missing(available, &wanted_extensions, &required_extensions)
         .map_err(|e| RequiredExtensionsMissing(e.0))?
     };
 
-    let create_info = InstanceCreateInfo::builder()
+    // Validation features are passed with instance creation, and only when the extension is available.
+    let validation_features = if enabled_extensions.contains(VALIDATION_FEATURES_EXTENSION_NAME) {
+      validation_features
+    } else {
+      ValidationFeatures::default()
+    };
+    let validation_feature_enables = validation_features.enables();
+    let mut validation_features_create_info = vk::ValidationFeaturesEXT::builder()
+      .enabled_validation_features(&validation_feature_enables);
+
+    let mut create_info = InstanceCreateInfo::builder()
       .application_info(&application_info)
       .enabled_layer_names(&enabled_layers_raw)
       .enabled_extension_names(&enabled_extensions_raw);
+    if validation_features.is_any_enabled() {
+      create_info = create_info.push_next(&mut validation_features_create_info);
+    }
 
     let instance = unsafe { entry.create_instance(&create_info, None) }
       .map_err(|e| InstanceCreateFail(e))?;
     debug!("Created instance {:?}", instance.handle());
-    let features = InstanceFeatures::new(enabled_layers, enabled_extensions);
+    let features = InstanceFeatures::new(enabled_layers, enabled_extensions, validation_features);
 
-    Ok(Self { entry, wrapped: instance, features })
+    Ok(Self { entry, wrapped: instance, features, destroyed: DestroyedFlag::default() })
   }
 
   pub unsafe fn destroy(&mut self) {
+    self.destroyed.assert_not_destroyed("Instance");
     debug!("Destroying instance {:?}", self.wrapped.handle());
     self.wrapped.destroy_instance(None);
+    self.destroyed.set();
+  }
+}
+
+// Raw access
+
+impl Instance {
+  /// Gets the wrapped ash instance. In debug builds, panics if this instance has been destroyed.
+  #[inline]
+  #[track_caller]
+  pub fn raw(&self) -> &VkInstance {
+    self.destroyed.assert_not_destroyed("Instance");
+    &self.wrapped
   }
 }
 
@@ -175,5 +216,6 @@ impl Deref for Instance {
   type Target = VkInstance;
 
   #[inline]
-  fn deref(&self) -> &Self::Target { &self.wrapped }
+  #[track_caller]
+  fn deref(&self) -> &Self::Target { self.raw() }
 }
@@ -40,7 +40,7 @@ pub enum SurfaceCreateError {
 
 impl Surface {
   pub fn new(instance: &Instance, window: RawWindowHandle) -> Result<Self, SurfaceCreateError> {
-    let loader = SurfaceLoader::new(&instance.entry.wrapped, &instance.wrapped);
+    let loader = SurfaceLoader::new(&instance.entry.wrapped, instance.raw());
     debug!("Created surface loader");
     let surface = Self::create_surface(instance, window)?;
     debug!("Created surface {:?}", surface);
@@ -64,7 +64,7 @@ impl Surface {
           .hinstance(handle.hinstance)
           .hwnd(handle.hwnd as *const c_void)
           ;
-        let loader = Win32Surface::new(&instance.entry.wrapped, &instance.wrapped);
+        let loader = Win32Surface::new(&instance.entry.wrapped, instance.raw());
         let surface = unsafe { loader.create_win32_surface(&create_info, None) }
           .map_err(|e| SurfaceCreateFail(e))?;
         Ok(surface)
@@ -98,7 +98,7 @@ impl Surface {
         let create_info = vk::MacOSSurfaceCreateInfoMVK::builder()
           .view(unsafe { handle.ns_view.as_ref() }.unwrap())
           ;
-        let loader = MacOSSurface::new(&instance.entry.wrapped, &instance.wrapped);
+        let loader = MacOSSurface::new(&instance.entry.wrapped, instance.raw());
         let surface = unsafe { loader.create_mac_os_surface_mvk(&create_info, None) }
           .map_err(|e| SurfaceCreateFail(e))?;
         Ok(surface)
@@ -1,15 +1,54 @@
 use std::ffi::CStr;
 
+use ash::vk::ValidationFeatureEnableEXT;
 use byte_strings::c_str;
 
 use crate::instance::{InstanceFeatures, InstanceFeaturesQuery};
 
+// Validation features
+
+/// Optional validation layer features, enabled through the `VK_EXT_validation_features` extension.
+#[derive(Default, Copy, Clone, Eq, PartialEq, Debug)]
+pub struct ValidationFeatures {
+  /// GPU-assisted validation, which instruments shaders to catch, for example, out-of-bounds descriptor indexing.
+  pub gpu_assisted: bool,
+  /// Warnings about API usage that is valid but potentially slow.
+  pub best_practices: bool,
+  /// Validation of synchronization between commands, catching hazards such as missing barriers.
+  pub synchronization: bool,
+}
+
+impl ValidationFeatures {
+  #[inline]
+  pub fn is_any_enabled(&self) -> bool { self.gpu_assisted || self.best_practices || self.synchronization }
+
+  pub(crate) fn enables(&self) -> Vec<ValidationFeatureEnableEXT> {
+    let mut enables = Vec::new();
+    if self.gpu_assisted {
+      enables.push(ValidationFeatureEnableEXT::GPU_ASSISTED);
+    }
+    // Best practices (2) and synchronization validation (4) are newer than the Vulkan headers of ash, so create them
+    // from their raw values.
+    if self.best_practices {
+      enables.push(ValidationFeatureEnableEXT::from_raw(2));
+    }
+    if self.synchronization {
+      enables.push(ValidationFeatureEnableEXT::from_raw(4));
+    }
+    enables
+  }
+}
+
 // API
 
 impl InstanceFeatures {
   pub fn is_validation_layer_enabled(&self) -> bool {
     self.is_layer_enabled(self::VALIDATION_LAYER_NAME)
   }
+
+  /// Gets the validation features that were enabled, which are none if the validation features extension is missing.
+  #[inline]
+  pub fn validation_features(&self) -> ValidationFeatures { self.validation_features }
 }
 
 impl InstanceFeaturesQuery {
@@ -22,8 +61,19 @@ impl InstanceFeaturesQuery {
     self.require_layer(self::VALIDATION_LAYER_NAME);
     self.require_debug_report_extension(); // Debug report extension is needed for reporting validation errors.
   }
+
+  /// Wants `validation_features` to be enabled, if the validation layer is enabled and supports the validation features
+  /// extension.
+  pub fn want_validation_features(&mut self, validation_features: ValidationFeatures) {
+    self.want_extension(self::VALIDATION_FEATURES_EXTENSION_NAME);
+    self.validation_features = validation_features;
+  }
 }
 
 // Layer name
 
 pub const VALIDATION_LAYER_NAME: &'static CStr = c_str!("VK_LAYER_LUNARG_standard_validation");
+
+// Extension name
+
+pub const VALIDATION_FEATURES_EXTENSION_NAME: &'static CStr = c_str!("VK_EXT_validation_features");
@@ -11,13 +11,17 @@ pub mod image;
 pub mod command_pool;
 pub mod command_buffer;
 pub mod sync;
+pub mod query_pool;
 pub mod render_pass;
 pub mod framebuffer;
 pub mod shader;
 pub mod graphics_pipeline;
+pub mod compute_pipeline;
 pub mod allocator;
 pub mod descriptor_set;
 pub mod push_constant;
+pub mod vertex;
+pub mod owned;
 
 pub mod renderer;
 pub mod presenter;
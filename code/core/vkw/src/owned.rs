@@ -0,0 +1,122 @@
+//! Optional RAII guards for Vulkan objects, which destroy their object when dropped. Useful to prevent leaks in code that
+//! creates objects on the side, and to clean up earlier objects when creating a later one fails. Performance-critical
+//! and long-lived objects can keep using raw handles with manual destruction.
+//!
+//! # Safety
+//!
+//! Safe usage prohibits:
+//!
+//! * Dropping an [`Owned`] object after its [`Device`] has been [destroyed](Device::destroy). The borrow of the device
+//!   does not prevent this, as devices are destroyed manually.
+//! * Dropping an [`Owned`] object while it is in use by the device.
+
+use std::fmt::{self, Debug, Formatter};
+use std::mem;
+use std::ops::Deref;
+
+use ash::vk::{BufferView, CommandPool, DescriptorPool, DescriptorSetLayout, Fence, Framebuffer, ImageView, Pipeline, PipelineCache, PipelineLayout, QueryPool, RenderPass, Sampler, Semaphore, ShaderModule};
+
+use crate::device::Device;
+
+// Device object trait
+
+/// Vulkan objects that are destroyed through a [Device].
+pub trait DeviceObject: Copy + Debug {
+  /// Destroys this object.
+  unsafe fn destroy_with(self, device: &Device);
+}
+
+macro_rules! impl_device_object {
+  ($($ty:ty => $destroy:ident),* $(,)?) => {
+    $(impl DeviceObject for $ty {
+      #[inline]
+      unsafe fn destroy_with(self, device: &Device) { device.$destroy(self) }
+    })*
+  };
+}
+
+impl_device_object!(
+  BufferView => destroy_buffer_view,
+  CommandPool => destroy_command_pool,
+  DescriptorPool => destroy_descriptor_pool,
+  DescriptorSetLayout => destroy_descriptor_set_layout,
+  Fence => destroy_fence,
+  Framebuffer => destroy_framebuffer,
+  ImageView => destroy_image_view,
+  Pipeline => destroy_pipeline,
+  PipelineCache => destroy_pipeline_cache,
+  PipelineLayout => destroy_pipeline_layout,
+  QueryPool => destroy_query_pool,
+  RenderPass => destroy_render_pass,
+  Sampler => destroy_sampler,
+  Semaphore => destroy_semaphore,
+  ShaderModule => destroy_shader_module,
+);
+
+// Owned object
+
+/// Guard that owns Vulkan object `T`, destroying it when dropped. Dereferences to the raw handle.
+pub struct Owned<'a, T: DeviceObject> {
+  device: &'a Device,
+  handle: T,
+}
+
+pub type OwnedBufferView<'a> = Owned<'a, BufferView>;
+pub type OwnedCommandPool<'a> = Owned<'a, CommandPool>;
+pub type OwnedDescriptorPool<'a> = Owned<'a, DescriptorPool>;
+pub type OwnedDescriptorSetLayout<'a> = Owned<'a, DescriptorSetLayout>;
+pub type OwnedFence<'a> = Owned<'a, Fence>;
+pub type OwnedFramebuffer<'a> = Owned<'a, Framebuffer>;
+pub type OwnedImageView<'a> = Owned<'a, ImageView>;
+pub type OwnedPipeline<'a> = Owned<'a, Pipeline>;
+pub type OwnedPipelineCache<'a> = Owned<'a, PipelineCache>;
+/// Owned pipeline layout. Must not be used for layouts [acquired](Device::acquire_pipeline_layout) from the pipeline
+/// layout cache, which must be released instead.
+pub type OwnedPipelineLayout<'a> = Owned<'a, PipelineLayout>;
+pub type OwnedRenderPass<'a> = Owned<'a, RenderPass>;
+pub type OwnedSampler<'a> = Owned<'a, Sampler>;
+pub type OwnedSemaphore<'a> = Owned<'a, Semaphore>;
+pub type OwnedShaderModule<'a> = Owned<'a, ShaderModule>;
+
+impl<'a, T: DeviceObject> Owned<'a, T> {
+  /// Takes ownership of `handle`, which must have been created with `device` and must not be destroyed elsewhere.
+  #[inline]
+  pub unsafe fn new(device: &'a Device, handle: T) -> Self { Self { device, handle } }
+
+  #[inline]
+  pub fn handle(&self) -> T { self.handle }
+
+  /// Releases ownership of the object without destroying it, returning its raw handle.
+  #[inline]
+  pub fn into_raw(self) -> T {
+    let handle = self.handle;
+    mem::forget(self);
+    handle
+  }
+}
+
+impl Device {
+  /// Takes ownership of `handle`, returning a guard that destroys it when dropped. See [Owned::new].
+  #[inline]
+  pub unsafe fn owned<T: DeviceObject>(&self, handle: T) -> Owned<T> { Owned::new(self, handle) }
+}
+
+impl<T: DeviceObject> Deref for Owned<'_, T> {
+  type Target = T;
+
+  #[inline]
+  fn deref(&self) -> &Self::Target { &self.handle }
+}
+
+impl<T: DeviceObject> Drop for Owned<'_, T> {
+  fn drop(&mut self) {
+    // CORRECTNESS: the object is owned by this guard, and the safety requirements of this module hold.
+    unsafe { self.handle.destroy_with(self.device); }
+  }
+}
+
+impl<T: DeviceObject> Debug for Owned<'_, T> {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    f.debug_tuple("Owned").field(&self.handle).finish()
+  }
+}
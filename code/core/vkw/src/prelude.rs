@@ -7,7 +7,8 @@ pub use ash::{
     BlendFactor, BlendOp, Buffer, BufferCopy, BufferCreateInfo, BufferUsageFlags, BufferView, ColorComponentFlags,
     CommandBuffer, CommandPool, CullModeFlags,
     DescriptorBindingFlagsEXT, DescriptorPool, DescriptorSet,
-    DescriptorSetLayout, DescriptorSetLayoutBinding, DescriptorType, DeviceSize, DynamicState, Extent2D,
+    DescriptorSetLayout, DescriptorSetLayoutBinding, DescriptorType, DeviceSize, DrawIndexedIndirectCommand, DynamicState,
+    Extent2D,
     Fence, Format, FormatFeatureFlags, Framebuffer, FrontFace, ImageTiling, IndexType,
     LogicOp, PhysicalDeviceFeatures, Pipeline, PipelineBindPoint, PipelineCache, PipelineLayout, PipelineShaderStageCreateInfoBuilder,
     PolygonMode, PresentModeKHR, PrimitiveTopology, PushConstantRange,
@@ -19,16 +20,18 @@ pub use vk_mem::{AllocationInfo, MemoryUsage};
 
 pub use crate::{
   allocator::{Allocator, BufferAllocation},
-  descriptor_set::{self, DescriptorSetUpdateBuilder, WriteDescriptorSetBuilder},
-  device::{Device, DeviceFeatures, DeviceFeaturesQuery, swapchain_extension::{Swapchain, SwapchainFeaturesQuery}},
+  descriptor_set::{self, DescriptorAllocator, DescriptorSetUpdateBuilder, WriteDescriptorSetBuilder},
+  device::{Device, DeviceFeatures, DeviceFeaturesQuery, draw_indirect_count_extension::DrawIndirectCount, swapchain_extension::{Swapchain, SwapchainFeaturesQuery}},
   image::texture::Texture,
-  instance::{debug_report_extension::DebugReport, Instance, InstanceFeatures, InstanceFeaturesQuery, surface_extension::Surface},
+  owned::{DeviceObject, Owned},
+  instance::{debug_report_extension::DebugReport, Instance, InstanceFeatures, InstanceFeaturesQuery, surface_extension::Surface, validation_layer::ValidationFeatures},
   presenter::Presenter,
   push_constant,
   renderer::{Renderer, RenderState},
-  shader::ShaderModuleEx,
+  shader::{ShaderModuleEx, SpecializationBuilder},
   surface_change_handler::SurfaceChangeHandler,
   timeout::Timeout,
   version::VkVersion,
+  vertex::Vertex,
 };
 
@@ -1,5 +1,5 @@
 use ash::version::DeviceV1_0;
-use ash::vk::{self, CommandBuffer, Extent2D, Framebuffer, Offset2D, Rect2D, Semaphore, Viewport};
+use ash::vk::{self, CommandBuffer, Extent2D, Framebuffer, Offset2D, Rect2D, Result as VkError, Semaphore, Viewport};
 use log::debug;
 use crate::device::Device;
 use crate::device::swapchain_extension::{AcquireNextImageError, QueuePresentError, Swapchain};
@@ -49,41 +49,59 @@ impl Presenter {
     framebuffers: I,
   ) -> Result<(), FramebufferCreateError> {
     debug!("Recreating presenter");
-    for image_state in self.swapchain_image_states.iter() {
+    for image_state in self.replace_swapchain_image_states(framebuffers).iter() {
       unsafe { device.destroy_framebuffer(image_state.framebuffer) };
     }
-    self.swapchain_image_states = Self::create_swapchain_image_states(framebuffers);
     Ok(())
   }
 
+  /// Replaces the image states with one per framebuffer in `framebuffers`, which may be a different number than before,
+  /// and returns the previous image states.
+  fn replace_swapchain_image_states<I: IntoIterator<Item=Framebuffer>>(&mut self, framebuffers: I) -> Box<[SwapchainImageState]> {
+    std::mem::replace(&mut self.swapchain_image_states, Self::create_swapchain_image_states(framebuffers))
+  }
+
+
+  /// Number of swapchain images that this presenter has image states for.
+  #[inline]
+  pub fn image_count(&self) -> u32 { self.swapchain_image_states.len() as u32 }
 
   pub fn full_render_area(&self, extent: Extent2D) -> Rect2D {
     return Rect2D { offset: Offset2D::default(), extent };
   }
 
+  /// Sets the dynamic viewport and scissor state to the full `extent`.
   pub unsafe fn set_dynamic_state(&self, device: &Device, command_buffer: CommandBuffer, extent: Extent2D) {
+    let full_render_area = self.full_render_area(extent);
+    self.set_viewport_and_scissor(device, command_buffer, full_render_area, full_render_area);
+  }
+
+  /// Sets the dynamic viewport state to `viewport` with a depth range of [0, 1], and the dynamic scissor state to
+  /// `scissor`, for rendering into a sub-region of the render area.
+  pub unsafe fn set_viewport_and_scissor(&self, device: &Device, command_buffer: CommandBuffer, viewport: Rect2D, scissor: Rect2D) {
     device.cmd_set_viewport(command_buffer, 0, &[Viewport {
-      x: 0.0,
-      y: 0.0,
-      width: extent.width as f32,
-      height: extent.height as f32,
+      x: viewport.offset.x as f32,
+      y: viewport.offset.y as f32,
+      width: viewport.extent.width as f32,
+      height: viewport.extent.height as f32,
       min_depth: 0.0,
       max_depth: 1.0,
     }]);
-    device.cmd_set_scissor(command_buffer, 0, &[self.full_render_area(extent)]);
+    device.cmd_set_scissor(command_buffer, 0, &[scissor]);
   }
 
+  /// Acquires the next swapchain image. Returns `None` if the swapchain is out of date, after signalling
+  /// `surface_change_handler` to recreate it. In that case, `image_acquired_semaphore` is not signalled and the frame
+  /// should be skipped.
   pub fn acquire_image_state(
     &self,
     swapchain: &Swapchain,
     image_acquired_semaphore: Option<Semaphore>,
     surface_change_handler: &mut SurfaceChangeHandler,
-  ) -> Result<&SwapchainImageState, AcquireNextImageError> {
-    let (swapchain_image_index, suboptimal_swapchain) = unsafe { swapchain.acquire_next_image(Timeout::Infinite, image_acquired_semaphore, None)? };
-    if suboptimal_swapchain {
-      surface_change_handler.signal_suboptimal_swapchain();
-    }
-    Ok(&self.swapchain_image_states[swapchain_image_index as usize])
+  ) -> Result<Option<&SwapchainImageState>, AcquireNextImageError> {
+    let result = unsafe { swapchain.acquire_next_image(Timeout::Infinite, image_acquired_semaphore, None) };
+    let swapchain_image_index = handle_acquire_result(result, surface_change_handler)?;
+    Ok(swapchain_image_index.map(|index| &self.swapchain_image_states[index as usize]))
   }
 
   pub fn present(
@@ -107,3 +125,104 @@ impl Presenter {
     return Ok(());
   }
 }
+
+/// Maps `result` of acquiring the next swapchain image to the index of the acquired image, or to `None` if the swapchain
+/// is out of date. Signals `surface_change_handler` to recreate the swapchain when it is out of date or suboptimal.
+fn handle_acquire_result(
+  result: Result<(u32, bool), AcquireNextImageError>,
+  surface_change_handler: &mut SurfaceChangeHandler,
+) -> Result<Option<u32>, AcquireNextImageError> {
+  let (swapchain_image_index, suboptimal_swapchain) = match result {
+    Err(AcquireNextImageError(VkError::ERROR_OUT_OF_DATE_KHR)) => {
+      surface_change_handler.signal_suboptimal_swapchain();
+      return Ok(None);
+    }
+    result => result?,
+  };
+  if suboptimal_swapchain {
+    surface_change_handler.signal_suboptimal_swapchain();
+  }
+  Ok(Some(swapchain_image_index))
+}
+
+#[cfg(test)]
+mod tests {
+  use ash::vk::{Extent2D, Framebuffer, Handle, Result as VkError};
+
+  use crate::device::swapchain_extension::AcquireNextImageError;
+  use crate::surface_change_handler::SurfaceChangeHandler;
+
+  use super::{handle_acquire_result, Presenter};
+
+  fn framebuffers(raw: &[u64]) -> Vec<Framebuffer> { raw.iter().map(|r| Framebuffer::from_raw(*r)).collect() }
+
+  fn image_states(presenter: &Presenter) -> Vec<(u32, u64)> {
+    presenter.swapchain_image_states.iter().map(|s| (s.index, s.framebuffer.as_raw())).collect()
+  }
+
+  #[test]
+  fn new_creates_image_state_per_framebuffer() {
+    let presenter = Presenter::new(framebuffers(&[10, 11])).unwrap();
+    assert_eq!(presenter.image_count(), 2);
+    assert_eq!(image_states(&presenter), vec![(0, 10), (1, 11)]);
+  }
+
+  #[test]
+  fn replace_image_states_with_more_images() {
+    let mut presenter = Presenter::new(framebuffers(&[10, 11])).unwrap();
+    let old = presenter.replace_swapchain_image_states(framebuffers(&[20, 21, 22]));
+    assert_eq!(old.iter().map(|s| s.framebuffer.as_raw()).collect::<Vec<_>>(), vec![10, 11]);
+    assert_eq!(presenter.image_count(), 3);
+    assert_eq!(image_states(&presenter), vec![(0, 20), (1, 21), (2, 22)]);
+  }
+
+  #[test]
+  fn replace_image_states_with_fewer_images() {
+    let mut presenter = Presenter::new(framebuffers(&[10, 11, 12])).unwrap();
+    let old = presenter.replace_swapchain_image_states(framebuffers(&[20, 21]));
+    assert_eq!(old.len(), 3);
+    assert_eq!(presenter.image_count(), 2);
+    assert_eq!(image_states(&presenter), vec![(0, 20), (1, 21)]);
+  }
+
+  const EXTENT: Extent2D = Extent2D { width: 800, height: 600 };
+
+  /// Queries the surface change of `surface_change_handler` as `(width, height)`, as [Extent2D] does not implement
+  /// `PartialEq`.
+  fn query_surface_change(surface_change_handler: &mut SurfaceChangeHandler) -> Option<(u32, u32)> {
+    surface_change_handler.query_surface_change(EXTENT).map(|extent| (extent.width, extent.height))
+  }
+
+  #[test]
+  fn out_of_date_acquire_skips_frame_and_recreates_next_frame() {
+    let mut surface_change_handler = SurfaceChangeHandler::new();
+    let result = handle_acquire_result(Err(AcquireNextImageError(VkError::ERROR_OUT_OF_DATE_KHR)), &mut surface_change_handler);
+    assert!(matches!(result, Ok(None)), "Expected Ok(None), got {:?}", result);
+    assert_eq!(query_surface_change(&mut surface_change_handler), Some((800, 600)));
+    assert_eq!(query_surface_change(&mut surface_change_handler), None);
+  }
+
+  #[test]
+  fn suboptimal_acquire_renders_frame_and_recreates_next_frame() {
+    let mut surface_change_handler = SurfaceChangeHandler::new();
+    let result = handle_acquire_result(Ok((1, true)), &mut surface_change_handler);
+    assert!(matches!(result, Ok(Some(1))), "Expected Ok(Some(1)), got {:?}", result);
+    assert_eq!(query_surface_change(&mut surface_change_handler), Some((800, 600)));
+  }
+
+  #[test]
+  fn optimal_acquire_does_not_recreate() {
+    let mut surface_change_handler = SurfaceChangeHandler::new();
+    let result = handle_acquire_result(Ok((0, false)), &mut surface_change_handler);
+    assert!(matches!(result, Ok(Some(0))), "Expected Ok(Some(0)), got {:?}", result);
+    assert_eq!(query_surface_change(&mut surface_change_handler), None);
+  }
+
+  #[test]
+  fn other_acquire_errors_are_propagated() {
+    let mut surface_change_handler = SurfaceChangeHandler::new();
+    let result = handle_acquire_result(Err(AcquireNextImageError(VkError::ERROR_DEVICE_LOST)), &mut surface_change_handler);
+    assert!(matches!(result, Err(AcquireNextImageError(VkError::ERROR_DEVICE_LOST))), "Expected device lost error, got {:?}", result);
+    assert_eq!(query_surface_change(&mut surface_change_handler), None);
+  }
+}
@@ -19,3 +19,7 @@ pub fn fragment_range(size: u32, offset: u32) -> PushConstantRange {
 pub fn vertex_and_fragment_range(size: u32, offset: u32) -> PushConstantRange {
   range(ShaderStageFlags::VERTEX | ShaderStageFlags::FRAGMENT, size, offset)
 }
+
+pub fn compute_range(size: u32, offset: u32) -> PushConstantRange {
+  range(ShaderStageFlags::COMPUTE, size, offset)
+}
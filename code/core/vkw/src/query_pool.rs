@@ -0,0 +1,88 @@
+use std::time::Duration;
+
+use ash::version::{DeviceV1_0, InstanceV1_0};
+use ash::vk::{self, CommandBuffer, PipelineStageFlags, QueryPool, QueryResultFlags, QueryType, Result as VkError};
+use log::trace;
+use thiserror::Error;
+
+use crate::device::Device;
+
+// Timestamp support
+
+impl Device {
+  /// Whether timestamps can be written on the graphics queue.
+  pub fn supports_graphics_queue_timestamps(&self) -> bool {
+    let queue_families_properties = unsafe { self.instance.get_physical_device_queue_family_properties(self.physical_device) };
+    queue_families_properties.get(self.graphics_queue_index as usize)
+      .map_or(false, |properties| properties.timestamp_valid_bits != 0)
+  }
+
+  /// Gets the number of nanoseconds it takes for a timestamp to be incremented by 1.
+  pub fn timestamp_period(&self) -> f32 {
+    unsafe { self.instance.get_physical_device_properties(self.physical_device) }.limits.timestamp_period
+  }
+
+  /// Converts the difference between timestamps `begin` and `end` into a duration.
+  pub fn timestamp_duration(&self, begin: u64, end: u64) -> Duration {
+    let ticks = end.saturating_sub(begin);
+    Duration::from_nanos((ticks as f64 * self.timestamp_period() as f64) as u64)
+  }
+}
+
+// Query pool creation and destruction
+
+#[derive(Error, Debug)]
+#[error("Failed to create query pool: {0:?}")]
+pub struct QueryPoolCreateError(#[from] VkError);
+
+impl Device {
+  /// Creates a query pool with `count` timestamp queries.
+  pub unsafe fn create_timestamp_query_pool(&self, count: u32) -> Result<QueryPool, QueryPoolCreateError> {
+    let create_info = vk::QueryPoolCreateInfo::builder()
+      .query_type(QueryType::TIMESTAMP)
+      .query_count(count)
+      ;
+    let query_pool = self.raw().create_query_pool(&create_info, None)?;
+    trace!("Created timestamp query pool {:?} with {} queries", query_pool, count);
+    Ok(query_pool)
+  }
+
+  pub unsafe fn destroy_query_pool(&self, query_pool: QueryPool) {
+    trace!("Destroying query pool {:?}", query_pool);
+    self.raw().destroy_query_pool(query_pool, None);
+  }
+}
+
+// Timestamp recording
+
+impl Device {
+  /// Records a reset of `count` queries starting at `first_query` into `command_buffer`, which must be outside of a
+  /// render pass. Queries must be reset before they are written.
+  pub unsafe fn cmd_reset_queries(&self, command_buffer: CommandBuffer, query_pool: QueryPool, first_query: u32, count: u32) {
+    self.raw().cmd_reset_query_pool(command_buffer, query_pool, first_query, count);
+  }
+
+  /// Records writing a timestamp into `query` when all previous commands have completed `stage`.
+  pub unsafe fn cmd_write_timestamp(&self, command_buffer: CommandBuffer, stage: PipelineStageFlags, query_pool: QueryPool, query: u32) {
+    self.raw().cmd_write_timestamp(command_buffer, stage, query_pool, query);
+  }
+}
+
+// Timestamp results
+
+#[derive(Error, Debug)]
+#[error("Failed to get query results: {0:?}")]
+pub struct QueryResultsGetError(#[from] VkError);
+
+impl Device {
+  /// Gets the 64-bit results of `count` timestamp queries starting at `first_query`, without waiting. Returns `None` if
+  /// not all results are available yet. The queries must have been reset.
+  pub unsafe fn get_timestamps(&self, query_pool: QueryPool, first_query: u32, count: u32) -> Result<Option<Vec<u64>>, QueryResultsGetError> {
+    let mut timestamps = vec![0u64; count as usize];
+    match self.raw().get_query_pool_results(query_pool, first_query, count, &mut timestamps, QueryResultFlags::TYPE_64) {
+      Ok(()) => Ok(Some(timestamps)),
+      Err(VkError::NOT_READY) => Ok(None),
+      Err(e) => Err(e.into()),
+    }
+  }
+}
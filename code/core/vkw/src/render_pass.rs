@@ -13,14 +13,14 @@ pub struct RenderPassCreateError(#[from] VkError);
 
 impl Device {
   pub unsafe fn create_render_pass(&self, create_info: &RenderPassCreateInfo) -> Result<RenderPass, RenderPassCreateError> {
-    let render_pass = self.wrapped.create_render_pass(create_info, None)?;
+    let render_pass = self.raw().create_render_pass(create_info, None)?;
     debug!("Created render pass {:?}", render_pass);
     Ok(render_pass)
   }
 
   pub unsafe fn destroy_render_pass(&self, render_pass: RenderPass) {
     debug!("Destroying render pass {:?}", render_pass);
-    self.wrapped.destroy_render_pass(render_pass, None)
+    self.raw().destroy_render_pass(render_pass, None)
   }
 }
 
@@ -41,10 +41,12 @@ impl Device {
       .render_area(render_area)
       .clear_values(clear_values)
       ;
-    self.wrapped.cmd_begin_render_pass(command_buffer, &begin_info, vk::SubpassContents::INLINE);
+    self.raw().cmd_begin_render_pass(command_buffer, &begin_info, vk::SubpassContents::INLINE);
+    self.render_pass_tracker.begin(command_buffer, render_pass);
   }
 
   pub unsafe fn end_render_pass(&self, command_buffer: CommandBuffer) {
-    self.wrapped.cmd_end_render_pass(command_buffer)
+    self.raw().cmd_end_render_pass(command_buffer);
+    self.render_pass_tracker.end(command_buffer);
   }
 }
@@ -1,41 +1,46 @@
 use std::fmt::Debug;
 use std::num::NonZeroU32;
 
-use ash::vk::{CommandPool, Fence, Semaphore};
+use ash::vk::{CommandPool, Fence};
 use thiserror::Error;
 
 use crate::command_pool::{CommandPoolCreateError, CommandPoolResetError};
 use crate::device::Device;
-use crate::sync::{FenceCreateError, FenceResetError, FenceWaitError, SemaphoreCreateError};
+use crate::sync::{FenceCreateError, FenceResetError, FenceWaitError};
 use crate::timeout::Timeout;
 
 // Renderer
 
+/// Ring of per-frame render states. `Send` when the custom state `T` is, so that it can be moved to a render thread.
+/// Frame states are advanced through `&mut self`, and their command pools must only be used by the thread that owns
+/// the renderer.
+///
+/// Render states do not own swapchain semaphores, as a frame may render to any number of swapchains. Resources that
+/// are per render state but owned elsewhere, such as those semaphores, are indexed with [RenderState::index].
 pub struct Renderer<T> {
   count: usize,
   index: usize,
+  max_queued_frames: usize,
   states: Box<[RenderState]>,
   states_custom: Box<[T]>,
 }
 
 pub struct RenderState {
+  /// Index of this render state in the ring, in `0..state_count()`.
+  pub index: u32,
   pub command_pool: CommandPool,
-  pub image_acquired_semaphore: Semaphore,
-  pub render_complete_semaphore: Semaphore,
   pub render_complete_fence: Fence,
   // TODO: track buffer allocations
 }
 
+crate::assert_send_sync!(Renderer<()>);
+
 // Creation and destruction
 
 #[derive(Error, Debug)]
 pub enum RenderCreateError {
   #[error(transparent)]
   CommandPoolCreateFail(#[from] CommandPoolCreateError),
-  #[error("Failed to create image acquired semaphore")]
-  ImageAcquiredSemaphoreCreateFail(#[source] SemaphoreCreateError),
-  #[error("Failed to create render complete semaphore")]
-  RenderCompleteSemaphoreCreateFail(#[source] SemaphoreCreateError),
   #[error("Failed to create render complete fence")]
   RenderCompleteFenceCreateFail(#[from] FenceCreateError),
   #[error("Failed to create custom render state")]
@@ -53,12 +58,11 @@ impl<T> Renderer<T> {
     let (states, states_custom) = {
       let mut states = Vec::with_capacity(count);
       let mut states_custom: Vec<T> = Vec::with_capacity(count);
-      for _i in 0..count {
+      for index in 0..count {
         let state = unsafe {
           RenderState {
+            index: index as u32,
             command_pool: device.create_command_pool(false, false)?,
-            image_acquired_semaphore: device.create_semaphore().map_err(|e| ImageAcquiredSemaphoreCreateFail(e))?,
-            render_complete_semaphore: device.create_semaphore().map_err(|e| RenderCompleteSemaphoreCreateFail(e))?,
             render_complete_fence: device.create_fence(true)?,
           }
         };
@@ -72,17 +76,16 @@ impl<T> Renderer<T> {
     Ok(Renderer {
       count,
       index: count - 1,
+      max_queued_frames: count,
       states,
       states_custom,
     })
   }
 
-  pub unsafe fn destroy<F: Fn(&RenderState, &T)>(&self, device: &Device, destroy_fn: F) {
-    for (state, state_custom) in self.states.iter().zip(self.states_custom.iter()) {
+  pub unsafe fn destroy<F: FnMut(&RenderState, &mut T)>(&mut self, device: &Device, mut destroy_fn: F) {
+    for (state, state_custom) in self.states.iter().zip(self.states_custom.iter_mut()) {
       destroy_fn(state, state_custom);
       device.destroy_command_pool(state.command_pool);
-      device.destroy_semaphore(state.image_acquired_semaphore);
-      device.destroy_semaphore(state.render_complete_semaphore);
       device.destroy_fence(state.render_complete_fence);
     }
   }
@@ -93,11 +96,29 @@ impl<T> Renderer<T> {
 impl<T> Renderer<T> {
   pub fn next_render_state(&mut self, device: &Device) -> Result<(&mut RenderState, &mut T), RenderStateWaitAndResetError> {
     self.index = (self.index + 1) % self.count;
+    if self.max_queued_frames < self.count {
+      // Bound frame latency by waiting for the frame that was submitted `max_queued_frames` frames ago. Its fence is
+      // not reset here; that happens when its render state is reused.
+      let latency_index = (self.index + self.count - self.max_queued_frames) % self.count;
+      unsafe { device.wait_for_fence(self.states[latency_index].render_complete_fence, Timeout::Infinite)?; }
+    }
     let state = &mut self.states[self.index];
     state.wait_and_reset(device)?;
     let state_custom = &mut self.states_custom[self.index];
     return Ok((state, state_custom));
   }
+
+  #[inline]
+  pub fn state_count(&self) -> u32 { self.count as u32 }
+
+  #[inline]
+  pub fn max_queued_frames(&self) -> u32 { self.max_queued_frames as u32 }
+
+  /// Sets the maximum number of frames that are submitted but not yet completed by the GPU, bounding how far the CPU
+  /// runs ahead of the GPU. Clamped to the number of render states, which is also the default.
+  pub fn set_max_queued_frames(&mut self, max_queued_frames: NonZeroU32) {
+    self.max_queued_frames = (max_queued_frames.get() as usize).min(self.count);
+  }
 }
 
 #[derive(Error, Debug)]
@@ -2,9 +2,11 @@ use std::io::{self, Cursor};
 
 use ash::util::read_spv;
 use ash::version::DeviceV1_0;
-use ash::vk::{self, PipelineShaderStageCreateInfoBuilder, Result as VkError, ShaderModule, ShaderStageFlags, SpecializationInfo};
+use ash::vk::{self, PipelineShaderStageCreateInfoBuilder, Result as VkError, ShaderModule, ShaderStageFlags, SpecializationInfo, SpecializationInfoBuilder, SpecializationMapEntry};
 use byte_strings::c_str;
 use log::debug;
+#[cfg(feature = "runtime-shaders")]
+use log::warn;
 use thiserror::Error;
 
 use crate::device::Device;
@@ -26,14 +28,44 @@ impl Device {
     let create_info = vk::ShaderModuleCreateInfo::builder()
       .code(&code)
       ;
-    let shader_module = self.wrapped.create_shader_module(&create_info, None)?;
+    let shader_module = self.raw().create_shader_module(&create_info, None)?;
     debug!("Created shader module {:?}", shader_module);
     Ok(shader_module)
   }
 
   pub unsafe fn destroy_shader_module(&self, shader_module: ShaderModule) {
     debug!("Destroying shader module {:?}", shader_module);
-    self.wrapped.destroy_shader_module(shader_module, None);
+    self.raw().destroy_shader_module(shader_module, None);
+  }
+}
+
+// Runtime compilation
+
+#[cfg(feature = "runtime-shaders")]
+pub use shaderc::ShaderKind;
+
+#[cfg(feature = "runtime-shaders")]
+#[derive(Error, Debug)]
+pub enum ShaderCompileError {
+  #[error("Failed to create shaderc compiler")]
+  CompilerCreateFail,
+  #[error("Failed to compile GLSL to SPIR-V: {0}")]
+  CompileFail(#[from] shaderc::Error),
+  #[error(transparent)]
+  ShaderModuleCreateFail(#[from] ShaderModuleCreateError),
+}
+
+#[cfg(feature = "runtime-shaders")]
+impl Device {
+  /// Compiles GLSL `source` of `kind` to SPIR-V at runtime with shaderc, and creates a shader module from it. Intended
+  /// for iterating on shaders without rebuilding; shaders are otherwise compiled at build time.
+  pub unsafe fn create_shader_module_from_glsl(&self, source: &str, kind: ShaderKind) -> Result<ShaderModule, ShaderCompileError> {
+    let mut compiler = shaderc::Compiler::new().ok_or(ShaderCompileError::CompilerCreateFail)?;
+    let artifact = compiler.compile_into_spirv(source, kind, "<runtime>", "main", None)?;
+    if artifact.get_num_warnings() > 0 {
+      warn!("Compiled GLSL shader with warnings:\n{}", artifact.get_warning_messages());
+    }
+    Ok(self.create_shader_module(artifact.as_binary_u8())?)
   }
 }
 
@@ -85,3 +117,56 @@ impl ShaderModuleEx for ShaderModule {
     create_info
   }
 }
+
+// Specialization
+
+/// Scalar types that can be the value of a specialization constant, with their in-memory representation matching the
+/// SPIR-V scalar type of the constant.
+pub trait SpecializationConstant: Copy {
+  fn to_bytes(self) -> Vec<u8>;
+}
+
+macro_rules! impl_specialization_constant {
+  ($($ty:ty),*) => {
+    $(impl SpecializationConstant for $ty {
+      #[inline]
+      fn to_bytes(self) -> Vec<u8> { self.to_ne_bytes().to_vec() }
+    })*
+  };
+}
+
+impl_specialization_constant!(u32, i32, f32, u64, i64, f64);
+
+// Booleans are 32-bit (`VkBool32`) specialization constants.
+impl SpecializationConstant for bool {
+  #[inline]
+  fn to_bytes(self) -> Vec<u8> { (self as u32).to_bytes() }
+}
+
+/// Builds the map entries and data blob of a [SpecializationInfo] from `(constant_id, value)` pairs.
+#[derive(Default, Clone, Debug)]
+pub struct SpecializationBuilder {
+  map_entries: Vec<SpecializationMapEntry>,
+  data: Vec<u8>,
+}
+
+impl SpecializationBuilder {
+  pub fn new() -> Self { Self::default() }
+
+  /// Adds specialization constant `value` for the constant with `constant_id` in the shader.
+  pub fn add<T: SpecializationConstant>(mut self, constant_id: u32, value: T) -> Self {
+    let offset = self.data.len() as u32;
+    let bytes = value.to_bytes();
+    self.map_entries.push(SpecializationMapEntry { constant_id, offset, size: bytes.len() });
+    self.data.extend(bytes);
+    self
+  }
+
+  /// Gets the specialization info referring to the constants added to this builder, to pass to
+  /// [ShaderModuleEx::create_shader_stage]. This builder must outlive pipeline creation with the info.
+  pub fn info(&self) -> SpecializationInfoBuilder {
+    vk::SpecializationInfo::builder()
+      .map_entries(&self.map_entries)
+      .data(&self.data)
+  }
+}
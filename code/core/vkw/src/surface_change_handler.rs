@@ -20,6 +20,15 @@ impl SurfaceChangeHandler {
     self.signal_suboptimal_swapchain = true;
   }
 
+  /// Returns whether the last signalled screen resize was to a zero-sized extent, e.g. because the window was minimized.
+  /// Swapchains cannot be created with a zero-sized extent, so the change should stay pending until this is false.
+  pub fn is_zero_extent_signalled(&self) -> bool {
+    match self.signal_screen_resize {
+      Some(extent) => extent.width == 0 || extent.height == 0,
+      None => false,
+    }
+  }
+
   pub fn query_surface_change(&mut self, swapchain_extent: Extent2D) -> Option<Extent2D> {
     let new_extent = self.signal_screen_resize;
     self.signal_screen_resize = None;
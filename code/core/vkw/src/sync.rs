@@ -19,14 +19,14 @@ impl Device {
     let create_info = FenceCreateInfo::builder()
       .flags(flags)
       ;
-    let fence = self.wrapped.create_fence(&create_info, None)?;
+    let fence = self.raw().create_fence(&create_info, None)?;
     trace!("Created fence {:?}", fence);
     Ok(fence)
   }
 
   pub unsafe fn destroy_fence(&self, fence: Fence) {
     trace!("Destroying fence {:?}", fence);
-    self.wrapped.destroy_fence(fence, None)
+    self.raw().destroy_fence(fence, None)
   }
 }
 
@@ -39,7 +39,7 @@ pub struct FenceWaitError(#[from] VkError);
 impl Device {
   pub unsafe fn wait_for_fences(&self, fences: &[Fence], wait_all: bool, timeout: Timeout) -> Result<(), FenceWaitError> {
     trace!("Waiting for {} fences {:?}", if wait_all { "all" } else { "one of" }, fences);
-    Ok(self.wrapped.wait_for_fences(fences, wait_all, timeout.into())?)
+    Ok(self.raw().wait_for_fences(fences, wait_all, timeout.into())?)
   }
 
   pub unsafe fn wait_for_fence(&self, fence: Fence, timeout: Timeout) -> Result<(), FenceWaitError> {
@@ -56,7 +56,7 @@ pub struct FenceResetError(#[from] VkError);
 impl Device {
   pub unsafe fn reset_fences(&self, fences: &[Fence]) -> Result<(), FenceResetError> {
     trace!("Resetting fences {:?}", fences);
-    Ok(self.wrapped.reset_fences(fences)?)
+    Ok(self.raw().reset_fences(fences)?)
   }
 
   pub unsafe fn reset_fence(&self, fence: Fence) -> Result<(), FenceResetError> {
@@ -73,14 +73,14 @@ pub struct SemaphoreCreateError(#[from] VkError);
 impl Device {
   pub unsafe fn create_semaphore(&self) -> Result<Semaphore, SemaphoreCreateError> {
     let create_info = vk::SemaphoreCreateInfo::builder();
-    let semaphore = self.wrapped.create_semaphore(&create_info, None)?;
+    let semaphore = self.raw().create_semaphore(&create_info, None)?;
     trace!("Created semaphore {:?}", semaphore);
     Ok(semaphore)
   }
 
   pub unsafe fn destroy_semaphore(&self, semaphore: Semaphore) {
     trace!("Destroying semaphore {:?}", semaphore);
-    self.wrapped.destroy_semaphore(semaphore, None)
+    self.raw().destroy_semaphore(semaphore, None)
   }
 }
 
@@ -93,7 +93,7 @@ pub struct QueueWaitIdleError(#[from] VkError);
 impl Device {
   pub unsafe fn queue_wait_idle(&self, queue: Queue) -> Result<(), QueueWaitIdleError> {
     debug!("Waiting for queue {:?} idle", queue);
-    Ok(self.wrapped.queue_wait_idle(queue)?)
+    Ok(self.raw().queue_wait_idle(queue)?)
   }
 }
 
@@ -103,7 +103,7 @@ pub struct DeviceWaitIdleError(#[from] VkError);
 
 impl Device {
   pub unsafe fn device_wait_idle(&self) -> Result<(), DeviceWaitIdleError> {
-    debug!("Waiting for device {:?} idle", self.wrapped.handle());
-    Ok(self.wrapped.device_wait_idle()?)
+    debug!("Waiting for device {:?} idle", self.raw().handle());
+    Ok(self.raw().device_wait_idle()?)
   }
 }
@@ -8,6 +8,28 @@ use thiserror::Error;
 #[error("One or more required items are missing: {0:?}")]
 pub struct MissingError(pub Vec<CString>);
 
+/// Fails to compile unless all given types are `Send`, enforcing the thread-safety that a type documents.
+#[macro_export]
+macro_rules! assert_send {
+  ($($ty:ty),+ $(,)?) => {
+    const _: fn() = || {
+      fn assert_send<T: Send>() {}
+      $(assert_send::<$ty>();)+
+    };
+  };
+}
+
+/// Fails to compile unless all given types are `Send` and `Sync`, enforcing the thread-safety that a type documents.
+#[macro_export]
+macro_rules! assert_send_sync {
+  ($($ty:ty),+ $(,)?) => {
+    const _: fn() = || {
+      fn assert_send_sync<T: Send + Sync>() {}
+      $(assert_send_sync::<$ty>();)+
+    };
+  };
+}
+
 pub fn get_enabled_or_missing<I: IntoIterator<Item=CString>>(available: I, wanted: &HashSet<CString>, required: &HashSet<CString>)
   -> Result<(HashSet<CString>, Vec<*const c_char>), MissingError> {
   let available: HashSet<_> = available.into_iter().collect();
@@ -19,3 +41,50 @@ pub fn get_enabled_or_missing<I: IntoIterator<Item=CString>>(available: I, wante
   let raw: Vec<_> = enabled.iter().map(|n| n.as_ptr()).collect();
   Ok((enabled, raw))
 }
+
+/// Tracks whether a wrapper has been destroyed, to panic with a clear message when it is used after destruction instead
+/// of crashing in the driver. Only tracks in debug builds.
+#[derive(Default, Debug)]
+pub(crate) struct DestroyedFlag {
+  #[cfg(debug_assertions)]
+  destroyed: bool,
+}
+
+impl DestroyedFlag {
+  #[inline]
+  pub(crate) fn set(&mut self) {
+    #[cfg(debug_assertions)] {
+      self.destroyed = true;
+    }
+  }
+
+  /// In debug builds, panics if this flag is set, naming `what` was used after destruction.
+  #[inline]
+  #[track_caller]
+  #[allow(unused_variables)]
+  pub(crate) fn assert_not_destroyed(&self, what: &str) {
+    #[cfg(debug_assertions)] {
+      assert!(!self.destroyed, "{} was used after it was destroyed", what);
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::DestroyedFlag;
+
+  #[test]
+  fn destroyed_flag_allows_use_before_destruction() {
+    let flag = DestroyedFlag::default();
+    flag.assert_not_destroyed("Device");
+  }
+
+  #[test]
+  #[cfg(debug_assertions)]
+  #[should_panic(expected = "Device was used after it was destroyed")]
+  fn destroyed_flag_panics_on_use_after_destruction() {
+    let mut flag = DestroyedFlag::default();
+    flag.set();
+    flag.assert_not_destroyed("Device");
+  }
+}
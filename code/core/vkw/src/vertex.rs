@@ -0,0 +1,131 @@
+use std::mem::size_of;
+
+use ash::vk::{Format, VertexInputAttributeDescription, VertexInputBindingDescription, VertexInputRate};
+
+#[doc(hidden)]
+pub use memoffset::offset_of;
+
+// Vertex attribute
+
+/// Format and byte offset of a single vertex attribute.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct VertexAttribute {
+  pub format: Format,
+  pub offset: u32,
+}
+
+// Vertex trait
+
+/// Vertex types that derive their vertex input binding and attribute descriptions from their layout. Implement with
+/// [impl_vertex], which takes attribute offsets from the struct fields.
+pub trait Vertex: Sized {
+  /// Gets the attributes of this vertex type, in location order.
+  fn attributes() -> Vec<VertexAttribute>;
+
+  /// Gets the binding description of a buffer of these vertices at `binding`, advanced per vertex.
+  fn binding_description(binding: u32) -> VertexInputBindingDescription {
+    VertexInputBindingDescription::builder()
+      .binding(binding)
+      .stride(size_of::<Self>() as u32)
+      .input_rate(VertexInputRate::VERTEX)
+      .build()
+  }
+
+  /// Gets the attribute descriptions of these vertices at `binding`, at consecutive locations starting at
+  /// `first_location`.
+  fn attribute_descriptions(binding: u32, first_location: u32) -> Vec<VertexInputAttributeDescription> {
+    Self::attributes().into_iter().enumerate().map(|(i, attribute)| {
+      VertexInputAttributeDescription::builder()
+        .location(first_location + i as u32)
+        .binding(binding)
+        .format(attribute.format)
+        .offset(attribute.offset)
+        .build()
+    }).collect()
+  }
+}
+
+/// Implements [Vertex] for a `#[repr(C)]` struct, with an attribute per listed field in location order. The offset of
+/// each attribute is the offset of its field, and an attribute may span multiple consecutive fields through its format.
+///
+/// ```ignore
+/// impl_vertex!(TextureUVVertexData {
+///   u: Format::R32G32B32_SFLOAT, // Spans `u`, `v`, and `i`.
+///   tint: Format::R8G8B8A8_UNORM,
+/// });
+/// ```
+#[macro_export]
+macro_rules! impl_vertex {
+  ($ty:path { $($field:tt: $format:expr),* $(,)? }) => {
+    impl $crate::vertex::Vertex for $ty {
+      fn attributes() -> Vec<$crate::vertex::VertexAttribute> {
+        vec![$(
+          $crate::vertex::VertexAttribute { format: $format, offset: $crate::vertex::offset_of!($ty, $field) as u32 },
+        )*]
+      }
+    }
+  };
+}
+
+#[cfg(test)]
+mod tests {
+  use std::mem::size_of;
+
+  use ash::vk::{Format, VertexInputRate};
+
+  use super::{Vertex, VertexAttribute};
+
+  #[allow(dead_code)]
+  #[repr(C)]
+  struct TestVertex {
+    position: [f32; 3],
+    normal: [f32; 3],
+    color: u32,
+  }
+
+  crate::impl_vertex!(TestVertex {
+    position: Format::R32G32B32_SFLOAT,
+    color: Format::R8G8B8A8_UNORM,
+  });
+
+  #[allow(dead_code)]
+  #[repr(C)]
+  struct TupleVertex(f32, f32, u32);
+
+  crate::impl_vertex!(TupleVertex {
+    0: Format::R32G32_SFLOAT, // Spans `0` and `1`.
+    2: Format::R32_UINT,
+  });
+
+  #[test]
+  fn attributes_take_field_offsets_and_skip_unlisted_fields() {
+    assert_eq!(TestVertex::attributes(), vec![
+      VertexAttribute { format: Format::R32G32B32_SFLOAT, offset: 0 },
+      VertexAttribute { format: Format::R8G8B8A8_UNORM, offset: 6 * size_of::<f32>() as u32 },
+    ]);
+  }
+
+  #[test]
+  fn attributes_of_tuple_struct() {
+    assert_eq!(TupleVertex::attributes(), vec![
+      VertexAttribute { format: Format::R32G32_SFLOAT, offset: 0 },
+      VertexAttribute { format: Format::R32_UINT, offset: 2 * size_of::<f32>() as u32 },
+    ]);
+  }
+
+  #[test]
+  fn binding_description_uses_vertex_stride() {
+    let binding = TestVertex::binding_description(3);
+    assert_eq!((binding.binding, binding.stride, binding.input_rate), (3, 7 * size_of::<f32>() as u32, VertexInputRate::VERTEX));
+  }
+
+  #[test]
+  fn attribute_descriptions_use_consecutive_locations() {
+    let descriptions = TestVertex::attribute_descriptions(3, 2);
+    let tuples: Vec<_> = descriptions.iter().map(|d| (d.location, d.binding, d.format, d.offset)).collect();
+    assert_eq!(tuples, vec![
+      (2, 3, Format::R32G32B32_SFLOAT, 0),
+      (3, 3, Format::R8G8B8A8_UNORM, 24),
+    ]);
+  }
+}
@@ -0,0 +1,174 @@
+use std::fmt::{self, Display, Formatter};
+use std::sync::mpsc::Receiver;
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result};
+use log::info;
+use metrics::timing;
+
+use gfx::camera::CameraInput;
+use gfx::Gfx;
+use math::prelude::Vec3;
+use os::event_sys::OsEvent;
+use sim::prelude::*;
+use util::timing::DurationExt;
+
+use crate::game_debug::GameDebug;
+use crate::game_def::GameDef;
+
+// Arguments
+
+/// Gets the number of frames to benchmark from the `--benchmark <frames>` argument, or `None` if not given.
+///
+/// The benchmark is not headless: it renders and presents to the main window, which must stay open and unminimized
+/// for the duration of the benchmark, as there is no headless rendering mode to render without a window.
+pub fn frames_from_args(args: impl IntoIterator<Item=String>) -> Result<Option<u32>> {
+  let mut args = args.into_iter();
+  while let Some(arg) = args.next() {
+    if arg == "--benchmark" {
+      let frames = args.next().with_context(|| "Missing frames argument for --benchmark")?;
+      let frames = frames.parse().with_context(|| format!("Invalid frames argument '{}' for --benchmark", frames))?;
+      return Ok(Some(frames));
+    }
+  }
+  Ok(None)
+}
+
+// Scene
+
+/// Fixed scene to benchmark: a grid of random tiles generated from `seed`, viewed from a fixed camera.
+#[derive(Copy, Clone, Debug)]
+pub struct BenchmarkScene {
+  pub seed: u64,
+  /// Bounds of the square of grid tiles, from `grid_lower_bound` (inclusive) to `grid_upper_bound` (exclusive).
+  pub grid_lower_bound: i32,
+  pub grid_upper_bound: i32,
+  pub camera_position: Vec3,
+  pub camera_zoom: f32,
+}
+
+impl Default for BenchmarkScene {
+  fn default() -> Self {
+    Self {
+      seed: sim::rng::DEFAULT_SEED,
+      grid_lower_bound: 16 * -1,
+      grid_upper_bound: 16 * 6,
+      camera_position: Vec3::new(-0.5, -0.5, 1.0),
+      camera_zoom: 16.0 * 7.0,
+    }
+  }
+}
+
+// Statistics
+
+/// Statistics of a set of frame time samples.
+#[derive(Copy, Clone, Debug)]
+pub struct FrameTimeStats {
+  pub count: usize,
+  pub avg: Duration,
+  /// 99th percentile: 99% of the samples are at most this long.
+  pub p99: Duration,
+  pub min: Duration,
+  pub max: Duration,
+}
+
+impl FrameTimeStats {
+  /// Computes statistics of `samples`, or `None` if there are no samples.
+  pub fn from_samples(mut samples: Vec<Duration>) -> Option<Self> {
+    if samples.is_empty() { return None; }
+    samples.sort();
+    let count = samples.len();
+    let total: Duration = samples.iter().sum();
+    let p99_index = ((count as f64 * 0.99).ceil() as usize).max(1) - 1;
+    Some(Self {
+      count,
+      avg: total / count as u32,
+      p99: samples[p99_index],
+      min: samples[0],
+      max: samples[count - 1],
+    })
+  }
+}
+
+impl Display for FrameTimeStats {
+  fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+    write!(f, "{} frames: avg {:.3}, p99 {:.3}, min {:.3}, max {:.3}", self.count, self.avg.display(),
+      self.p99.display(), self.min.display(), self.max.display())
+  }
+}
+
+/// Result of a benchmark: statistics of CPU frame times, which include waiting for the GPU when it is the bottleneck,
+/// and of GPU frame times if the device supports timestamps.
+#[derive(Copy, Clone, Debug)]
+pub struct BenchmarkReport {
+  pub frame_time: Option<FrameTimeStats>,
+  pub gpu_frame_time: Option<FrameTimeStats>,
+}
+
+impl Display for BenchmarkReport {
+  fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+    match &self.frame_time {
+      Some(stats) => write!(f, "Frame time: {}", stats)?,
+      None => write!(f, "Frame time: no frames")?,
+    }
+    if let Some(stats) = &self.gpu_frame_time {
+      write!(f, "\nGPU frame time: {}", stats)?;
+    }
+    Ok(())
+  }
+}
+
+// Benchmark
+
+/// Number of frames rendered before measuring, to exclude pipeline warm-up and initial uploads.
+const WARMUP_FRAMES: u32 = 30;
+/// Frame time passed to rendering, independent of real time, so that animations are reproducible.
+const FIXED_FRAME_TIME: Duration = Duration::from_nanos(16_666_667);
+
+/// Renders `scene` for `frames` frames after a warm-up, without simulating or processing input, and reports frame time
+/// statistics. Stops early if termination is requested.
+pub fn run_benchmark(
+  frames: u32,
+  scene: &BenchmarkScene,
+  os_event_rx: &Receiver<OsEvent>,
+  game_def: &GameDef,
+  sim: &mut Sim,
+  gfx: &mut Gfx,
+  game_debug: &mut GameDebug,
+) -> Result<BenchmarkReport> {
+  sim.reseed(scene.seed);
+  game_debug.reset_to_random_grid(scene.grid_lower_bound, scene.grid_upper_bound, game_def, sim);
+  gfx.camera_sys.set_position(scene.camera_position);
+  gfx.camera_sys.set_zoom(scene.camera_zoom);
+
+  info!("Benchmarking {} frames after {} warm-up frames", frames, WARMUP_FRAMES);
+  let mut frame_times = Vec::with_capacity(frames as usize);
+  let mut gpu_frame_times = Vec::with_capacity(frames as usize);
+  'main: for frame in 0..WARMUP_FRAMES + frames {
+    for os_event in os_event_rx.try_iter() {
+      match os_event {
+        OsEvent::TerminateRequested => break 'main,
+        OsEvent::WindowResized(screen_size) => gfx.screen_size_changed(screen_size),
+        // No secondary windows are opened when benchmarking.
+        OsEvent::SecondaryWindowResized(..) | OsEvent::SecondaryWindowClosed(_) => {}
+      }
+    }
+    let start = Instant::now();
+    gfx.render_frame(&mut sim.world, CameraInput::default(), 0, 0.0, FIXED_FRAME_TIME)?;
+    let frame_time = start.elapsed();
+    if frame >= WARMUP_FRAMES {
+      timing!("client.benchmark.frame_time", frame_time);
+      frame_times.push(frame_time);
+      // GPU frame time lags behind by the number of frames in flight, which does not matter for statistics.
+      if let Some(gpu_frame_time) = gfx.gpu_frame_time() {
+        gpu_frame_times.push(gpu_frame_time);
+      }
+    }
+  }
+  gfx.wait_idle()?;
+
+  Ok(BenchmarkReport {
+    frame_time: FrameTimeStats::from_samples(frame_times),
+    gpu_frame_time: FrameTimeStats::from_samples(gpu_frame_times),
+  })
+}
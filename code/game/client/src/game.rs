@@ -1,4 +1,4 @@
-use ultraviolet::Vec3;
+use math::prelude::Vec3;
 
 use gfx::Gfx;
 use sim::legion_sim::Sim;
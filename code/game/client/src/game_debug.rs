@@ -1,16 +1,17 @@
 use legion::prelude::*;
+use log::info;
 use rand::Rng;
 use rand::seq::SliceRandom;
-use rand_pcg::Pcg64Mcg;
-use ultraviolet::{Isometry2, Rotor2, Vec2, Vec3};
 
-use gfx::Gfx;
+use gfx::{Gfx, MAIN_RENDER_SURFACE};
+use math::prelude::{Isometry2, Vec2, Vec3};
 use gfx::grid_renderer::GridTileRender;
 use sim::prelude::*;
 
 use crate::game::Game;
 use crate::game_def::GameDef;
 use crate::metrics::Metrics;
+use crate::timing::TickTimer;
 
 pub struct GameDebug {
   grid: Entity,
@@ -40,7 +41,7 @@ impl GameDebug {
   }
 }
 
-#[derive(Default, Copy, Clone, Debug)]
+#[derive(Default, Copy, Clone, PartialEq, Debug)]
 pub struct GameDebugInput {
   pub grid_linear_velocity_x_inc: bool,
   pub grid_linear_velocity_x_dec: bool,
@@ -51,6 +52,12 @@ pub struct GameDebugInput {
   pub grid_randomize: bool,
   pub grid_reset: bool,
 
+  pub toggle_pause: bool,
+  pub single_step: bool,
+  pub toggle_wireframe: bool,
+  pub cycle_tonemap_operator: bool,
+  pub cycle_time_scale: bool,
+
   pub activate_setup_1: bool,
   pub activate_setup_2: bool,
   pub activate_setup_3: bool,
@@ -73,31 +80,59 @@ impl GameDebug {
     sim: &mut Sim,
     gfx: &mut Gfx,
     _game: &mut Game,
+    tick_timer: &mut TickTimer,
     metrics: &mut Metrics,
   ) {
     if input.grid_randomize {
       self.clear_grid_tiles(sim);
-      let mut rng = rand::thread_rng();
-      let lower_bound = rng.gen_range(-100, 0);
-      let upper_bound = rng.gen_range(0, 100);
+      let (lower_bound, upper_bound) = {
+        let mut rng = sim.rng();
+        (rng.gen_range(-100, 0), rng.gen_range(0, 100))
+      };
       self.randomize_grid_tiles(lower_bound, upper_bound, game_def, sim);
     }
 
     if input.grid_reset {
       if let Some(mut grid_world_dynamics) = sim.world.get_component_mut::<WorldDynamics>(self.grid) {
         grid_world_dynamics.linear_velocity = Vec2::zero();
-        grid_world_dynamics.angular_velocity = Rotor2::identity();
+        grid_world_dynamics.angular_velocity = 0.0;
       }
       if let Some(mut grid_world_transform) = sim.world.get_component_mut::<WorldTransform>(self.grid) {
         grid_world_transform.isometry = Isometry2::identity();
       }
     }
 
+    if input.toggle_pause {
+      tick_timer.set_paused(!tick_timer.is_paused());
+    }
+    if input.single_step {
+      tick_timer.request_single_step();
+    }
+    if input.cycle_time_scale {
+      let time_scale = match sim.time_scale() {
+        s if s > 0.5 => 0.5,
+        s if s > 0.25 => 0.25,
+        _ => 1.0,
+      };
+      sim.set_time_scale(time_scale);
+      info!("Time scale: {}", time_scale);
+    }
+
+    if input.toggle_wireframe {
+      let wireframe = gfx.grid_render_sys.wireframe();
+      gfx.grid_render_sys.set_wireframe(!wireframe);
+    }
+    if input.cycle_tonemap_operator {
+      let tonemap_sys = &mut gfx.render_surface_mut(MAIN_RENDER_SURFACE).tonemap_sys;
+      let operator = tonemap_sys.operator().next();
+      tonemap_sys.set_operator(operator);
+      info!("Tonemap operator: {:?}", operator);
+    }
+
     if input.activate_setup_1 {
       gfx.camera_sys.set_position(Vec3::new(-0.5, -0.5, 1.0));
       gfx.camera_sys.set_zoom(16.0*7.0);
-      self.clear_grid_tiles(sim);
-      self.randomize_grid_tiles(16*-1, 16*6, game_def, sim);
+      self.reset_to_random_grid(16*-1, 16*6, game_def, sim);
     }
 
     if input.print_metrics {
@@ -115,27 +150,34 @@ impl GameDebug {
   ) {
     let mut grid_world_dynamics = sim.world.get_component_mut::<WorldDynamics>(self.grid).unwrap();
     if input.grid_linear_velocity_x_inc {
-      grid_world_dynamics.linear_velocity.x += 0.001;
+      grid_world_dynamics.linear_velocity.x += 0.06;
     }
     if input.grid_linear_velocity_x_dec {
-      grid_world_dynamics.linear_velocity.x -= 0.001;
+      grid_world_dynamics.linear_velocity.x -= 0.06;
     }
     if input.grid_linear_velocity_y_inc {
-      grid_world_dynamics.linear_velocity.y += 0.001;
+      grid_world_dynamics.linear_velocity.y += 0.06;
     }
     if input.grid_linear_velocity_y_dec {
-      grid_world_dynamics.linear_velocity.y -= 0.001;
+      grid_world_dynamics.linear_velocity.y -= 0.06;
     }
     if input.grid_angular_velocity_inc {
-      grid_world_dynamics.angular_velocity = grid_world_dynamics.angular_velocity * Rotor2::from_angle(0.01);
+      grid_world_dynamics.angular_velocity += 0.6;
     }
     if input.grid_angular_velocity_dec {
-      grid_world_dynamics.angular_velocity = grid_world_dynamics.angular_velocity * Rotor2::from_angle(-0.01);
+      grid_world_dynamics.angular_velocity -= 0.6;
     }
   }
 }
 
 impl GameDebug {
+  /// Replaces all grid tiles with random tiles in the square from `lower_bound` (inclusive) to `upper_bound`
+  /// (exclusive), using the deterministic random number generator of the simulation.
+  pub fn reset_to_random_grid(&mut self, lower_bound: i32, upper_bound: i32, game_def: &GameDef, sim: &mut Sim) {
+    self.clear_grid_tiles(sim);
+    self.randomize_grid_tiles(lower_bound, upper_bound, game_def, sim);
+  }
+
   fn clear_grid_tiles(&mut self, sim: &mut Sim) {
     let mut command_buffer = legion::command::CommandBuffer::new(&sim.world);
     let in_grid = InGrid::new(self.grid);
@@ -147,17 +189,12 @@ impl GameDebug {
   }
 
   fn randomize_grid_tiles(&mut self, lower_bound: i32, upper_bound: i32, game_def: &GameDef, sim: &mut Sim) {
-    let mut rng = Pcg64Mcg::new(0xcafef00dd15ea5e5);
-    let mut command_buffer = legion::command::CommandBuffer::new(&sim.world);
-    for y in lower_bound..upper_bound {
-      for x in lower_bound..upper_bound {
-        if let Some(texture_idx) = game_def.grid_tile_textures.choose(&mut rng) {
-          command_buffer.insert((InGrid::new(self.grid), ), vec![
-            (GridPosition::new(x, y), GridOrientation::default(), GridTileRender(*texture_idx)),
-          ]);
-        }
-      }
-    }
-    command_buffer.write(&mut sim.world);
+    let mut rng = sim.resources.get_mut::<SimRng>().unwrap();
+    let textures = &game_def.grid_tile_textures;
+    let tiles = (lower_bound..upper_bound)
+      .flat_map(|y| (lower_bound..upper_bound).map(move |x| (x, y)))
+      .filter_map(|(x, y)| textures.choose(&mut *rng)
+        .map(|texture_idx| (GridPosition::new(x, y), GridOrientation::default(), GridTileRender(*texture_idx))));
+    spawn_grid_tiles(&mut sim.world, InGrid::new(self.grid), tiles);
   }
 }
@@ -3,17 +3,28 @@ use anyhow::Result;
 use gfx::texture_def::{TextureDefBuilder, TextureIdx};
 use util::image::{Components, ImageData};
 
+/// Absolute path to an asset file, for embedding it into the binary and for watching it for changes.
+macro_rules! asset_path {
+  ($path:literal) => { concat!(env!("CARGO_MANIFEST_DIR"), "/../../../asset/", $path) }
+}
+
 pub struct GameDef {
   pub grid_tile_textures: Vec<TextureIdx>,
+  pub texture_paths: Vec<(TextureIdx, &'static str)>,
 }
 
 impl GameDef {
   pub fn new() -> Result<(GameDef, TextureDefBuilder)> {
     let mut texture_def_builder = TextureDefBuilder::new();
-    let tex1 = texture_def_builder.add_texture(ImageData::from_encoded(include_bytes!("../../../../asset/wall_tile/dark.png"), Some(Components::Components4))?);
-    let tex2 = texture_def_builder.add_texture(ImageData::from_encoded(include_bytes!("../../../../asset/wall_tile/light.png"), Some(Components::Components4))?);
-    let tex3 = texture_def_builder.add_texture(ImageData::from_encoded(include_bytes!("../../../../asset/wall_tile/green.png"), Some(Components::Components4))?);
-    let game_def = GameDef { grid_tile_textures: vec![tex1, tex2, tex3] };
+    let tex1 = texture_def_builder.add_texture(ImageData::from_encoded(include_bytes!(asset_path!("wall_tile/dark.png")), Some(Components::Components4))?);
+    let tex2 = texture_def_builder.add_texture(ImageData::from_encoded(include_bytes!(asset_path!("wall_tile/light.png")), Some(Components::Components4))?);
+    let tex3 = texture_def_builder.add_texture(ImageData::from_encoded(include_bytes!(asset_path!("wall_tile/green.png")), Some(Components::Components4))?);
+    let texture_paths = vec![
+      (tex1, asset_path!("wall_tile/dark.png")),
+      (tex2, asset_path!("wall_tile/light.png")),
+      (tex3, asset_path!("wall_tile/green.png")),
+    ];
+    let game_def = GameDef { grid_tile_textures: vec![tex1, tex2, tex3], texture_paths };
     Ok((game_def, texture_def_builder))
   }
 }
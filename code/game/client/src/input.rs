@@ -5,7 +5,7 @@ use os::input_sys::RawInput;
 
 use crate::game_debug::GameDebugInput;
 
-#[derive(Default, Copy, Clone, Debug)]
+#[derive(Default, Copy, Clone, PartialEq, Debug)]
 pub struct Input {
   pub game_debug: GameDebugInput,
   pub camera: CameraInput,
@@ -23,6 +23,12 @@ impl Input {
       grid_randomize: input.is_key_pressed(VirtualKeyCode::R),
       grid_reset: input.is_key_pressed(VirtualKeyCode::Return),
 
+      toggle_pause: input.is_key_pressed(VirtualKeyCode::P),
+      single_step: input.is_key_pressed(VirtualKeyCode::Period),
+      toggle_wireframe: input.is_key_pressed(VirtualKeyCode::F),
+      cycle_tonemap_operator: input.is_key_pressed(VirtualKeyCode::T),
+      cycle_time_scale: input.is_key_pressed(VirtualKeyCode::Comma),
+
       activate_setup_1: input.is_key_pressed(VirtualKeyCode::Key1),
       activate_setup_2: input.is_key_pressed(VirtualKeyCode::Key2),
       activate_setup_3: input.is_key_pressed(VirtualKeyCode::Key3),
@@ -42,8 +48,10 @@ impl Input {
       move_down: input.is_key_down(VirtualKeyCode::S),
       move_left: input.is_key_down(VirtualKeyCode::A),
       zoom_delta: input.mouse_wheel_delta.y as f32,
+      zoom_in_step: input.is_key_pressed(VirtualKeyCode::Equals) || input.is_key_pressed(VirtualKeyCode::Add),
+      zoom_out_step: input.is_key_pressed(VirtualKeyCode::Minus) || input.is_key_pressed(VirtualKeyCode::Subtract),
       drag: input.mouse_buttons.right,
-      drag_pos: input.mouse_pos,
+      mouse_pos: input.mouse_pos,
     };
     Input { game_debug, camera }
   }
@@ -1,24 +1,29 @@
 use std::num::NonZeroU32;
-use std::sync::mpsc::Receiver;
+use std::sync::mpsc::{Receiver, Sender};
 use std::thread;
 use std::time::Duration;
 
 use anyhow::{Context, Result};
-use log::debug;
+use log::{debug, info};
+use ::metrics::timing;
 
 use gfx::Gfx;
+use gfx::tonemap::TonemapOperator;
 use math::prelude::*;
 use os::context::OsContext;
-use os::event_sys::{OsEvent, OsEventSys};
+use os::event_sys::{OsCommand, OsEvent, OsEventSys};
 use os::input_sys::OsInputSys;
-use os::window::Window;
+use os::window::{CursorIcon, Window};
 use sim::prelude::*;
+use vkw::prelude::ValidationFeatures;
 
+use crate::benchmark::BenchmarkScene;
 use crate::game::Game;
 use crate::game_debug::GameDebug;
 use crate::game_def::GameDef;
 use crate::input::Input;
 use crate::metrics::Metrics;
+use crate::replay::{Replay, ReplayFrame};
 use crate::timing::{FrameTime, FrameTimer, TickTimer};
 
 pub mod timing;
@@ -29,6 +34,23 @@ pub mod game;
 
 pub mod game_debug;
 pub mod metrics;
+pub mod replay;
+pub mod benchmark;
+
+/// Initial duration of a simulation tick (60 Hz), which can be changed at runtime with [TickTimer::set_tick_duration].
+const TICK_DURATION: Duration = Duration::from_nanos(16_666_667);
+/// Point of a grid tile that its grid position maps to, shared by the simulation and the grid renderer.
+const GRID_PIVOT: GridPivot = GridPivot::Center;
+
+/// Secondary window opened with the `--inspector` argument, showing the scene with a different tonemap operator than
+/// the main window, for comparing operators side by side.
+#[derive(Copy, Clone, Debug)]
+struct Inspector {
+  /// Index of the window in secondary window events.
+  window_index: usize,
+  /// Index of the render surface of the window in [Gfx].
+  surface_index: usize,
+}
 
 fn main() -> Result<()> {
   // Initialize logger.
@@ -51,38 +73,72 @@ fn main() -> Result<()> {
     let input_sys = OsInputSys::new(input_event_rx);
     (event_sys, event_rx, input_sys)
   };
+  let os_command_tx = os_event_sys.command_sender();
 
   // Initialize game definition.
   let (game_def, texture_def_builder) = GameDef::new()
     .with_context(|| "Failed to initialize game definition")?;
 
+  // Initialize input recording or playback.
+  let replay = Replay::from_args(std::env::args().skip(1), sim::rng::DEFAULT_SEED)
+    .with_context(|| "Failed to initialize input recording or playback")?;
+
+  // Benchmark instead of running the game, if requested.
+  let benchmark_frames = benchmark::frames_from_args(std::env::args().skip(1))
+    .with_context(|| "Failed to initialize benchmark")?;
+
   // Initialize simulation.
   let mut sim = Sim::new();
+  sim.reseed(replay.playback_seed().unwrap_or(sim::rng::DEFAULT_SEED));
+  sim.set_grid_pivot(GRID_PIVOT);
   // Initialize graphics.
   let mut gfx = Gfx::new(
-    cfg!(debug_assertions),
+    if cfg!(debug_assertions) { Some(gfx::default_debug_report_flags()) } else { None },
+    ValidationFeatures::default(),
     NonZeroU32::new(2).unwrap(),
+    None,
     window.winit_raw_window_handle(),
     window.window_inner_size(),
     texture_def_builder,
+    GRID_PIVOT,
   ).with_context(|| "Failed to create GFX instance")?;
 
+  // Open the inspector window if requested, except when benchmarking, which only renders the main window.
+  let inspector = if benchmark_frames.is_none() && std::env::args().skip(1).any(|arg| arg == "--inspector") {
+    let inspector_window = Window::new(&os_context, LogicalSize::new(960.0, 540.0), LogicalSize::new(320.0, 180.0), "SG Inspector")
+      .with_context(|| "Failed to create inspector window")?;
+    let surface_index = gfx.add_render_surface(inspector_window.winit_raw_window_handle(), inspector_window.window_inner_size())
+      .with_context(|| "Failed to create inspector render surface")?;
+    gfx.render_surface_mut(surface_index).tonemap_sys.set_operator(TonemapOperator::AcesApprox);
+    let window_index = os_event_sys.add_secondary_window(inspector_window);
+    Some(Inspector { window_index, surface_index })
+  } else {
+    None
+  };
+
   // Initialize game.
   let mut game = Game::new(&game_def, &mut sim, &mut gfx);
-  let game_debug = GameDebug::new(&game_def, &mut sim, &mut gfx, &mut game);
+  let mut game_debug = GameDebug::new(&game_def, &mut sim, &mut gfx, &mut game);
 
   // Spawn game thread and run OS event loop.
   let game_thread = thread::Builder::new()
     .name("Game".to_string())
     .spawn(move || {
       debug!("Game thread started");
-      run(window, os_event_rx, os_input_sys, game_def, sim, gfx, game, game_debug, &mut metrics)
-        .with_context(|| "Game thread stopped with an error").unwrap();
+      if let Some(frames) = benchmark_frames {
+        let report = benchmark::run_benchmark(frames, &BenchmarkScene::default(), &os_event_rx, &game_def, &mut sim, &mut gfx, &mut game_debug)
+          .with_context(|| "Benchmark failed").unwrap();
+        info!("Benchmark results:\n{}", report);
+        os_command_tx.send(OsCommand::Exit).ok();
+      } else {
+        run(os_command_tx, os_event_rx, os_input_sys, replay, game_def, sim, gfx, game, game_debug, inspector, &mut metrics)
+          .with_context(|| "Game thread stopped with an error").unwrap();
+      }
       debug!("Game thread stopped");
     })
     .with_context(|| "Failed to create game thread")?;
   debug!("Main thread OS-event loop started");
-  os_event_sys.run_return(&mut os_context);
+  os_event_sys.run_return(&mut os_context, &window);
 
   // OS-event loop stopped; stop the game thread.
   debug!("Main thread OS-event loop stopped");
@@ -93,22 +149,32 @@ fn main() -> Result<()> {
 }
 
 fn run(
-  _window: Window,
+  os_command_tx: Sender<OsCommand>,
   os_event_rx: Receiver<OsEvent>,
   mut os_input_sys: OsInputSys,
+  mut replay: Replay,
   game_def: GameDef,
   mut sim: Sim,
   mut gfx: Gfx,
   mut game: Game,
   mut game_debug: GameDebug,
+  mut inspector: Option<Inspector>,
   metrics: &mut Metrics,
 ) -> Result<()> {
+  #[cfg(feature = "hot-reload")] let texture_watcher = {
+    let mut texture_watcher = gfx::texture_watcher::TextureWatcher::new()?;
+    for (texture_idx, path) in &game_def.texture_paths {
+      texture_watcher.watch(*texture_idx, path)?;
+    }
+    texture_watcher
+  };
+
+  let mut dragging = false;
   let mut frame_timer = FrameTimer::new();
-  let mut tick_timer = TickTimer::new(Duration::from_nanos(16_666_667));
+  let mut tick_timer = TickTimer::new(TICK_DURATION);
   'main: loop {
     // Timing
-    let FrameTime { frame_time, .. } = frame_timer.frame();
-    tick_timer.update_lag(frame_time);
+    let FrameTime { mut frame_time, .. } = frame_timer.frame();
 
     // Process OS events
     for os_event in os_event_rx.try_iter() {
@@ -117,28 +183,92 @@ fn run(
         OsEvent::WindowResized(screen_size) => {
           gfx.screen_size_changed(screen_size);
         },
+        OsEvent::SecondaryWindowResized(window_index, screen_size) => {
+          if let Some(inspector) = inspector.filter(|i| i.window_index == window_index) {
+            gfx.render_surface_size_changed(inspector.surface_index, screen_size);
+          }
+        },
+        OsEvent::SecondaryWindowClosed(window_index) => {
+          if let Some(closed) = inspector.filter(|i| i.window_index == window_index) {
+            gfx.remove_render_surface(closed.surface_index)?;
+            inspector = None;
+          }
+        },
+      }
+    }
+
+    // Reload changed textures
+    #[cfg(feature = "hot-reload")] {
+      for (texture_idx, path) in texture_watcher.changed_textures() {
+        debug!("Reloading texture {:?} from '{}'", texture_idx, path.display());
+        if let Err(e) = gfx.reload_texture(texture_idx, &path) {
+          log::error!("{:?}", e);
+        }
       }
     }
 
     // Process input
     let raw_input = os_input_sys.update();
-    let Input { game_debug: game_debug_input, camera: camera_input } = Input::from_raw(raw_input);
+    let mut input = Input::from_raw(raw_input);
+    // Number of ticks to simulate this frame when playing back a recording, instead of the tick timer deciding.
+    let mut playback_ticks = None;
+    if let Replay::Playback(playback) = &mut replay {
+      if let Some(frame) = playback.next_frame()? {
+        frame_time = frame.frame_time;
+        input = frame.input;
+        playback_ticks = Some(frame.ticks);
+      } else {
+        info!("Input playback ended; switching to live input");
+        replay = Replay::Live;
+        // Lag did not accumulate during playback, but start live ticking without a backlog regardless.
+        tick_timer.reset_lag();
+      }
+    }
+    // Playback decides the number of ticks itself, so only accumulate lag for live and recorded input.
+    if playback_ticks.is_none() {
+      let dropped_time = tick_timer.update_lag(frame_time);
+      if dropped_time > Duration::default() {
+        timing!("client.tick_timer.dropped_time", dropped_time);
+      }
+    }
+    let Input { game_debug: game_debug_input, camera: camera_input } = input;
 
-    game_debug.update_before_tick(&game_debug_input, &game_def, &mut sim, &mut gfx, &mut game, metrics);
+    if camera_input.drag != dragging {
+      dragging = camera_input.drag;
+      let cursor_icon = if dragging { CursorIcon::Grabbing } else { CursorIcon::Default };
+      os_command_tx.send(OsCommand::SetCursorIcon(cursor_icon)).ok();
+    }
+
+    game_debug.update_before_tick(&game_debug_input, &game_def, &mut sim, &mut gfx, &mut game, &mut tick_timer, metrics);
 
     // Simulate tick
-    if tick_timer.should_tick() {
+    let mut ticks = 0;
+    if let Some(playback_ticks) = playback_ticks {
+      for _ in 0..playback_ticks { // Run simulation exactly as many ticks as recorded.
+        game_debug.tick_before_sim(&game_debug_input, &game_def, &mut sim, &mut gfx, &mut game);
+        sim.simulate_tick(tick_timer.time_target());
+        ticks += 1;
+      }
+    } else if tick_timer.should_tick() {
       while tick_timer.should_tick() { // Run simulation.
         tick_timer.tick_start();
         game_debug.tick_before_sim(&game_debug_input, &game_def, &mut sim, &mut gfx, &mut game);
         sim.simulate_tick(tick_timer.time_target());
         tick_timer.tick_end();
+        ticks += 1;
       }
     }
+    if let Replay::Record(recorder) = &mut replay {
+      recorder.record(&ReplayFrame { frame_time, ticks, input })?;
+    }
 
-    // Render frame
-    gfx.render_frame(&mut sim.world, camera_input, tick_timer.extrapolation(), frame_time)?;
+    // Render frame. Playback renders the simulated state as is, since the recorded ticks are not driven by lag.
+    let extrapolation = if playback_ticks.is_some() { 0.0 } else { tick_timer.extrapolation() };
+    gfx.render_frame(&mut sim.world, camera_input, ticks, extrapolation, frame_time)?;
   }
 
+  if let Replay::Record(recorder) = replay {
+    recorder.finish()?;
+  }
   Ok(gfx.wait_idle()?)
 }
@@ -1,12 +1,17 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::time::Duration;
+
 use anyhow::{Context, Result};
 use log::info;
-use metrics_core::{Builder, Drain, Observe};
-use metrics_observer_yaml::{YamlBuilder, YamlObserver};
+use metrics_core::{Drain, Key, Observe, Observer};
 use metrics_runtime::{Controller, Receiver};
 
+use util::timing::DurationExt;
+
 pub struct Metrics {
   controller: Controller,
-  observer: YamlObserver,
+  observer: TimingObserver,
 }
 
 impl Metrics {
@@ -14,7 +19,7 @@ impl Metrics {
     let metrics_receiver = Receiver::builder().build()
       .with_context(|| "Failed to initialize metrics receiver")?;
     let controller = metrics_receiver.controller();
-    let observer = YamlBuilder::new().build();
+    let observer = TimingObserver::default();
     metrics_receiver.install();
     Ok(Metrics { controller, observer })
   }
@@ -25,3 +30,61 @@ impl Metrics {
     info!("{}", output);
   }
 }
+
+// Observer
+
+/// Observes metrics into one line per metric, sorted by name. Histograms are only recorded with `timing!`, so their
+/// values are formatted as durations instead of raw nanoseconds.
+#[derive(Default)]
+struct TimingObserver {
+  values: BTreeMap<String, String>,
+  histograms: BTreeMap<String, Vec<u64>>,
+}
+
+impl Observer for TimingObserver {
+  fn observe_counter(&mut self, key: Key, value: u64) {
+    self.values.insert(key.name().to_string(), value.to_string());
+  }
+
+  fn observe_gauge(&mut self, key: Key, value: i64) {
+    self.values.insert(key.name().to_string(), value.to_string());
+  }
+
+  fn observe_histogram(&mut self, key: Key, values: &[u64]) {
+    self.histograms.entry(key.name().to_string()).or_default().extend_from_slice(values);
+  }
+}
+
+impl Drain<String> for TimingObserver {
+  fn drain(&mut self) -> String {
+    for (name, mut values) in std::mem::take(&mut self.histograms) {
+      if values.is_empty() { continue; }
+      values.sort_unstable();
+      let quantile = |q: f64| Duration::from_nanos(values[((values.len() - 1) as f64 * q).round() as usize]).display();
+      let formatted = format!("count {}, min {}, p50 {}, p99 {}, max {}", values.len(), quantile(0.0), quantile(0.5), quantile(0.99), quantile(1.0));
+      self.values.insert(name, formatted);
+    }
+    let mut output = String::new();
+    for (name, value) in std::mem::take(&mut self.values) {
+      writeln!(output, "{}: {}", name, value).unwrap();
+    }
+    output
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use metrics_core::{Drain, Key, Observer};
+
+  use super::TimingObserver;
+
+  #[test]
+  fn drain_formats_histograms_as_durations() {
+    let mut observer = TimingObserver::default();
+    observer.observe_counter(Key::from_name("b.frames"), 3);
+    observer.observe_histogram(Key::from_name("a.frame_time"), &[2_500_000, 1_000, 16_000_000]);
+    observer.observe_histogram(Key::from_name("a.frame_time"), &[500]);
+    assert_eq!(observer.drain(), "a.frame_time: count 4, min 500 ns, p50 2.50 ms, p99 16.00 ms, max 16.00 ms\nb.frames: 3\n");
+    assert_eq!(observer.drain(), "");
+  }
+}
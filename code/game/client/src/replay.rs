@@ -0,0 +1,307 @@
+//! Recording and playback of the per-frame input stream. Together with a reseeded simulation, playing back a
+//! recording reproduces the simulation of the recorded session.
+//!
+//! A recording starts with a header (magic, version, and simulation seed), followed by a frame record per frame: the
+//! frame time, the number of ticks that were simulated in that frame, and the input. All values are little-endian.
+
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, ErrorKind, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::{bail, Context, Result};
+
+use gfx::camera::CameraInput;
+use math::prelude::PhysicalPosition;
+
+use crate::game_debug::GameDebugInput;
+use crate::input::Input;
+
+const MAGIC: &[u8; 4] = b"SGIR";
+const VERSION: u32 = 1;
+
+// Replay frame
+
+/// Input of a single frame, along with how many ticks were simulated in that frame.
+#[derive(Default, Copy, Clone, PartialEq, Debug)]
+pub struct ReplayFrame {
+  pub frame_time: Duration,
+  pub ticks: u32,
+  pub input: Input,
+}
+
+// Replay mode
+
+/// Whether input is live, recorded to a file, or played back from a file.
+pub enum Replay {
+  Live,
+  Record(InputRecorder),
+  Playback(InputPlayback),
+}
+
+impl Replay {
+  /// Creates a replay from command-line arguments: `--record <path>` records to `path`, and `--replay <path>` plays
+  /// back from `path`. Recordings use simulation seed `seed`.
+  pub fn from_args(args: impl IntoIterator<Item=String>, seed: u64) -> Result<Replay> {
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+      match arg.as_str() {
+        "--record" => {
+          let path = PathBuf::from(args.next().with_context(|| "Missing path argument for --record")?);
+          return Ok(Replay::Record(InputRecorder::create(path, seed)?));
+        }
+        "--replay" => {
+          let path = PathBuf::from(args.next().with_context(|| "Missing path argument for --replay")?);
+          return Ok(Replay::Playback(InputPlayback::open(path)?));
+        }
+        _ => {}
+      }
+    }
+    Ok(Replay::Live)
+  }
+
+  /// Gets the simulation seed of the recording being played back, if any.
+  pub fn playback_seed(&self) -> Option<u64> {
+    if let Replay::Playback(playback) = self { Some(playback.seed()) } else { None }
+  }
+}
+
+// Recording
+
+pub struct InputRecorder {
+  writer: BufWriter<File>,
+}
+
+impl InputRecorder {
+  pub fn create<P: AsRef<Path>>(path: P, seed: u64) -> Result<Self> {
+    let path = path.as_ref();
+    let file = File::create(path)
+      .with_context(|| format!("Failed to create input recording file '{}'", path.display()))?;
+    let mut writer = BufWriter::new(file);
+    writer.write_all(MAGIC)?;
+    writer.write_all(&VERSION.to_le_bytes())?;
+    writer.write_all(&seed.to_le_bytes())?;
+    Ok(Self { writer })
+  }
+
+  pub fn record(&mut self, frame: &ReplayFrame) -> Result<()> {
+    let writer = &mut self.writer;
+    writer.write_all(&(frame.frame_time.as_nanos() as u64).to_le_bytes())?;
+    writer.write_all(&frame.ticks.to_le_bytes())?;
+    writer.write_all(&game_debug_input_bits(&frame.input.game_debug).to_le_bytes())?;
+    let camera = &frame.input.camera;
+    writer.write_all(&camera_input_bits(camera).to_le_bytes())?;
+    writer.write_all(&camera.zoom_delta.to_le_bytes())?;
+    writer.write_all(&camera.mouse_pos.x.to_le_bytes())?;
+    writer.write_all(&camera.mouse_pos.y.to_le_bytes())?;
+    Ok(())
+  }
+
+  /// Flushes buffered frames to the file.
+  pub fn finish(mut self) -> Result<()> {
+    Ok(self.writer.flush().with_context(|| "Failed to flush input recording")?)
+  }
+}
+
+// Playback
+
+pub struct InputPlayback {
+  reader: BufReader<File>,
+  seed: u64,
+}
+
+impl InputPlayback {
+  pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+    let path = path.as_ref();
+    let file = File::open(path)
+      .with_context(|| format!("Failed to open input recording file '{}'", path.display()))?;
+    let mut reader = BufReader::new(file);
+    let mut magic = [0u8; 4];
+    reader.read_exact(&mut magic)?;
+    if &magic != MAGIC {
+      bail!("File '{}' is not an input recording", path.display());
+    }
+    let version = u32::from_le_bytes(read_array(&mut reader)?);
+    if version != VERSION {
+      bail!("Input recording '{}' has unsupported version {}, expected {}", path.display(), version, VERSION);
+    }
+    let seed = u64::from_le_bytes(read_array(&mut reader)?);
+    Ok(Self { reader, seed })
+  }
+
+  #[inline]
+  pub fn seed(&self) -> u64 { self.seed }
+
+  /// Reads the next frame, or returns `None` if the recording has ended.
+  pub fn next_frame(&mut self) -> Result<Option<ReplayFrame>> {
+    let frame_time = match read_array(&mut self.reader) {
+      Ok(bytes) => Duration::from_nanos(u64::from_le_bytes(bytes)),
+      Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+      Err(e) => return Err(e.into()),
+    };
+    let reader = &mut self.reader;
+    let ticks = u32::from_le_bytes(read_array(reader)?);
+    let game_debug = game_debug_input_from_bits(u32::from_le_bytes(read_array(reader)?));
+    let camera_bits = u32::from_le_bytes(read_array(reader)?);
+    let zoom_delta = f32::from_le_bytes(read_array(reader)?);
+    let mouse_pos = PhysicalPosition::new(i32::from_le_bytes(read_array(reader)?), i32::from_le_bytes(read_array(reader)?));
+    let camera = camera_input_from_bits(camera_bits, zoom_delta, mouse_pos);
+    Ok(Some(ReplayFrame { frame_time, ticks, input: Input { game_debug, camera } }))
+  }
+}
+
+// Encoding
+
+fn read_array<R: Read, B: ArrayBuf>(reader: &mut R) -> io::Result<B> {
+  let mut buf = B::default();
+  reader.read_exact(buf.as_mut())?;
+  Ok(buf)
+}
+
+/// Fixed-size byte arrays that values are decoded from.
+trait ArrayBuf: Default + AsMut<[u8]> {}
+
+impl ArrayBuf for [u8; 4] {}
+
+impl ArrayBuf for [u8; 8] {}
+
+fn pack_bits(bits: &[bool]) -> u32 {
+  bits.iter().enumerate().fold(0, |packed, (i, bit)| packed | ((*bit as u32) << i))
+}
+
+#[inline]
+fn bit(packed: u32, i: u32) -> bool { packed & (1 << i) != 0 }
+
+fn game_debug_input_bits(input: &GameDebugInput) -> u32 {
+  pack_bits(&[
+    input.grid_linear_velocity_x_inc,
+    input.grid_linear_velocity_x_dec,
+    input.grid_linear_velocity_y_inc,
+    input.grid_linear_velocity_y_dec,
+    input.grid_angular_velocity_inc,
+    input.grid_angular_velocity_dec,
+    input.grid_randomize,
+    input.grid_reset,
+    input.activate_setup_1,
+    input.activate_setup_2,
+    input.activate_setup_3,
+    input.activate_setup_4,
+    input.activate_setup_5,
+    input.activate_setup_6,
+    input.activate_setup_7,
+    input.activate_setup_8,
+    input.activate_setup_9,
+    input.activate_setup_0,
+    input.print_metrics,
+    input.toggle_pause,
+    input.single_step,
+    input.toggle_wireframe,
+    input.cycle_tonemap_operator,
+    input.cycle_time_scale,
+  ])
+}
+
+fn game_debug_input_from_bits(bits: u32) -> GameDebugInput {
+  GameDebugInput {
+    grid_linear_velocity_x_inc: bit(bits, 0),
+    grid_linear_velocity_x_dec: bit(bits, 1),
+    grid_linear_velocity_y_inc: bit(bits, 2),
+    grid_linear_velocity_y_dec: bit(bits, 3),
+    grid_angular_velocity_inc: bit(bits, 4),
+    grid_angular_velocity_dec: bit(bits, 5),
+    grid_randomize: bit(bits, 6),
+    grid_reset: bit(bits, 7),
+    activate_setup_1: bit(bits, 8),
+    activate_setup_2: bit(bits, 9),
+    activate_setup_3: bit(bits, 10),
+    activate_setup_4: bit(bits, 11),
+    activate_setup_5: bit(bits, 12),
+    activate_setup_6: bit(bits, 13),
+    activate_setup_7: bit(bits, 14),
+    activate_setup_8: bit(bits, 15),
+    activate_setup_9: bit(bits, 16),
+    activate_setup_0: bit(bits, 17),
+    print_metrics: bit(bits, 18),
+    toggle_pause: bit(bits, 19),
+    single_step: bit(bits, 20),
+    toggle_wireframe: bit(bits, 21),
+    cycle_tonemap_operator: bit(bits, 22),
+    cycle_time_scale: bit(bits, 23),
+  }
+}
+
+fn camera_input_bits(input: &CameraInput) -> u32 {
+  pack_bits(&[
+    input.move_up,
+    input.move_right,
+    input.move_down,
+    input.move_left,
+    input.zoom_in_step,
+    input.zoom_out_step,
+    input.drag,
+  ])
+}
+
+fn camera_input_from_bits(bits: u32, zoom_delta: f32, mouse_pos: PhysicalPosition) -> CameraInput {
+  CameraInput {
+    move_up: bit(bits, 0),
+    move_right: bit(bits, 1),
+    move_down: bit(bits, 2),
+    move_left: bit(bits, 3),
+    zoom_delta,
+    zoom_in_step: bit(bits, 4),
+    zoom_out_step: bit(bits, 5),
+    drag: bit(bits, 6),
+    mouse_pos,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::time::Duration;
+
+  use gfx::camera::CameraInput;
+  use math::prelude::PhysicalPosition;
+
+  use crate::game_debug::GameDebugInput;
+  use crate::input::Input;
+
+  use super::{InputPlayback, InputRecorder, ReplayFrame};
+
+  #[test]
+  fn recorded_frames_are_played_back() {
+    let frames = vec![
+      ReplayFrame::default(),
+      ReplayFrame {
+        frame_time: Duration::from_nanos(16_666_667),
+        ticks: 1,
+        input: Input {
+          game_debug: GameDebugInput { grid_randomize: true, toggle_pause: true, ..GameDebugInput::default() },
+          camera: CameraInput { drag: true, zoom_delta: -1.5, mouse_pos: PhysicalPosition::new(-3, 700), ..CameraInput::default() },
+        },
+      },
+      ReplayFrame {
+        frame_time: Duration::from_millis(50),
+        ticks: 3,
+        input: Input {
+          game_debug: GameDebugInput { grid_linear_velocity_x_inc: true, cycle_time_scale: true, ..GameDebugInput::default() },
+          camera: CameraInput { move_up: true, zoom_in_step: true, ..CameraInput::default() },
+        },
+      },
+    ];
+    let path = std::env::temp_dir().join(format!("sg_replay_test_{}.sgir", std::process::id()));
+    let mut recorder = InputRecorder::create(&path, 42).unwrap();
+    for frame in &frames {
+      recorder.record(frame).unwrap();
+    }
+    recorder.finish().unwrap();
+
+    let mut playback = InputPlayback::open(&path).unwrap();
+    assert_eq!(playback.seed(), 42);
+    for frame in &frames {
+      assert_eq!(playback.next_frame().unwrap().as_ref(), Some(frame));
+    }
+    assert_eq!(playback.next_frame().unwrap(), None);
+    std::fs::remove_file(&path).unwrap();
+  }
+}
@@ -1,4 +1,4 @@
-use util::timing::{Time, Timer};
+use util::timing::{FixedTimestep, Time, Timer};
 use std::time::{Duration, Instant};
 
 pub struct FrameTimer {
@@ -28,32 +28,47 @@ impl FrameTimer {
 pub struct TickTimer {
   tick: u64,
   start: Instant,
-  time_target: Duration,
-  accumulated_lag: Duration,
+  timestep: FixedTimestep,
+  paused: bool,
+  single_step_requested: bool,
 }
 
 impl TickTimer {
   pub fn new(tick_time_target: Duration) -> TickTimer {
+    TickTimer::with_max_ticks_per_frame(tick_time_target, 8)
+  }
+
+  /// Creates a tick timer that runs at most `max_ticks_per_frame` ticks per frame. Lag exceeding that amount is
+  /// dropped, preventing the simulation from falling further and further behind after a long frame (spiral of death).
+  pub fn with_max_ticks_per_frame(tick_time_target: Duration, max_ticks_per_frame: u32) -> TickTimer {
     TickTimer {
       tick: 0,
       start: Instant::now(),
-      time_target: tick_time_target,
-      accumulated_lag: Duration::default(),
+      timestep: FixedTimestep::new(tick_time_target, max_ticks_per_frame),
+      paused: false,
+      single_step_requested: false,
     }
   }
 
 
+  /// Adds `frame_time` to the accumulated lag, clamping the lag to `max_ticks_per_frame` ticks. Returns the time that
+  /// was dropped due to clamping, which is zero if the lag was not clamped. Lag does not accumulate while paused.
   pub fn update_lag(&mut self, frame_time: Duration) -> Duration {
-    self.accumulated_lag += frame_time;
-    self.accumulated_lag
+    if self.paused { return Duration::default(); }
+    self.timestep.advance(frame_time)
+  }
+
+  /// Discards the accumulated lag, for example after ticks were not driven by lag for a while.
+  pub fn reset_lag(&mut self) {
+    self.timestep.reset();
   }
 
   pub fn num_upcoming_ticks(&self) -> u64 {
-    (self.accumulated_lag.as_secs_f64() / self.time_target.as_secs_f64()).floor() as u64
+    self.timestep.num_upcoming_steps()
   }
 
   pub fn should_tick(&self) -> bool {
-    self.accumulated_lag >= self.time_target
+    if self.paused { self.single_step_requested } else { self.timestep.should_tick() }
   }
 
   pub fn tick_start(&mut self) -> u64 {
@@ -63,20 +78,61 @@ impl TickTimer {
 
   pub fn tick_end(&mut self) -> Duration {
     self.tick += 1;
-    self.accumulated_lag -= self.time_target;
+    if self.paused {
+      self.single_step_requested = false;
+    } else {
+      self.timestep.step();
+    }
     self.start.elapsed()
   }
 
 
   pub fn time_target(&self) -> Duration {
-    self.time_target
+    self.timestep.time_step()
+  }
+
+  /// Changes the duration of a tick, for example to run the simulation in slow motion or fast forward. Progress towards
+  /// the next tick is preserved as a fraction of a tick, so changing the duration does not cause a burst of ticks.
+  pub fn set_tick_duration(&mut self, tick_duration: Duration) {
+    self.timestep.set_time_step(tick_duration);
   }
 
   pub fn accumulated_lag(&self) -> Duration {
-    self.accumulated_lag
+    self.timestep.accumulated()
+  }
+
+  pub fn max_ticks_per_frame(&self) -> u32 {
+    self.timestep.max_steps_per_advance()
+  }
+
+  pub fn set_max_ticks_per_frame(&mut self, max_ticks_per_frame: u32) {
+    self.timestep.set_max_steps_per_advance(max_ticks_per_frame);
+  }
+
+  /// Total time that was dropped because the accumulated lag exceeded `max_ticks_per_frame` ticks.
+  pub fn dropped_time(&self) -> Duration {
+    self.timestep.dropped()
+  }
+
+  #[inline]
+  pub fn is_paused(&self) -> bool { self.paused }
+
+  /// Pauses or resumes ticking. While paused, lag does not accumulate, so extrapolation stays frozen and resuming does
+  /// not run a burst of ticks.
+  pub fn set_paused(&mut self, paused: bool) {
+    self.paused = paused;
+    self.single_step_requested = false;
   }
 
+  /// Requests a single tick while paused. Does nothing when not paused.
+  pub fn request_single_step(&mut self) {
+    if self.paused {
+      self.single_step_requested = true;
+    }
+  }
+
+
   pub fn extrapolation(&self) -> f64 {
-    self.accumulated_lag.as_secs_f64() / self.time_target.as_secs_f64()
+    self.timestep.extrapolation()
   }
 }
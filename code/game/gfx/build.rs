@@ -1,25 +1,79 @@
 use std::fs;
 use std::fs::OpenOptions;
 use std::io::{Read, Write};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
-use shaderc::{Compiler, ShaderKind};
+use shaderc::{CompileOptions, Compiler, IncludeType, ResolvedInclude, ShaderKind};
 
 fn main() {
   let mut compiler = Compiler::new().unwrap();
   let src_dir = Path::new("src");
   let dst_dir = Path::new("../../../target/shader");
-  compiler.compile_shader_pair(src_dir.join("grid_renderer"), dst_dir.join("grid_renderer"), "grid");
+  let include_dir = src_dir.join("shader_include");
+  let mut options = CompileOptions::new().unwrap();
+  options.set_include_callback(move |requested, include_type, requesting, _depth| resolve_include(&include_dir, requested, include_type, requesting));
+  compile_shaders_in_dir(&mut compiler, &options, src_dir, dst_dir);
+}
+
+/// Recursively compiles all GLSL shaders in `src_dir` whose file name ends in `.<stage>.glsl`, writing SPIR-V to the
+/// mirrored path under `dst_dir` with the `.glsl` extension replaced by `.spv`.
+fn compile_shaders_in_dir(compiler: &mut Compiler, options: &CompileOptions, src_dir: &Path, dst_dir: &Path) {
+  let entries = fs::read_dir(src_dir)
+    .unwrap_or_else(|e| panic!("Failed to read source directory '{}': {:?}", src_dir.display(), e));
+  for entry in entries {
+    let entry = entry.unwrap_or_else(|e| panic!("Failed to read entry of source directory '{}': {:?}", src_dir.display(), e));
+    let src_path = entry.path();
+    let file_name = entry.file_name();
+    let file_name = file_name.to_string_lossy();
+    if src_path.is_dir() {
+      compile_shaders_in_dir(compiler, options, &src_path, &dst_dir.join(file_name.as_ref()));
+    } else if let Some(stem) = file_name.strip_suffix(".glsl") {
+      if let Some(kind) = shader_kind(stem) {
+        compiler.compile_shader(kind, options, &src_path, dst_dir.join(format!("{}.spv", stem)));
+      }
+    }
+  }
+}
+
+/// Resolves `#include "requested"` relative to the directory of `requesting`, falling back to `include_dir`, and
+/// `#include <requested>` in `include_dir`.
+fn resolve_include(include_dir: &Path, requested: &str, include_type: IncludeType, requesting: &str) -> Result<ResolvedInclude, String> {
+  let mut candidates: Vec<PathBuf> = Vec::new();
+  if let IncludeType::Relative = include_type {
+    candidates.push(Path::new(requesting).parent().unwrap_or(Path::new("")).join(requested));
+  }
+  candidates.push(include_dir.join(requested));
+  let path = candidates.iter().find(|p| p.is_file()).ok_or_else(|| {
+    let searched: Vec<String> = candidates.iter().map(|p| format!("'{}'", p.display())).collect();
+    format!("Cannot find include file '{}' requested by '{}'; searched {}", requested, requesting, searched.join(", "))
+  })?;
+  let content = fs::read_to_string(path)
+    .map_err(|e| format!("Failed to read include file '{}' requested by '{}': {:?}", path.display(), requesting, e))?;
+  println!("cargo:rerun-if-changed={}", path.display());
+  Ok(ResolvedInclude { resolved_name: path.to_string_lossy().into_owned(), content })
+}
+
+/// Gets the shader kind from the stage extension of `stem`, such as `grid.vert`, or `None` if it has no known stage.
+fn shader_kind(stem: &str) -> Option<ShaderKind> {
+  let stage = Path::new(stem).extension()?.to_str()?;
+  match stage {
+    "vert" => Some(ShaderKind::Vertex),
+    "tesc" => Some(ShaderKind::TessControl),
+    "tese" => Some(ShaderKind::TessEvaluation),
+    "geom" => Some(ShaderKind::Geometry),
+    "frag" => Some(ShaderKind::Fragment),
+    "comp" => Some(ShaderKind::Compute),
+    _ => None,
+  }
 }
 
 
 trait CompilerEx {
-  fn compile_shader<S: AsRef<Path>, D: AsRef<Path>>(&mut self, kind: ShaderKind, src_path: S, dst_path: D);
-  fn compile_shader_pair<S: AsRef<Path>, D: AsRef<Path>>(&mut self, src_dir: S, dst_dir: D, name: &str);
+  fn compile_shader<S: AsRef<Path>, D: AsRef<Path>>(&mut self, kind: ShaderKind, options: &CompileOptions, src_path: S, dst_path: D);
 }
 
 impl CompilerEx for Compiler {
-  fn compile_shader<S: AsRef<Path>, D: AsRef<Path>>(&mut self, kind: ShaderKind, src_path: S, dst_path: D) {
+  fn compile_shader<S: AsRef<Path>, D: AsRef<Path>>(&mut self, kind: ShaderKind, options: &CompileOptions, src_path: S, dst_path: D) {
     let src_path = src_path.as_ref();
     let dst_path = dst_path.as_ref();
     let source_text = {
@@ -33,13 +87,15 @@ impl CompilerEx for Compiler {
       println!("cargo:rerun-if-changed={}", src_path.display());
       string
     };
-    let result = self.compile_into_spirv(
-      &source_text,
-      kind,
-      src_path.file_name().map(|p| p.to_str().unwrap_or_default()).unwrap_or_default(),
-      "main",
-      None
-    ).unwrap_or_else(|e| panic!("Failed to compile shader file '{}': {:?}", src_path.display(), e));
+    // Pass the path as file name, so that relative includes can be resolved against it.
+    let file_name = src_path.to_str().unwrap_or_default();
+    let result = match self.compile_into_spirv(&source_text, kind, file_name, "main", Some(options)) {
+      Ok(result) => result,
+      Err(shaderc::Error::CompilationError(_, message)) => {
+        panic!("Failed to compile shader file '{}':\n{}", src_path.display(), with_source_context(&message, file_name, &source_text))
+      }
+      Err(e) => panic!("Failed to compile shader file '{}': {:?}", src_path.display(), e),
+    };
     fs::create_dir_all(dst_path.parent().unwrap())
       .unwrap_or_else(|e| panic!("Failed to create destination directory '{}': {:}", dst_path.display(), e));
     let mut writer = OpenOptions::new()
@@ -51,11 +107,24 @@ impl CompilerEx for Compiler {
     writer.write(result.as_binary_u8())
       .unwrap_or_else(|e| panic!("Failed to write bytes to destination file '{}': {:?}", dst_path.display(), e));
   }
+}
+
 
-  fn compile_shader_pair<S: AsRef<Path>, D: AsRef<Path>>(&mut self, src_dir: S, dst_dir: D, name: &str) {
-    let src_dir = src_dir.as_ref();
-    let dst_dir = dst_dir.as_ref();
-    self.compile_shader(ShaderKind::Vertex, src_dir.join(format!("{}.vert.glsl", name)), dst_dir.join(format!("{}.vert.spv", name)));
-    self.compile_shader(ShaderKind::Fragment, src_dir.join(format!("{}.frag.glsl", name)), dst_dir.join(format!("{}.frag.spv", name)));
+/// Annotates each line of shaderc error `message` that references a line of `file_name`, in the form
+/// `<file_name>:<line>: error: ...`, with that line of `source_text`.
+fn with_source_context(message: &str, file_name: &str, source_text: &str) -> String {
+  let source_lines: Vec<&str> = source_text.lines().collect();
+  let mut annotated = String::new();
+  for message_line in message.lines() {
+    annotated.push_str(message_line);
+    annotated.push('\n');
+    let line_number = message_line.strip_prefix(file_name)
+      .and_then(|rest| rest.strip_prefix(':'))
+      .and_then(|rest| rest.split(':').next())
+      .and_then(|line_number| line_number.trim().parse::<usize>().ok());
+    if let Some(source_line) = line_number.and_then(|n| n.checked_sub(1)).and_then(|i| source_lines.get(i)) {
+      annotated.push_str(&format!("{:>5} | {}\n", line_number.unwrap(), source_line));
+    }
   }
+  annotated
 }
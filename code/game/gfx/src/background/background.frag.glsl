@@ -0,0 +1,33 @@
+#version 450
+#extension GL_ARB_separate_shader_objects : enable
+
+// Inputs
+layout(location = 0) in vec2 frgUV;
+/// Texture array with a nearest (0) and linear (1) sampler
+layout(set = 0, binding = 0) uniform sampler2DArray samplerArrays[2];
+/// Background mode (1 = gradient, 2 = tiled), gradient colors, and tiling parameters in pixels
+layout(push_constant) uniform BackgroundUniformData {
+  vec4 top;
+  vec4 bottom;
+  vec2 offset;
+  float tileSize;
+  uint layer;
+  uint mode;
+  uint samplerIndex;
+} ud;
+
+// Outputs
+/// Color
+layout(location = 0) out vec4 outCol;
+
+void main() {
+  // Sample with both samplers outside of control flow, as implicit derivatives are undefined in non-uniform control flow.
+  vec3 tex = vec3((gl_FragCoord.xy + ud.offset) / ud.tileSize, float(ud.layer));
+  vec4 nearest = texture(samplerArrays[0], tex);
+  vec4 linear = texture(samplerArrays[1], tex);
+  if(ud.mode == 2u) {
+    outCol = ud.samplerIndex == 0u ? nearest : linear;
+  } else {
+    outCol = mix(ud.top, ud.bottom, frgUV.y);
+  }
+}
@@ -0,0 +1,133 @@
+use std::mem::size_of;
+use std::time::Duration;
+
+use anyhow::Result;
+use ash::version::DeviceV1_0;
+
+use math::prelude::{Rgba, Vec2};
+use vkw::prelude::*;
+
+use crate::fullscreen_pass::FullscreenPass;
+use crate::texture_def::{TextureDef, TextureIdx};
+
+// Background
+
+/// Background of the scene, drawn behind everything else.
+#[derive(Copy, Clone, Debug)]
+pub enum Background {
+  /// Clears the scene to a single color, without drawing.
+  SolidColor(Rgba),
+  /// Vertical gradient from `top` at the top of the screen to `bottom` at the bottom.
+  Gradient { top: Rgba, bottom: Rgba },
+  /// Texture tiled in screen space with tiles of `tile_size` pixels, scrolling by `scroll_velocity` pixels per second.
+  Tiled { texture: TextureIdx, tile_size: f32, scroll_velocity: Vec2 },
+}
+
+impl Background {
+  /// Gets the color to clear the scene with: the solid color, or black if the background is drawn over it.
+  pub fn clear_color(&self) -> Rgba {
+    match self {
+      Background::SolidColor(color) => *color,
+      _ => Rgba::BLACK,
+    }
+  }
+}
+
+impl Default for Background {
+  #[inline]
+  fn default() -> Self { Background::SolidColor(Rgba::new(0.5, 0.5, 1.0, 1.0)) }
+}
+
+// Background system
+
+/// Draws [Background]s that are not a solid color with a full-screen pass, sampling tiled textures from the
+/// [TextureDef].
+pub struct BackgroundSys {
+  frag_shader: ShaderModule,
+  pass: FullscreenPass,
+  background: Background,
+}
+
+impl BackgroundSys {
+  /// Creates the background pass for subpass 0 of `render_pass`, sampling textures through the descriptor set layout of
+  /// `texture_def`.
+  pub fn new(device: &Device, render_pass: RenderPass, pipeline_cache: PipelineCache, texture_def: &TextureDef) -> Result<Self> {
+    unsafe {
+      // Own the shader until the system is assembled, so that it is destroyed if creating the pass fails.
+      let frag_shader = device.owned(device.create_shader_module(include_bytes!("../../../../../target/shader/background/background.frag.spv"))?);
+      let pass = FullscreenPass::new(device, render_pass, pipeline_cache, *frag_shader, &[texture_def.descriptor_set_layout], &[BackgroundUniformData::push_constant_range()])?;
+      Ok(Self { frag_shader: frag_shader.into_raw(), pass, background: Background::default() })
+    }
+  }
+
+  #[inline]
+  pub fn background(&self) -> Background { self.background }
+
+  #[inline]
+  pub fn set_background(&mut self, background: Background) { self.background = background; }
+
+  /// Records drawing the background into `command_buffer`, which must be inside subpass 0 of the render pass that this
+  /// system was created with, with dynamic viewport and scissor state set. Does nothing for solid color backgrounds,
+  /// which are drawn by clearing with [Background::clear_color]. `elapsed` is the time that scrolling is based on.
+  pub unsafe fn render(&self, device: &Device, command_buffer: CommandBuffer, texture_def: &TextureDef, elapsed: Duration) {
+    let uniform_data = match self.background {
+      Background::SolidColor(_) => return,
+      Background::Gradient { top, bottom } => BackgroundUniformData {
+        top,
+        bottom,
+        offset: [0.0, 0.0],
+        tile_size: 1.0,
+        layer: 0,
+        mode: 1,
+        sampler_index: 0,
+      },
+      Background::Tiled { texture, tile_size, scroll_velocity } => {
+        let offset = scroll_velocity * elapsed.as_secs_f32();
+        BackgroundUniformData {
+          top: Rgba::WHITE,
+          bottom: Rgba::WHITE,
+          offset: [offset.x, offset.y],
+          tile_size: tile_size.max(1.0),
+          layer: texture.layer(),
+          mode: 2,
+          sampler_index: texture_def.filter(texture) as u32,
+        }
+      }
+    };
+    device.cmd_push_constants(command_buffer, self.pass.pipeline_layout(), ShaderStageFlags::FRAGMENT, 0, uniform_data.as_bytes());
+    self.pass.draw(device, command_buffer, texture_def.descriptor_set);
+  }
+
+  pub fn destroy(&self, device: &Device) {
+    unsafe {
+      self.pass.destroy(device);
+      device.destroy_shader_module(self.frag_shader);
+    }
+  }
+}
+
+// Background uniform data (push constant, mutable)
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+struct BackgroundUniformData {
+  top: Rgba,
+  bottom: Rgba,
+  offset: [f32; 2],
+  tile_size: f32,
+  layer: u32,
+  mode: u32,
+  sampler_index: u32,
+}
+
+impl BackgroundUniformData {
+  pub fn push_constant_range() -> PushConstantRange {
+    push_constant::fragment_range(size_of::<Self>() as u32, 0)
+  }
+
+  pub unsafe fn as_bytes(&self) -> &[u8] {
+    let ptr = self as *const Self;
+    let bytes_ptr = ptr as *const u8;
+    std::slice::from_raw_parts(bytes_ptr, size_of::<Self>())
+  }
+}
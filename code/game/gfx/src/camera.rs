@@ -1,18 +1,49 @@
-use ultraviolet::{Mat4, Vec2, Vec3};
-use ultraviolet::projection;
+//! Camera, and conversions between screen and world coordinates.
+//!
+//! All rendering-space math uses physical pixels. Screen coordinates are physical pixels relative to the top-left of
+//! the viewport, window coordinates are physical pixels relative to the top-left of the window, as received from OS
+//! input events. Logical coordinates (physical divided by the scale factor) are only used by the UI, and are converted
+//! at the boundary with [logical_to_world](CameraSys::logical_to_world) and
+//! [world_to_logical](CameraSys::world_to_logical).
 
-use math::screen::{PhysicalPosition, PhysicalSize};
 use std::time::Duration;
 
+use math::prelude::{Mat4, projection, Vec2, Vec3, Vec4};
+use math::screen::{LogicalPosition, PhysicalPosition, PhysicalSize, Scale};
+
+/// Projection used by [CameraSys].
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Projection {
+  /// Orthographic projection, where zoom is the visible height in meters.
+  Orthographic,
+  /// Perspective projection with vertical field of view `fov` in radians. Zoom is the visible height in meters at the
+  /// z=0 plane, which is converted into the distance of the camera to that plane.
+  Perspective { fov: f32 },
+}
+
+impl Default for Projection {
+  fn default() -> Self { Projection::Orthographic }
+}
+
 #[derive(Debug)]
 pub struct CameraSys {
   position: Vec3,
   zoom: f32,
+  projection: Projection,
+  shakes: Vec<Shake>,
+  shake_time: f32,
+  shake_offset: Vec2,
   pan_speed: f32,
   mag_speed: f32,
+  zoom_to_cursor: bool,
+  zoom_step: f32,
+  min_zoom: f32,
+  max_zoom: f32,
   view_proj: Mat4,
   view_proj_inverse: Mat4,
   viewport: PhysicalSize,
+  viewport_offset: PhysicalPosition,
+  scale: Scale,
   last_mouse_pos: Option<Vec2>,
 }
 
@@ -26,11 +57,21 @@ impl CameraSys {
       // TODO: why is z 1.0? Shouldn't Z be -1.0, since 1.0 z is going INTO the screen? Is it because the view transformation is applied BEFORE the projection transformation, which flips the Z around?
       position: Vec3::new(0.0, 0.0, 1.0),
       zoom: 1.0,
+      projection: Projection::default(),
+      shakes: Vec::new(),
+      shake_time: 0.0,
+      shake_offset: Vec2::zero(),
       pan_speed,
       mag_speed,
+      zoom_to_cursor: true,
+      zoom_step: 1.2,
+      min_zoom: 0.1,
+      max_zoom: 1000.0,
       view_proj: Mat4::identity(),
       view_proj_inverse: Mat4::identity().inversed(),
       viewport,
+      viewport_offset: PhysicalPosition::default(),
+      scale: Scale::default(),
       last_mouse_pos: None
     }
   }
@@ -38,34 +79,155 @@ impl CameraSys {
   #[inline]
   pub fn position(&self) -> Vec3 { self.position }
 
+  /// Gets the zoom, which is the height of the viewport in world units (meters). Alias of
+  /// [viewport_world_height](Self::viewport_world_height).
   #[inline]
   pub fn zoom(&self) -> f32 { self.zoom }
 
   #[inline]
   pub fn set_position(&mut self, position: Vec3) { self.position = position; }
 
+  /// Sets the zoom, clamped to the zoom range.
+  #[inline]
+  pub fn set_zoom(&mut self, zoom: f32) { self.zoom = zoom.max(self.min_zoom).min(self.max_zoom); }
+
+  /// Gets the height of the viewport in world units (meters). For perspective projections, this is the height at the
+  /// z=0 plane.
+  #[inline]
+  pub fn viewport_world_height(&self) -> f32 { self.zoom }
+
+  /// Sets the height of the viewport in world units (meters), clamped to the zoom range. The width is derived from the
+  /// aspect ratio of the viewport, so the height stays constant when the viewport is resized.
+  #[inline]
+  pub fn set_viewport_world_height(&mut self, height: f32) { self.set_zoom(height); }
+
+  /// Gets the width of the viewport in world units (meters), derived from the height and the aspect ratio.
+  #[inline]
+  pub fn viewport_world_width(&self) -> f32 {
+    let (width, height): (f32, f32) = self.viewport.into();
+    if height == 0.0 { return 0.0; }
+    self.zoom * width / height
+  }
+
+  #[inline]
+  pub fn zoom_range(&self) -> (f32, f32) { (self.min_zoom, self.max_zoom) }
+
+  /// Sets the range that zoom is clamped to, and clamps the current zoom to it.
+  pub fn set_zoom_range(&mut self, min_zoom: f32, max_zoom: f32) {
+    self.min_zoom = min_zoom;
+    self.max_zoom = max_zoom.max(min_zoom);
+    self.set_zoom(self.zoom);
+  }
+
+  #[inline]
+  pub fn projection(&self) -> Projection { self.projection }
+
+  /// Sets the projection, which takes effect on the next update.
   #[inline]
-  pub fn set_zoom(&mut self, zoom: f32) { self.zoom = zoom; }
+  pub fn set_projection(&mut self, projection: Projection) { self.projection = projection; }
+
+  /// Adds a camera shake that offsets the view by at most `intensity` meters, decaying to zero over `duration`. Shakes
+  /// are summed and decay independently, and do not change the position of the camera.
+  pub fn add_shake(&mut self, intensity: f32, duration: Duration) {
+    let duration = duration.as_secs_f32();
+    if duration > 0.0 && intensity != 0.0 {
+      self.shakes.push(Shake { intensity, duration, elapsed: 0.0 });
+    }
+  }
+
+  /// Gets the current offset of the view caused by camera shakes.
+  #[inline]
+  pub fn shake_offset(&self) -> Vec2 { self.shake_offset }
 
   #[inline]
   pub fn view_projection_matrix(&self) -> Mat4 { self.view_proj }
 
-  /// Converts screen coordinates (in pixels, relative to the top-left of the screen) to view coordinates (in meters,
-  /// relative to the center of the screen).
+  /// Gets the scale factor used to convert between logical and physical coordinates.
+  #[inline]
+  pub fn scale(&self) -> Scale { self.scale }
+
+  /// Converts screen coordinates (in physical pixels, relative to the top-left of the viewport) to view coordinates (in
+  /// meters, relative to the center of the viewport).
   #[inline]
   pub fn screen_to_view(&self, x: f32, y:f32) -> Vec3 {
+    self.screen_to_world(x, y) - Vec3::new(self.position.x, self.position.y, 0.0)
+  }
+
+  /// Converts screen coordinates (in physical pixels, relative to the top-left of the viewport) to world coordinates (in
+  /// meters, absolute) on the z=0 plane.
+  #[inline]
+  pub fn screen_to_world(&self, x: f32, y:f32) -> Vec3 {
     let (width, height): (f32, f32) = self.viewport.into();
     let x = 2.0 * x / width - 1.0;
     let y = 2.0 * y / height - 1.0;
-    let vec = Vec3::new(x, y, 0.0);
-    Vec3::from_homogeneous_point(self.view_proj_inverse * vec.into_homogeneous_point())
+    // Unproject the point on the near and far plane, and intersect the ray between them with the z=0 plane. For an
+    // orthographic projection the ray is parallel to the z axis, so this is equal to just taking x and y of either.
+    let near = Vec3::from_homogeneous_point(self.view_proj_inverse * Vec4::new(x, y, 0.0, 1.0));
+    let far = Vec3::from_homogeneous_point(self.view_proj_inverse * Vec4::new(x, y, 1.0, 1.0));
+    let direction = far - near;
+    if direction.z.abs() <= std::f32::EPSILON {
+      return Vec3::new(near.x, near.y, 0.0);
+    }
+    let t = -near.z / direction.z;
+    let point = near + direction * t;
+    Vec3::new(point.x, point.y, 0.0)
   }
 
-  /// Converts screen coordinates (in pixels, relative to the top-left of the screen) to world coordinates (in meters,
-  /// absolute).
+  /// Converts world coordinates (in meters, absolute) on the z=0 plane to screen coordinates (in physical pixels,
+  /// relative to the top-left of the viewport). The result is outside of the screen for points that are not visible, and meaningless for
+  /// points behind a perspective camera; use [world_to_screen_visible](Self::world_to_screen_visible) to detect those.
   #[inline]
-  pub fn screen_to_world(&self, x: f32, y:f32) -> Vec3 {
-    self.position + self.screen_to_view(x, y)
+  pub fn world_to_screen(&self, world: Vec2) -> Vec2 {
+    let (screen, _) = self.project(world);
+    screen
+  }
+
+  /// Converts world coordinates to screen coordinates like [world_to_screen](Self::world_to_screen), but returns `None`
+  /// if the point is behind the camera or off-screen.
+  pub fn world_to_screen_visible(&self, world: Vec2) -> Option<Vec2> {
+    let (screen, w) = self.project(world);
+    let (width, height): (f32, f32) = self.viewport.into();
+    if w <= 0.0 || screen.x < 0.0 || screen.y < 0.0 || screen.x > width || screen.y > height {
+      None
+    } else {
+      Some(screen)
+    }
+  }
+
+  /// Converts a window position (in physical pixels, relative to the top-left of the window), such as the mouse
+  /// position from OS input events, to world coordinates (in meters, absolute) on the z=0 plane.
+  #[inline]
+  pub fn window_to_world(&self, position: PhysicalPosition) -> Vec3 {
+    let screen = self.window_to_screen(position);
+    self.screen_to_world(screen.x, screen.y)
+  }
+
+  /// Converts world coordinates (in meters, absolute) on the z=0 plane to a window position (in physical pixels,
+  /// relative to the top-left of the window).
+  #[inline]
+  pub fn world_to_window(&self, world: Vec2) -> PhysicalPosition {
+    let screen = self.world_to_screen(world);
+    PhysicalPosition::new(
+      screen.x.round() as i32 + self.viewport_offset.x,
+      screen.y.round() as i32 + self.viewport_offset.y,
+    )
+  }
+
+  /// Converts a UI position (in logical pixels, relative to the top-left of the window) to world coordinates (in
+  /// meters, absolute) on the z=0 plane.
+  #[inline]
+  pub fn logical_to_world(&self, position: LogicalPosition) -> Vec3 {
+    self.window_to_world(position.into_physical(self.scale))
+  }
+
+  /// Converts world coordinates (in meters, absolute) on the z=0 plane to a UI position (in logical pixels, relative to
+  /// the top-left of the window).
+  #[inline]
+  pub fn world_to_logical(&self, world: Vec2) -> LogicalPosition {
+    let screen = self.world_to_screen(world);
+    let x = (screen.x as f64 + self.viewport_offset.x as f64) / self.scale;
+    let y = (screen.y as f64 + self.viewport_offset.y as f64) / self.scale;
+    LogicalPosition::new(x, y)
   }
 
 
@@ -77,9 +239,30 @@ impl CameraSys {
 
   pub fn set_magnification_speed(&mut self, mag_speed: f32) { self.mag_speed = mag_speed; }
 
+  pub fn zoom_step(&self) -> f32 { self.zoom_step }
+
+  /// Sets the factor that zoom is multiplied or divided by when zooming in discrete steps.
+  pub fn set_zoom_step(&mut self, zoom_step: f32) { self.zoom_step = zoom_step; }
+
+  pub fn zoom_to_cursor(&self) -> bool { self.zoom_to_cursor }
 
-  pub(crate) fn signal_viewport_resize(&mut self, viewport: PhysicalSize) {
+  /// Sets whether zooming keeps the world point under the mouse cursor fixed, instead of zooming around the center of
+  /// the screen.
+  pub fn set_zoom_to_cursor(&mut self, zoom_to_cursor: bool) { self.zoom_to_cursor = zoom_to_cursor; }
+
+
+  /// Signals that the viewport changed to `viewport`, with its top-left at `viewport_offset` in the window, both in
+  /// physical pixels.
+  pub(crate) fn signal_viewport_resize(&mut self, viewport: PhysicalSize, viewport_offset: PhysicalPosition) {
     self.viewport = viewport;
+    self.viewport_offset = viewport_offset;
+    // Recompute immediately, so that screen/world conversions use the new aspect ratio before the next update.
+    self.update_view_projection();
+  }
+
+  /// Signals that the scale factor of the window changed to `scale`.
+  pub(crate) fn signal_scale_change(&mut self, scale: Scale) {
+    self.scale = scale;
   }
 
   pub(crate) fn update(
@@ -93,13 +276,20 @@ impl CameraSys {
     if input.move_right { self.position.x += pan_speed };
     if input.move_down { self.position.y -= pan_speed };
     if input.move_left { self.position.x -= pan_speed };
-    self.zoom *= 1.0 - input.zoom_delta * mag_speed;
+    self.update_shakes(frame_time);
+    let mouse_pos = self.window_to_screen(input.mouse_pos);
+
+    if input.zoom_delta != 0.0 {
+      let cursor = if self.zoom_to_cursor { Some(mouse_pos) } else { None };
+      self.zoom_by(1.0 - input.zoom_delta * mag_speed, cursor);
+    }
+    if input.zoom_in_step { self.zoom_by(1.0 / self.zoom_step, None); }
+    if input.zoom_out_step { self.zoom_by(self.zoom_step, None); }
 
     let (width, height): (f32, f32) = self.viewport.into();
 
     // TODO: fix mouse dragging.
     if input.drag {
-      let mouse_pos = Vec2::new(input.drag_pos.x as f32, input.drag_pos.y as f32);
       if self.last_mouse_pos.is_none() {
         self.last_mouse_pos = Some(mouse_pos);
       }
@@ -110,35 +300,120 @@ impl CameraSys {
       self.last_mouse_pos = None;
     }
 
-    // View matrix.
+    self.update_view_projection();
+  }
+
+  /// Multiplies zoom by `factor`, clamped to the zoom range. If `cursor` is given, the world point under the cursor is
+  /// kept fixed by moving the camera by the distance that point moved.
+  fn zoom_by(&mut self, factor: f32, cursor: Option<Vec2>) {
+    if let Some(cursor) = cursor {
+      self.update_view_projection();
+      let before = self.screen_to_world(cursor.x, cursor.y);
+      self.set_zoom(self.zoom * factor);
+      self.update_view_projection();
+      let after = self.screen_to_world(cursor.x, cursor.y);
+      self.position += before - after;
+    } else {
+      self.set_zoom(self.zoom * factor);
+    }
+  }
+
+  /// Converts a window position to screen coordinates, by making it relative to the top-left of the viewport.
+  #[inline]
+  fn window_to_screen(&self, position: PhysicalPosition) -> Vec2 {
+    Vec2::new((position.x - self.viewport_offset.x) as f32, (position.y - self.viewport_offset.y) as f32)
+  }
+
+  /// Projects `world` on the z=0 plane to screen coordinates, also returning the clip-space w, which is not positive for
+  /// points behind the camera.
+  fn project(&self, world: Vec2) -> (Vec2, f32) {
+    let (width, height): (f32, f32) = self.viewport.into();
+    let clip = self.view_proj * Vec4::new(world.x, world.y, 0.0, 1.0);
+    let ndc = Vec2::new(clip.x / clip.w, clip.y / clip.w);
+    (Vec2::new((ndc.x + 1.0) * 0.5 * width, (ndc.y + 1.0) * 0.5 * height), clip.w)
+  }
+
+  fn update_view_projection(&mut self) {
+    let (width, height): (f32, f32) = self.viewport.into();
+    let aspect_ratio = width / height;
+    let min_z = 0.01f32;
+    let max_z = 1000.0f32;
+    let (eye_z, proj) = match self.projection {
+      Projection::Orthographic => {
+        // Orthographic (zoomable) projection matrix.
+        let min_x = aspect_ratio * self.zoom / -2.0;
+        let max_x = aspect_ratio * self.zoom / 2.0;
+        let min_y = self.zoom / -2.0;
+        let max_y = self.zoom / 2.0;
+        let proj = projection::lh_yup::orthographic_vk(min_x, max_x,
+          min_y, max_y,
+          min_z, max_z
+        );
+        (self.position.z, proj)
+      }
+      Projection::Perspective { fov } => {
+        // Perspective projection matrix, with the camera placed at the distance where the visible height at the z=0
+        // plane equals the zoom.
+        let distance = (self.zoom / (2.0 * (fov / 2.0).tan())).max(min_z * 2.0);
+        let proj = projection::lh_yup::perspective_vk(fov, aspect_ratio, min_z, max_z);
+        (distance.copysign(self.position.z), proj)
+      }
+    };
+
+    // View matrix, offset by camera shakes.
+    let x = self.position.x + self.shake_offset.x;
+    let y = self.position.y + self.shake_offset.y;
     let view = Mat4::look_at_lh(
-      Vec3::new(self.position.x, self.position.y, self.position.z),
-      Vec3::new(self.position.x, self.position.y, 0.0),
+      Vec3::new(x, y, eye_z),
+      Vec3::new(x, y, 0.0),
       Vec3::unit_y()
     );
 
-    // Orthographic (zoomable) projection matrix.
-    let proj = {
-      let aspect_ratio = width / height;
-      let min_x = aspect_ratio * self.zoom / -2.0;
-      let max_x = aspect_ratio * self.zoom / 2.0;
-      let min_y = self.zoom / -2.0;
-      let max_y = self.zoom / 2.0;
-      let min_z = 0.01f32;
-      let max_z = 1000.0f32;
-      projection::lh_yup::orthographic_vk(min_x, max_x,
-        min_y, max_y,
-        min_z, max_z
-      )
-    };
-
     let view_proj = proj * view;
     self.view_proj = view_proj;
     self.view_proj_inverse = view_proj.inversed();
   }
+
+  fn update_shakes(&mut self, frame_time: Duration) {
+    let frame_time = frame_time.as_secs_f32();
+    for shake in &mut self.shakes {
+      shake.elapsed += frame_time;
+    }
+    self.shakes.retain(|s| s.elapsed < s.duration);
+    if self.shakes.is_empty() {
+      // Reset so that the view settles back exactly at the position of the camera.
+      self.shake_time = 0.0;
+      self.shake_offset = Vec2::zero();
+      return;
+    }
+    self.shake_time += frame_time;
+    let noise = shake_noise(self.shake_time);
+    let amplitude: f32 = self.shakes.iter().map(|s| {
+      let remaining = 1.0 - s.elapsed / s.duration;
+      s.intensity * remaining * remaining
+    }).sum();
+    self.shake_offset = noise * amplitude;
+  }
+}
+
+// Camera shake
+
+#[derive(Copy, Clone, Debug)]
+struct Shake {
+  intensity: f32,
+  duration: f32,
+  elapsed: f32,
+}
+
+/// Smooth pseudo-random noise in [-1, 1] for both axes, made by summing sines with incommensurable frequencies.
+#[inline]
+fn shake_noise(t: f32) -> Vec2 {
+  let x = (t * 37.0).sin() * 0.5 + (t * 61.3).sin() * 0.3 + (t * 97.7).sin() * 0.2;
+  let y = (t * 41.9).sin() * 0.5 + (t * 67.1).sin() * 0.3 + (t * 89.3).sin() * 0.2;
+  Vec2::new(x, y)
 }
 
-#[derive(Default, Copy, Clone, Debug)]
+#[derive(Default, Copy, Clone, PartialEq, Debug)]
 pub struct CameraInput {
   // Keyboard movement.
   pub move_up: bool,
@@ -147,7 +422,107 @@ pub struct CameraInput {
   pub move_left: bool,
   // Mouse scroll zoom.
   pub zoom_delta: f32,
+  // Keyboard zoom steps.
+  pub zoom_in_step: bool,
+  pub zoom_out_step: bool,
   // Mouse dragging.
   pub drag: bool,
-  pub drag_pos: PhysicalPosition,
+  // Mouse position in physical pixels relative to the top-left of the window, for dragging and zooming toward the cursor.
+  pub mouse_pos: PhysicalPosition,
+}
+
+#[cfg(test)]
+mod tests {
+  use std::time::Duration;
+
+  use math::prelude::{Vec2, Vec3};
+  use math::screen::{LogicalPosition, PhysicalPosition, PhysicalSize, Scale};
+  use sim::prelude::*;
+
+  use super::{CameraInput, CameraSys, Projection};
+
+  fn assert_vec2_eq(actual: Vec2, expected: Vec2, epsilon: f32) {
+    assert!((actual - expected).mag() < epsilon, "Expected {:?}, got {:?}", expected, actual);
+  }
+
+  fn assert_vec3_eq(actual: Vec3, expected: Vec3, epsilon: f32) {
+    assert!((actual - expected).mag() < epsilon, "Expected {:?}, got {:?}", expected, actual);
+  }
+
+  /// Creates a camera with a viewport of 800x600 physical pixels, showing 60 meters vertically (10 pixels per meter),
+  /// with an updated view projection.
+  fn camera() -> CameraSys {
+    let mut camera = CameraSys::new(PhysicalSize::new(800, 600));
+    camera.set_zoom(60.0);
+    camera.update(CameraInput::default(), Duration::from_secs(0));
+    camera
+  }
+
+  #[test]
+  fn viewport_resize_updates_aspect_ratio_without_update() {
+    let mut camera = camera();
+    // Screen y points down, world y points up.
+    assert_vec3_eq(camera.screen_to_world(0.0, 0.0), Vec3::new(-40.0, 30.0, 0.0), 1e-3);
+    camera.signal_viewport_resize(PhysicalSize::new(1200, 600), PhysicalPosition::default());
+    assert_vec3_eq(camera.screen_to_world(0.0, 0.0), Vec3::new(-60.0, 30.0, 0.0), 1e-3);
+    assert_vec3_eq(camera.screen_to_world(1200.0, 600.0), Vec3::new(60.0, -30.0, 0.0), 1e-3);
+    assert!((camera.viewport_world_width() - 120.0).abs() < 1e-3);
+  }
+
+  #[test]
+  fn world_to_screen_maps_camera_position_to_viewport_center() {
+    let mut camera = camera();
+    camera.set_position(Vec3::new(12.0, -7.0, 1.0));
+    camera.update(CameraInput::default(), Duration::from_secs(0));
+    assert_vec2_eq(camera.world_to_screen(Vec2::new(12.0, -7.0)), Vec2::new(400.0, 300.0), 1e-3);
+    // 10 pixels per meter, with screen y pointing down.
+    assert_vec2_eq(camera.world_to_screen(Vec2::new(13.0, -6.0)), Vec2::new(410.0, 290.0), 1e-3);
+  }
+
+  #[test]
+  fn world_to_screen_visible_rejects_points_beyond_view() {
+    let camera = camera();
+    assert!(camera.world_to_screen_visible(Vec2::new(39.0, 29.0)).is_some());
+    assert!(camera.world_to_screen_visible(Vec2::new(41.0, 0.0)).is_none());
+    assert!(camera.world_to_screen_visible(Vec2::new(0.0, -31.0)).is_none());
+  }
+
+  #[test]
+  fn logical_to_world_picks_tile_at_scale() {
+    let mut camera = camera();
+    camera.signal_viewport_resize(PhysicalSize::new(800, 600), PhysicalPosition::new(30, 15));
+    camera.signal_scale_change(Scale::new(1.5));
+    // Logical (324, 112) is physical (486, 168) in the window, which is (456, 153) in the viewport: 56 pixels right and
+    // 147 pixels up from the center, at 10 pixels per meter.
+    let world = camera.logical_to_world(LogicalPosition::new(324.0, 112.0));
+    assert_vec3_eq(world, Vec3::new(5.6, 14.7, 0.0), 1e-3);
+    let point = Vec2::new(world.x, world.y);
+    assert_eq!(GridPivot::Center.position_at(point), GridPosition::new(6, 15));
+    assert_eq!(GridPivot::BottomLeft.position_at(point), GridPosition::new(5, 14));
+    // And back to the logical click.
+    let logical = camera.world_to_logical(point);
+    assert!((logical.x - 324.0).abs() < 1e-2 && (logical.y - 112.0).abs() < 1e-2, "Expected (324, 112), got {:?}", logical);
+  }
+
+  fn assert_screen_world_round_trip(projection: Projection, epsilon: f32) {
+    let mut camera = camera();
+    camera.set_projection(projection);
+    camera.set_position(Vec3::new(3.0, -2.0, 1.0));
+    camera.update(CameraInput::default(), Duration::from_secs(0));
+    for &(x, y) in &[(3.0, -2.0), (0.0, 0.0), (-20.0, 15.5), (35.0, -27.25)] {
+      let screen = camera.world_to_screen(Vec2::new(x, y));
+      assert_vec3_eq(camera.screen_to_world(screen.x, screen.y), Vec3::new(x, y, 0.0), epsilon);
+    }
+  }
+
+  #[test]
+  fn screen_world_round_trip_orthographic() {
+    assert_screen_world_round_trip(Projection::Orthographic, 1e-3);
+  }
+
+  #[test]
+  fn screen_world_round_trip_perspective() {
+    // Unprojecting through the near and far plane loses some precision with perspective projection.
+    assert_screen_world_round_trip(Projection::Perspective { fov: std::f32::consts::FRAC_PI_3 }, 0.05);
+  }
 }
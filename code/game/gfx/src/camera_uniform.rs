@@ -0,0 +1,120 @@
+use std::mem::size_of;
+
+use anyhow::Result;
+
+use math::prelude::Mat4;
+use vkw::prelude::*;
+
+// Camera uniform constants
+
+/// Descriptor set index at which the camera uniform is bound. Pipeline layouts of render items must have
+/// [CameraUniformLayout::descriptor_set_layout] at this index.
+pub const CAMERA_UNIFORM_SET: u32 = 1;
+/// Binding of the camera uniform buffer in its descriptor set.
+pub const CAMERA_UNIFORM_BINDING: u32 = 0;
+/// Binding of the frame uniform buffer, in the same descriptor set as the camera uniform buffer.
+pub const FRAME_UNIFORM_BINDING: u32 = 1;
+
+// Camera uniform data (uniform buffer, mutable)
+
+/// Per-frame camera data, shared by all renderers. Laid out according to std140, matching `shader_include/camera.glsl`.
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+pub struct CameraUniformData {
+  pub view_projection: Mat4,
+  /// Viewport size in pixels.
+  pub viewport: [f32; 2],
+  _padding: [f32; 2],
+}
+
+impl CameraUniformData {
+  pub fn new(view_projection: Mat4, viewport: [f32; 2]) -> Self {
+    Self { view_projection, viewport, _padding: [0.0; 2] }
+  }
+}
+
+// Frame uniform data (uniform buffer, mutable)
+
+/// Per-frame timing data for animating shaders, shared by all renderers. Laid out according to std140, matching
+/// `shader_include/camera.glsl`.
+#[repr(C)]
+#[derive(Default, Copy, Clone, Debug)]
+pub struct FrameUniformData {
+  /// Elapsed time in seconds, accumulated from frame times.
+  pub time: f32,
+  /// Index of the frame, wrapping around on overflow.
+  pub frame: u32,
+}
+
+// Camera uniform layout
+
+/// Descriptor set layout of the camera and frame uniforms, shared by all render states.
+pub struct CameraUniformLayout {
+  pub descriptor_set_layout: DescriptorSetLayout,
+}
+
+impl CameraUniformLayout {
+  pub fn new(device: &Device) -> Result<Self> {
+    let descriptor_set_layout = unsafe {
+      device.create_descriptor_set_layout(&[
+        descriptor_set::uniform_layout_binding(CAMERA_UNIFORM_BINDING, 1, ShaderStageFlags::VERTEX | ShaderStageFlags::FRAGMENT),
+        descriptor_set::uniform_layout_binding(FRAME_UNIFORM_BINDING, 1, ShaderStageFlags::VERTEX | ShaderStageFlags::FRAGMENT),
+      ], &[])?
+    };
+    Ok(Self { descriptor_set_layout })
+  }
+
+  pub fn destroy(&self, device: &Device) {
+    unsafe { device.destroy_descriptor_set_layout(self.descriptor_set_layout) };
+  }
+}
+
+// Camera uniform
+
+/// Camera and frame uniform buffers and descriptor set of a single render state, updated once per frame.
+pub struct CameraUniform {
+  buffer: BufferAllocation,
+  frame_buffer: BufferAllocation,
+  descriptor_pool: DescriptorPool,
+  pub descriptor_set: DescriptorSet,
+}
+
+impl CameraUniform {
+  pub fn new(device: &Device, allocator: &Allocator, layout: &CameraUniformLayout) -> Result<Self> {
+    unsafe {
+      let size = size_of::<CameraUniformData>();
+      let buffer = allocator.create_cpugpu_uniform_buffer_mapped(size)?;
+      let frame_size = size_of::<FrameUniformData>();
+      let frame_buffer = allocator.create_cpugpu_uniform_buffer_mapped(frame_size)?;
+      let descriptor_pool = device.create_descriptor_pool(1, &[descriptor_set::uniform_pool_size(2)], false)?;
+      let descriptor_set = device.allocate_descriptor_set(descriptor_pool, layout.descriptor_set_layout)?;
+      DescriptorSetUpdateBuilder::new()
+        .add_uniform_buffer_write(descriptor_set, CAMERA_UNIFORM_BINDING, 0, buffer.buffer, 0, size as u64)
+        .add_uniform_buffer_write(descriptor_set, FRAME_UNIFORM_BINDING, 0, frame_buffer.buffer, 0, frame_size as u64)
+        .do_update(device);
+      Ok(Self { buffer, frame_buffer, descriptor_pool, descriptor_set })
+    }
+  }
+
+  /// Writes `data` and `frame_data` into the uniform buffers. The render state owning this uniform must not be in
+  /// flight.
+  pub fn update(&self, allocator: &Allocator, data: &CameraUniformData, frame_data: &FrameUniformData) -> Result<()> {
+    unsafe {
+      let mapped = self.buffer.get_mapped_data(allocator).unwrap();
+      mapped.copy_from(data);
+      mapped.flush(..)?;
+      let mapped = self.frame_buffer.get_mapped_data(allocator).unwrap();
+      mapped.copy_from(frame_data);
+      mapped.flush(..)?;
+    }
+    Ok(())
+  }
+
+  pub fn destroy(&self, device: &Device, allocator: &Allocator) {
+    unsafe {
+      device.destroy_descriptor_pool(self.descriptor_pool);
+      self.frame_buffer.destroy(allocator);
+      self.buffer.destroy(allocator);
+    }
+  }
+}
@@ -0,0 +1,154 @@
+use std::ops::RangeInclusive;
+use std::time::Duration;
+
+// Dynamic resolution
+
+/// Feedback loop that adjusts the render scale, the fraction of the window size in each dimension that the scene is
+/// rendered at, to keep the measured GPU frame time below a target frame time.
+///
+/// The GPU cost of the scene is assumed to be proportional to the number of rendered pixels, which is the square of the
+/// render scale. Slow frames decrease the scale immediately, proportional to how much the target was exceeded. Frames
+/// with enough headroom increase the scale gradually, so that the scale does not oscillate around the target.
+#[derive(Clone, Debug)]
+pub struct DynamicResolution {
+  range: RangeInclusive<f32>,
+  target_frame_time: Duration,
+  scale: f32,
+}
+
+/// Fraction of the target frame time that GPU frame times must stay below before increasing the render scale.
+const HEADROOM: f32 = 0.85;
+/// Maximum increase of the render scale per frame.
+const MAX_SCALE_INCREASE: f32 = 0.02;
+
+impl DynamicResolution {
+  /// Creates a feedback loop that keeps the render scale in `range`, aiming for GPU frame times of `target_frame_time`.
+  /// The range is clamped to (0, 1]. Starts at the maximum scale.
+  pub fn new(range: RangeInclusive<f32>, target_frame_time: Duration) -> Self {
+    let range = clamp_range(range);
+    let scale = *range.end();
+    Self { range, target_frame_time, scale }
+  }
+
+  /// Creates a feedback loop that always renders at full resolution.
+  pub fn disabled(target_frame_time: Duration) -> Self { Self::new(1.0..=1.0, target_frame_time) }
+
+  #[inline]
+  pub fn range(&self) -> RangeInclusive<f32> { self.range.clone() }
+
+  /// Sets the range that the render scale is kept in, clamped to (0, 1], and clamps the current scale to it.
+  pub fn set_range(&mut self, range: RangeInclusive<f32>) {
+    self.range = clamp_range(range);
+    self.scale = self.scale.max(*self.range.start()).min(*self.range.end());
+  }
+
+  #[inline]
+  pub fn is_enabled(&self) -> bool { self.range.start() < self.range.end() }
+
+  #[inline]
+  pub fn target_frame_time(&self) -> Duration { self.target_frame_time }
+
+  #[inline]
+  pub fn set_target_frame_time(&mut self, target_frame_time: Duration) { self.target_frame_time = target_frame_time; }
+
+  /// Gets the current render scale.
+  #[inline]
+  pub fn scale(&self) -> f32 { self.scale }
+
+  /// Updates the render scale from the GPU frame time `gpu_frame_time` that was measured at the current scale, and
+  /// returns the new scale.
+  pub fn update(&mut self, gpu_frame_time: Duration) -> f32 {
+    let frame_time = gpu_frame_time.as_secs_f32();
+    let target = self.target_frame_time.as_secs_f32();
+    if frame_time <= 0.0 || target <= 0.0 { return self.scale; }
+    // Scale that would have hit the target exactly, given that cost is proportional to the square of the scale.
+    let ideal_scale = self.scale * (target / frame_time).sqrt();
+    let scale = if frame_time > target {
+      ideal_scale
+    } else if frame_time < target * HEADROOM {
+      // Increase towards the scale that would hit the headroom fraction of the target, which is above the current scale.
+      (self.scale + MAX_SCALE_INCREASE).min(ideal_scale * HEADROOM.sqrt())
+    } else {
+      self.scale
+    };
+    self.scale = scale.max(*self.range.start()).min(*self.range.end());
+    self.scale
+  }
+}
+
+fn clamp_range(range: RangeInclusive<f32>) -> RangeInclusive<f32> {
+  let (start, end) = range.into_inner();
+  let end = end.max(std::f32::EPSILON).min(1.0);
+  let start = start.max(std::f32::EPSILON).min(end);
+  start..=end
+}
+
+#[cfg(test)]
+mod tests {
+  use std::time::Duration;
+
+  use super::DynamicResolution;
+
+  const TARGET: Duration = Duration::from_millis(16);
+
+  fn assert_scale_eq(actual: f32, expected: f32) {
+    assert!((actual - expected).abs() < 1e-4, "Expected scale {}, got {}", expected, actual);
+  }
+
+  #[test]
+  fn new_clamps_range_and_starts_at_maximum() {
+    let dynamic_resolution = DynamicResolution::new(-1.0..=2.0, TARGET);
+    assert_eq!(dynamic_resolution.range(), std::f32::EPSILON..=1.0);
+    assert_scale_eq(dynamic_resolution.scale(), 1.0);
+    assert!(dynamic_resolution.is_enabled());
+    assert!(!DynamicResolution::disabled(TARGET).is_enabled());
+  }
+
+  #[test]
+  fn update_decreases_scale_to_hit_target() {
+    let mut dynamic_resolution = DynamicResolution::new(0.5..=1.0, TARGET);
+    // Twice the target means half the pixels, so each dimension is scaled by sqrt(1/2).
+    assert_scale_eq(dynamic_resolution.update(TARGET * 2), 0.5f32.sqrt());
+  }
+
+  #[test]
+  fn update_clamps_scale_to_range() {
+    let mut dynamic_resolution = DynamicResolution::new(0.5..=1.0, TARGET);
+    assert_scale_eq(dynamic_resolution.update(TARGET * 100), 0.5);
+    for _ in 0..100 {
+      dynamic_resolution.update(Duration::from_millis(1));
+    }
+    assert_scale_eq(dynamic_resolution.scale(), 1.0);
+  }
+
+  #[test]
+  fn update_increases_scale_gradually_with_headroom() {
+    let mut dynamic_resolution = DynamicResolution::new(0.5..=1.0, TARGET);
+    dynamic_resolution.update(TARGET * 100);
+    assert_scale_eq(dynamic_resolution.update(TARGET / 2), 0.52);
+  }
+
+  #[test]
+  fn update_keeps_scale_near_target() {
+    let mut dynamic_resolution = DynamicResolution::new(0.5..=1.0, TARGET);
+    dynamic_resolution.update(TARGET * 2);
+    let scale = dynamic_resolution.scale();
+    assert_scale_eq(dynamic_resolution.update(Duration::from_millis(15)), scale);
+    assert_scale_eq(dynamic_resolution.update(TARGET), scale);
+  }
+
+  #[test]
+  fn update_ignores_zero_frame_time() {
+    let mut dynamic_resolution = DynamicResolution::new(0.5..=1.0, TARGET);
+    dynamic_resolution.update(TARGET * 2);
+    let scale = dynamic_resolution.scale();
+    assert_scale_eq(dynamic_resolution.update(Duration::from_secs(0)), scale);
+  }
+
+  #[test]
+  fn set_range_clamps_scale() {
+    let mut dynamic_resolution = DynamicResolution::new(0.5..=1.0, TARGET);
+    dynamic_resolution.set_range(0.25..=0.75);
+    assert_scale_eq(dynamic_resolution.scale(), 0.75);
+  }
+}
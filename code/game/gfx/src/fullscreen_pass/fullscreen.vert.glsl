@@ -0,0 +1,13 @@
+#version 450
+#extension GL_ARB_separate_shader_objects : enable
+
+// Outputs
+/// Builtin vertex position
+out gl_PerVertex { vec4 gl_Position; };
+layout(location = 0) out vec2 frgUV;
+
+void main() {
+  // Full-screen triangle from the vertex index alone, covering the screen with UVs in [0, 1]: (0, 0), (2, 0), (0, 2).
+  frgUV = vec2((gl_VertexIndex << 1) & 2, gl_VertexIndex & 2);
+  gl_Position = vec4(frgUV * 2.0 - 1.0, 0.0, 1.0);
+}
@@ -0,0 +1,15 @@
+#version 450
+#extension GL_ARB_separate_shader_objects : enable
+
+// Inputs
+layout(location = 0) in vec2 frgUV;
+/// Scene color image
+layout(set = 0, binding = 0) uniform sampler2D scene;
+
+// Outputs
+/// Color
+layout(location = 0) out vec4 outCol;
+
+void main() {
+  outCol = texture(scene, frgUV);
+}
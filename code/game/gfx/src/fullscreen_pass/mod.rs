@@ -0,0 +1,123 @@
+use anyhow::Result;
+use ash::version::DeviceV1_0;
+use ash::vk;
+
+use vkw::prelude::*;
+use vkw::shader::ShaderModuleEx;
+
+// Full-screen pass
+
+/// Pipeline that draws a single full-screen triangle without vertex buffers, running a fragment shader over the entire
+/// screen. The backbone of post-processing passes that sample a scene image.
+///
+/// The fragment shader receives UVs in [0, 1] at location 0, and has its descriptor set bound at set 0.
+pub struct FullscreenPass {
+  vert_shader: ShaderModule,
+  pipeline_layout: PipelineLayout,
+  pipeline: Pipeline,
+}
+
+impl FullscreenPass {
+  /// Creates a full-screen pass running `frag_shader`, which stays owned by the caller. Viewport and scissor are dynamic
+  /// states, which must be set before [draw](Self::draw).
+  pub fn new(
+    device: &Device,
+    render_pass: RenderPass,
+    pipeline_cache: PipelineCache,
+    frag_shader: ShaderModule,
+    descriptor_set_layouts: &[DescriptorSetLayout],
+    push_constant_ranges: &[PushConstantRange],
+  ) -> Result<Self> {
+    unsafe {
+      let vert_shader = device.create_shader_module(include_bytes!("../../../../../target/shader/fullscreen_pass/fullscreen.vert.spv"))?;
+      let pipeline_layout = device.acquire_pipeline_layout(descriptor_set_layouts, push_constant_ranges)?;
+      let pipeline = {
+        let stages = &[
+          vert_shader.create_vertex_shader_stage(None).build(),
+          frag_shader.create_fragment_shader_stage(None).build(),
+        ];
+        let vertex_input_state = vk::PipelineVertexInputStateCreateInfo::builder();
+        let input_assembly_state = vk::PipelineInputAssemblyStateCreateInfo::builder()
+          .topology(PrimitiveTopology::TRIANGLE_LIST)
+          .primitive_restart_enable(false)
+          ;
+        let viewports = &[vk::Viewport::builder().max_depth(1.0).build()];
+        let scissors = &[Rect2D::default()];
+        let viewport_state = vk::PipelineViewportStateCreateInfo::builder()
+          .viewports(viewports)
+          .scissors(scissors)
+          ;
+        let rasterization_state = vk::PipelineRasterizationStateCreateInfo::builder()
+          .depth_clamp_enable(false)
+          .rasterizer_discard_enable(false)
+          .polygon_mode(PolygonMode::FILL)
+          .cull_mode(CullModeFlags::NONE)
+          .front_face(FrontFace::COUNTER_CLOCKWISE)
+          .line_width(1.0)
+          ;
+        let multisample_state = vk::PipelineMultisampleStateCreateInfo::builder()
+          .rasterization_samples(SampleCountFlags::TYPE_1)
+          .min_sample_shading(1.0)
+          ;
+        let color_blend_state_attachments = &[vk::PipelineColorBlendAttachmentState::builder()
+          .blend_enable(false)
+          .color_write_mask(ColorComponentFlags::all())
+          .build()
+        ];
+        let color_blend_state = vk::PipelineColorBlendStateCreateInfo::builder()
+          .logic_op_enable(false)
+          .logic_op(LogicOp::CLEAR)
+          .attachments(color_blend_state_attachments)
+          .blend_constants([0.0, 0.0, 0.0, 0.0])
+          ;
+        let dynamic_states = &[DynamicState::VIEWPORT, DynamicState::SCISSOR];
+        let dynamic_state = vk::PipelineDynamicStateCreateInfo::builder().dynamic_states(dynamic_states);
+        let create_info = vk::GraphicsPipelineCreateInfo::builder()
+          .stages(stages)
+          .vertex_input_state(&vertex_input_state)
+          .input_assembly_state(&input_assembly_state)
+          .viewport_state(&viewport_state)
+          .rasterization_state(&rasterization_state)
+          .multisample_state(&multisample_state)
+          .color_blend_state(&color_blend_state)
+          .dynamic_state(&dynamic_state)
+          .layout(pipeline_layout)
+          .render_pass(render_pass)
+          ;
+        // CORRECTNESS: slices are taken by pointer but are alive until `create_graphics_pipeline` is called.
+        device.create_graphics_pipeline(pipeline_cache, &create_info)?
+      };
+      Ok(Self { vert_shader, pipeline_layout, pipeline })
+    }
+  }
+
+  /// Creates a descriptor set layout with a single combined image sampler at binding 0, for sampling the scene image.
+  pub fn create_scene_descriptor_set_layout(device: &Device) -> Result<DescriptorSetLayout> {
+    Ok(unsafe { device.create_descriptor_set_layout(&[descriptor_set::sampler_layout_binding(0, 1)], &[])? })
+  }
+
+  /// Creates a fragment shader that outputs the scene image unchanged, sampled from binding 0 of set 0.
+  pub fn create_identity_frag_shader(device: &Device) -> Result<ShaderModule> {
+    Ok(unsafe { device.create_shader_module(include_bytes!("../../../../../target/shader/fullscreen_pass/identity.frag.spv"))? })
+  }
+
+  #[inline]
+  pub fn pipeline_layout(&self) -> PipelineLayout { self.pipeline_layout }
+
+  /// Records drawing the full-screen triangle into `command_buffer`, which must be inside a render pass, with
+  /// `descriptor_set` bound at set 0. Push constants must be pushed with [pipeline_layout](Self::pipeline_layout)
+  /// before calling this.
+  pub unsafe fn draw(&self, device: &Device, command_buffer: CommandBuffer, descriptor_set: DescriptorSet) {
+    device.cmd_bind_graphics_pipeline(command_buffer, self.pipeline);
+    device.cmd_bind_descriptor_sets(command_buffer, PipelineBindPoint::GRAPHICS, self.pipeline_layout, 0, &[descriptor_set], &[]);
+    device.cmd_draw(command_buffer, 3, 1, 0, 0);
+  }
+
+  pub fn destroy(&self, device: &Device) {
+    unsafe {
+      device.destroy_pipeline(self.pipeline);
+      device.release_pipeline_layout(self.pipeline_layout);
+      device.destroy_shader_module(self.vert_shader);
+    }
+  }
+}
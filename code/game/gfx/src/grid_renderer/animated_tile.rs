@@ -0,0 +1,60 @@
+use std::time::{Duration, Instant};
+
+use legion::borrow::RefMut;
+use legion::prelude::*;
+use metrics::timing;
+
+use crate::grid_renderer::GridTileRender;
+use crate::texture_def::TextureIdx;
+
+// Animated grid tile component
+
+#[derive(Clone, PartialEq, Debug)]
+/// Component that cycles the texture of a [GridTileRender] entity through `frames`, at `fps` frames per second.
+pub struct AnimatedGridTile {
+  pub frames: Vec<TextureIdx>,
+  pub fps: f32,
+  elapsed: f32,
+}
+
+impl AnimatedGridTile {
+  #[inline]
+  pub fn new(frames: Vec<TextureIdx>, fps: f32) -> Self { Self { frames, fps, elapsed: 0.0 } }
+
+  /// Gets the texture of the current frame, or `None` if there are no frames.
+  #[inline]
+  pub fn current_frame(&self) -> Option<TextureIdx> {
+    if self.frames.is_empty() { return None; }
+    let frame = (self.elapsed * self.fps.max(0.0)) as usize % self.frames.len();
+    Some(self.frames[frame])
+  }
+
+  fn advance(&mut self, frame_time: f32) {
+    self.elapsed += frame_time;
+    // Wrap around after a full cycle to keep precision when running for a long time.
+    if self.fps > 0.0 {
+      let cycle = self.frames.len() as f32 / self.fps;
+      if cycle > 0.0 { self.elapsed %= cycle; }
+    }
+  }
+}
+
+// Animated grid tile system
+
+/// Advances all [AnimatedGridTile]s by `frame_time`, and sets the texture of their [GridTileRender] to the current
+/// frame.
+pub fn update_animated_grid_tiles(world: &mut World, frame_time: Duration) {
+  let start = Instant::now();
+  let frame_time = frame_time.as_secs_f32();
+  let query = <(Write<AnimatedGridTile>, Write<GridTileRender>)>::query();
+  for i in query.iter_mut(world) {
+    let (mut animated, mut render): (RefMut<AnimatedGridTile>, RefMut<GridTileRender>) = i;
+    animated.advance(frame_time);
+    if let Some(texture_idx) = animated.current_frame() {
+      if render.0 != texture_idx {
+        render.0 = texture_idx;
+      }
+    }
+  }
+  timing!("gfx.grid_renderer.update_animated_grid_tiles", start.elapsed());
+}
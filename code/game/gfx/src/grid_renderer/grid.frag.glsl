@@ -4,13 +4,18 @@
 // Inputs
 /// Builtin fragment coordinates
 layout(location = 0) in vec3 tex;
-/// Dynamic inform data
-layout(set = 0, binding = 0) uniform sampler2DArray samplerArray;
+layout(location = 1) in vec4 tint;
+layout(location = 2) flat in uint samplerIndex;
+/// Dynamic inform data: the texture array with a nearest (0) and linear (1) sampler
+layout(set = 0, binding = 0) uniform sampler2DArray samplerArrays[2];
 
 // Outputs
 /// Color
 layout(location = 0) out vec4 outCol;
 
 void main() {
-  outCol = texture(samplerArray, tex);
+  // Sample with both samplers outside of control flow, as implicit derivatives are undefined in non-uniform control flow.
+  vec4 nearest = texture(samplerArrays[0], tex);
+  vec4 linear = texture(samplerArrays[1], tex);
+  outCol = (samplerIndex == 0u ? nearest : linear) * tint;
 }
@@ -1,19 +1,39 @@
 #version 450
 #extension GL_ARB_separate_shader_objects : enable
 
+// Constants
+/// Sampler index marking vertices of empty tiles; must match grid_uv.comp.glsl
+const uint EMPTY_TILE_SAMPLER_INDEX = 0xFFFFFFFFu;
+
 // Inputs
 /// Dynamic vertex data
 layout(location = 0) in vec2 pos;
 layout(location = 1) in vec3 tex;
+layout(location = 2) in vec4 tint;
+layout(location = 3) in uint samplerIndex;
+/// Per-frame camera uniform data
+#include <camera.glsl>
 /// Dynamic uniform data
-layout(push_constant) uniform VertexUniformData { mat4 mvp; } ud;
+layout(push_constant) uniform VertexUniformData { mat4 model; } ud;
 
 // Outputs
 /// Builtin vertex position
 out gl_PerVertex { vec4 gl_Position; };
 layout(location = 0) out vec3 frgTex;
+layout(location = 1) out vec4 frgTint;
+layout(location = 2) flat out uint frgSamplerIndex;
 
 void main() {
-  gl_Position = ud.mvp * vec4(pos, 0.0, 1.0);
+  if(samplerIndex == EMPTY_TILE_SAMPLER_INDEX) {
+    // Place all vertices of empty tiles beyond the far plane, so that their quads are clipped before rasterization.
+    gl_Position = vec4(0.0, 0.0, 2.0, 1.0);
+    frgTex = vec3(0.0);
+    frgTint = vec4(0.0);
+    frgSamplerIndex = 0u;
+    return;
+  }
+  gl_Position = camera.viewProjection * ud.model * vec4(pos, 0.0, 1.0);
   frgTex = tex;
+  frgTint = tint;
+  frgSamplerIndex = samplerIndex;
 }
@@ -0,0 +1,52 @@
+#version 450
+#extension GL_ARB_separate_shader_objects : enable
+
+// Constants
+/// Length of a grid chunk in tiles, specialized by the grid renderer
+layout(constant_id = 0) const uint GRID_LENGTH = 16u;
+/// Offset of the minimum bound of a tile from its grid position, matching the quad vertices of the grid renderer:
+/// -0.5 for tiles centered on their position, 0.0 for tiles with their bottom-left corner at their position.
+/// Specialized by the grid renderer
+layout(constant_id = 1) const float TILE_MIN_OFFSET = -0.5;
+/// Minimum bound of a grid chunk in grid-chunk-space
+const float CHUNK_MIN = TILE_MIN_OFFSET;
+
+// Inputs
+layout(local_size_x = 64) in;
+/// Dynamic uniform data
+layout(push_constant) uniform CullUniformData { uint chunk_count; uint index_count; } ud;
+/// Per-chunk model-view-projection matrix
+layout(std430, set = 0, binding = 0) readonly buffer Chunks { mat4 mvps[]; };
+
+// Outputs
+/// Per-chunk indirect draw command, with an instance count of 0 when culled
+struct DrawIndexedIndirectCommand { uint index_count; uint instance_count; uint first_index; int vertex_offset; uint first_instance; };
+layout(std430, set = 0, binding = 1) writeonly buffer Commands { DrawIndexedIndirectCommand commands[]; };
+
+bool is_visible(mat4 mvp) {
+  // Maximum bound of a grid chunk, computed at runtime as specialization constants cannot be converted to floats.
+  float chunk_max = float(GRID_LENGTH) + CHUNK_MIN;
+  vec4 corners[4] = vec4[4](
+    mvp * vec4(CHUNK_MIN, CHUNK_MIN, 0.0, 1.0),
+    mvp * vec4(chunk_max, CHUNK_MIN, 0.0, 1.0),
+    mvp * vec4(CHUNK_MIN, chunk_max, 0.0, 1.0),
+    mvp * vec4(chunk_max, chunk_max, 0.0, 1.0)
+  );
+  // Culled when all corners are outside of the same clip plane.
+  bool left = true, right = true, bottom = true, top = true;
+  for(int i = 0; i < 4; ++i) {
+    vec4 c = corners[i];
+    left = left && c.x < -c.w;
+    right = right && c.x > c.w;
+    bottom = bottom && c.y < -c.w;
+    top = top && c.y > c.w;
+  }
+  return !(left || right || bottom || top);
+}
+
+void main() {
+  uint chunk_index = gl_GlobalInvocationID.x;
+  if(chunk_index >= ud.chunk_count) { return; }
+  uint visible = is_visible(mvps[chunk_index]) ? 1u : 0u;
+  commands[chunk_index] = DrawIndexedIndirectCommand(ud.index_count, visible, 0u, 0, 0u);
+}
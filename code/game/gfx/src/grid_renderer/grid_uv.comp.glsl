@@ -0,0 +1,41 @@
+#version 450
+#extension GL_ARB_separate_shader_objects : enable
+
+// Constants
+/// Length of a grid chunk in tiles, specialized by the grid renderer
+layout(constant_id = 0) const uint GRID_LENGTH = 16u;
+const uint GRID_TILE_COUNT = GRID_LENGTH * GRID_LENGTH;
+/// Sampler index marking vertices of empty tiles, which the vertex shader moves outside of the view volume
+const uint EMPTY_TILE_SAMPLER_INDEX = 0xFFFFFFFFu;
+
+// Inputs
+layout(local_size_x = 64) in;
+/// Per-tile texture index (0 for no tile), orientation, tint packed as RGBA8, and sampler index
+struct Tile { uint texture; uint orientation; uint tint; uint sampler_index; };
+layout(std430, set = 0, binding = 0) readonly buffer Tiles { Tile tiles[GRID_TILE_COUNT]; };
+
+// Outputs
+/// Per-vertex texture UV, array layer, tint packed as RGBA8, and sampler index, 4 vertices per tile. Vertices of empty
+/// tiles have sampler index EMPTY_TILE_SAMPLER_INDEX
+struct TextureUV { float u; float v; float i; uint tint; uint sampler_index; };
+layout(std430, set = 0, binding = 1) writeonly buffer TextureUVs { TextureUV uvs[GRID_TILE_COUNT * 4u]; };
+
+void main() {
+  uint tile_index = gl_GlobalInvocationID.x;
+  if(tile_index >= GRID_TILE_COUNT) { return; }
+  Tile tile = tiles[tile_index];
+  uint vertex_index = tile_index * 4u;
+  if(tile.texture == 0u) {
+    uvs[vertex_index + 0u] = TextureUV(0.0, 0.0, 0.0, 0u, EMPTY_TILE_SAMPLER_INDEX);
+    uvs[vertex_index + 1u] = TextureUV(0.0, 0.0, 0.0, 0u, EMPTY_TILE_SAMPLER_INDEX);
+    uvs[vertex_index + 2u] = TextureUV(0.0, 0.0, 0.0, 0u, EMPTY_TILE_SAMPLER_INDEX);
+    uvs[vertex_index + 3u] = TextureUV(0.0, 0.0, 0.0, 0u, EMPTY_TILE_SAMPLER_INDEX);
+    return;
+  }
+  // Texture indices start at 1, so layer 0 holds the first texture. Orientation is not applied yet.
+  float layer = float(tile.texture - 1u);
+  uvs[vertex_index + 0u] = TextureUV(0.0, 1.0, layer, tile.tint, tile.sampler_index);
+  uvs[vertex_index + 1u] = TextureUV(1.0, 1.0, layer, tile.tint, tile.sampler_index);
+  uvs[vertex_index + 2u] = TextureUV(0.0, 0.0, layer, tile.tint, tile.sampler_index);
+  uvs[vertex_index + 3u] = TextureUV(1.0, 0.0, layer, tile.tint, tile.sampler_index);
+}
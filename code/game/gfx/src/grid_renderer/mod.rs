@@ -1,7 +1,7 @@
 use std::collections::{HashMap, HashSet};
 use std::collections::hash_map::Entry;
 use std::iter::FromIterator;
-use std::mem::size_of;
+use std::mem::{size_of, size_of_val};
 use std::time::Instant;
 
 use anyhow::Result;
@@ -10,17 +10,24 @@ use ash::vk;
 use itertools::izip;
 use legion::prelude::{Query, Read, Tagged};
 use legion::world::World;
+use log::warn;
 use metrics::timing;
-use ultraviolet::{Mat4, Vec2};
 
+use math::prelude::{Isometry2, Mat4, Rgba, rotor2_slerp, Vec2};
 use sim::prelude::*;
-use util::idx_assigner::Item;
+use vkw::image::sampler::SamplerLod;
 use vkw::prelude::*;
 use vkw::shader::ShaderModuleEx;
 use legion::filter::EntityFilterTuple;
 use legion::filter::Passthrough;
 
-use crate::texture_def::{TextureDef, TextureIdx};
+use util::idx_assigner::Item;
+
+use crate::camera_uniform::{CAMERA_UNIFORM_SET, CameraUniformLayout};
+use crate::render_queue::{DrawCommand, RenderItem, RenderQueue, SortKey};
+use crate::texture_def::{TextureDef, TextureFilter, TextureIdx};
+
+pub mod animated_tile;
 
 // Grid length/count constants
 
@@ -28,6 +35,20 @@ const GRID_LENGTH: usize = 16;
 const GRID_LENGTH_I32: i32 = GRID_LENGTH as i32;
 const GRID_LENGTH_F32: f32 = GRID_LENGTH as f32;
 const GRID_TILE_COUNT: usize = GRID_LENGTH * GRID_LENGTH;
+/// Specialization constant ID of `GRID_LENGTH` in `grid_uv.comp.glsl` and `grid_cull.comp.glsl`.
+const GRID_LENGTH_SPECIALIZATION_ID: u32 = 0;
+/// Specialization constant ID of `TILE_MIN_OFFSET` in `grid_cull.comp.glsl`.
+const TILE_MIN_OFFSET_SPECIALIZATION_ID: u32 = 1;
+/// Work group count of the UV compute pass; must match `local_size_x` in `grid_uv.comp.glsl`.
+const GRID_TILE_DISPATCH_GROUP_COUNT: u32 = (GRID_TILE_COUNT / 64) as u32;
+/// Work group size of the cull compute pass; must match `local_size_x` in `grid_cull.comp.glsl`.
+const CULL_LOCAL_SIZE: u32 = 64;
+
+// Grid descriptor set constants
+
+/// Descriptor set index of the first extra descriptor set layout passed to [GridRendererSys::new], after the texture
+/// set and the camera uniform set.
+pub const GRID_EXTRA_DESCRIPTOR_SET_START: u32 = CAMERA_UNIFORM_SET + 1;
 
 // Grid renderer component
 
@@ -37,6 +58,12 @@ const GRID_TILE_COUNT: usize = GRID_LENGTH * GRID_LENGTH;
 /// position by [GridPosition], and grid-space orientation by [GridOrientation].
 pub struct GridTileRender(pub TextureIdx);
 
+#[repr(C)]
+#[derive(Default, Copy, Clone, PartialEq, Debug)]
+/// Component indicating the color that the texture of a [GridTileRender] entity is multiplied with. Tiles without this
+/// component are rendered with a white tint, which leaves their texture unchanged.
+pub struct GridTileTint(pub Rgba);
+
 // Grid chunks
 
 #[repr(C)]
@@ -71,111 +98,118 @@ impl GridChunkIndex {
 
 pub struct GridRendererSys {
   pipeline_layout: PipelineLayout,
+  extra_descriptor_set_count: u32,
 
   vert_shader: ShaderModule,
   frag_shader: ShaderModule,
 
   pipeline: Pipeline,
+  wireframe_pipeline: Option<Pipeline>,
+  wireframe: bool,
+  wireframe_line_width: f32,
+  wide_lines: bool,
+
+  uv_descriptor_set_layout: DescriptorSetLayout,
+  uv_pipeline_layout: PipelineLayout,
+  uv_shader: ShaderModule,
+  uv_pipeline: Pipeline,
+
+  cull_descriptor_set_layout: DescriptorSetLayout,
+  cull_pipeline_layout: PipelineLayout,
+  cull_shader: ShaderModule,
+  cull_pipeline: Pipeline,
 
   quads_vertex_buffer: BufferAllocation,
   quads_index_buffer: BufferAllocation,
+  pivot: GridPivot,
+
+  /// Transforms of grids at the previous and current tick, shared by all render states.
+  grid_transforms: HashMap<Entity, InterpolatedGridTransform>,
+
+  /// Whether to render tiles of grids without a [WorldTransform] at the identity transform instead of skipping them.
+  /// A warning is logged once per missing grid either way.
+  pub render_missing_grid_transforms_at_identity: bool,
 }
 
 impl GridRendererSys {
+  /// Sampler level of detail settings suited for grids: a slight negative bias keeps zoomed-out (minified) tiles sharp
+  /// without shimmering, and all mipmap levels are used.
+  pub fn sampler_lod() -> SamplerLod {
+    SamplerLod { mip_lod_bias: -0.25, max_lod: vk::LOD_CLAMP_NONE }
+  }
+
+  /// Creates the grid renderer. The grid pipeline layout has the texture set at set 0, the camera uniform set at
+  /// [CAMERA_UNIFORM_SET], and `extra_descriptor_set_layouts` from [GRID_EXTRA_DESCRIPTOR_SET_START] onwards, so that
+  /// callers can make additional sets (e.g., lights) available to grid shaders. Tiles are drawn as 1x1 quads placed
+  /// according to `pivot`, which must match the [grid pivot of the simulation](Sim::grid_pivot).
   pub fn new(
     device: &Device,
     allocator: &Allocator,
     texture_def: &TextureDef,
+    camera_uniform_layout: &CameraUniformLayout,
+    extra_descriptor_set_layouts: &[DescriptorSetLayout],
+    pivot: GridPivot,
     _render_state_count: u32,
     render_pass: RenderPass,
     pipeline_cache: PipelineCache,
     transient_command_pool: CommandPool,
   ) -> Result<Self> {
     unsafe {
-      let pipeline_layout = device.create_pipeline_layout(&[texture_def.descriptor_set_layout], &[MVPUniformData::push_constant_range()])?;
+      let descriptor_set_layouts: Vec<_> = [texture_def.descriptor_set_layout, camera_uniform_layout.descriptor_set_layout].iter()
+        .chain(extra_descriptor_set_layouts)
+        .copied()
+        .collect();
+      let pipeline_layout = device.acquire_pipeline_layout(&descriptor_set_layouts, &[ModelUniformData::push_constant_range()])?;
 
       let vert_shader = device.create_shader_module(include_bytes!("../../../../../target/shader/grid_renderer/grid.vert.spv"))?;
       let frag_shader = device.create_shader_module(include_bytes!("../../../../../target/shader/grid_renderer/grid.frag.spv"))?;
 
-      let vertex_bindings = {
-        let mut vec = QuadsVertexData::bindings();
-        vec.extend(TextureUVVertexData::bindings());
-        vec
-      };
-      let vertex_attributes = {
-        let mut vec = QuadsVertexData::attributes();
-        vec.extend(TextureUVVertexData::attributes());
-        vec
+      let pipeline = Self::create_pipeline(device, pipeline_cache, render_pass, pipeline_layout, vert_shader, frag_shader, PolygonMode::FILL, None)?;
+      // Pre-create the wireframe variant, as toggling it should not stall on pipeline creation. Derive it from the
+      // regular pipeline, as they only differ in polygon mode.
+      let wireframe_pipeline = if device.features.is_fill_mode_non_solid_enabled() {
+        Some(Self::create_pipeline(device, pipeline_cache, render_pass, pipeline_layout, vert_shader, frag_shader, PolygonMode::LINE, Some(pipeline))?)
+      } else {
+        None
       };
 
-      let pipeline = {
-        let stages = &[
-          vert_shader.create_vertex_shader_stage(None).build(),
-          frag_shader.create_fragment_shader_stage(None).build(),
-        ];
-        let vertex_input_state = vk::PipelineVertexInputStateCreateInfo::builder()
-          .vertex_binding_descriptions(&vertex_bindings)
-          .vertex_attribute_descriptions(&vertex_attributes)
+      // Create compute pipeline that writes texture UVs of grid chunks from their tile data.
+      let uv_descriptor_set_layout = device.create_descriptor_set_layout(&[
+        descriptor_set::storage_buffer_layout_binding(0, 1, ShaderStageFlags::COMPUTE),
+        descriptor_set::storage_buffer_layout_binding(1, 1, ShaderStageFlags::COMPUTE),
+      ], &[])?;
+      let uv_pipeline_layout = device.acquire_pipeline_layout(&[uv_descriptor_set_layout], &[])?;
+      let uv_shader = device.create_shader_module(include_bytes!("../../../../../target/shader/grid_renderer/grid_uv.comp.spv"))?;
+      // Specialize the chunk length and tile bounds of compute shaders, so that they match GRID_LENGTH and the quads.
+      let grid_specialization = SpecializationBuilder::new()
+        .add(GRID_LENGTH_SPECIALIZATION_ID, GRID_LENGTH as u32)
+        .add(TILE_MIN_OFFSET_SPECIALIZATION_ID, pivot.tile_min_offset());
+      let grid_specialization_info = grid_specialization.info();
+      let uv_pipeline = {
+        let create_info = vk::ComputePipelineCreateInfo::builder()
+          .stage(uv_shader.create_compute_shader_stage(Some(&grid_specialization_info)).build())
+          .layout(uv_pipeline_layout)
           ;
-        let input_assembly_state = vk::PipelineInputAssemblyStateCreateInfo::builder()
-          .topology(PrimitiveTopology::TRIANGLE_LIST)
-          .primitive_restart_enable(false)
-          ;
-        let viewports = &[vk::Viewport::builder().max_depth(1.0).build()];
-        let scissors = &[Rect2D::default()];
-        let viewport_state = vk::PipelineViewportStateCreateInfo::builder()
-          .viewports(viewports)
-          .scissors(scissors)
-          ;
-        let rasterization_state = vk::PipelineRasterizationStateCreateInfo::builder()
-          .depth_clamp_enable(false)
-          .rasterizer_discard_enable(false)
-          .polygon_mode(PolygonMode::FILL)
-          .cull_mode(CullModeFlags::NONE) // TODO: enable culling
-          .front_face(FrontFace::COUNTER_CLOCKWISE)
-          .line_width(1.0)
-          ;
-        let multisample_state = vk::PipelineMultisampleStateCreateInfo::builder()
-          .rasterization_samples(SampleCountFlags::TYPE_1)
-          .min_sample_shading(1.0)
-          ;
-        let color_blend_state_attachments = &[vk::PipelineColorBlendAttachmentState::builder()
-          .blend_enable(true)
-          .src_color_blend_factor(BlendFactor::SRC_ALPHA)
-          .dst_color_blend_factor(BlendFactor::ONE_MINUS_SRC_ALPHA)
-          .color_blend_op(BlendOp::ADD)
-          .src_alpha_blend_factor(BlendFactor::SRC_ALPHA)
-          .dst_alpha_blend_factor(BlendFactor::ONE_MINUS_SRC_ALPHA)
-          .alpha_blend_op(BlendOp::ADD)
-          .color_write_mask(ColorComponentFlags::all())
-          .build()
-        ];
-        let color_blend_state = vk::PipelineColorBlendStateCreateInfo::builder()
-          .logic_op_enable(false)
-          .logic_op(LogicOp::CLEAR)
-          .attachments(color_blend_state_attachments)
-          .blend_constants([0.0, 0.0, 0.0, 0.0])
-          ;
-        let dynamic_states = &[DynamicState::VIEWPORT, DynamicState::SCISSOR];
-        let dynamic_state = vk::PipelineDynamicStateCreateInfo::builder().dynamic_states(dynamic_states);
-        let create_info = vk::GraphicsPipelineCreateInfo::builder()
-          .stages(stages)
-          .vertex_input_state(&vertex_input_state)
-          .input_assembly_state(&input_assembly_state)
-          .viewport_state(&viewport_state)
-          .rasterization_state(&rasterization_state)
-          .multisample_state(&multisample_state)
-          .color_blend_state(&color_blend_state)
-          .dynamic_state(&dynamic_state)
-          .layout(pipeline_layout)
-          .render_pass(render_pass)
+        device.create_compute_pipeline(pipeline_cache, &create_info)?
+      };
+
+      // Create compute pipeline that culls grid chunks against the view frustum, writing indirect draw commands.
+      let cull_descriptor_set_layout = device.create_descriptor_set_layout(&[
+        descriptor_set::storage_buffer_layout_binding(0, 1, ShaderStageFlags::COMPUTE),
+        descriptor_set::storage_buffer_layout_binding(1, 1, ShaderStageFlags::COMPUTE),
+      ], &[])?;
+      let cull_pipeline_layout = device.acquire_pipeline_layout(&[cull_descriptor_set_layout], &[CullUniformData::push_constant_range()])?;
+      let cull_shader = device.create_shader_module(include_bytes!("../../../../../target/shader/grid_renderer/grid_cull.comp.spv"))?;
+      let cull_pipeline = {
+        let create_info = vk::ComputePipelineCreateInfo::builder()
+          .stage(cull_shader.create_compute_shader_stage(Some(&grid_specialization_info)).build())
+          .layout(cull_pipeline_layout)
           ;
-        // CORRECTNESS: slices are taken by pointer but are alive until `create_graphics_pipeline` is called.
-        device.create_graphics_pipeline(pipeline_cache, &create_info)?
+        device.create_compute_pipeline(pipeline_cache, &create_info)?
       };
 
       // Create GPU buffers for immutable quad vertex and index data.
-      let quads_vertices = QuadsVertexData::create_vertices();
+      let quads_vertices = QuadsVertexData::create_vertices(pivot);
       let quads_indices = QuadsIndexData::create_indices();
       let vertex_staging = allocator.create_staging_buffer_from_slice(&quads_vertices)?;
       let index_staging = allocator.create_staging_buffer_from_slice(&quads_indices)?;
@@ -199,15 +233,126 @@ impl GridRendererSys {
 
       Ok(Self {
         pipeline_layout,
+        extra_descriptor_set_count: extra_descriptor_set_layouts.len() as u32,
         vert_shader,
         frag_shader,
         pipeline,
+        wireframe_pipeline,
+        wireframe: false,
+        wireframe_line_width: 1.0,
+        wide_lines: device.features.is_wide_lines_enabled(),
+        uv_descriptor_set_layout,
+        uv_pipeline_layout,
+        uv_shader,
+        uv_pipeline,
+        cull_descriptor_set_layout,
+        cull_pipeline_layout,
+        cull_shader,
+        cull_pipeline,
         quads_vertex_buffer,
         quads_index_buffer,
+        pivot,
+        grid_transforms: HashMap::default(),
+        render_missing_grid_transforms_at_identity: false,
       })
     }
   }
 
+  unsafe fn create_pipeline(
+    device: &Device,
+    pipeline_cache: PipelineCache,
+    render_pass: RenderPass,
+    pipeline_layout: PipelineLayout,
+    vert_shader: ShaderModule,
+    frag_shader: ShaderModule,
+    polygon_mode: PolygonMode,
+    base_pipeline: Option<Pipeline>,
+  ) -> Result<Pipeline> {
+    let vertex_bindings = &[
+      QuadsVertexData::binding_description(0),
+      TextureUVVertexData::binding_description(1),
+    ];
+    let vertex_attributes = {
+      let mut vec = QuadsVertexData::attribute_descriptions(0, 0);
+      vec.extend(TextureUVVertexData::attribute_descriptions(1, vec.len() as u32));
+      vec
+    };
+
+    let stages = &[
+      vert_shader.create_vertex_shader_stage(None).build(),
+      frag_shader.create_fragment_shader_stage(None).build(),
+    ];
+    let vertex_input_state = vk::PipelineVertexInputStateCreateInfo::builder()
+      .vertex_binding_descriptions(vertex_bindings)
+      .vertex_attribute_descriptions(&vertex_attributes)
+      ;
+    let input_assembly_state = vk::PipelineInputAssemblyStateCreateInfo::builder()
+      .topology(PrimitiveTopology::TRIANGLE_LIST)
+      .primitive_restart_enable(false)
+      ;
+    let viewports = &[vk::Viewport::builder().max_depth(1.0).build()];
+    let scissors = &[Rect2D::default()];
+    let viewport_state = vk::PipelineViewportStateCreateInfo::builder()
+      .viewports(viewports)
+      .scissors(scissors)
+      ;
+    let rasterization_state = vk::PipelineRasterizationStateCreateInfo::builder()
+      .depth_clamp_enable(false)
+      .rasterizer_discard_enable(false)
+      .polygon_mode(polygon_mode)
+      .cull_mode(CullModeFlags::NONE) // TODO: enable culling
+      .front_face(FrontFace::COUNTER_CLOCKWISE)
+      .line_width(1.0)
+      ;
+    let multisample_state = vk::PipelineMultisampleStateCreateInfo::builder()
+      .rasterization_samples(SampleCountFlags::TYPE_1)
+      .min_sample_shading(1.0)
+      ;
+    let color_blend_state_attachments = &[vk::PipelineColorBlendAttachmentState::builder()
+      .blend_enable(true)
+      .src_color_blend_factor(BlendFactor::SRC_ALPHA)
+      .dst_color_blend_factor(BlendFactor::ONE_MINUS_SRC_ALPHA)
+      .color_blend_op(BlendOp::ADD)
+      .src_alpha_blend_factor(BlendFactor::SRC_ALPHA)
+      .dst_alpha_blend_factor(BlendFactor::ONE_MINUS_SRC_ALPHA)
+      .alpha_blend_op(BlendOp::ADD)
+      .color_write_mask(ColorComponentFlags::all())
+      .build()
+    ];
+    let color_blend_state = vk::PipelineColorBlendStateCreateInfo::builder()
+      .logic_op_enable(false)
+      .logic_op(LogicOp::CLEAR)
+      .attachments(color_blend_state_attachments)
+      .blend_constants([0.0, 0.0, 0.0, 0.0])
+      ;
+    // Line width is dynamic for wireframes, so that it can be changed without recreating the pipeline.
+    let dynamic_states: &[DynamicState] = if polygon_mode == PolygonMode::LINE {
+      &[DynamicState::VIEWPORT, DynamicState::SCISSOR, DynamicState::LINE_WIDTH]
+    } else {
+      &[DynamicState::VIEWPORT, DynamicState::SCISSOR]
+    };
+    let dynamic_state = vk::PipelineDynamicStateCreateInfo::builder().dynamic_states(dynamic_states);
+    let create_info = vk::GraphicsPipelineCreateInfo::builder()
+      .stages(stages)
+      .vertex_input_state(&vertex_input_state)
+      .input_assembly_state(&input_assembly_state)
+      .viewport_state(&viewport_state)
+      .rasterization_state(&rasterization_state)
+      .multisample_state(&multisample_state)
+      .color_blend_state(&color_blend_state)
+      .dynamic_state(&dynamic_state)
+      .layout(pipeline_layout)
+      .render_pass(render_pass)
+      ;
+    // CORRECTNESS: slices are taken by pointer but are alive until `create_graphics_pipeline` is called.
+    if let Some(base_pipeline) = base_pipeline {
+      Ok(device.create_graphics_pipeline_derivative(pipeline_cache, base_pipeline, &create_info)?)
+    } else {
+      let create_info = create_info.flags(vk::PipelineCreateFlags::ALLOW_DERIVATIVES);
+      Ok(device.create_graphics_pipeline(pipeline_cache, &create_info)?)
+    }
+  }
+
   pub fn create_render_state(
     &self,
     _device: &Device,
@@ -216,7 +361,32 @@ impl GridRendererSys {
     Ok(GridRenderState::new())
   }
 
-  pub fn render(
+  /// Updates the transforms of grids from `world`, after `ticks` simulation ticks since the previous call. Must be called
+  /// once per frame, before [update](Self::update), including frames where no ticks were simulated.
+  pub fn update_grid_transforms(&mut self, world: &World, ticks: u32) {
+    use legion::borrow::Ref;
+    use legion::prelude::*;
+
+    let start = Instant::now();
+    let grid_transform_query = Read::<WorldTransform>::query()
+      .filter(tag::<Grid>() /*& changed::<WorldTransform>()*/);
+    for i in grid_transform_query.iter_entities(world) {
+      let (entity, transform): (_, Ref<WorldTransform>) = i;
+      self.grid_transforms.entry(entity)
+        .and_modify(|t| t.update(*transform, ticks))
+        .or_insert_with(|| InterpolatedGridTransform::new(*transform));
+    }
+    // Remove transforms of grids that have been deleted.
+    self.grid_transforms.retain(|entity, _| world.is_alive(*entity));
+    timing!("gfx.grid_renderer.update_grid_transforms", start.elapsed());
+  }
+
+  /// Updates grid chunk buffers from `world`, and records the dispatch of the compute passes that write their texture
+  /// UVs and cull them against `view_projection` into `command_buffer`. Must be recorded outside of a render pass,
+  /// before [render](Self::render), and after [update_grid_transforms](Self::update_grid_transforms). Grid transforms are
+  /// interpolated between the previous and current tick by `extrapolation`, the fraction of a tick that has elapsed since
+  /// the current tick.
+  pub fn update(
     &self,
     device: &Device,
     allocator: &Allocator,
@@ -225,20 +395,29 @@ impl GridRendererSys {
     render_state: &mut GridRenderState,
     world: &mut World,
     view_projection: Mat4,
+    extrapolation: f32,
   ) -> Result<()> {
     use legion::borrow::Ref;
     use legion::prelude::*;
 
-    // Update grid transforms
+    // Forget warnings for grids that have since received a transform or have been deleted, so they warn again when they
+    // go missing again.
+    {
+      let grid_transforms = &self.grid_transforms;
+      render_state.warned_missing_grid_transforms.retain(|entity| !grid_transforms.contains_key(entity) && world.is_alive(*entity));
+    }
+
+    // Clear visual state of grids that have been deleted.
     {
       let start = Instant::now();
-      let grid_transform_query = Read::<WorldTransform>::query()
-        .filter(tag::<Grid>() /*& changed::<WorldTransform>()*/);
-      for i in grid_transform_query.iter_entities(world) {
-        let (entity, transform): (_, Ref<WorldTransform>) = i;
-        render_state.grid_transforms.insert(entity, *transform);
+      let deleted_grids: HashSet<InGrid> = render_state.grid_chunk_buffers.keys()
+        .map(|(in_grid, _)| *in_grid)
+        .filter(|in_grid| !world.is_alive(in_grid.grid))
+        .collect();
+      for in_grid in deleted_grids {
+        render_state.clear_grid(in_grid, allocator);
       }
-      timing!("gfx.grid_renderer.render.update_grid_transforms", start.elapsed());
+      timing!("gfx.grid_renderer.render.clear_deleted_grids", start.elapsed());
     }
 
     // Assign initial chunk and chunk position for new grid tile entities.
@@ -280,12 +459,12 @@ impl GridRendererSys {
     // Keep set of buffers to remove.
     let mut remove_buffers = {
       let start = Instant::now();
-      let remove_buffers: HashSet<(InGrid, InGridChunk)> = HashSet::from_iter(render_state.grid_uv_buffers.keys().copied());
+      let remove_buffers: HashSet<(InGrid, InGridChunk)> = HashSet::from_iter(render_state.grid_chunk_buffers.keys().copied());
       timing!("gfx.grid_renderer.render.copy_uv_chunk_buffer_keys", start.elapsed());
       remove_buffers
     };
 
-    // Update chunk buffers with texture UVs.
+    // Update chunk buffers with tile data.
     {
       let start = Instant::now();
       // OPTO: reuse query?
@@ -293,101 +472,397 @@ impl GridRendererSys {
         .filter(tag::<InGrid>() & tag::<InGridChunk>());
       for chunk in update_query.iter_chunks(world) {
         let in_grid: &InGrid = chunk.tag().unwrap();
+        // Skip tiles of deleted grids, so that their buffers are not recreated.
+        if !world.is_alive(in_grid.grid) { continue; }
         let grid_chunk: &InGridChunk = chunk.tag().unwrap();
         let map_key = (*in_grid, *grid_chunk);
-        remove_buffers.remove(&map_key); // Keep buffer by removing it from the remove set.
+        // Keep buffer by removing it from the remove set. Multiple archetype chunks can map to the same grid chunk, so
+        // only the first archetype chunk of an existing buffer clears it. This empties the slots of removed tiles.
+        let clear_buffer = remove_buffers.remove(&map_key);
 
         {
-          let buffer_allocation = match render_state.grid_uv_buffers.entry(map_key) {
+          let chunk_buffers = match render_state.grid_chunk_buffers.entry(map_key) {
             Entry::Occupied(e) => {
               e.into_mut()
             }
             Entry::Vacant(e) => {
-              let buffer_allocation = unsafe {
-                let allocation = allocator.create_cpugpu_vertex_buffer_mapped(TextureUVVertexData::uv_size())?;
-                allocation.get_mapped_data().unwrap().copy_zeroes(TextureUVVertexData::uv_size());
-                allocator.flush_allocation(&allocation.allocation, 0, ash::vk::WHOLE_SIZE as usize)?;
-                allocation
-              };
-              e.insert(buffer_allocation)
+              let descriptor_set = unsafe { render_state.descriptor_sets.allocate_chunk_set(device, self.uv_descriptor_set_layout)? };
+              e.insert(unsafe { GridChunkBuffers::new(device, allocator, descriptor_set)? })
             }
           };
 
-          let mapped = unsafe { buffer_allocation.get_mapped_data() }.unwrap();
-          unsafe { mapped.copy_zeroes(TextureUVVertexData::uv_size()); }
-          let buffer_slice = unsafe { std::slice::from_raw_parts_mut(mapped.ptr() as *mut TextureUVVertexData, TextureUVVertexData::uv_count()) };
+          let mapped = unsafe { chunk_buffers.tiles.get_mapped_data(allocator) }.unwrap();
+          if clear_buffer {
+            unsafe { mapped.copy_zeroes(GridTileData::tiles_size()); }
+          }
+          let buffer_slice = unsafe { std::slice::from_raw_parts_mut(mapped.ptr() as *mut GridTileData, GRID_TILE_COUNT) };
           let indices = chunk.components::<GridChunkIndex>().unwrap();
           let orientations = chunk.components::<GridOrientation>().unwrap();
           let renderers = chunk.components::<GridTileRender>().unwrap();
-          for (index, _orientation, render) in izip!(indices.iter(), orientations.iter(), renderers.iter()) {
-            let texture_index = render.0.into_idx() as f32;
-            let slice_index = index.0 as usize * 4;
-            // OPTO: use memcpy?
-            buffer_slice[slice_index + 0] = TextureUVVertexData::new(0.0, 1.0, texture_index);
-            buffer_slice[slice_index + 1] = TextureUVVertexData::new(1.0, 1.0, texture_index);
-            buffer_slice[slice_index + 2] = TextureUVVertexData::new(0.0, 0.0, texture_index);
-            buffer_slice[slice_index + 3] = TextureUVVertexData::new(1.0, 0.0, texture_index);
+          if let Some(tints) = chunk.components::<GridTileTint>() {
+            for (index, orientation, render, tint) in izip!(indices.iter(), orientations.iter(), renderers.iter(), tints.iter()) {
+              buffer_slice[index.0 as usize] = GridTileData::new(render.0, texture_def.filter(render.0), *orientation, tint.0);
+            }
+          } else {
+            for (index, orientation, render) in izip!(indices.iter(), orientations.iter(), renderers.iter()) {
+              buffer_slice[index.0 as usize] = GridTileData::new(render.0, texture_def.filter(render.0), *orientation, Rgba::WHITE);
+            }
           }
-          allocator.flush_allocation(&buffer_allocation.allocation, 0, ash::vk::WHOLE_SIZE as usize)?;
+          unsafe { mapped.flush(..)?; }
         }
       }
-      timing!("gfx.grid_renderer.render.update_uv_buffers", start.elapsed());
+      timing!("gfx.grid_renderer.render.update_tile_buffers", start.elapsed());
     }
 
     // Remove buffers that are not needed any more.
     {
       let start = Instant::now();
       for grid_key in remove_buffers {
-        if let Some(buffer_allocation) = render_state.grid_uv_buffers.remove(&grid_key) {
-          unsafe { buffer_allocation.destroy(allocator); }
+        if let Some(chunk_buffers) = render_state.grid_chunk_buffers.remove(&grid_key) {
+          unsafe { chunk_buffers.destroy(allocator); }
+          render_state.descriptor_sets.free_chunk_set(chunk_buffers.descriptor_set);
         }
       }
       timing!("gfx.grid_renderer.render.remove_unused_uv_buffer", start.elapsed());
     }
 
-    // Issue bind and draw commands.
+    // Dispatch compute pass writing texture UVs of all chunks.
     {
       let start = Instant::now();
-      unsafe {
-        device.cmd_bind_pipeline(command_buffer, PipelineBindPoint::GRAPHICS, self.pipeline);
-        device.cmd_bind_vertex_buffers(command_buffer, 0, &[self.quads_vertex_buffer.buffer], &[0]);
-        device.cmd_bind_index_buffer(command_buffer, self.quads_index_buffer.buffer, 0, QuadsIndexData::index_type());
-        device.cmd_bind_descriptor_sets(command_buffer, PipelineBindPoint::GRAPHICS, self.pipeline_layout, 0, &[texture_def.descriptor_set], &[]);
-        for ((in_grid, in_grid_chunk), buffer_allocation) in render_state.grid_uv_buffers.iter() {
-          if let Some(world_transform) = render_state.grid_transforms.get(&in_grid.grid) {
-            let mut isometry = world_transform.isometry;
-            isometry.prepend_translation(Vec2::new(in_grid_chunk.x as f32 * GRID_LENGTH_F32, in_grid_chunk.y as f32 * GRID_LENGTH_F32));
-            let model = Mat4::from_translation(isometry.translation.into_homogeneous_vector()) * isometry.rotation.into_matrix().into_homogeneous().into_homogeneous();
-            let mvp_uniform_data = MVPUniformData(view_projection * model);
-            device.cmd_push_constants(command_buffer, self.pipeline_layout, ShaderStageFlags::VERTEX, 0, mvp_uniform_data.as_bytes());
-            device.cmd_bind_vertex_buffers(command_buffer, 1, &[buffer_allocation.buffer], &[0]);
-            device.cmd_draw_indexed(command_buffer, QuadsIndexData::index_count() as u32, 1, 0, 0, 0);
+      if !render_state.grid_chunk_buffers.is_empty() {
+        unsafe {
+          device.cmd_bind_pipeline(command_buffer, PipelineBindPoint::COMPUTE, self.uv_pipeline);
+          for chunk_buffers in render_state.grid_chunk_buffers.values() {
+            device.cmd_bind_descriptor_sets(command_buffer, PipelineBindPoint::COMPUTE, self.uv_pipeline_layout, 0, &[chunk_buffers.descriptor_set], &[]);
+            device.cmd_dispatch(command_buffer, GRID_TILE_DISPATCH_GROUP_COUNT, 1, 1);
           }
         }
       }
-      timing!("gfx.grid_renderer.render.issue_draw_commands", start.elapsed());
+      timing!("gfx.grid_renderer.render.dispatch_uv_update", start.elapsed());
+    }
+
+    // Validate GPU-side culling of the previous use of this render state against CPU-side culling.
+    #[cfg(debug_assertions)] {
+      if let (Some(cull_buffers), Some(expected)) = (&render_state.cull_buffers, render_state.expected_draw_count.take()) {
+        let actual = unsafe { cull_buffers.read_draw_count(allocator, render_state.draw_chunks.len()) }?;
+        if actual != expected {
+          warn!("GPU-side grid chunk culling drew {} chunks, but CPU-side culling expected {} chunks", actual, expected);
+        }
+      }
+    }
+
+    // Determine chunks to draw and their model-view-projection matrices.
+    {
+      let start = Instant::now();
+      render_state.draw_chunks.clear();
+      render_state.draw_chunk_mvps.clear();
+      render_state.draw_chunk_models.clear();
+      determine_draw_chunks(
+        render_state.grid_chunk_buffers.keys(),
+        &self.grid_transforms,
+        extrapolation,
+        self.render_missing_grid_transforms_at_identity,
+        &mut render_state.warned_missing_grid_transforms,
+        &mut render_state.draw_chunks,
+        &mut render_state.draw_chunk_models,
+      );
+      render_state.draw_chunk_mvps.extend(render_state.draw_chunk_models.iter().map(|model| view_projection * *model));
+      #[cfg(debug_assertions)] {
+        let expected = render_state.draw_chunk_mvps.iter().filter(|mvp| is_grid_chunk_visible(mvp, self.pivot)).count();
+        render_state.expected_draw_count = Some(expected as u32);
+      }
+      timing!("gfx.grid_renderer.render.determine_draw_chunks", start.elapsed());
+    }
+
+    // Dispatch compute pass culling chunks against the view frustum, writing indirect draw commands.
+    {
+      let start = Instant::now();
+      let chunk_count = render_state.draw_chunks.len();
+      if chunk_count > 0 {
+        unsafe {
+          render_state.ensure_cull_buffers(device, allocator, self.cull_descriptor_set_layout, chunk_count)?;
+          let cull_buffers = render_state.cull_buffers.as_ref().unwrap();
+          let mapped = cull_buffers.mvps.get_mapped_data(allocator).unwrap();
+          mapped.copy_from_slice(render_state.draw_chunk_mvps.as_slice());
+          mapped.flush(..size_of_val(render_state.draw_chunk_mvps.as_slice()))?;
+          device.cmd_bind_pipeline(command_buffer, PipelineBindPoint::COMPUTE, self.cull_pipeline);
+          device.cmd_bind_descriptor_sets(command_buffer, PipelineBindPoint::COMPUTE, self.cull_pipeline_layout, 0, &[cull_buffers.descriptor_set], &[]);
+          let cull_uniform_data = CullUniformData { chunk_count: chunk_count as u32, index_count: QuadsIndexData::index_count() as u32 };
+          device.cmd_push_constants(command_buffer, self.cull_pipeline_layout, ShaderStageFlags::COMPUTE, 0, cull_uniform_data.as_bytes());
+          device.cmd_dispatch(command_buffer, (chunk_count as u32 + CULL_LOCAL_SIZE - 1) / CULL_LOCAL_SIZE, 1, 1);
+        }
+      }
+      timing!("gfx.grid_renderer.render.dispatch_cull", start.elapsed());
+    }
+
+    // Make culling writes available to the host, so that they are visible when reading them back to validate GPU-side
+    // culling, after waiting for the render complete fence of this render state and invalidating the mapped memory.
+    #[cfg(debug_assertions)] {
+      if !render_state.draw_chunks.is_empty() {
+        let memory_barrier = vk::MemoryBarrier::builder()
+          .src_access_mask(vk::AccessFlags::SHADER_WRITE)
+          .dst_access_mask(vk::AccessFlags::HOST_READ)
+          .build();
+        unsafe {
+          device.cmd_pipeline_barrier(
+            command_buffer,
+            vk::PipelineStageFlags::COMPUTE_SHADER,
+            vk::PipelineStageFlags::HOST,
+            vk::DependencyFlags::empty(),
+            &[memory_barrier],
+            &[],
+            &[],
+          );
+        }
+      }
+    }
+
+    // Make compute writes visible to vertex input and indirect draw command reads.
+    if !render_state.grid_chunk_buffers.is_empty() {
+      let memory_barrier = vk::MemoryBarrier::builder()
+        .src_access_mask(vk::AccessFlags::SHADER_WRITE)
+        .dst_access_mask(vk::AccessFlags::VERTEX_ATTRIBUTE_READ | vk::AccessFlags::INDIRECT_COMMAND_READ)
+        .build();
+      unsafe {
+        device.cmd_pipeline_barrier(
+          command_buffer,
+          vk::PipelineStageFlags::COMPUTE_SHADER,
+          vk::PipelineStageFlags::VERTEX_INPUT | vk::PipelineStageFlags::DRAW_INDIRECT,
+          vk::DependencyFlags::empty(),
+          &[memory_barrier],
+          &[],
+          &[],
+        );
+      }
     }
 
     Ok(())
   }
 
+  #[inline]
+  pub fn wireframe(&self) -> bool { self.wireframe }
+
+  /// Gets the point of a tile that its [GridPosition] maps to, as given at creation.
+  #[inline]
+  pub fn pivot(&self) -> GridPivot { self.pivot }
+
+  /// Sets whether tiles are rendered as wireframes. Requires the `fillModeNonSolid` device feature; logs a warning and
+  /// does nothing if it is not enabled.
+  pub fn set_wireframe(&mut self, wireframe: bool) {
+    if wireframe && self.wireframe_pipeline.is_none() {
+      warn!("Cannot render grids as wireframes; the fillModeNonSolid device feature is not enabled");
+      return;
+    }
+    self.wireframe = wireframe;
+  }
+
+  #[inline]
+  pub fn wireframe_line_width(&self) -> f32 { self.wireframe_line_width }
+
+  /// Sets the line width of wireframes. Widths other than 1.0 require the `wideLines` device feature; logs a warning and
+  /// falls back to 1.0 if it is not enabled.
+  pub fn set_wireframe_line_width(&mut self, line_width: f32) {
+    if line_width != 1.0 && !self.wide_lines {
+      warn!("Cannot render wireframes with line width {}; the wideLines device feature is not enabled, using 1.0", line_width);
+      self.wireframe_line_width = 1.0;
+      return;
+    }
+    self.wireframe_line_width = line_width;
+  }
+
+  /// Records binding `descriptor_sets` at [GRID_EXTRA_DESCRIPTOR_SET_START] with the grid pipeline layout. Must be
+  /// called before flushing the render queue that grids were [rendered](Self::render) into, with one set for each extra
+  /// descriptor set layout passed to [new](Self::new). Render queue flushes only bind lower sets, so these stay bound as
+  /// long as all pipeline layouts used in between are compatible up to the extra sets.
+  pub unsafe fn bind_extra_descriptor_sets(&self, device: &Device, command_buffer: CommandBuffer, descriptor_sets: &[DescriptorSet]) {
+    debug_assert_eq!(descriptor_sets.len() as u32, self.extra_descriptor_set_count, "Number of extra descriptor sets does not match number of extra descriptor set layouts");
+    if descriptor_sets.is_empty() { return; }
+    device.cmd_bind_descriptor_sets(command_buffer, PipelineBindPoint::GRAPHICS, self.pipeline_layout, GRID_EXTRA_DESCRIPTOR_SET_START, descriptor_sets, &[]);
+  }
+
+  /// Pushes indirect draws for all grid chunks into `render_queue`. Whether a chunk is drawn is decided by the culling
+  /// compute pass recorded in [update](Self::update), which sets the instance count of culled chunks to 0.
+  ///
+  /// The CPU still pushes, and the render queue still records, one bind and one draw per chunk. Batching all chunks
+  /// into a single indirect count draw, which requires a shared texture UV buffer and reading model matrices in the
+  /// vertex shader, is deferred.
+  pub fn render(
+    &self,
+    render_queue: &mut RenderQueue,
+    texture_def: &TextureDef,
+    render_state: &GridRenderState,
+  ) {
+    let start = Instant::now();
+    let (pipeline, line_width) = match self.wireframe_pipeline {
+      Some(wireframe_pipeline) if self.wireframe => (wireframe_pipeline, Some(self.wireframe_line_width)),
+      _ => (self.pipeline, None),
+    };
+    if let Some(cull_buffers) = &render_state.cull_buffers {
+      let draw_chunks = izip!(&render_state.draw_chunks, &render_state.draw_chunk_models, &render_state.draw_chunk_mvps);
+      for (slot, (map_key, model, mvp)) in draw_chunks.enumerate() {
+        let chunk_buffers = &render_state.grid_chunk_buffers[map_key];
+        let buffer = cull_buffers.commands.buffer;
+        let offset = (slot * size_of::<DrawIndexedIndirectCommand>()) as DeviceSize;
+        let draw = DrawCommand::IndexedIndirect { buffer, offset };
+        render_queue.push(RenderItem {
+          pipeline,
+          pipeline_layout: self.pipeline_layout,
+          descriptor_set: texture_def.descriptor_set,
+          // The view-projection matrix is read from the camera uniform.
+          transform: *model,
+          // Tiles are alpha blended. Depth is the clip-space depth of the chunk origin.
+          sort_key: SortKey::Transparent { depth: mvp.cols[3].z },
+          vertex_buffers: [self.quads_vertex_buffer.buffer, chunk_buffers.uvs.buffer],
+          index_buffer: self.quads_index_buffer.buffer,
+          index_type: QuadsIndexData::index_type(),
+          line_width,
+          draw,
+        });
+      }
+    }
+    timing!("gfx.grid_renderer.render.push_render_items", start.elapsed());
+  }
+
   pub fn destroy(&mut self, device: &Device, allocator: &Allocator) {
     unsafe {
       self.quads_vertex_buffer.destroy(allocator);
       self.quads_index_buffer.destroy(allocator);
+      device.destroy_pipeline(self.cull_pipeline);
+      device.release_pipeline_layout(self.cull_pipeline_layout);
+      device.destroy_descriptor_set_layout(self.cull_descriptor_set_layout);
+      device.destroy_shader_module(self.cull_shader);
+      device.destroy_pipeline(self.uv_pipeline);
+      device.release_pipeline_layout(self.uv_pipeline_layout);
+      device.destroy_descriptor_set_layout(self.uv_descriptor_set_layout);
+      device.destroy_shader_module(self.uv_shader);
+      if let Some(wireframe_pipeline) = self.wireframe_pipeline {
+        device.destroy_pipeline(wireframe_pipeline);
+      }
       device.destroy_pipeline(self.pipeline);
-      device.destroy_pipeline_layout(self.pipeline_layout);
+      device.release_pipeline_layout(self.pipeline_layout);
       device.destroy_shader_module(self.vert_shader);
       device.destroy_shader_module(self.frag_shader);
     }
   }
 }
 
+// Grid chunk model matrix
+
+/// Model matrix of grid chunk `in_grid_chunk` inside the grid with transform `world_transform`. Chunk coordinates of
+/// different grids may overlap; they are disambiguated by each grid's own transform.
+fn grid_chunk_model(world_transform: &WorldTransform, in_grid_chunk: &InGridChunk) -> Mat4 {
+  let mut isometry = world_transform.isometry;
+  isometry.prepend_translation(Vec2::new(in_grid_chunk.x as f32 * GRID_LENGTH_F32, in_grid_chunk.y as f32 * GRID_LENGTH_F32));
+  Mat4::from_translation(isometry.translation.into_homogeneous_vector()) * isometry.rotation.into_matrix().into_homogeneous().into_homogeneous()
+}
+
+/// Determines which grid chunks of `keys` to draw, pushing them onto `draw_chunks`, and their model matrices onto
+/// `draw_chunk_models`. The transform of each chunk's grid is taken from `grid_transforms`, interpolated by
+/// `extrapolation`. Chunks of grids without a transform are skipped, or drawn at identity if
+/// `render_missing_grid_transforms_at_identity` is set, warning once per grid recorded in
+/// `warned_missing_grid_transforms`.
+fn determine_draw_chunks<'a>(
+  keys: impl IntoIterator<Item=&'a (InGrid, InGridChunk)>,
+  grid_transforms: &HashMap<Entity, InterpolatedGridTransform>,
+  extrapolation: f32,
+  render_missing_grid_transforms_at_identity: bool,
+  warned_missing_grid_transforms: &mut HashSet<Entity>,
+  draw_chunks: &mut Vec<(InGrid, InGridChunk)>,
+  draw_chunk_models: &mut Vec<Mat4>,
+) {
+  for (in_grid, in_grid_chunk) in keys {
+    let world_transform = match grid_transforms.get(&in_grid.grid) {
+      Some(grid_transform) => grid_transform.interpolate(extrapolation),
+      None => {
+        let first_warning = warned_missing_grid_transforms.insert(in_grid.grid);
+        if !render_missing_grid_transforms_at_identity {
+          if first_warning { warn!("Grid {:?} has no WorldTransform, skipping its tiles", in_grid.grid); }
+          continue;
+        }
+        if first_warning { warn!("Grid {:?} has no WorldTransform, rendering its tiles at identity", in_grid.grid); }
+        WorldTransform::new(0.0, 0.0, 0.0)
+      }
+    };
+    draw_chunks.push((*in_grid, *in_grid_chunk));
+    draw_chunk_models.push(grid_chunk_model(&world_transform, in_grid_chunk));
+  }
+}
+
+/// Whether grid chunk with model-view-projection matrix `mvp` is inside the view frustum. CPU-side equivalent of
+/// `grid_cull.comp.glsl`, used to validate GPU-side culling.
+#[cfg(debug_assertions)]
+fn is_grid_chunk_visible(mvp: &Mat4, pivot: GridPivot) -> bool {
+  use math::prelude::Vec4;
+  let min = pivot.tile_min_offset();
+  let max = GRID_LENGTH_F32 + min;
+  let corners = [Vec4::new(min, min, 0.0, 1.0), Vec4::new(max, min, 0.0, 1.0), Vec4::new(min, max, 0.0, 1.0), Vec4::new(max, max, 0.0, 1.0)];
+  let (mut left, mut right, mut bottom, mut top) = (true, true, true, true);
+  for corner in corners.iter() {
+    let c = *mvp * *corner;
+    left &= c.x < -c.w;
+    right &= c.x > c.w;
+    bottom &= c.y < -c.w;
+    top &= c.y > c.w;
+  }
+  !(left || right || bottom || top)
+}
+
+// Grid transform interpolation
+
+/// Transform of a grid at the previous and current tick, for interpolating between ticks when rendering. The previous
+/// transform is shifted once per simulated tick, whether the grid moved or not, so a grid that stops moving comes to
+/// rest at its last transform. When multiple ticks are simulated in a single frame, only the transform of the last of
+/// those ticks is known, so the previous transform is that of the last tick of the previous frame instead.
+#[derive(Copy, Clone, Debug)]
+struct InterpolatedGridTransform {
+  previous: WorldTransform,
+  current: WorldTransform,
+}
+
+impl InterpolatedGridTransform {
+  #[inline]
+  fn new(transform: WorldTransform) -> Self { Self { previous: transform, current: transform } }
+
+  /// Updates to `transform` after `ticks` simulated ticks. Changes without ticks, which were not simulated, are snapped
+  /// to instead of interpolated.
+  fn update(&mut self, transform: WorldTransform, ticks: u32) {
+    if ticks > 0 {
+      self.previous = self.current;
+      self.current = transform;
+    } else if !transform_eq(&transform, &self.current) {
+      self.previous = transform;
+      self.current = transform;
+    }
+  }
+
+  /// Interpolates between the previous and current transform by `alpha`, linearly for translation, and spherically
+  /// along the shortest arc for rotation.
+  fn interpolate(&self, alpha: f32) -> WorldTransform {
+    let (a, b) = (&self.previous.isometry, &self.current.isometry);
+    let translation = a.translation + (b.translation - a.translation) * alpha;
+    let rotation = rotor2_slerp(a.rotation, b.rotation, alpha);
+    WorldTransform { isometry: Isometry2::new(translation, rotation) }
+  }
+}
+
+#[inline]
+fn transform_eq(a: &WorldTransform, b: &WorldTransform) -> bool {
+  let (a, b) = (&a.isometry, &b.isometry);
+  a.translation.x == b.translation.x && a.translation.y == b.translation.y && a.rotation.s == b.rotation.s && a.rotation.bv.xy == b.rotation.bv.xy
+}
+
 // Render state
 
 pub struct GridRenderState {
-  grid_transforms: HashMap<Entity, WorldTransform>,
-  grid_uv_buffers: HashMap<(InGrid, InGridChunk), BufferAllocation>,
+  warned_missing_grid_transforms: HashSet<Entity>,
+  grid_chunk_buffers: HashMap<(InGrid, InGridChunk), GridChunkBuffers>,
+  draw_chunks: Vec<(InGrid, InGridChunk)>,
+  draw_chunk_mvps: Vec<Mat4>,
+  draw_chunk_models: Vec<Mat4>,
+  cull_buffers: Option<GridCullBuffers>,
+  descriptor_sets: GridDescriptorSets,
+  #[cfg(debug_assertions)]
+  expected_draw_count: Option<u32>,
   grid_chunk_update_query: Query<(Read<GridPosition>, Tagged<InGridChunk>), legion::filter::EntityFilterTuple<legion::filter::And<(legion::filter::ComponentFilter<GridPosition>, legion::filter::TagFilter<InGridChunk>, legion::filter::And<(legion::filter::TagFilter<InGrid>, legion::filter::TagFilter<InGridChunk>, legion::filter::ComponentFilter<GridTileRender>, legion::filter::ComponentFilter<GridPosition>)>)>, legion::filter::And<(legion::filter::Passthrough, legion::filter::Passthrough)>, legion::filter::And<(legion::filter::Passthrough, legion::filter::Passthrough, legion::filter::ComponentChangedFilter<GridPosition>)>>>,
 }
 
@@ -397,16 +872,177 @@ impl GridRenderState {
     let grid_chunk_update_query = <(Read<GridPosition>, Tagged<InGridChunk>)>::query()
       .filter(tag::<InGrid>() & tag::<InGridChunk>() & component::<GridTileRender>() & changed::<GridPosition>());
     Self {
-      grid_transforms: HashMap::default(),
-      grid_uv_buffers: HashMap::default(),
+      warned_missing_grid_transforms: HashSet::default(),
+      grid_chunk_buffers: HashMap::default(),
+      draw_chunks: Vec::new(),
+      draw_chunk_mvps: Vec::new(),
+      draw_chunk_models: Vec::new(),
+      cull_buffers: None,
+      descriptor_sets: GridDescriptorSets::new(),
+      #[cfg(debug_assertions)]
+      expected_draw_count: None,
       grid_chunk_update_query,
     }
   }
 
-  pub(crate) fn destroy(&self, allocator: &Allocator) {
-    for buffer_allocation in self.grid_uv_buffers.values() {
-      unsafe { buffer_allocation.destroy(allocator) };
+  /// Ensures that cull buffers fit at least `chunk_count` chunks, recreating them if they are too small. Only call when
+  /// the GPU is done with the previous use of this render state.
+  unsafe fn ensure_cull_buffers(
+    &mut self,
+    device: &Device,
+    allocator: &Allocator,
+    descriptor_set_layout: DescriptorSetLayout,
+    chunk_count: usize,
+  ) -> Result<()> {
+    if self.cull_buffers.as_ref().map_or(true, |b| b.capacity < chunk_count) {
+      // Reuse the descriptor set of the previous cull buffers, as descriptor sets cannot be freed individually.
+      let descriptor_set = match self.cull_buffers.take() {
+        Some(cull_buffers) => {
+          cull_buffers.destroy(allocator);
+          cull_buffers.descriptor_set
+        }
+        None => self.descriptor_sets.allocator.allocate(device, descriptor_set_layout)?,
+      };
+      let capacity = chunk_count.next_power_of_two().max(CULL_LOCAL_SIZE as usize);
+      self.cull_buffers = Some(GridCullBuffers::new(device, allocator, descriptor_set, capacity)?);
+    }
+    Ok(())
+  }
+
+  /// Destroys and removes all chunk buffers of grid `in_grid`, and forgets its missing transform warning. Only call when the GPU is done
+  /// with the previous use of this render state. Grids that have been deleted are cleared automatically.
+  pub fn clear_grid(&mut self, in_grid: InGrid, allocator: &Allocator) {
+    let keys: Vec<(InGrid, InGridChunk)> = self.grid_chunk_buffers.keys().filter(|(g, _)| *g == in_grid).copied().collect();
+    for key in keys {
+      if let Some(chunk_buffers) = self.grid_chunk_buffers.remove(&key) {
+        unsafe { chunk_buffers.destroy(allocator); }
+        self.descriptor_sets.free_chunk_set(chunk_buffers.descriptor_set);
+      }
+    }
+    self.warned_missing_grid_transforms.remove(&in_grid.grid);
+  }
+
+  pub(crate) fn destroy(&mut self, device: &Device, allocator: &Allocator) {
+    for chunk_buffers in self.grid_chunk_buffers.values() {
+      unsafe { chunk_buffers.destroy(allocator) };
+    }
+    if let Some(cull_buffers) = &self.cull_buffers {
+      unsafe { cull_buffers.destroy(allocator) };
+    }
+    unsafe { self.descriptor_sets.allocator.destroy(device) };
+  }
+}
+
+// Grid descriptor sets
+
+/// Number of descriptor sets per pool of [GridDescriptorSets].
+const DESCRIPTOR_SETS_PER_POOL: u32 = 64;
+
+/// Descriptor sets of the chunk and cull buffers of a render state, allocated from shared pools instead of a pool per
+/// set. Descriptor sets cannot be freed individually, so the sets of removed chunk buffers are reused for new ones.
+struct GridDescriptorSets {
+  allocator: DescriptorAllocator,
+  free_chunk_sets: Vec<DescriptorSet>,
+}
+
+impl GridDescriptorSets {
+  fn new() -> Self {
+    // Chunk and cull sets both have 2 storage buffers per set.
+    let allocator = DescriptorAllocator::new(DESCRIPTOR_SETS_PER_POOL, &[descriptor_set::storage_buffer_pool_size(DESCRIPTOR_SETS_PER_POOL * 2)]);
+    Self { allocator, free_chunk_sets: Vec::new() }
+  }
+
+  /// Allocates a descriptor set with chunk buffer `layout`, reusing the set of removed chunk buffers if possible.
+  unsafe fn allocate_chunk_set(&mut self, device: &Device, layout: DescriptorSetLayout) -> Result<DescriptorSet> {
+    match self.free_chunk_sets.pop() {
+      Some(descriptor_set) => Ok(descriptor_set),
+      None => Ok(self.allocator.allocate(device, layout)?),
+    }
+  }
+
+  /// Frees `descriptor_set` of removed chunk buffers for reuse. Only call when the GPU is done with it.
+  fn free_chunk_set(&mut self, descriptor_set: DescriptorSet) {
+    self.free_chunk_sets.push(descriptor_set);
+  }
+}
+
+// Grid cull buffers
+
+/// Buffers of the culling compute pass: model-view-projection matrices per chunk written by the CPU, and indirect draw
+/// commands per chunk written by the culling compute pass. In debug builds, the commands are readable by the CPU to
+/// validate GPU-side culling.
+struct GridCullBuffers {
+  capacity: usize,
+  mvps: BufferAllocation,
+  commands: BufferAllocation,
+  descriptor_set: DescriptorSet,
+}
+
+impl GridCullBuffers {
+  /// Creates cull buffers for `capacity` chunks, and writes them into `descriptor_set`.
+  unsafe fn new(device: &Device, allocator: &Allocator, descriptor_set: DescriptorSet, capacity: usize) -> Result<Self> {
+    let mvps_size = capacity * size_of::<Mat4>();
+    let commands_size = capacity * size_of::<DrawIndexedIndirectCommand>();
+    let mvps = allocator.create_cpugpu_storage_buffer_mapped(mvps_size)?;
+    #[cfg(debug_assertions)]
+    let commands = allocator.create_gpucpu_indirect_buffer_mapped(commands_size)?;
+    #[cfg(not(debug_assertions))]
+    let commands = allocator.create_gpu_indirect_buffer(commands_size)?;
+    DescriptorSetUpdateBuilder::new()
+      .add_storage_buffer_write(descriptor_set, 0, 0, mvps.buffer, 0, mvps_size as u64)
+      .add_storage_buffer_write(descriptor_set, 1, 0, commands.buffer, 0, commands_size as u64)
+      .do_update(device);
+    Ok(Self { capacity, mvps, commands, descriptor_set })
+  }
+
+  /// Reads back the number of chunks drawn out of the first `chunk_count` chunks. Only call when the GPU is done with
+  /// the previous use of this render state, which made the culling writes available to the host with a barrier.
+  #[cfg(debug_assertions)]
+  unsafe fn read_draw_count(&self, allocator: &Allocator, chunk_count: usize) -> Result<u32> {
+    let mapped = self.commands.get_mapped_data(allocator).unwrap();
+    let chunk_count = chunk_count.min(self.capacity);
+    mapped.invalidate(..chunk_count * size_of::<DrawIndexedIndirectCommand>())?;
+    let commands = std::slice::from_raw_parts(mapped.ptr() as *const DrawIndexedIndirectCommand, chunk_count);
+    Ok(commands.iter().map(|command| command.instance_count).sum())
+  }
+
+  /// Destroys the buffers. The descriptor set is owned by [GridDescriptorSets].
+  unsafe fn destroy(&self, allocator: &Allocator) {
+    self.commands.destroy(allocator);
+    self.mvps.destroy(allocator);
+  }
+}
+
+// Grid chunk buffers
+
+/// Per-grid-chunk buffers: tile data written by the CPU, and texture UVs written from the tile data by the compute pass.
+struct GridChunkBuffers {
+  tiles: BufferAllocation,
+  uvs: BufferAllocation,
+  descriptor_set: DescriptorSet,
+}
+
+impl GridChunkBuffers {
+  /// Creates chunk buffers, and writes them into `descriptor_set`.
+  unsafe fn new(device: &Device, allocator: &Allocator, descriptor_set: DescriptorSet) -> Result<Self> {
+    let tiles = allocator.create_cpugpu_storage_buffer_mapped(GridTileData::tiles_size())?;
+    {
+      let mapped = tiles.get_mapped_data(allocator).unwrap();
+      mapped.copy_zeroes(GridTileData::tiles_size());
+      mapped.flush(..)?;
     }
+    let uvs = allocator.create_gpu_storage_vertex_buffer(TextureUVVertexData::uv_size())?;
+    DescriptorSetUpdateBuilder::new()
+      .add_storage_buffer_write(descriptor_set, 0, 0, tiles.buffer, 0, GridTileData::tiles_size() as u64)
+      .add_storage_buffer_write(descriptor_set, 1, 0, uvs.buffer, 0, TextureUVVertexData::uv_size() as u64)
+      .do_update(device);
+    Ok(Self { tiles, uvs, descriptor_set })
+  }
+
+  /// Destroys the buffers. The descriptor set is owned by [GridDescriptorSets].
+  unsafe fn destroy(&self, allocator: &Allocator) {
+    self.uvs.destroy(allocator);
+    self.tiles.destroy(allocator);
   }
 }
 
@@ -417,42 +1053,24 @@ impl GridRenderState {
 #[derive(Copy, Clone, Debug)]
 struct QuadsVertexData(Vec2);
 
+vkw::impl_vertex!(QuadsVertexData {
+  0: Format::R32G32_SFLOAT,
+});
+
 #[allow(dead_code)]
 impl QuadsVertexData {
-  fn bindings() -> Vec<VertexInputBindingDescription> {
-    vec![
-      VertexInputBindingDescription::builder()
-        .binding(0)
-        .stride(size_of::<Self>() as u32)
-        .input_rate(VertexInputRate::VERTEX)
-        .build(),
-    ]
-  }
-
-  fn attributes() -> Vec<VertexInputAttributeDescription> {
-    vec![
-      VertexInputAttributeDescription::builder()
-        .location(0)
-        .binding(0)
-        .format(Format::R32G32_SFLOAT)
-        .offset(0)
-        .build(),
-    ]
-  }
-
-
   fn vertex_count() -> usize { GRID_TILE_COUNT * 4 }
 
-  fn create_vertices() -> Vec<Self> {
+  /// Creates the vertices of a quad per tile of a chunk, in chunk-space, placed according to `pivot`.
+  fn create_vertices(pivot: GridPivot) -> Vec<Self> {
     let mut vec = Vec::with_capacity(Self::vertex_count());
-    for y in 0..GRID_LENGTH {
-      let y = y as f32;
-      for x in 0..GRID_LENGTH {
-        let x = x as f32;
-        vec.push(Self(Vec2::new(x - 0.5, y - 0.5)));
-        vec.push(Self(Vec2::new(x + 0.5, y - 0.5)));
-        vec.push(Self(Vec2::new(x - 0.5, y + 0.5)));
-        vec.push(Self(Vec2::new(x + 0.5, y + 0.5)));
+    for y in 0..GRID_LENGTH_I32 {
+      for x in 0..GRID_LENGTH_I32 {
+        let min = pivot.tile_min(GridPosition::new(x, y));
+        vec.push(Self(Vec2::new(min.x, min.y)));
+        vec.push(Self(Vec2::new(min.x + 1.0, min.y)));
+        vec.push(Self(Vec2::new(min.x, min.y + 1.0)));
+        vec.push(Self(Vec2::new(min.x + 1.0, min.y + 1.0)));
       }
     }
     vec
@@ -492,7 +1110,27 @@ impl QuadsIndexData {
   fn indices_size() -> usize { Self::index_count() * size_of::<Self>() }
 }
 
-// Texture UV vertex data (CPU-GPU buffer, mutable)
+// Grid tile data (CPU-GPU storage buffer, mutable)
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+/// Tile data read by the compute pass. Texture index 0 denotes an empty tile. Tint is packed as RGBA8.
+struct GridTileData {
+  texture: u32,
+  orientation: u32,
+  tint: u32,
+  sampler_index: u32,
+}
+
+impl GridTileData {
+  fn new(texture_idx: TextureIdx, filter: TextureFilter, orientation: GridOrientation, tint: Rgba) -> Self {
+    Self { texture: texture_idx.into_idx() as u32, orientation: orientation as u32, tint: tint.to_rgba8_packed(), sampler_index: filter as u32 }
+  }
+
+  fn tiles_size() -> usize { GRID_TILE_COUNT * size_of::<Self>() }
+}
+
+// Texture UV vertex data (GPU storage and vertex buffer, written by compute pass)
 
 #[allow(dead_code)]
 #[repr(C)]
@@ -501,53 +1139,40 @@ struct TextureUVVertexData {
   u: f32,
   v: f32,
   i: f32,
+  /// Tint packed as RGBA8.
+  tint: u32,
+  /// Sampler index, see [TextureFilter], or `EMPTY_TILE_SAMPLER_INDEX` (`u32::MAX`) for vertices of empty tiles, which
+  /// the vertex shader moves beyond the far plane so that they are clipped.
+  sampler_index: u32,
 }
 
+vkw::impl_vertex!(TextureUVVertexData {
+  // Spans `u`, `v`, and `i`.
+  u: Format::R32G32B32_SFLOAT,
+  tint: Format::R8G8B8A8_UNORM,
+  sampler_index: Format::R32_UINT,
+});
+
 #[allow(dead_code)]
 impl TextureUVVertexData {
-  fn bindings() -> Vec<VertexInputBindingDescription> {
-    vec![
-      VertexInputBindingDescription::builder()
-        .binding(1)
-        .stride(size_of::<Self>() as u32)
-        .input_rate(VertexInputRate::VERTEX)
-        .build(),
-    ]
-  }
-
-  fn attributes() -> Vec<VertexInputAttributeDescription> {
-    vec![
-      VertexInputAttributeDescription::builder()
-        .location(1)
-        .binding(1)
-        .format(Format::R32G32B32_SFLOAT)
-        .offset(0)
-        .build(),
-    ]
-  }
-
-
-  fn new(u: f32, v: f32, i: f32) -> Self {
-    Self { u, v, i }
-  }
-
   fn uv_count() -> usize { GRID_TILE_COUNT * 4 }
 
   fn uv_size() -> usize { Self::uv_count() * size_of::<Self>() }
 }
 
 
-// MVP (model-view-projection matrix) uniform data (push constant, mutable)
+// Cull uniform data (push constant, mutable)
 
-#[allow(dead_code)]
 #[repr(C)]
 #[derive(Copy, Clone, Debug)]
-struct MVPUniformData(Mat4);
-
+struct CullUniformData {
+  chunk_count: u32,
+  index_count: u32,
+}
 
-impl MVPUniformData {
+impl CullUniformData {
   pub fn push_constant_range() -> PushConstantRange {
-    push_constant::vertex_range(size_of::<Self>() as u32, 0)
+    push_constant::compute_range(size_of::<Self>() as u32, 0)
   }
 
   pub unsafe fn as_bytes(&self) -> &[u8] {
@@ -556,3 +1181,149 @@ impl MVPUniformData {
     std::slice::from_raw_parts(bytes_ptr, size_of::<Self>())
   }
 }
+
+// Model matrix uniform data (push constant, mutable)
+
+#[allow(dead_code)]
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+struct ModelUniformData(Mat4);
+
+
+impl ModelUniformData {
+  pub fn push_constant_range() -> PushConstantRange {
+    push_constant::vertex_range(size_of::<Self>() as u32, 0)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::collections::{HashMap, HashSet};
+  use std::f32::consts::FRAC_PI_2;
+  use std::mem::size_of;
+
+  use legion::prelude::*;
+
+  use math::prelude::{Mat4, Vec2};
+  use sim::prelude::*;
+  use vkw::prelude::*;
+
+  use super::{determine_draw_chunks, grid_chunk_model, InGridChunk, InterpolatedGridTransform, QuadsVertexData, TextureUVVertexData};
+
+  fn assert_translation_eq(model: Mat4, expected: Vec2) {
+    let translation = model.cols[3];
+    assert!((translation.x - expected.x).abs() < 1e-4 && (translation.y - expected.y).abs() < 1e-4, "Expected translation {:?}, got {:?}", expected, translation);
+  }
+
+  #[test]
+  fn in_grid_chunk_from_negative_grid_position() {
+    assert_eq!(InGridChunk::from_grid_position(&GridPosition::new(15, 16)), InGridChunk { x: 0, y: 1 });
+    assert_eq!(InGridChunk::from_grid_position(&GridPosition::new(-1, -16)), InGridChunk { x: -1, y: -1 });
+    assert_eq!(InGridChunk::from_grid_position(&GridPosition::new(-17, 0)), InGridChunk { x: -2, y: 0 });
+  }
+
+  #[test]
+  fn grid_chunk_model_offsets_chunk_in_grid_space() {
+    let model = grid_chunk_model(&WorldTransform::new(0.0, 0.0, 0.0), &InGridChunk { x: 1, y: -2 });
+    assert_translation_eq(model, Vec2::new(16.0, -32.0));
+  }
+
+  #[test]
+  fn grid_chunk_model_disambiguates_grids_by_transform() {
+    let in_grid_chunk = InGridChunk { x: 1, y: 0 };
+    let model = grid_chunk_model(&WorldTransform::new(0.0, 0.0, 0.0), &in_grid_chunk);
+    let other_model = grid_chunk_model(&WorldTransform::new(5.0, 0.0, FRAC_PI_2), &in_grid_chunk);
+    assert_translation_eq(model, Vec2::new(16.0, 0.0));
+    // The chunk offset is rotated along with the grid.
+    assert_translation_eq(other_model, Vec2::new(5.0, 16.0));
+  }
+
+  #[test]
+  fn determine_draw_chunks_disambiguates_grids_with_same_chunk() {
+    let mut world = World::default();
+    let grids = world.insert((Grid, ), vec![(WorldTransform::default(), ), (WorldTransform::default(), )]).to_vec();
+    let in_grid_chunk = InGridChunk { x: 1, y: 0 };
+    let keys = vec![(InGrid::new(grids[0]), in_grid_chunk), (InGrid::new(grids[1]), in_grid_chunk)];
+    let mut grid_transforms = HashMap::new();
+    grid_transforms.insert(grids[0], InterpolatedGridTransform::new(WorldTransform::new(0.0, 0.0, 0.0)));
+    grid_transforms.insert(grids[1], InterpolatedGridTransform::new(WorldTransform::new(5.0, 0.0, FRAC_PI_2)));
+    let mut warned = HashSet::new();
+    let mut draw_chunks = Vec::new();
+    let mut draw_chunk_models = Vec::new();
+    determine_draw_chunks(&keys, &grid_transforms, 0.0, false, &mut warned, &mut draw_chunks, &mut draw_chunk_models);
+    assert_eq!(draw_chunks, keys);
+    assert_eq!(draw_chunk_models.len(), 2);
+    assert_translation_eq(draw_chunk_models[0], Vec2::new(16.0, 0.0));
+    assert_translation_eq(draw_chunk_models[1], Vec2::new(5.0, 16.0));
+    assert!(warned.is_empty());
+  }
+
+  /// Gets `(location, binding, format, offset)` of each attribute description, as they do not implement `PartialEq`.
+  fn attribute_tuples(descriptions: Vec<VertexInputAttributeDescription>) -> Vec<(u32, u32, Format, u32)> {
+    descriptions.iter().map(|d| (d.location, d.binding, d.format, d.offset)).collect()
+  }
+
+  #[test]
+  fn quads_vertex_descriptions_match_layout() {
+    let binding = QuadsVertexData::binding_description(0);
+    assert_eq!((binding.binding, binding.stride, binding.input_rate), (0, 8, VertexInputRate::VERTEX));
+    assert_eq!(attribute_tuples(QuadsVertexData::attribute_descriptions(0, 0)), vec![
+      (0, 0, Format::R32G32_SFLOAT, 0),
+    ]);
+  }
+
+  #[test]
+  fn texture_uv_vertex_descriptions_match_layout() {
+    let binding = TextureUVVertexData::binding_description(1);
+    assert_eq!((binding.binding, binding.stride, binding.input_rate), (1, 5 * size_of::<u32>() as u32, VertexInputRate::VERTEX));
+    assert_eq!(attribute_tuples(TextureUVVertexData::attribute_descriptions(1, 1)), vec![
+      (1, 1, Format::R32G32B32_SFLOAT, 0),
+      (2, 1, Format::R8G8B8A8_UNORM, 3 * size_of::<f32>() as u32),
+      (3, 1, Format::R32_UINT, 3 * size_of::<f32>() as u32 + size_of::<u32>() as u32),
+    ]);
+  }
+
+  fn assert_interpolated_x_eq(transform: &InterpolatedGridTransform, alpha: f32, expected: f32) {
+    let x = transform.interpolate(alpha).isometry.translation.x;
+    assert!((x - expected).abs() < 1e-4, "Expected x {} at alpha {}, got {}", expected, alpha, x);
+  }
+
+  #[test]
+  fn interpolated_grid_transform_interpolates_between_ticks() {
+    let mut transform = InterpolatedGridTransform::new(WorldTransform::new(0.0, 0.0, 0.0));
+    transform.update(WorldTransform::new(1.0, 0.0, 0.0), 1);
+    assert_interpolated_x_eq(&transform, 0.5, 0.5);
+    // Frames without ticks keep interpolating between the same ticks.
+    transform.update(WorldTransform::new(1.0, 0.0, 0.0), 0);
+    assert_interpolated_x_eq(&transform, 0.75, 0.75);
+  }
+
+  #[test]
+  fn interpolated_grid_transform_comes_to_rest_when_grid_stops() {
+    let mut transform = InterpolatedGridTransform::new(WorldTransform::new(0.0, 0.0, 0.0));
+    transform.update(WorldTransform::new(1.0, 0.0, 0.0), 1);
+    transform.update(WorldTransform::new(2.0, 0.0, 0.0), 1);
+    // The grid stops: a tick without movement.
+    transform.update(WorldTransform::new(2.0, 0.0, 0.0), 1);
+    for &alpha in &[0.0, 0.5, 1.0] {
+      assert_interpolated_x_eq(&transform, alpha, 2.0);
+    }
+  }
+
+  #[test]
+  fn interpolated_grid_transform_settles_after_teleport() {
+    let mut transform = InterpolatedGridTransform::new(WorldTransform::new(0.0, 0.0, 0.0));
+    transform.update(WorldTransform::new(100.0, 0.0, 0.0), 1);
+    transform.update(WorldTransform::new(100.0, 0.0, 0.0), 1);
+    assert_interpolated_x_eq(&transform, 0.0, 100.0);
+    assert_interpolated_x_eq(&transform, 0.5, 100.0);
+  }
+
+  #[test]
+  fn interpolated_grid_transform_snaps_to_changes_without_ticks() {
+    let mut transform = InterpolatedGridTransform::new(WorldTransform::new(0.0, 0.0, 0.0));
+    transform.update(WorldTransform::new(5.0, 0.0, 0.0), 0);
+    assert_interpolated_x_eq(&transform, 0.0, 5.0);
+    assert_interpolated_x_eq(&transform, 0.5, 5.0);
+  }
+}
@@ -1,69 +1,251 @@
 #![feature(never_type)]
 
+use std::error::Error as StdError;
 use std::num::NonZeroU32;
+use std::ops::RangeInclusive;
 
 use anyhow::{Context, Result};
-use ash::vk::{self, ClearColorValue, ClearValue, CommandBuffer, DebugReportFlagsEXT, PipelineStageFlags, RenderPass};
+use ash::vk::{self, ClearColorValue, ClearValue, CommandBuffer, CompositeAlphaFlagsKHR, DebugReportFlagsEXT, PipelineStageFlags, QueryPool, RenderPass, SurfaceFormatKHR, SurfaceTransformFlagsKHR, SwapchainKHR};
 use byte_strings::c_str;
 use legion::world::World;
 use log::debug;
 use raw_window_handle::RawWindowHandle;
+use thiserror::Error;
 
 use math::prelude::*;
+use sim::grid::GridPivot;
+use vkw::command_buffer::{CommandBufferBeginError, CommandBufferEndError, CommandBufferSubmitError};
+use vkw::device::swapchain_extension::{AcquireNextImageError, QueuePresentError, SwapchainCreateError};
 use vkw::entry::Entry;
 use vkw::framebuffer::FramebufferCreateError;
 use vkw::prelude::*;
+use vkw::presenter::SwapchainImageState;
+use vkw::query_pool::QueryResultsGetError;
+use vkw::renderer::RenderStateWaitAndResetError;
+use vkw::sync::DeviceWaitIdleError;
 
+use crate::background::{Background, BackgroundSys};
 use crate::camera::{CameraInput, CameraSys};
+use crate::camera_uniform::{CameraUniform, CameraUniformData, CameraUniformLayout, FrameUniformData};
+use crate::dynamic_resolution::DynamicResolution;
 use crate::grid_renderer::{GridRendererSys, GridRenderState};
-use crate::texture_def::{TextureDef, TextureDefBuilder};
+use crate::grid_renderer::animated_tile::update_animated_grid_tiles;
+use crate::present_thread::{PresentJob, PresentThread, SwapchainPresent};
+use crate::render_queue::RenderQueue;
+use crate::render_surface::{RenderSurface, RenderSurfaceSemaphores};
+use crate::scene_target::SceneTarget;
+use crate::texture_def::{TextureDef, TextureDefBuilder, TextureIdx};
+use std::path::Path;
 use std::time::Duration;
 
 pub mod grid_renderer;
 pub mod texture_def;
+pub mod present_thread;
+pub mod render_queue;
+pub mod render_surface;
+pub mod texture_atlas;
+#[cfg(feature = "hot-reload")]
+pub mod texture_watcher;
+pub mod background;
 pub mod camera;
-
+pub mod camera_uniform;
+pub mod fullscreen_pass;
+pub mod scene_target;
+pub mod dynamic_resolution;
+pub mod tonemap;
+
+/// Graphics state. `Send`, so that it can be moved into the game thread, which then owns it and is the only thread that
+/// records and submits with it; presentation is handed off to the [PresentThread]. Not `Sync`, as sharing it between
+/// threads would race on its command pools and queues.
 pub struct Gfx {
   pub instance: Instance,
   pub debug_report: Option<DebugReport>,
-  pub surface: Surface,
   pub device: Device,
   pub allocator: Allocator,
   pub transient_command_pool: CommandPool,
-  pub swapchain: Swapchain,
+  /// Surfaces of the windows that the scene is presented to, each with its own swapchain and tonemap pass that
+  /// post-processes the scene into swapchain images. The [main render surface](MAIN_RENDER_SURFACE) determines the
+  /// extent that the scene is rendered at.
+  render_surfaces: Vec<RenderSurface>,
   pub pipeline_cache: PipelineCache,
-  pub render_pass: RenderPass,
-  pub presenter: Presenter,
-  pub surface_change_handler: SurfaceChangeHandler,
+  /// Render pass that renders the scene into [scene_target](Self::scene_target).
+  pub scene_render_pass: RenderPass,
+  pub scene_target: SceneTarget,
 
   pub texture_def: TextureDef,
 
   pub camera_sys: CameraSys,
+  pub camera_uniform_layout: CameraUniformLayout,
   pub grid_render_sys: GridRendererSys,
+  pub background_sys: BackgroundSys,
+
+  pub render_queue: RenderQueue,
 
   pub renderer: Renderer<GameRenderState>,
+
+  pub present_thread: Option<PresentThread>,
+
+  /// Region of the window that the scene is rendered into, and the scissor region within it, or `None` for the full
+  /// window and the viewport, respectively.
+  viewport: Option<Rect2D>,
+  scissor: Option<Rect2D>,
+
+  /// Render scale feedback loop, and GPU time of the most recent frame whose timestamps were read back.
+  dynamic_resolution: DynamicResolution,
+  gpu_frame_time: Option<Duration>,
+
+  /// Elapsed time accumulated from frame times, and index of the next frame, for the frame uniform.
+  elapsed: Duration,
+  frame_index: u32,
+}
+
+vkw::assert_send!(Gfx);
+
+/// Features negotiated with the instance, device, surface, and swapchain at creation.
+#[derive(Copy, Clone, Debug)]
+pub struct GfxCapabilities {
+  pub present_mode: PresentModeKHR,
+  pub surface_format: SurfaceFormatKHR,
+  /// Transform applied to swapchain images before presenting, which the scene is pre-rotated by when not identity.
+  pub pre_transform: SurfaceTransformFlagsKHR,
+  pub composite_alpha: CompositeAlphaFlagsKHR,
+  pub sample_count: SampleCountFlags,
+  pub swapchain_image_count: u32,
+  pub validation_layer: bool,
+  pub validation_features: ValidationFeatures,
+  pub sampler_anisotropy: bool,
+  pub descriptor_indexing: bool,
+  pub fill_mode_non_solid: bool,
+  pub wide_lines: bool,
 }
 
+/// Index of the render surface of the main window, which is created with the graphics system and cannot be removed.
+pub const MAIN_RENDER_SURFACE: usize = 0;
+
+/// Number of samples of the scene render pass color attachment.
+const SAMPLE_COUNT: SampleCountFlags = SampleCountFlags::TYPE_1;
+/// Default GPU frame time that dynamic resolution aims for: 60 frames per second.
+const DEFAULT_TARGET_GPU_FRAME_TIME: Duration = Duration::from_micros(16_667);
+
 pub struct GameRenderState {
   pub command_buffer: CommandBuffer,
+  pub camera_uniform: CameraUniform,
   pub grid_render_sys: GridRenderState,
+  /// Timestamps written at the start and end of the command buffer, or `None` if the graphics queue does not support
+  /// timestamps.
+  pub timestamp_query_pool: Option<QueryPool>,
+  /// Whether the timestamps were written by the last submission of this render state.
+  pub timestamps_written: bool,
+}
+
+/// Swapchain image of a render surface that was acquired for the frame being rendered.
+struct AcquiredImage {
+  surface_index: usize,
+  image_state: SwapchainImageState,
+  semaphores: RenderSurfaceSemaphores,
+}
+
+/// Debug report flags for all validation layer messages except informational ones.
+pub fn default_debug_report_flags() -> DebugReportFlagsEXT {
+  DebugReportFlagsEXT::all() - DebugReportFlagsEXT::INFORMATION
+}
+
+#[derive(Error, Debug)]
+pub enum GfxError {
+  #[error("Failed to create graphics system")]
+  CreateFail(#[source] anyhow::Error),
+  #[error("Failed to wait for device idle")]
+  DeviceWaitIdleFail(#[from] DeviceWaitIdleError),
+  #[error("Failed to recreate swapchain")]
+  SwapchainRecreateFail(#[from] SwapchainCreateError),
+  #[error("Failed to recreate framebuffers")]
+  FramebufferRecreateFail(#[from] FramebufferCreateError),
+  #[error("Failed to recreate render pass")]
+  RenderPassRecreateFail(#[source] anyhow::Error),
+  #[error("Failed to recreate tonemap pass")]
+  TonemapRecreateFail(#[source] anyhow::Error),
+  #[error("Failed to recreate scene target")]
+  SceneTargetRecreateFail(#[source] anyhow::Error),
+  #[error("Failed to acquire render state")]
+  RenderStateAcquireFail(#[from] RenderStateWaitAndResetError),
+  #[error("Failed to acquire swapchain image")]
+  AcquireNextImageFail(#[from] AcquireNextImageError),
+  #[error("Failed to begin command buffer")]
+  CommandBufferBeginFail(#[from] CommandBufferBeginError),
+  #[error("Failed to record command buffer")]
+  RecordFail(#[source] anyhow::Error),
+  #[error("Failed to read GPU timestamps")]
+  TimestampReadFail(#[from] QueryResultsGetError),
+  #[error("Failed to end command buffer")]
+  CommandBufferEndFail(#[from] CommandBufferEndError),
+  #[error("Failed to submit command buffer")]
+  SubmitFail(#[from] CommandBufferSubmitError),
+  #[error("Failed to present")]
+  PresentFail(#[from] QueuePresentError),
+  #[error("Present thread failed")]
+  PresentThreadFail(#[source] anyhow::Error),
+}
+
+impl GfxError {
+  /// Gets the Vulkan result code that caused this error, if any.
+  pub fn vk_result(&self) -> Option<vk::Result> {
+    let mut source = self.source();
+    while let Some(error) = source {
+      if let Some(result) = error.downcast_ref::<vk::Result>() {
+        return Some(*result);
+      }
+      source = error.source();
+    }
+    None
+  }
+
+  /// Whether the device was lost, which is not recoverable.
+  #[inline]
+  pub fn is_device_lost(&self) -> bool { self.vk_result() == Some(vk::Result::ERROR_DEVICE_LOST) }
+
+  /// Whether the swapchain is out of date, which is recoverable by recreating the swapchain.
+  #[inline]
+  pub fn is_out_of_date(&self) -> bool { self.vk_result() == Some(vk::Result::ERROR_OUT_OF_DATE_KHR) }
 }
 
 impl Gfx {
+  /// Creates the graphics system. If `validation_layer` is `Some`, the validation layer is required, and its messages
+  /// are reported when they match the given flags (e.g. [default_debug_report_flags]). `validation_features` are then
+  /// enabled if the validation layer supports them. The swapchain is created with `swapchain_image_count` images, or
+  /// `max_frames_in_flight + 1` images if `None`, clamped to what the surface supports. Grid tiles are drawn according
+  /// to `grid_pivot`, which must match the [grid pivot of the simulation](sim::legion_sim::Sim::grid_pivot).
   pub fn new(
-    require_validation_layer: bool,
+    validation_layer: Option<DebugReportFlagsEXT>,
+    validation_features: ValidationFeatures,
     max_frames_in_flight: NonZeroU32,
+    swapchain_image_count: Option<NonZeroU32>,
     window: RawWindowHandle,
     initial_screen_size: ScreenSize,
     texture_def_builder: TextureDefBuilder,
+    grid_pivot: GridPivot,
+  ) -> Result<Gfx, GfxError> {
+    Self::create(validation_layer, validation_features, max_frames_in_flight, swapchain_image_count, window, initial_screen_size, texture_def_builder, grid_pivot)
+      .map_err(GfxError::CreateFail)
+  }
+
+  fn create(
+    validation_layer: Option<DebugReportFlagsEXT>,
+    validation_features: ValidationFeatures,
+    max_frames_in_flight: NonZeroU32,
+    swapchain_image_count: Option<NonZeroU32>,
+    window: RawWindowHandle,
+    initial_screen_size: ScreenSize,
+    mut texture_def_builder: TextureDefBuilder,
+    grid_pivot: GridPivot,
   ) -> Result<Gfx> {
     let entry = Entry::new()
       .with_context(|| "Failed to create VKW entry")?;
     let instance = {
       let features_query = {
         let mut query = InstanceFeaturesQuery::new();
-        if require_validation_layer {
+        if validation_layer.is_some() {
           query.require_validation_layer();
+          query.want_validation_features(validation_features);
         }
         query.require_surface();
         query
@@ -81,8 +263,8 @@ impl Gfx {
     };
     debug!("{:#?}", &instance.features);
 
-    let debug_report = if require_validation_layer {
-      Some(DebugReport::new(&instance, DebugReportFlagsEXT::all() - DebugReportFlagsEXT::INFORMATION).with_context(|| "Failed to create VKW debug report")?)
+    let debug_report = if let Some(debug_report_flags) = validation_layer {
+      Some(DebugReport::new(&instance, debug_report_flags).with_context(|| "Failed to create VKW debug report")?)
     } else {
       None
     };
@@ -92,6 +274,8 @@ impl Gfx {
       let features_query = {
         let mut query = DeviceFeaturesQuery::new();
         query.require_swapchain_extension();
+        query.want_fill_mode_non_solid();
+        query.want_wide_lines();
         query.require_features(PhysicalDeviceFeatures::builder()
           .shader_uniform_buffer_array_dynamic_indexing(true)
           .shader_sampled_image_array_dynamic_indexing(true)
@@ -110,154 +294,207 @@ impl Gfx {
     let transient_command_pool = unsafe { device.create_command_pool(true, false) }
       .with_context(|| "Failed to create transient command pool")?;
 
-    let swapchain = {
-      let features_query = {
-        let mut query = SwapchainFeaturesQuery::new();
-        query.want_image_count(unsafe { NonZeroU32::new_unchecked(max_frames_in_flight.get() + 1) });
-        query.want_present_mode(vec![
-          PresentModeKHR::IMMEDIATE,
-          PresentModeKHR::MAILBOX,
-          PresentModeKHR::FIFO_RELAXED,
-          PresentModeKHR::FIFO,
-        ]);
-        query
-      };
-      let (width, height) = initial_screen_size.physical.into();
-      Swapchain::new(&instance, &device, &surface, features_query, Extent2D { width, height })
-        .with_context(|| "Failed to create VKW swapchain")?
-    };
-    debug!("{:#?}", &swapchain.features);
-
     let pipeline_cache = unsafe { device.create_pipeline_cache() }
       .with_context(|| "Failed to create Vulkan pipeline cache")?;
 
-    let render_pass = {
-      use vk::{AttachmentDescription, AttachmentLoadOp, AttachmentStoreOp, SubpassDescription, AttachmentReference, ImageLayout};
-      let attachments = &[
-        AttachmentDescription::builder()
-          .format(swapchain.features.surface_format.format)
-          .samples(SampleCountFlags::TYPE_1)
-          .load_op(AttachmentLoadOp::CLEAR)
-          .store_op(AttachmentStoreOp::STORE)
-          .stencil_load_op(AttachmentLoadOp::DONT_CARE)
-          .stencil_store_op(AttachmentStoreOp::DONT_CARE)
-          .initial_layout(ImageLayout::UNDEFINED)
-          .final_layout(ImageLayout::PRESENT_SRC_KHR)
-          .build(),
-      ];
-      let color_attachments = &[
-        AttachmentReference::builder()
-          .attachment(0)
-          .layout(ImageLayout::COLOR_ATTACHMENT_OPTIMAL)
-          .build(),
-      ];
-      let subpasses = &[
-        SubpassDescription::builder()
-          .pipeline_bind_point(PipelineBindPoint::GRAPHICS)
-          .color_attachments(color_attachments)
-          .build(),
-      ];
-      let create_info = vk::RenderPassCreateInfo::builder()
-        .attachments(attachments)
-        .subpasses(subpasses)
-        ;
-      // CORRECTNESS: slices are taken by pointer but are alive until `create_render_pass` is called.
-      unsafe { device.create_render_pass(&create_info) }
-        .with_context(|| "Failed to create Vulkan render pass")?
+    let render_surface = {
+      let image_count = swapchain_image_count.unwrap_or(unsafe { NonZeroU32::new_unchecked(max_frames_in_flight.get() + 1) });
+      let (width, height) = initial_screen_size.physical.into();
+      RenderSurface::new(&instance, &device, surface, image_count, Extent2D { width, height }, pipeline_cache, max_frames_in_flight.get())?
     };
-    let framebuffers = Self::create_framebuffers(&device, &swapchain, render_pass)
-      .with_context(|| "Failed to create Vulkan framebuffer")?;
-    let presenter = Presenter::new(framebuffers)?;
 
-    let surface_change_handler = SurfaceChangeHandler::new();
+    let scene_render_pass = SceneTarget::create_render_pass(&device, SAMPLE_COUNT)
+      .with_context(|| "Failed to create scene render pass")?;
+    let scene_target = SceneTarget::new(&device, &allocator, scene_render_pass, SAMPLE_COUNT, render_surface.extent())
+      .with_context(|| "Failed to create scene target")?;
+    unsafe { render_surface.tonemap_sys.set_scene_view(&device, scene_target.view); }
 
+    if texture_def_builder.sampler_lod().is_none() {
+      texture_def_builder.set_sampler_lod(GridRendererSys::sampler_lod());
+    }
     let texture_def = unsafe { texture_def_builder.build(&device, &allocator, transient_command_pool)? };
 
-    let camera_sys = CameraSys::new(initial_screen_size.physical);
-    let grid_render_sys = GridRendererSys::new(&device, &allocator, &texture_def, max_frames_in_flight.get(), render_pass, pipeline_cache, transient_command_pool)
+    let mut camera_sys = CameraSys::new(initial_screen_size.physical);
+    camera_sys.signal_scale_change(initial_screen_size.scale);
+    let camera_uniform_layout = CameraUniformLayout::new(&device)
+      .with_context(|| "Failed to create camera uniform layout")?;
+    let grid_render_sys = GridRendererSys::new(&device, &allocator, &texture_def, &camera_uniform_layout, &[], grid_pivot, max_frames_in_flight.get(), scene_render_pass, pipeline_cache, transient_command_pool)
       .with_context(|| "Failed to create triangle renderer")?;
+    let background_sys = BackgroundSys::new(&device, scene_render_pass, pipeline_cache, &texture_def)
+      .with_context(|| "Failed to create background system")?;
 
+    let render_queue = RenderQueue::new();
+
+    let supports_timestamps = device.supports_graphics_queue_timestamps();
     let renderer = Renderer::new(&device, max_frames_in_flight, |state| {
       Ok(GameRenderState {
         command_buffer: unsafe { device.allocate_command_buffer(state.command_pool, false) }?,
+        camera_uniform: CameraUniform::new(&device, &allocator, &camera_uniform_layout)?,
         grid_render_sys: grid_render_sys.create_render_state(&device, &allocator)?,
+        timestamp_query_pool: if supports_timestamps { Some(unsafe { device.create_timestamp_query_pool(2) }?) } else { None },
+        timestamps_written: false,
       })
     })?;
 
     Ok(Self {
       instance,
-      surface,
       debug_report,
       device,
       allocator,
       transient_command_pool,
-      swapchain,
+      render_surfaces: vec![render_surface],
       pipeline_cache,
-      render_pass,
-      presenter,
-      surface_change_handler,
+      scene_render_pass,
+      scene_target,
 
       texture_def,
 
       camera_sys,
+      camera_uniform_layout,
       grid_render_sys,
+      background_sys,
+
+      render_queue,
 
       renderer,
+
+      present_thread: None,
+
+      viewport: None,
+      scissor: None,
+
+      dynamic_resolution: DynamicResolution::disabled(DEFAULT_TARGET_GPU_FRAME_TIME),
+      gpu_frame_time: None,
+
+      elapsed: Duration::default(),
+      frame_index: 0,
     })
   }
 
+  /// Renders a frame of `world`, after `ticks` simulation ticks since the previous frame. `extrapolation` is the fraction
+  /// of a tick that has elapsed since the last tick.
   pub fn render_frame(
     &mut self,
     world: &mut World,
     camera_input: CameraInput,
-    _extrapolation: f64,
+    ticks: u32,
+    extrapolation: f64,
     frame_time: Duration,
-  ) -> Result<()> {
-    // Recreate surface-extent dependent items if needed.
-    if let Some(extent) = self.surface_change_handler.query_surface_change(self.swapchain.extent) {
+  ) -> Result<(), GfxError> {
+    // Handle results of frames presented on the present thread.
+    if let Some(present_thread) = &self.present_thread {
+      let suboptimal_swapchains = present_thread.poll_results().map_err(GfxError::PresentThreadFail)?;
+      self.signal_suboptimal_swapchains(&suboptimal_swapchains);
+    }
+
+    // Update grid transforms every frame, also when skipping rendering, so that no ticks are missed.
+    self.grid_render_sys.update_grid_transforms(world, ticks);
+
+    // Skip rendering while the main surface has a zero-sized extent (e.g. the window is minimized), keeping the surface
+    // change pending until a non-zero extent is signalled. The scene is rendered at the extent of the main surface, so
+    // other surfaces are not rendered to either.
+    if self.render_surfaces[MAIN_RENDER_SURFACE].surface_change_handler.is_zero_extent_signalled() {
+      return Ok(());
+    }
+
+    // Recreate surface-extent dependent items of surfaces that changed. Changes of other surfaces with a zero-sized
+    // extent stay pending, and those surfaces are skipped until a non-zero extent is signalled.
+    let surface_changes: Vec<_> = self.render_surfaces.iter_mut().enumerate()
+      .filter(|(_, render_surface)| !render_surface.surface_change_handler.is_zero_extent_signalled())
+      .filter_map(|(index, render_surface)| render_surface.query_surface_change().map(|extent| (index, extent)))
+      .collect();
+    if !surface_changes.is_empty() {
+      self.flush_present_thread()?;
       unsafe {
-        self.device.device_wait_idle()
-          .with_context(|| "Failed to wait for device idle before recreating surface-extent dependent items")?;
-        self.swapchain.recreate(&self.device, &self.surface, extent)
-          .with_context(|| "Failed to recreate VKW swapchain")?;
-        let framebuffers = Self::create_framebuffers(&self.device, &self.swapchain, self.render_pass)
-          .with_context(|| "Failed to recreate Vulkan framebuffer")?;
-        self.presenter.recreate(&self.device, framebuffers)
-          .with_context(|| "Failed to recreate VKW presenter")?;
+        self.device.device_wait_idle()?;
+        for (index, extent) in surface_changes {
+          // Per-frame resources are per frame in flight and do not depend on the swapchain, but framebuffers are per
+          // swapchain image, and the number of images and the surface format may change with recreation.
+          self.render_surfaces[index].recreate(&self.device, extent, self.pipeline_cache)?;
+          if index == MAIN_RENDER_SURFACE {
+            let scene_target = SceneTarget::new(&self.device, &self.allocator, self.scene_render_pass, SAMPLE_COUNT, self.render_surfaces[index].extent())
+              .map_err(GfxError::SceneTargetRecreateFail)?;
+            std::mem::replace(&mut self.scene_target, scene_target).destroy(&self.device, &self.allocator);
+            for render_surface in &self.render_surfaces {
+              render_surface.tonemap_sys.set_scene_view(&self.device, self.scene_target.view);
+            }
+          }
+        }
       }
     }
-    let extent = self.swapchain.extent;
+    // Extent of the main surface as displayed, which the scene is rendered at.
+    let extent = self.render_surfaces[MAIN_RENDER_SURFACE].extent();
 
     // Update camera
     self.camera_sys.update(camera_input, frame_time);
 
+    // Update animated grid tiles
+    update_animated_grid_tiles(world, frame_time);
+
     // Acquire render state.
-    let (render_state, game_render_state) = self.renderer.next_render_state(&self.device)
-      .with_context(|| "Failed to acquire render state")?;
+    let (render_state, game_render_state) = self.renderer.next_render_state(&self.device)?;
     let command_buffer = game_render_state.command_buffer;
 
-    // Acquire swapchain image.
-    let swapchain_image_state = self.presenter.acquire_image_state(
-      &self.swapchain,
-      Some(render_state.image_acquired_semaphore),
-      &mut self.surface_change_handler
-    )
-      .with_context(|| "Failed to acquire swapchain image state")?;
+    // Read back the GPU time of the last frame rendered with this render state, which has completed, and adjust the
+    // render scale with it.
+    if let (Some(query_pool), true) = (game_render_state.timestamp_query_pool, game_render_state.timestamps_written) {
+      if let Some(timestamps) = unsafe { self.device.get_timestamps(query_pool, 0, 2) }? {
+        let gpu_frame_time = self.device.timestamp_duration(timestamps[0], timestamps[1]);
+        self.gpu_frame_time = Some(gpu_frame_time);
+        self.dynamic_resolution.update(gpu_frame_time);
+      }
+    }
+    game_render_state.timestamps_written = false;
+
+    // Render the scene into a region of the scene target scaled by the render scale, which the tonemap pass upscales.
+    let render_extent = scale_extent(extent, self.dynamic_resolution.scale());
+    let viewport = scale_rect(clamp_to_extent(self.viewport, extent), extent, render_extent);
+    let scissor = scale_rect(clamp_to_extent(self.scissor.or(self.viewport), extent), extent, render_extent);
+    let scene_uv_scale = [render_extent.width as f32 / extent.width as f32, render_extent.height as f32 / extent.height as f32];
+
+    // Update per-frame camera and frame uniforms, shared by all renderers.
+    self.elapsed += frame_time;
+    let camera_uniform_data = CameraUniformData::new(self.camera_sys.view_projection_matrix(), [viewport.extent.width as f32, viewport.extent.height as f32]);
+    let frame_uniform_data = FrameUniformData { time: self.elapsed.as_secs_f32(), frame: self.frame_index };
+    self.frame_index = self.frame_index.wrapping_add(1);
+    game_render_state.camera_uniform.update(&self.allocator, &camera_uniform_data, &frame_uniform_data).map_err(GfxError::RecordFail)?;
+
+    // Acquire a swapchain image of each surface. Surfaces with a zero-sized extent are skipped, as are surfaces whose
+    // swapchain is out of date, which are recreated next frame.
+    let swapchain_lock = self.present_thread.as_ref().map(|t| t.lock_swapchain());
+    let mut acquired_images = Vec::with_capacity(self.render_surfaces.len());
+    for (surface_index, render_surface) in self.render_surfaces.iter_mut().enumerate() {
+      if render_surface.surface_change_handler.is_zero_extent_signalled() { continue; }
+      let semaphores = render_surface.semaphores(render_state.index);
+      let swapchain_image_state = render_surface.presenter.acquire_image_state(
+        &render_surface.swapchain,
+        Some(semaphores.image_acquired),
+        &mut render_surface.surface_change_handler
+      )?;
+      if let Some(&SwapchainImageState { index, framebuffer }) = swapchain_image_state {
+        acquired_images.push(AcquiredImage { surface_index, image_state: SwapchainImageState { index, framebuffer }, semaphores });
+      }
+    }
+    drop(swapchain_lock);
+    if acquired_images.is_empty() {
+      // No swapchain image to render to; skip this frame. The render complete fence was reset when acquiring the render
+      // state, so signal it with an empty submit to keep the render state usable.
+      let render_complete_fence = render_state.render_complete_fence;
+      self.flush_present_thread()?;
+      unsafe {
+        self.device.submit_command_buffers(&[], &[], &[], &[], render_complete_fence)?;
+      }
+      return Ok(());
+    }
 
     unsafe {
       // Record primary command buffer.
-      self.device.begin_command_buffer(command_buffer, true)
-        .with_context(|| "Failed to begin command buffer")?;
-      self.presenter.set_dynamic_state(&self.device, command_buffer, extent);
-      self.device.begin_render_pass(
-        command_buffer,
-        self.render_pass,
-        swapchain_image_state.framebuffer,
-        self.presenter.full_render_area(extent),
-        &[ClearValue { color: ClearColorValue { float32: [0.5, 0.5, 1.0, 1.0] } }]
-      );
-
-      self.grid_render_sys.render(
+      self.device.begin_command_buffer(command_buffer, true)?;
+      if let Some(query_pool) = game_render_state.timestamp_query_pool {
+        self.device.cmd_reset_queries(command_buffer, query_pool, 0, 2);
+        self.device.cmd_write_timestamp(command_buffer, PipelineStageFlags::TOP_OF_PIPE, query_pool, 0);
+      }
+      self.grid_render_sys.update(
         &self.device,
         &self.allocator,
         command_buffer,
@@ -265,82 +502,326 @@ impl Gfx {
         &mut game_render_state.grid_render_sys,
         world,
         self.camera_sys.view_projection_matrix(),
-      )?;
+        extrapolation as f32,
+      ).map_err(GfxError::RecordFail)?;
 
-      // Done recording primary command buffer.
+      // Render scene into the (scaled) viewport of the scene target.
+      let main_presenter = &self.render_surfaces[MAIN_RENDER_SURFACE].presenter;
+      main_presenter.set_viewport_and_scissor(&self.device, command_buffer, viewport, scissor);
+      self.device.begin_render_pass(
+        command_buffer,
+        self.scene_render_pass,
+        self.scene_target.framebuffer,
+        main_presenter.full_render_area(render_extent),
+        &[ClearValue { color: ClearColorValue { float32: self.background_sys.background().clear_color().into() } }]
+      );
+      self.background_sys.render(&self.device, command_buffer, &self.texture_def, self.elapsed);
+      self.grid_render_sys.render(&mut self.render_queue, &self.texture_def, &game_render_state.grid_render_sys);
+      self.grid_render_sys.bind_extra_descriptor_sets(&self.device, command_buffer, &[]);
+      self.render_queue.flush(&self.device, command_buffer, game_render_state.camera_uniform.descriptor_set);
       self.device.end_render_pass(command_buffer);
-      self.device.end_command_buffer(command_buffer)
-        .with_context(|| "Failed to end command buffer")?;
 
-      // Submit command buffer: render to swapchain image.
+      // Tonemap, upscale, and pre-rotate scene target into the acquired swapchain image of each surface, stretching it to
+      // the extent of the surface.
+      for acquired_image in &acquired_images {
+        let render_surface = &self.render_surfaces[acquired_image.surface_index];
+        let image_extent = render_surface.image_extent();
+        self.device.begin_render_pass(
+          command_buffer,
+          render_surface.render_pass,
+          acquired_image.image_state.framebuffer,
+          render_surface.presenter.full_render_area(image_extent),
+          &[]
+        );
+        render_surface.presenter.set_dynamic_state(&self.device, command_buffer, image_extent);
+        let pre_rotation = render_surface.swapchain.features.pre_rotation().unwrap_or(0);
+        render_surface.tonemap_sys.render(&self.device, command_buffer, scene_uv_scale, pre_rotation);
+        self.device.end_render_pass(command_buffer);
+      }
+
+      // Done recording primary command buffer.
+      if let Some(query_pool) = game_render_state.timestamp_query_pool {
+        self.device.cmd_write_timestamp(command_buffer, PipelineStageFlags::BOTTOM_OF_PIPE, query_pool, 1);
+        game_render_state.timestamps_written = true;
+      }
+      self.device.end_command_buffer(command_buffer)?;
+    }
+
+    // Wait for all acquired images before rendering, and signal completion for each image to present.
+    let wait_semaphores: Vec<_> = acquired_images.iter().map(|i| i.semaphores.image_acquired).collect();
+    let wait_dst_stage_masks = vec![PipelineStageFlags::TOP_OF_PIPE; acquired_images.len()];
+    let signal_semaphores: Vec<_> = acquired_images.iter().map(|i| i.semaphores.render_complete).collect();
+
+    // Submit and present on the present thread, if enabled.
+    if let Some(present_thread) = &self.present_thread {
+      let render_surfaces = &self.render_surfaces;
+      let presents = acquired_images.iter().map(|i| SwapchainPresent {
+        swapchain: render_surfaces[i.surface_index].swapchain.wrapped,
+        image_index: i.image_state.index,
+        wait_semaphore: i.semaphores.render_complete,
+      }).collect();
+      present_thread.send(PresentJob {
+        command_buffer,
+        wait_semaphores,
+        wait_dst_stage_masks,
+        signal_semaphores,
+        fence: render_state.render_complete_fence,
+        presents,
+      }).map_err(GfxError::PresentThreadFail)?;
+      return Ok(());
+    }
+
+    unsafe {
+      // Submit command buffer: render to swapchain images.
       self.device.submit_command_buffer(
         command_buffer,
-        &[render_state.image_acquired_semaphore],
-        &[PipelineStageFlags::TOP_OF_PIPE],
-        &[render_state.render_complete_semaphore],
+        &wait_semaphores,
+        &wait_dst_stage_masks,
+        &signal_semaphores,
         Some(render_state.render_complete_fence),
-      ).with_context(|| "Failed to submit command buffer")?;
+      )?;
+    }
+
+    // Present: take rendered swapchain images and present each to the user.
+    for acquired_image in &acquired_images {
+      let render_surface = &mut self.render_surfaces[acquired_image.surface_index];
+      render_surface.presenter.present(
+        &self.device,
+        &render_surface.swapchain,
+        &acquired_image.image_state,
+        &[acquired_image.semaphores.render_complete],
+        &mut render_surface.surface_change_handler
+      )?;
+    }
+
+    Ok(())
+  }
+
+  /// Adds a render surface presenting to `window` of size `screen_size`, and returns its index. The scene is rendered
+  /// from the same camera as the main render surface and stretched to the extent of the window, with its own
+  /// [tonemap pass](RenderSurface::tonemap_sys). Fails if the device cannot present to the window.
+  pub fn add_render_surface(&mut self, window: RawWindowHandle, screen_size: ScreenSize) -> Result<usize> {
+    let surface = Surface::new(&self.instance, window).with_context(|| "Failed to create VKW surface")?;
+    let image_count = NonZeroU32::new(self.swapchain_image_count()).with_context(|| "Main swapchain has no images")?;
+    let (width, height) = screen_size.physical.into();
+    let render_surface = RenderSurface::new(&self.instance, &self.device, surface, image_count, Extent2D { width, height }, self.pipeline_cache, self.renderer.state_count())?;
+    unsafe { render_surface.tonemap_sys.set_scene_view(&self.device, self.scene_target.view); }
+    self.render_surfaces.push(render_surface);
+    Ok(self.render_surfaces.len() - 1)
+  }
+
+  /// Removes and destroys the render surface at `index`, waiting for frames that render to it to complete first.
+  /// Render surfaces after it shift down by one index. Panics if `index` is the [main render surface](MAIN_RENDER_SURFACE)
+  /// or out of bounds.
+  pub fn remove_render_surface(&mut self, index: usize) -> Result<(), GfxError> {
+    assert_ne!(index, MAIN_RENDER_SURFACE, "Cannot remove the main render surface");
+    assert!(index < self.render_surfaces.len(), "Render surface index {} is out of bounds", index);
+    if let Some(present_thread) = &self.present_thread {
+      present_thread.flush().map_err(GfxError::PresentThreadFail)?;
+      let suboptimal_swapchains = present_thread.poll_results().map_err(GfxError::PresentThreadFail)?;
+      self.signal_suboptimal_swapchains(&suboptimal_swapchains);
+    }
+    unsafe {
+      self.device.device_wait_idle()?;
+      self.render_surfaces.remove(index).destroy(&self.device);
+    }
+    Ok(())
+  }
+
+  #[inline]
+  pub fn render_surface_count(&self) -> usize { self.render_surfaces.len() }
+
+  #[inline]
+  pub fn render_surface(&self, index: usize) -> &RenderSurface { &self.render_surfaces[index] }
+
+  #[inline]
+  pub fn render_surface_mut(&mut self, index: usize) -> &mut RenderSurface { &mut self.render_surfaces[index] }
+
+  /// Reloads texture `texture_idx` from the image file at `path`, waiting for in-flight frames to complete first.
+  pub fn reload_texture(&self, texture_idx: TextureIdx, path: &Path) -> Result<()> {
+    self.flush_present_thread()?;
+    unsafe { self.texture_def.reload_texture(&self.device, &self.allocator, self.transient_command_pool, texture_idx, path) }
+  }
+
+  #[inline]
+  pub fn background(&self) -> Background { self.background_sys.background() }
+
+  /// Sets the background drawn behind the scene. Solid colors clear the scene, and other backgrounds are drawn before
+  /// the grids.
+  #[inline]
+  pub fn set_background(&mut self, background: Background) { self.background_sys.set_background(background); }
+
+  /// Gets the region of the window that the scene is rendered into.
+  #[inline]
+  pub fn viewport(&self) -> Rect2D { clamp_to_extent(self.viewport, self.render_surfaces[MAIN_RENDER_SURFACE].extent()) }
+
+  /// Sets the region of the window that the scene is rendered into to `viewport`, or the full window if `None`, and
+  /// restricts rendering to `scissor`, or the viewport if `None`. Both are clamped to the window. The camera aspect
+  /// ratio follows the viewport size, and camera screen coordinates are relative to the viewport origin; use
+  /// [CameraSys::window_to_world] to convert window positions such as the mouse position. The rest of the window shows
+  /// the clear color of the background.
+  pub fn set_viewport(&mut self, viewport: Option<Rect2D>, scissor: Option<Rect2D>) {
+    self.viewport = viewport;
+    self.scissor = scissor;
+    self.signal_camera_viewport_resize(self.render_surfaces[MAIN_RENDER_SURFACE].extent());
+  }
+
+  /// Gets the range that the render scale is kept in by dynamic resolution.
+  #[inline]
+  pub fn dynamic_resolution(&self) -> RangeInclusive<f32> { self.dynamic_resolution.range() }
+
+  /// Enables dynamic resolution, which renders the scene at a fraction of the window size in each dimension, the render
+  /// scale, and upscales it in the tonemap pass. The render scale is kept in `range`, clamped to (0, 1], and is lowered
+  /// when the GPU frame time exceeds the [target](Self::set_target_gpu_frame_time), and raised when there is headroom.
+  /// Pass `1.0..=1.0` to disable. Has no effect other than fixing the scale at the maximum of `range` when the device
+  /// does not support timestamps.
+  #[inline]
+  pub fn set_dynamic_resolution(&mut self, range: RangeInclusive<f32>) { self.dynamic_resolution.set_range(range); }
+
+  /// Gets the current render scale.
+  #[inline]
+  pub fn render_scale(&self) -> f32 { self.dynamic_resolution.scale() }
+
+  #[inline]
+  pub fn target_gpu_frame_time(&self) -> Duration { self.dynamic_resolution.target_frame_time() }
+
+  /// Sets the GPU frame time that dynamic resolution aims for. Defaults to 1/60th of a second.
+  #[inline]
+  pub fn set_target_gpu_frame_time(&mut self, target: Duration) { self.dynamic_resolution.set_target_frame_time(target); }
+
+  /// Gets the GPU time of the most recently completed frame, or `None` if not measured yet or if the device does not
+  /// support timestamps.
+  #[inline]
+  pub fn gpu_frame_time(&self) -> Option<Duration> { self.gpu_frame_time }
+
+  /// Number of images in the swapchain of the main render surface, as negotiated with the surface.
+  #[inline]
+  pub fn swapchain_image_count(&self) -> u32 { self.render_surfaces[MAIN_RENDER_SURFACE].swapchain.features.image_count }
+
+  /// Gets the features that were negotiated at creation, with swapchain features of the main render surface. Swapchain
+  /// features may change when the swapchain is recreated.
+  pub fn capabilities(&self) -> GfxCapabilities {
+    let device_features = &self.device.features;
+    let swapchain_features = &self.render_surfaces[MAIN_RENDER_SURFACE].swapchain.features;
+    GfxCapabilities {
+      present_mode: swapchain_features.present_mode,
+      surface_format: swapchain_features.surface_format,
+      pre_transform: swapchain_features.pre_transform,
+      composite_alpha: swapchain_features.composite_alpha,
+      sample_count: SAMPLE_COUNT,
+      swapchain_image_count: swapchain_features.image_count,
+      validation_layer: self.debug_report.is_some(),
+      validation_features: self.instance.features.validation_features(),
+      sampler_anisotropy: device_features.enabled_features.sampler_anisotropy == vk::TRUE,
+      descriptor_indexing: device_features.is_descriptor_indexing_extension_enabled(),
+      fill_mode_non_solid: device_features.is_fill_mode_non_solid_enabled(),
+      wide_lines: device_features.is_wide_lines_enabled(),
     }
+  }
 
-    // Present: take rendered swapchain image and present to the user.
-    self.presenter.present(
-      &self.device,
-      &self.swapchain,
-      swapchain_image_state,
-      &[render_state.render_complete_semaphore],
-      &mut self.surface_change_handler
-    )
-      .with_context(|| "Failed to present")?;
+  /// Caps the number of frames queued on the GPU to `max_queued_frames`, clamped between 1 and the maximum number of
+  /// frames in flight. Lower values reduce input latency at the cost of less CPU/GPU parallelism.
+  pub fn set_max_queued_frames(&mut self, max_queued_frames: u32) {
+    let max_queued_frames = NonZeroU32::new(max_queued_frames).unwrap_or(unsafe { NonZeroU32::new_unchecked(1) });
+    self.renderer.set_max_queued_frames(max_queued_frames);
+  }
 
+  #[inline]
+  pub fn max_queued_frames(&self) -> u32 { self.renderer.max_queued_frames() }
+
+  /// Enables or disables submitting and presenting frames on a dedicated [PresentThread], which stops the game thread
+  /// from blocking on submission and presentation.
+  pub fn set_present_thread(&mut self, enabled: bool) -> Result<()> {
+    if enabled && self.present_thread.is_none() {
+      self.present_thread = Some(PresentThread::new(&self.device, &self.render_surfaces[MAIN_RENDER_SURFACE].swapchain)?);
+    } else if !enabled {
+      if let Some(mut present_thread) = self.present_thread.take() {
+        present_thread.stop();
+        let suboptimal_swapchains = present_thread.poll_results()?;
+        self.signal_suboptimal_swapchains(&suboptimal_swapchains);
+      }
+    }
     Ok(())
   }
 
-  pub fn wait_idle(&self) -> Result<()> {
-    Ok(unsafe { self.device.device_wait_idle() }.with_context(|| "Failed to wait for device idle")?)
+  pub fn wait_idle(&self) -> Result<(), GfxError> {
+    self.flush_present_thread()?;
+    Ok(unsafe { self.device.device_wait_idle() }?)
   }
 
+  /// Signals that the main window was resized to `screen_size`.
   pub fn screen_size_changed(&mut self, screen_size: ScreenSize) {
-    self.camera_sys.signal_viewport_resize(screen_size.physical);
     let (width, height) = screen_size.physical.into();
-    self.surface_change_handler.signal_screen_resize(Extent2D { width, height });
+    self.camera_sys.signal_scale_change(screen_size.scale);
+    self.signal_camera_viewport_resize(Extent2D { width, height });
+    self.render_surfaces[MAIN_RENDER_SURFACE].surface_change_handler.signal_screen_resize(Extent2D { width, height });
   }
 
+  /// Signals that the window of the render surface at `index` was resized to `screen_size`. Use
+  /// [screen_size_changed](Self::screen_size_changed) for the main window, which also updates the camera.
+  pub fn render_surface_size_changed(&mut self, index: usize, screen_size: ScreenSize) {
+    let (width, height) = screen_size.physical.into();
+    self.render_surfaces[index].surface_change_handler.signal_screen_resize(Extent2D { width, height });
+  }
+
+  /// Signals render surfaces whose swapchain is in `suboptimal_swapchains` to recreate their swapchain. Swapchains that
+  /// no longer belong to a render surface, because it was recreated or removed since, are ignored.
+  fn signal_suboptimal_swapchains(&mut self, suboptimal_swapchains: &[SwapchainKHR]) {
+    for render_surface in &mut self.render_surfaces {
+      if suboptimal_swapchains.contains(&render_surface.swapchain.wrapped) {
+        render_surface.surface_change_handler.signal_suboptimal_swapchain();
+      }
+    }
+  }
+
+  /// Signals the camera that the viewport resized, given window extent `extent`. Ignores zero-sized viewports.
+  fn signal_camera_viewport_resize(&mut self, extent: Extent2D) {
+    let Rect2D { offset, extent: Extent2D { width, height } } = clamp_to_extent(self.viewport, extent);
+    if width != 0 && height != 0 {
+      self.camera_sys.signal_viewport_resize(PhysicalSize::new(width, height), PhysicalPosition::new(offset.x, offset.y));
+    }
+  }
 
-  fn create_framebuffers(device: &Device, swapchain: &Swapchain, render_pass: RenderPass) -> Result<Vec<Framebuffer>, FramebufferCreateError> {
-    swapchain.image_views.iter().map(|v| {
-      let attachments = &[*v];
-      let create_info = vk::FramebufferCreateInfo::builder()
-        .render_pass(render_pass)
-        .attachments(attachments)
-        .width(swapchain.extent.width)
-        .height(swapchain.extent.height)
-        .layers(1)
-        ;
-      Ok(unsafe { device.create_framebuffer(&create_info) }?)
-    }).collect()
+
+  fn flush_present_thread(&self) -> Result<(), GfxError> {
+    if let Some(present_thread) = &self.present_thread {
+      present_thread.flush().map_err(GfxError::PresentThreadFail)?;
+    }
+    Ok(())
   }
 }
 
 impl Drop for Gfx {
   fn drop(&mut self) {
+    if let Some(mut present_thread) = self.present_thread.take() {
+      present_thread.stop();
+    }
     unsafe {
-      self.renderer.destroy(&self.device, |render_state, game_render_state| {
-        self.device.free_command_buffer(render_state.command_pool, game_render_state.command_buffer);
-        game_render_state.grid_render_sys.destroy(&self.allocator);
+      let (device, allocator) = (&self.device, &self.allocator);
+      self.renderer.destroy(device, |render_state, game_render_state| {
+        device.free_command_buffer(render_state.command_pool, game_render_state.command_buffer);
+        game_render_state.camera_uniform.destroy(device, allocator);
+        game_render_state.grid_render_sys.destroy(device, allocator);
+        if let Some(query_pool) = game_render_state.timestamp_query_pool {
+          device.destroy_query_pool(query_pool);
+        }
       });
 
+      self.background_sys.destroy(&self.device);
       self.grid_render_sys.destroy(&self.device, &self.allocator);
+      self.camera_uniform_layout.destroy(&self.device);
 
       self.texture_def.destroy(&self.device, &self.allocator);
 
-      self.presenter.destroy(&self.device);
-      self.device.destroy_render_pass(self.render_pass);
+      self.scene_target.destroy(&self.device, &self.allocator);
+      self.device.destroy_render_pass(self.scene_render_pass);
       self.device.destroy_command_pool(self.transient_command_pool);
       self.allocator.destroy();
       self.device.destroy_pipeline_cache(self.pipeline_cache);
-      self.swapchain.destroy(&self.device);
+      for render_surface in &mut self.render_surfaces {
+        render_surface.destroy(&self.device);
+      }
       self.device.destroy();
-      self.surface.destroy();
       if let Some(debug_report) = &mut self.debug_report {
         debug_report.destroy();
       }
@@ -348,3 +829,91 @@ impl Drop for Gfx {
     }
   }
 }
+
+/// Clamps `region` to `extent`, or gets the full `extent` if `region` is `None`.
+fn clamp_to_extent(region: Option<Rect2D>, extent: Extent2D) -> Rect2D {
+  let full = Rect2D { offset: vk::Offset2D::default(), extent };
+  let region = match region {
+    Some(region) => region,
+    None => return full,
+  };
+  let x = region.offset.x.max(0).min(extent.width as i32);
+  let y = region.offset.y.max(0).min(extent.height as i32);
+  let width = region.extent.width.min(extent.width - x as u32);
+  let height = region.extent.height.min(extent.height - y as u32);
+  Rect2D { offset: vk::Offset2D { x, y }, extent: Extent2D { width, height } }
+}
+
+/// Scales `extent` by `scale`, keeping at least 1 pixel in each dimension.
+fn scale_extent(extent: Extent2D, scale: f32) -> Extent2D {
+  let width = ((extent.width as f32 * scale).round() as u32).max(1).min(extent.width);
+  let height = ((extent.height as f32 * scale).round() as u32).max(1).min(extent.height);
+  Extent2D { width, height }
+}
+
+/// Maps `region` within extent `from` to the corresponding region within extent `to`.
+fn scale_rect(region: Rect2D, from: Extent2D, to: Extent2D) -> Rect2D {
+  if from.width == 0 || from.height == 0 { return region; }
+  let scale_x = to.width as f32 / from.width as f32;
+  let scale_y = to.height as f32 / from.height as f32;
+  let x0 = (region.offset.x as f32 * scale_x).round() as i32;
+  let y0 = (region.offset.y as f32 * scale_y).round() as i32;
+  let x1 = ((region.offset.x + region.extent.width as i32) as f32 * scale_x).round() as i32;
+  let y1 = ((region.offset.y + region.extent.height as i32) as f32 * scale_y).round() as i32;
+  Rect2D { offset: vk::Offset2D { x: x0, y: y0 }, extent: Extent2D { width: (x1 - x0) as u32, height: (y1 - y0) as u32 } }
+}
+
+#[cfg(test)]
+mod tests {
+  use ash::vk::{Extent2D, Offset2D, Rect2D};
+
+  use super::{scale_extent, scale_rect};
+
+  fn rect(x: i32, y: i32, width: u32, height: u32) -> Rect2D {
+    Rect2D { offset: Offset2D { x, y }, extent: Extent2D { width, height } }
+  }
+
+  fn assert_rect_eq(actual: Rect2D, expected: Rect2D) {
+    assert_eq!(
+      (actual.offset.x, actual.offset.y, actual.extent.width, actual.extent.height),
+      (expected.offset.x, expected.offset.y, expected.extent.width, expected.extent.height),
+    );
+  }
+
+  #[test]
+  fn scale_extent_rounds_and_clamps() {
+    let extent = Extent2D { width: 1920, height: 1080 };
+    let scaled = scale_extent(extent, 0.5);
+    assert_eq!((scaled.width, scaled.height), (960, 540));
+    let scaled = scale_extent(extent, 1.0 / 3.0);
+    assert_eq!((scaled.width, scaled.height), (640, 360));
+    let scaled = scale_extent(extent, 0.0001);
+    assert_eq!((scaled.width, scaled.height), (1, 1));
+    let scaled = scale_extent(extent, 2.0);
+    assert_eq!((scaled.width, scaled.height), (1920, 1080));
+  }
+
+  #[test]
+  fn scale_rect_maps_between_extents() {
+    let from = Extent2D { width: 1000, height: 500 };
+    let to = Extent2D { width: 500, height: 250 };
+    assert_rect_eq(scale_rect(rect(100, 50, 200, 100), from, to), rect(50, 25, 100, 50));
+    assert_rect_eq(scale_rect(rect(0, 0, 1000, 500), from, to), rect(0, 0, 500, 250));
+  }
+
+  #[test]
+  fn scale_rect_keeps_adjacent_regions_adjacent() {
+    let from = Extent2D { width: 1000, height: 1000 };
+    let to = Extent2D { width: 333, height: 333 };
+    let left = scale_rect(rect(0, 0, 501, 1000), from, to);
+    let right = scale_rect(rect(501, 0, 499, 1000), from, to);
+    assert_eq!(left.offset.x + left.extent.width as i32, right.offset.x);
+    assert_eq!(right.offset.x + right.extent.width as i32, 333);
+  }
+
+  #[test]
+  fn scale_rect_from_empty_extent_is_identity() {
+    let region = rect(1, 2, 3, 4);
+    assert_rect_eq(scale_rect(region, Extent2D { width: 0, height: 0 }, Extent2D { width: 10, height: 10 }), region);
+  }
+}
@@ -0,0 +1,192 @@
+//! Optional thread that submits recorded frames to the graphics queue and presents them, so that the game thread does
+//! not block on submission and presentation.
+//!
+//! # Safety
+//!
+//! Vulkan requires external synchronization of queues and swapchains. While a [`PresentThread`] is running:
+//!
+//! * Do not submit to or wait on the graphics or present queue from another thread (e.g. with
+//!   `Device::device_wait_idle` or `Device::allocate_record_submit_wait`) without first calling
+//!   [`PresentThread::flush`].
+//! * Only acquire images from a swapchain while holding the guard returned by [`PresentThread::lock_swapchain`].
+//! * Only recreate or destroy a swapchain after calling [`PresentThread::flush`].
+//! * Keep the command buffers, semaphores, and fences of sent [`PresentJob`]s alive until the thread is flushed or
+//!   stopped, or until their fence has been signalled.
+
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::sync::mpsc::{channel, Receiver, Sender, sync_channel, SyncSender, TryRecvError};
+use std::thread::{self, JoinHandle};
+
+use anyhow::{anyhow, Context, Result};
+use ash::Device as VkDevice;
+use ash::version::DeviceV1_0;
+use ash::vk::{self, CommandBuffer, Fence, PipelineStageFlags, Queue, Semaphore, SwapchainKHR};
+use log::error;
+
+use vkw::device::swapchain_extension::SwapchainPresentLoader;
+use vkw::prelude::*;
+
+// Present job
+
+/// Recorded frame to submit and present to any number of swapchains.
+pub struct PresentJob {
+  pub command_buffer: CommandBuffer,
+  /// Semaphores that the submission waits on, at the pipeline stage with the same index in `wait_dst_stage_masks`.
+  pub wait_semaphores: Vec<Semaphore>,
+  pub wait_dst_stage_masks: Vec<PipelineStageFlags>,
+  /// Semaphores that the submission signals, which should include the wait semaphores of `presents`.
+  pub signal_semaphores: Vec<Semaphore>,
+  pub fence: Fence,
+  /// Swapchain images to present after the submission, each presented independently.
+  pub presents: Vec<SwapchainPresent>,
+}
+
+/// Presentation of a single swapchain image, after `wait_semaphore` is signalled.
+pub struct SwapchainPresent {
+  pub swapchain: SwapchainKHR,
+  pub image_index: u32,
+  pub wait_semaphore: Semaphore,
+}
+
+enum Message {
+  Present(PresentJob),
+  Flush(SyncSender<()>),
+}
+
+// Present thread
+
+pub struct PresentThread {
+  sender: Option<Sender<Message>>,
+  results: Receiver<Result<Vec<SwapchainKHR>>>,
+  swapchain_lock: Arc<Mutex<()>>,
+  handle: Option<JoinHandle<()>>,
+}
+
+impl PresentThread {
+  /// Creates a present thread for `device`. Only the function loader of `swapchain` is used, which can present to any
+  /// swapchain of `device`.
+  pub fn new(device: &Device, swapchain: &Swapchain) -> Result<Self> {
+    let (sender, receiver) = channel();
+    let (results_sender, results) = channel();
+    let swapchain_lock = Arc::new(Mutex::new(()));
+    let state = PresentThreadState {
+      device: device.raw().clone(),
+      graphics_queue: device.graphics_queue,
+      present_queue: device.present_queue,
+      loader: swapchain.present_loader(),
+      swapchain_lock: swapchain_lock.clone(),
+    };
+    let handle = thread::Builder::new()
+      .name("present".to_string())
+      .spawn(move || state.run(receiver, results_sender))
+      .with_context(|| "Failed to spawn present thread")?;
+    Ok(Self { sender: Some(sender), results, swapchain_lock, handle: Some(handle) })
+  }
+
+  pub fn send(&self, job: PresentJob) -> Result<()> {
+    self.sender().send(Message::Present(job)).map_err(|_| anyhow!("Present thread has stopped"))
+  }
+
+  /// Returns the swapchains that presents since the last call reported to be suboptimal, or the first error that
+  /// occurred.
+  pub fn poll_results(&self) -> Result<Vec<SwapchainKHR>> {
+    let mut suboptimal_swapchains = Vec::new();
+    loop {
+      match self.results.try_recv() {
+        Ok(result) => suboptimal_swapchains.extend(result?),
+        Err(TryRecvError::Empty) => return Ok(suboptimal_swapchains),
+        Err(TryRecvError::Disconnected) if self.handle.is_none() => return Ok(suboptimal_swapchains),
+        Err(TryRecvError::Disconnected) => return Err(anyhow!("Present thread has stopped unexpectedly")),
+      }
+    }
+  }
+
+  /// Blocks until all sent jobs have been submitted and presented.
+  pub fn flush(&self) -> Result<()> {
+    let (ack_sender, ack_receiver) = sync_channel(1);
+    self.sender().send(Message::Flush(ack_sender)).map_err(|_| anyhow!("Present thread has stopped"))?;
+    ack_receiver.recv().map_err(|_| anyhow!("Present thread has stopped"))
+  }
+
+  pub fn lock_swapchain(&self) -> MutexGuard<()> {
+    self.swapchain_lock.lock().unwrap_or_else(|e| e.into_inner())
+  }
+
+  /// Stops the thread after it has handled all sent jobs.
+  pub fn stop(&mut self) {
+    self.sender = None;
+    if let Some(handle) = self.handle.take() {
+      if handle.join().is_err() {
+        error!("Present thread panicked");
+      }
+    }
+  }
+
+  fn sender(&self) -> &Sender<Message> {
+    self.sender.as_ref().unwrap()
+  }
+}
+
+impl Drop for PresentThread {
+  fn drop(&mut self) {
+    self.stop();
+  }
+}
+
+// Present thread state
+
+struct PresentThreadState {
+  device: VkDevice,
+  graphics_queue: Queue,
+  present_queue: Queue,
+  loader: SwapchainPresentLoader,
+  swapchain_lock: Arc<Mutex<()>>,
+}
+
+impl PresentThreadState {
+  fn run(self, receiver: Receiver<Message>, results_sender: Sender<Result<Vec<SwapchainKHR>>>) {
+    for message in receiver {
+      match message {
+        Message::Present(job) => {
+          let result = unsafe { self.submit_and_present(job) };
+          if results_sender.send(result).is_err() { break; }
+        }
+        Message::Flush(ack_sender) => {
+          let _ = ack_sender.send(());
+        }
+      }
+    }
+  }
+
+  unsafe fn submit_and_present(&self, job: PresentJob) -> Result<Vec<SwapchainKHR>> {
+    let command_buffers = &[job.command_buffer];
+    let submits = &[vk::SubmitInfo::builder()
+      .wait_semaphores(&job.wait_semaphores)
+      .wait_dst_stage_mask(&job.wait_dst_stage_masks)
+      .command_buffers(command_buffers)
+      .signal_semaphores(&job.signal_semaphores)
+      .build()
+    ];
+    // CORRECTNESS: slices are taken by pointer but are alive until `queue_submit` is called.
+    self.device.queue_submit(self.graphics_queue, submits, job.fence)
+      .with_context(|| "Failed to submit command buffer")?;
+
+    // Present after the submission signals the semaphores, which also orders presentation on a separate present queue.
+    // Each swapchain is presented separately, so that one being out of date does not affect the others.
+    let _guard = self.swapchain_lock.lock().unwrap_or_else(|e| e.into_inner());
+    let mut suboptimal_swapchains = Vec::new();
+    for present in job.presents {
+      let wait_semaphores = &[present.wait_semaphore];
+      let swapchains = &[present.swapchain];
+      let image_indices = &[present.image_index];
+      let present_info = vk::PresentInfoKHR::builder()
+        .wait_semaphores(wait_semaphores)
+        .swapchains(swapchains)
+        .image_indices(image_indices);
+      if self.loader.queue_present(self.present_queue, &present_info).with_context(|| "Failed to present")? {
+        suboptimal_swapchains.push(present.swapchain);
+      }
+    }
+    Ok(suboptimal_swapchains)
+  }
+}
@@ -0,0 +1,338 @@
+use std::cmp::Ordering;
+use std::mem::size_of;
+
+use ash::version::DeviceV1_0;
+use ash::vk::{DeviceSize, IndexType};
+
+use math::prelude::Mat4;
+use vkw::prelude::*;
+
+use crate::camera_uniform::CAMERA_UNIFORM_SET;
+
+// Render item
+
+/// Maximum number of vertex buffer bindings of a [RenderItem].
+pub const MAX_VERTEX_BUFFERS: usize = 2;
+
+/// Item to draw, collected in a [RenderQueue]. The transform is pushed as a vertex shader push constant at offset 0,
+/// so `pipeline_layout` must have a push constant range for it. `descriptor_set` is bound at set 0, and the camera
+/// uniform at [CAMERA_UNIFORM_SET], so `pipeline_layout` must have compatible descriptor set layouts for both.
+#[derive(Copy, Clone, Debug)]
+pub struct RenderItem {
+  pub pipeline: Pipeline,
+  pub pipeline_layout: PipelineLayout,
+  pub descriptor_set: DescriptorSet,
+  pub transform: Mat4,
+  pub sort_key: SortKey,
+  /// Vertex buffers bound to bindings 0 to `MAX_VERTEX_BUFFERS`. Null buffers are not bound.
+  pub vertex_buffers: [Buffer; MAX_VERTEX_BUFFERS],
+  pub index_buffer: Buffer,
+  pub index_type: IndexType,
+  /// Dynamic line width to set, for pipelines with `DynamicState::LINE_WIDTH`.
+  pub line_width: Option<f32>,
+  pub draw: DrawCommand,
+}
+
+/// How a [RenderItem] is sorted, with `depth` in view-space where larger is further away. Opaque items are drawn first,
+/// front-to-back. Transparent items are drawn after, back-to-front.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum SortKey {
+  Opaque { depth: f32 },
+  Transparent { depth: f32 },
+}
+
+#[derive(Copy, Clone, Debug)]
+pub enum DrawCommand {
+  Indexed { index_count: u32 },
+  /// Draws a single [DrawIndexedIndirectCommand] from `buffer` at `offset`.
+  IndexedIndirect { buffer: Buffer, offset: DeviceSize },
+}
+
+// Render queue
+
+/// Collects [RenderItem]s, and records them into a command buffer sorted by their [SortKey], grouping items with the
+/// same pipeline and descriptor set so that redundant binds are skipped.
+pub struct RenderQueue {
+  opaque: Vec<RenderItem>,
+  transparent: Vec<RenderItem>,
+}
+
+/// Number of commands recorded by [RenderQueue::flush].
+#[derive(Default, Copy, Clone, Eq, PartialEq, Debug)]
+pub struct RenderQueueStats {
+  pub pipeline_binds: u32,
+  pub descriptor_set_binds: u32,
+  pub vertex_buffer_binds: u32,
+  pub index_buffer_binds: u32,
+  pub draws: u32,
+}
+
+impl RenderQueue {
+  pub fn new() -> Self {
+    Self { opaque: Vec::new(), transparent: Vec::new() }
+  }
+
+  pub fn push(&mut self, item: RenderItem) {
+    match item.sort_key {
+      SortKey::Opaque { .. } => self.opaque.push(item),
+      SortKey::Transparent { .. } => self.transparent.push(item),
+    }
+  }
+
+  /// Sorts the collected items and returns them in the order they will be recorded.
+  pub fn sort(&mut self) -> impl Iterator<Item=&RenderItem> {
+    // Sorts are stable, so items with equal keys keep their push order.
+    self.opaque.sort_by(|a, b| depth(a).partial_cmp(&depth(b)).unwrap_or(Ordering::Equal).then_with(|| state_cmp(a, b)));
+    self.transparent.sort_by(|a, b| depth(b).partial_cmp(&depth(a)).unwrap_or(Ordering::Equal).then_with(|| state_cmp(a, b)));
+    self.opaque.iter().chain(self.transparent.iter())
+  }
+
+  /// Sorts the collected items and records them into `command_buffer`, which must be inside a render pass, binding
+  /// `camera_descriptor_set` for every pipeline layout. Clears the queue afterwards.
+  pub unsafe fn flush(&mut self, device: &Device, command_buffer: CommandBuffer, camera_descriptor_set: DescriptorSet) -> RenderQueueStats {
+    let mut stats = RenderQueueStats::default();
+    let mut bound = BoundState::default();
+    for item in self.sort() {
+      let binds = bound.bind(item);
+      if binds.pipeline {
+        device.cmd_bind_graphics_pipeline(command_buffer, item.pipeline);
+      }
+      if binds.camera_descriptor_set {
+        device.cmd_bind_descriptor_sets(command_buffer, PipelineBindPoint::GRAPHICS, item.pipeline_layout, CAMERA_UNIFORM_SET, &[camera_descriptor_set], &[]);
+      }
+      if binds.descriptor_set {
+        device.cmd_bind_descriptor_sets(command_buffer, PipelineBindPoint::GRAPHICS, item.pipeline_layout, 0, &[item.descriptor_set], &[]);
+      }
+      for (binding, buffer) in item.vertex_buffers.iter().enumerate() {
+        if binds.vertex_buffers[binding] {
+          device.cmd_bind_vertex_buffers(command_buffer, binding as u32, &[*buffer], &[0]);
+        }
+      }
+      if binds.index_buffer {
+        device.cmd_bind_index_buffer(command_buffer, item.index_buffer, 0, item.index_type);
+      }
+      if let (true, Some(line_width)) = (binds.line_width, item.line_width) {
+        device.cmd_set_line_width(command_buffer, line_width);
+      }
+      binds.add_to(&mut stats);
+      device.cmd_push_constants(command_buffer, item.pipeline_layout, ShaderStageFlags::VERTEX, 0, mat4_as_bytes(&item.transform));
+      match item.draw {
+        DrawCommand::Indexed { index_count } => {
+          device.cmd_draw_indexed(command_buffer, index_count, 1, 0, 0, 0);
+        }
+        DrawCommand::IndexedIndirect { buffer, offset } => {
+          device.cmd_draw_indexed_indirect(command_buffer, buffer, offset, 1);
+        }
+      }
+      stats.draws += 1;
+    }
+    self.opaque.clear();
+    self.transparent.clear();
+    stats
+  }
+}
+
+// Sorting
+
+#[inline]
+fn depth(item: &RenderItem) -> f32 {
+  match item.sort_key {
+    SortKey::Opaque { depth } => depth,
+    SortKey::Transparent { depth } => depth,
+  }
+}
+
+/// Groups items with equal depth by pipeline and descriptor set, to minimize binds.
+#[inline]
+fn state_cmp(a: &RenderItem, b: &RenderItem) -> Ordering {
+  use ash::vk::Handle;
+  (a.pipeline.as_raw(), a.descriptor_set.as_raw()).cmp(&(b.pipeline.as_raw(), b.descriptor_set.as_raw()))
+}
+
+// Bound state
+
+#[derive(Default)]
+struct BoundState {
+  pipeline: Option<Pipeline>,
+  descriptor_set: Option<(PipelineLayout, DescriptorSet)>,
+  camera_pipeline_layout: Option<PipelineLayout>,
+  vertex_buffers: [Buffer; MAX_VERTEX_BUFFERS],
+  index_buffer: Option<(Buffer, IndexType)>,
+  line_width: Option<f32>,
+}
+
+/// Commands to record before drawing an item, skipping state that is already bound.
+#[derive(Default, Copy, Clone, Debug)]
+struct Binds {
+  pipeline: bool,
+  camera_descriptor_set: bool,
+  descriptor_set: bool,
+  vertex_buffers: [bool; MAX_VERTEX_BUFFERS],
+  index_buffer: bool,
+  line_width: bool,
+}
+
+impl BoundState {
+  /// Gets the binds needed to draw `item`, and updates the bound state as if they were recorded.
+  fn bind(&mut self, item: &RenderItem) -> Binds {
+    let mut binds = Binds::default();
+    if self.pipeline != Some(item.pipeline) {
+      self.pipeline = Some(item.pipeline);
+      // Binding a pipeline with static line width invalidates the dynamic line width.
+      self.line_width = None;
+      binds.pipeline = true;
+    }
+    if self.camera_pipeline_layout != Some(item.pipeline_layout) {
+      self.camera_pipeline_layout = Some(item.pipeline_layout);
+      binds.camera_descriptor_set = true;
+    }
+    if self.descriptor_set != Some((item.pipeline_layout, item.descriptor_set)) {
+      self.descriptor_set = Some((item.pipeline_layout, item.descriptor_set));
+      binds.descriptor_set = true;
+    }
+    for (binding, buffer) in item.vertex_buffers.iter().enumerate() {
+      if *buffer != Buffer::null() && self.vertex_buffers[binding] != *buffer {
+        self.vertex_buffers[binding] = *buffer;
+        binds.vertex_buffers[binding] = true;
+      }
+    }
+    if self.index_buffer != Some((item.index_buffer, item.index_type)) {
+      self.index_buffer = Some((item.index_buffer, item.index_type));
+      binds.index_buffer = true;
+    }
+    if let Some(line_width) = item.line_width {
+      if self.line_width != Some(line_width) {
+        self.line_width = Some(line_width);
+        binds.line_width = true;
+      }
+    }
+    binds
+  }
+}
+
+impl Binds {
+  fn add_to(&self, stats: &mut RenderQueueStats) {
+    stats.pipeline_binds += self.pipeline as u32;
+    stats.descriptor_set_binds += self.camera_descriptor_set as u32 + self.descriptor_set as u32;
+    stats.vertex_buffer_binds += self.vertex_buffers.iter().filter(|b| **b).count() as u32;
+    stats.index_buffer_binds += self.index_buffer as u32;
+  }
+}
+
+#[inline]
+unsafe fn mat4_as_bytes(mat: &Mat4) -> &[u8] {
+  std::slice::from_raw_parts(mat as *const Mat4 as *const u8, size_of::<Mat4>())
+}
+
+#[cfg(test)]
+mod tests {
+  use ash::vk::Handle;
+
+  use super::*;
+
+  /// Creates an item with fake handles, using `id` as index count to identify it.
+  fn item(id: u32, pipeline: u64, descriptor_set: u64, sort_key: SortKey) -> RenderItem {
+    RenderItem {
+      pipeline: Pipeline::from_raw(pipeline),
+      pipeline_layout: PipelineLayout::from_raw(1),
+      descriptor_set: DescriptorSet::from_raw(descriptor_set),
+      transform: Mat4::identity(),
+      sort_key,
+      vertex_buffers: [Buffer::from_raw(1), Buffer::null()],
+      index_buffer: Buffer::from_raw(2),
+      index_type: IndexType::UINT32,
+      line_width: None,
+      draw: DrawCommand::Indexed { index_count: id },
+    }
+  }
+
+  fn queue(items: &[RenderItem]) -> RenderQueue {
+    let mut queue = RenderQueue::new();
+    for item in items {
+      queue.push(*item);
+    }
+    queue
+  }
+
+  fn sorted_ids(queue: &mut RenderQueue) -> Vec<u32> {
+    queue.sort().map(|item| match item.draw {
+      DrawCommand::Indexed { index_count } => index_count,
+      _ => unreachable!(),
+    }).collect()
+  }
+
+  /// Gets the stats of recording the sorted items of `queue`, without recording them.
+  fn bind_stats(queue: &mut RenderQueue) -> RenderQueueStats {
+    let mut bound = BoundState::default();
+    let mut stats = RenderQueueStats::default();
+    for item in queue.sort() {
+      bound.bind(item).add_to(&mut stats);
+      stats.draws += 1;
+    }
+    stats
+  }
+
+  #[test]
+  fn sort_opaque_front_to_back_then_transparent_back_to_front() {
+    let mut queue = queue(&[
+      item(0, 1, 1, SortKey::Transparent { depth: 1.0 }),
+      item(1, 1, 1, SortKey::Opaque { depth: 5.0 }),
+      item(2, 1, 1, SortKey::Transparent { depth: 3.0 }),
+      item(3, 1, 1, SortKey::Opaque { depth: 2.0 }),
+    ]);
+    assert_eq!(sorted_ids(&mut queue), vec![3, 1, 2, 0]);
+  }
+
+  #[test]
+  fn sort_groups_equal_depth_by_pipeline_and_descriptor_set() {
+    let mut queue = queue(&[
+      item(0, 2, 1, SortKey::Opaque { depth: 1.0 }),
+      item(1, 1, 2, SortKey::Opaque { depth: 1.0 }),
+      item(2, 2, 1, SortKey::Opaque { depth: 1.0 }),
+      item(3, 1, 1, SortKey::Opaque { depth: 1.0 }),
+    ]);
+    assert_eq!(sorted_ids(&mut queue), vec![3, 1, 0, 2]);
+  }
+
+  #[test]
+  fn sort_is_stable() {
+    let mut queue = queue(&[
+      item(0, 1, 1, SortKey::Opaque { depth: 1.0 }),
+      item(1, 1, 1, SortKey::Opaque { depth: 1.0 }),
+      item(2, 1, 1, SortKey::Transparent { depth: 1.0 }),
+      item(3, 1, 1, SortKey::Transparent { depth: 1.0 }),
+    ]);
+    assert_eq!(sorted_ids(&mut queue), vec![0, 1, 2, 3]);
+  }
+
+  #[test]
+  fn bind_skips_bound_state() {
+    let mut queue = queue(&[
+      item(0, 1, 1, SortKey::Opaque { depth: 1.0 }),
+      item(1, 2, 1, SortKey::Opaque { depth: 1.0 }),
+      item(2, 1, 1, SortKey::Opaque { depth: 1.0 }),
+      item(3, 1, 2, SortKey::Opaque { depth: 1.0 }),
+    ]);
+    let stats = bind_stats(&mut queue);
+    // Sorted as pipeline 1 with sets 1 and 1, pipeline 1 with set 2, then pipeline 2 with set 1.
+    assert_eq!(stats, RenderQueueStats {
+      pipeline_binds: 2,
+      // The camera set is bound once for the shared pipeline layout.
+      descriptor_set_binds: 1 + 3,
+      vertex_buffer_binds: 1,
+      index_buffer_binds: 1,
+      draws: 4,
+    });
+  }
+
+  #[test]
+  fn bind_resets_line_width_after_pipeline_bind() {
+    let mut bound = BoundState::default();
+    let mut lines = item(0, 1, 1, SortKey::Opaque { depth: 1.0 });
+    lines.line_width = Some(2.0);
+    assert!(bound.bind(&lines).line_width);
+    assert!(!bound.bind(&lines).line_width);
+    bound.bind(&item(1, 2, 1, SortKey::Opaque { depth: 1.0 }));
+    assert!(bound.bind(&lines).line_width);
+  }
+}
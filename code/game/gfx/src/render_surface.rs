@@ -0,0 +1,209 @@
+use std::num::NonZeroU32;
+
+use anyhow::{anyhow, Context, Result};
+use ash::vk::{self, ImageLayout, Semaphore};
+use log::debug;
+
+use vkw::framebuffer::FramebufferCreateError;
+use vkw::prelude::*;
+
+use crate::GfxError;
+use crate::tonemap::TonemapSys;
+
+// Render surface
+
+/// Everything needed to present to a single window surface: the surface, its swapchain, the render pass that writes
+/// swapchain images, a framebuffer per swapchain image, the tonemap pass that post-processes the scene into swapchain
+/// images, semaphores per render state, and surface change handling. Multiple render surfaces can share one [Device],
+/// as long as the device supports presenting to each surface, with each surface presented independently.
+///
+/// A render surface must be manually destroyed with [destroy](Self::destroy), before the device is destroyed.
+pub struct RenderSurface {
+  pub surface: Surface,
+  pub swapchain: Swapchain,
+  /// Render pass that writes to swapchain images. Its only attachment has the surface format, which may differ between
+  /// render surfaces, so pipelines used in this render pass are per render surface.
+  pub render_pass: RenderPass,
+  pub presenter: Presenter,
+  pub surface_change_handler: SurfaceChangeHandler,
+  /// Tonemap pass into swapchain images, with its own pipeline for [render_pass](Self::render_pass), and its own
+  /// operator and gamma, so that each surface can show a different view of the scene.
+  pub tonemap_sys: TonemapSys,
+  semaphores: Vec<RenderSurfaceSemaphores>,
+}
+
+/// Semaphores of a render surface for a single render state.
+#[derive(Copy, Clone, Debug)]
+pub struct RenderSurfaceSemaphores {
+  /// Signalled when a swapchain image was acquired, and waited on before rendering to it.
+  pub image_acquired: Semaphore,
+  /// Signalled when rendering to a swapchain image completed, and waited on before presenting it.
+  pub render_complete: Semaphore,
+}
+
+impl RenderSurface {
+  /// Creates a render surface presenting to `surface`, which it takes ownership of, with a swapchain of `image_count`
+  /// images (clamped to what the surface supports) sized to `extent`, with semaphores for `render_state_count` render
+  /// states. The scene color image must be set on its [tonemap pass](Self::tonemap_sys) before rendering. Fails if the
+  /// present queue of `device` cannot present to `surface`.
+  pub fn new(
+    instance: &Instance,
+    device: &Device,
+    mut surface: Surface,
+    image_count: NonZeroU32,
+    extent: Extent2D,
+    pipeline_cache: PipelineCache,
+    render_state_count: u32,
+  ) -> Result<Self> {
+    let present_supported = unsafe { surface.loader.get_physical_device_surface_support(device.physical_device, device.present_queue_index, surface.wrapped) };
+    if !present_supported {
+      unsafe { surface.destroy(); }
+      return Err(anyhow!("Present queue of the device does not support presenting to the surface"));
+    }
+    let swapchain = {
+      let mut features_query = SwapchainFeaturesQuery::new();
+      features_query.want_image_count(image_count);
+      features_query.want_present_mode(vec![
+        PresentModeKHR::IMMEDIATE,
+        PresentModeKHR::MAILBOX,
+        PresentModeKHR::FIFO_RELAXED,
+        PresentModeKHR::FIFO,
+      ]);
+      Swapchain::new(instance, device, &surface, features_query, extent)
+        .with_context(|| "Failed to create VKW swapchain")?
+    };
+    debug!("{:#?}", &swapchain.features);
+    let render_pass = Self::create_render_pass(device, swapchain.features.surface_format.format)
+      .with_context(|| "Failed to create Vulkan render pass")?;
+    let framebuffers = Self::create_framebuffers(device, &swapchain, render_pass)
+      .with_context(|| "Failed to create Vulkan framebuffer")?;
+    let presenter = Presenter::new(framebuffers)?;
+    let surface_change_handler = SurfaceChangeHandler::new();
+    let tonemap_sys = TonemapSys::new(device, render_pass, pipeline_cache)
+      .with_context(|| "Failed to create tonemap system")?;
+    let semaphores = (0..render_state_count).map(|_| unsafe {
+      Ok(RenderSurfaceSemaphores {
+        image_acquired: device.create_semaphore()?,
+        render_complete: device.create_semaphore()?,
+      })
+    }).collect::<Result<_>>()
+      .with_context(|| "Failed to create render surface semaphores")?;
+    Ok(Self { surface, swapchain, render_pass, presenter, surface_change_handler, tonemap_sys, semaphores })
+  }
+
+  /// Creates a render pass with a single color attachment of `format`, which does not load its previous contents and
+  /// is transitioned for presentation at the end.
+  fn create_render_pass(device: &Device, format: Format) -> Result<RenderPass> {
+    use vk::{AttachmentDescription, AttachmentLoadOp, AttachmentReference, AttachmentStoreOp, SubpassDescription};
+    let attachments = &[
+      AttachmentDescription::builder()
+        .format(format)
+        .samples(SampleCountFlags::TYPE_1)
+        .load_op(AttachmentLoadOp::DONT_CARE)
+        .store_op(AttachmentStoreOp::STORE)
+        .stencil_load_op(AttachmentLoadOp::DONT_CARE)
+        .stencil_store_op(AttachmentStoreOp::DONT_CARE)
+        .initial_layout(ImageLayout::UNDEFINED)
+        .final_layout(ImageLayout::PRESENT_SRC_KHR)
+        .build(),
+    ];
+    let color_attachments = &[
+      AttachmentReference::builder()
+        .attachment(0)
+        .layout(ImageLayout::COLOR_ATTACHMENT_OPTIMAL)
+        .build(),
+    ];
+    let subpasses = &[
+      SubpassDescription::builder()
+        .pipeline_bind_point(PipelineBindPoint::GRAPHICS)
+        .color_attachments(color_attachments)
+        .build(),
+    ];
+    let create_info = vk::RenderPassCreateInfo::builder()
+      .attachments(attachments)
+      .subpasses(subpasses)
+      ;
+    // CORRECTNESS: slices are taken by pointer but are alive until `create_render_pass` is called.
+    Ok(unsafe { device.create_render_pass(&create_info)? })
+  }
+
+  fn create_framebuffers(device: &Device, swapchain: &Swapchain, render_pass: RenderPass) -> Result<Vec<Framebuffer>, FramebufferCreateError> {
+    swapchain.image_views.iter().map(|v| {
+      let attachments = &[*v];
+      let create_info = vk::FramebufferCreateInfo::builder()
+        .render_pass(render_pass)
+        .attachments(attachments)
+        .width(swapchain.extent.width)
+        .height(swapchain.extent.height)
+        .layers(1)
+        ;
+      Ok(unsafe { device.create_framebuffer(&create_info) }?)
+    }).collect()
+  }
+
+  /// Gets the extent of the surface as displayed, which the scene is rendered at. Differs from the
+  /// [image extent](Self::image_extent) when the swapchain is pre-rotated sideways.
+  #[inline]
+  pub fn extent(&self) -> Extent2D { self.swapchain.display_extent() }
+
+  /// Gets the extent of swapchain images, in the natural orientation of the surface.
+  #[inline]
+  pub fn image_extent(&self) -> Extent2D { self.swapchain.extent }
+
+  /// Gets the semaphores for the render state at `render_state_index`.
+  #[inline]
+  pub fn semaphores(&self, render_state_index: u32) -> RenderSurfaceSemaphores { self.semaphores[render_state_index as usize] }
+
+  /// Gets the extent to recreate the swapchain with if the surface was resized or the swapchain became suboptimal
+  /// since the last call, or `None` if it does not need to be recreated.
+  #[inline]
+  pub fn query_surface_change(&mut self) -> Option<Extent2D> {
+    self.surface_change_handler.query_surface_change(self.swapchain.extent)
+  }
+
+  /// Recreates the swapchain and framebuffers with `extent`. The device must be idle, and presentation of the previous
+  /// swapchain must have finished.
+  ///
+  /// The new swapchain may have a different number of images, for which a framebuffer is created each, replacing all
+  /// previous framebuffers. It may also have a different surface format, in which case the render pass and the tonemap
+  /// pipeline are recreated as well.
+  pub unsafe fn recreate(&mut self, device: &Device, extent: Extent2D, pipeline_cache: PipelineCache) -> Result<(), GfxError> {
+    let old_image_count = self.swapchain.features.image_count;
+    let old_format = self.swapchain.features.surface_format.format;
+    self.swapchain.recreate(device, &self.surface, extent)?;
+    let image_count = self.swapchain.features.image_count;
+    if image_count != old_image_count {
+      debug!("Swapchain image count changed from {} to {}", old_image_count, image_count);
+    }
+    let format = self.swapchain.features.surface_format.format;
+    let old_render_pass = if format != old_format {
+      debug!("Swapchain surface format changed from {:?} to {:?}, recreating render pass", old_format, format);
+      let render_pass = Self::create_render_pass(device, format).map_err(GfxError::RenderPassRecreateFail)?;
+      Some(std::mem::replace(&mut self.render_pass, render_pass))
+    } else {
+      None
+    };
+    let framebuffers = Self::create_framebuffers(device, &self.swapchain, self.render_pass)?;
+    self.presenter.recreate(device, framebuffers)?;
+    debug_assert_eq!(self.presenter.image_count(), image_count, "Presenter has image states for a different number of images than the swapchain");
+    // Destroy the old render pass after the framebuffers and the pipeline that were created with it.
+    if let Some(old_render_pass) = old_render_pass {
+      self.tonemap_sys.set_render_pass(device, self.render_pass, pipeline_cache)
+        .map_err(GfxError::TonemapRecreateFail)?;
+      device.destroy_render_pass(old_render_pass);
+    }
+    Ok(())
+  }
+
+  pub unsafe fn destroy(&mut self, device: &Device) {
+    for semaphores in &self.semaphores {
+      device.destroy_semaphore(semaphores.image_acquired);
+      device.destroy_semaphore(semaphores.render_complete);
+    }
+    self.tonemap_sys.destroy(device);
+    self.presenter.destroy(device);
+    device.destroy_render_pass(self.render_pass);
+    self.swapchain.destroy(device);
+    self.surface.destroy();
+  }
+}
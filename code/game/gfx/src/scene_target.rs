@@ -0,0 +1,117 @@
+use anyhow::Result;
+use ash::vk::{self, Extent3D, ImageAspectFlags, ImageLayout, ImageUsageFlags, ImageView, ImageViewType};
+
+use vkw::allocator::ImageAllocation;
+use vkw::prelude::*;
+
+// Scene target
+
+/// Format of the scene color image: linear, with a high dynamic range for tonemapping. Support for rendering to and
+/// sampling from this format is mandatory.
+pub const SCENE_FORMAT: Format = Format::R16G16B16A16_SFLOAT;
+
+/// Offscreen color image that the scene is rendered into, and that post-processing passes sample from. Sized to the
+/// swapchain extent, and recreated along with the swapchain. With dynamic resolution, only a scaled top-left region is
+/// rendered into, so that the render scale can change every frame without recreating the image.
+pub struct SceneTarget {
+  allocation: ImageAllocation,
+  pub view: ImageView,
+  pub framebuffer: Framebuffer,
+  pub extent: Extent2D,
+}
+
+impl SceneTarget {
+  /// Creates the render pass that renders the scene into a [SceneTarget]. The color image is cleared at the start, and
+  /// transitioned for sampling in fragment shaders at the end.
+  pub fn create_render_pass(device: &Device, sample_count: SampleCountFlags) -> Result<RenderPass> {
+    use vk::{AccessFlags, AttachmentDescription, AttachmentLoadOp, AttachmentReference, AttachmentStoreOp, PipelineStageFlags, SubpassDependency, SubpassDescription, SUBPASS_EXTERNAL};
+    let attachments = &[
+      AttachmentDescription::builder()
+        .format(SCENE_FORMAT)
+        .samples(sample_count)
+        .load_op(AttachmentLoadOp::CLEAR)
+        .store_op(AttachmentStoreOp::STORE)
+        .stencil_load_op(AttachmentLoadOp::DONT_CARE)
+        .stencil_store_op(AttachmentStoreOp::DONT_CARE)
+        .initial_layout(ImageLayout::UNDEFINED)
+        .final_layout(ImageLayout::SHADER_READ_ONLY_OPTIMAL)
+        .build(),
+    ];
+    let color_attachments = &[
+      AttachmentReference::builder()
+        .attachment(0)
+        .layout(ImageLayout::COLOR_ATTACHMENT_OPTIMAL)
+        .build(),
+    ];
+    let subpasses = &[
+      SubpassDescription::builder()
+        .pipeline_bind_point(PipelineBindPoint::GRAPHICS)
+        .color_attachments(color_attachments)
+        .build(),
+    ];
+    let dependencies = &[
+      // Wait for post-processing of the previous frame to stop reading before writing.
+      SubpassDependency::builder()
+        .src_subpass(SUBPASS_EXTERNAL)
+        .dst_subpass(0)
+        .src_stage_mask(PipelineStageFlags::FRAGMENT_SHADER)
+        .dst_stage_mask(PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT)
+        .src_access_mask(AccessFlags::empty())
+        .dst_access_mask(AccessFlags::COLOR_ATTACHMENT_WRITE)
+        .build(),
+      // Make writes visible to post-processing of this frame.
+      SubpassDependency::builder()
+        .src_subpass(0)
+        .dst_subpass(SUBPASS_EXTERNAL)
+        .src_stage_mask(PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT)
+        .dst_stage_mask(PipelineStageFlags::FRAGMENT_SHADER)
+        .src_access_mask(AccessFlags::COLOR_ATTACHMENT_WRITE)
+        .dst_access_mask(AccessFlags::SHADER_READ)
+        .build(),
+    ];
+    let create_info = vk::RenderPassCreateInfo::builder()
+      .attachments(attachments)
+      .subpasses(subpasses)
+      .dependencies(dependencies)
+      ;
+    // CORRECTNESS: slices are taken by pointer but are alive until `create_render_pass` is called.
+    Ok(unsafe { device.create_render_pass(&create_info)? })
+  }
+
+  pub fn new(device: &Device, allocator: &Allocator, render_pass: RenderPass, sample_count: SampleCountFlags, extent: Extent2D) -> Result<Self> {
+    unsafe {
+      let image_info = vk::ImageCreateInfo::builder()
+        .image_type(vk::ImageType::TYPE_2D)
+        .format(SCENE_FORMAT)
+        .extent(Extent3D { width: extent.width, height: extent.height, depth: 1 })
+        .mip_levels(1)
+        .array_layers(1)
+        .samples(sample_count)
+        .tiling(ImageTiling::OPTIMAL)
+        .usage(ImageUsageFlags::COLOR_ATTACHMENT | ImageUsageFlags::SAMPLED)
+        .sharing_mode(vk::SharingMode::EXCLUSIVE)
+        .initial_layout(ImageLayout::UNDEFINED)
+        ;
+      let allocation = allocator.create_image(&image_info, MemoryUsage::GpuOnly, vk_mem::AllocationCreateFlags::NONE)?;
+      let view = device.create_image_view(allocation.image, SCENE_FORMAT, ImageViewType::TYPE_2D, ImageAspectFlags::COLOR, 1)?;
+      let attachments = &[view];
+      let create_info = vk::FramebufferCreateInfo::builder()
+        .render_pass(render_pass)
+        .attachments(attachments)
+        .width(extent.width)
+        .height(extent.height)
+        .layers(1)
+        ;
+      let framebuffer = device.create_framebuffer(&create_info)?;
+      Ok(Self { allocation, view, framebuffer, extent })
+    }
+  }
+
+  pub fn destroy(&self, device: &Device, allocator: &Allocator) {
+    unsafe {
+      device.destroy_framebuffer(self.framebuffer);
+      device.destroy_image_view(self.view);
+      self.allocation.destroy(allocator);
+    }
+  }
+}
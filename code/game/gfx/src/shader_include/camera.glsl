@@ -0,0 +1,8 @@
+#ifndef CAMERA_GLSL
+#define CAMERA_GLSL
+
+// Per-frame camera and frame uniform data, shared by all renderers; must match camera_uniform.rs
+layout(set = 1, binding = 0) uniform CameraUniformData { mat4 viewProjection; vec2 viewport; } camera;
+layout(set = 1, binding = 1) uniform FrameUniformData { float time; uint frame; } frameData;
+
+#endif
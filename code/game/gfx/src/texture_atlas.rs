@@ -0,0 +1,221 @@
+use anyhow::{bail, Result};
+
+use math::prelude::Vec2;
+use util::idx_assigner::{self, IdxAssigner};
+use util::image::{Components, Dimensions, ImageData};
+
+// Sprite index
+
+#[derive(Default, Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug)]
+pub struct SpriteIdx(u16);
+
+// Texture atlas builder
+
+/// Packs sprites of differing sizes into a single image of a fixed width, using shelf packing: sprites are sorted by
+/// height and placed left to right on shelves, starting a new shelf when a sprite does not fit on the current one.
+pub struct TextureAtlasBuilder {
+  width: u32,
+  assigner: IdxAssigner<SpriteIdx, u16>,
+  data: Vec<ImageData>,
+}
+
+impl TextureAtlasBuilder {
+  pub fn new(width: u32) -> Self {
+    Self { width, assigner: IdxAssigner::new(), data: Vec::new() }
+  }
+
+  /// Adds a sprite, converting `data` to 4 components if needed.
+  pub fn add_sprite(&mut self, data: ImageData) -> SpriteIdx {
+    let data = if data.dimensions.components != Components::Components4 { data.to_rgba() } else { data };
+    let idx = self.assigner.assign_item();
+    self.data.push(data);
+    idx
+  }
+
+  pub fn build(self) -> Result<TextureAtlas> {
+    let width = self.width;
+    let mut order: Vec<usize> = (0..self.data.len()).collect();
+    order.sort_by(|a, b| self.data[*b].dimensions.height.cmp(&self.data[*a].dimensions.height));
+
+    // Place sprites on shelves.
+    let mut rects = vec![AtlasRect::default(); self.data.len()];
+    let mut shelf_x = 0;
+    let mut shelf_y = 0;
+    let mut shelf_height = 0;
+    for i in order {
+      let Dimensions { width: sprite_width, height: sprite_height, .. } = self.data[i].dimensions;
+      if sprite_width > width {
+        bail!("Sprite {} of width {} does not fit in texture atlas of width {}", i, sprite_width, width);
+      }
+      if shelf_x + sprite_width > width {
+        shelf_y += shelf_height;
+        shelf_x = 0;
+        shelf_height = 0;
+      }
+      rects[i] = AtlasRect { x: shelf_x, y: shelf_y, width: sprite_width, height: sprite_height };
+      shelf_x += sprite_width;
+      shelf_height = shelf_height.max(sprite_height);
+    }
+    let height = (shelf_y + shelf_height).max(1);
+
+    // Copy sprites into atlas image.
+    let dimensions = Dimensions::new(width, height, Components::Components4);
+    let mut image = ImageData::from_vec(dimensions, vec![0; dimensions.num_bytes()]);
+    let row_stride = width as usize * 4;
+    for (sprite, rect) in self.data.iter().zip(rects.iter()) {
+      let sprite_row_size = rect.width as usize * 4;
+      let src = sprite.data_slice();
+      let dst = image.data_slice_mut();
+      for row in 0..rect.height as usize {
+        let src_start = row * sprite_row_size;
+        let dst_start = (rect.y as usize + row) * row_stride + rect.x as usize * 4;
+        dst[dst_start..dst_start + sprite_row_size].copy_from_slice(&src[src_start..src_start + sprite_row_size]);
+      }
+    }
+
+    Ok(TextureAtlas { image, rects })
+  }
+}
+
+// Texture atlas
+
+/// Rectangle of a sprite in a texture atlas, in pixels relative to the top-left of the atlas.
+#[derive(Default, Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub struct AtlasRect {
+  pub x: u32,
+  pub y: u32,
+  pub width: u32,
+  pub height: u32,
+}
+
+pub struct TextureAtlas {
+  pub image: ImageData,
+  rects: Vec<AtlasRect>,
+}
+
+impl TextureAtlas {
+  #[inline]
+  pub fn rect(&self, idx: SpriteIdx) -> AtlasRect { self.rects[Self::index(idx)] }
+
+  /// Returns the texture coordinates of sprite `idx` in the order of the vertices of a grid quad: bottom-left,
+  /// bottom-right, top-left, top-right.
+  pub fn uv_rect(&self, idx: SpriteIdx) -> [Vec2; 4] {
+    let AtlasRect { x, y, width, height } = self.rect(idx);
+    let (atlas_width, atlas_height) = (self.image.dimensions.width as f32, self.image.dimensions.height as f32);
+    let u0 = x as f32 / atlas_width;
+    let u1 = (x + width) as f32 / atlas_width;
+    let v0 = y as f32 / atlas_height;
+    let v1 = (y + height) as f32 / atlas_height;
+    [Vec2::new(u0, v1), Vec2::new(u1, v1), Vec2::new(u0, v0), Vec2::new(u1, v0)]
+  }
+
+  #[inline]
+  pub fn sprite_count(&self) -> usize { self.rects.len() }
+
+  #[inline]
+  fn index(idx: SpriteIdx) -> usize {
+    use util::idx_assigner::Item;
+    // Assigned indices start at 1.
+    (idx.into_idx() as usize).checked_sub(1).expect("invalid SpriteIdx")
+  }
+}
+
+// Implementations
+
+impl idx_assigner::Item for SpriteIdx {
+  type Idx = u16;
+
+  #[inline]
+  fn new(index: Self::Idx) -> Self {
+    Self(index)
+  }
+
+  #[inline]
+  fn into_idx(self) -> Self::Idx {
+    self.0
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use util::image::{Components, Dimensions, ImageData};
+
+  use super::{AtlasRect, SpriteIdx, TextureAtlas, TextureAtlasBuilder};
+
+  fn sprite(width: u32, height: u32, value: u8) -> ImageData {
+    let dimensions = Dimensions::new(width, height, Components::Components4);
+    ImageData::from_vec(dimensions, vec![value; dimensions.num_bytes()])
+  }
+
+  fn overlaps(a: &AtlasRect, b: &AtlasRect) -> bool {
+    a.x < b.x + b.width && b.x < a.x + a.width && a.y < b.y + b.height && b.y < a.y + a.height
+  }
+
+  fn build_atlas() -> (TextureAtlas, Vec<SpriteIdx>) {
+    let mut builder = TextureAtlasBuilder::new(32);
+    let sprites = vec![
+      builder.add_sprite(sprite(16, 8, 1)),
+      builder.add_sprite(sprite(20, 16, 2)),
+      builder.add_sprite(sprite(8, 4, 3)),
+    ];
+    (builder.build().unwrap(), sprites)
+  }
+
+  #[test]
+  fn sprites_do_not_overlap_and_stay_inside_atlas() {
+    let (atlas, sprites) = build_atlas();
+    assert_eq!(atlas.sprite_count(), 3);
+    let (atlas_width, atlas_height) = (atlas.image.dimensions.width, atlas.image.dimensions.height);
+    let rects: Vec<AtlasRect> = sprites.iter().map(|idx| atlas.rect(*idx)).collect();
+    assert_eq!(rects.iter().map(|r| (r.width, r.height)).collect::<Vec<_>>(), vec![(16, 8), (20, 16), (8, 4)]);
+    for (i, a) in rects.iter().enumerate() {
+      assert!(a.x + a.width <= atlas_width && a.y + a.height <= atlas_height, "Sprite {:?} outside of atlas", a);
+      for b in &rects[i + 1..] {
+        assert!(!overlaps(a, b), "Sprites {:?} and {:?} overlap", a, b);
+      }
+    }
+  }
+
+  #[test]
+  fn sprites_are_copied_into_their_rects() {
+    let (atlas, sprites) = build_atlas();
+    let row_stride = atlas.image.dimensions.width as usize * 4;
+    for (idx, value) in sprites.iter().zip(1u8..) {
+      let AtlasRect { x, y, width, height } = atlas.rect(*idx);
+      for row in y..y + height {
+        let start = row as usize * row_stride + x as usize * 4;
+        assert!(atlas.image.data_slice()[start..start + width as usize * 4].iter().all(|b| *b == value));
+      }
+    }
+  }
+
+  #[test]
+  fn uv_rect_maps_to_rect() {
+    let (atlas, sprites) = build_atlas();
+    let (atlas_width, atlas_height) = (atlas.image.dimensions.width as f32, atlas.image.dimensions.height as f32);
+    for idx in sprites {
+      let AtlasRect { x, y, width, height } = atlas.rect(idx);
+      let [bottom_left, bottom_right, top_left, top_right] = atlas.uv_rect(idx);
+      let (u0, u1) = (x as f32 / atlas_width, (x + width) as f32 / atlas_width);
+      let (v0, v1) = (y as f32 / atlas_height, (y + height) as f32 / atlas_height);
+      assert_eq!((bottom_left.x, bottom_left.y), (u0, v1));
+      assert_eq!((bottom_right.x, bottom_right.y), (u1, v1));
+      assert_eq!((top_left.x, top_left.y), (u0, v0));
+      assert_eq!((top_right.x, top_right.y), (u1, v0));
+    }
+  }
+
+  #[test]
+  fn build_fails_for_sprite_wider_than_atlas() {
+    let mut builder = TextureAtlasBuilder::new(8);
+    builder.add_sprite(sprite(9, 1, 0));
+    assert!(builder.build().is_err());
+  }
+
+  #[test]
+  #[should_panic(expected = "invalid SpriteIdx")]
+  fn rect_of_default_sprite_idx_panics() {
+    let (atlas, _) = build_atlas();
+    atlas.rect(SpriteIdx::default());
+  }
+}
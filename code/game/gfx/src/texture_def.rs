@@ -1,8 +1,12 @@
-use anyhow::Result;
-use ash::vk::ImageLayout;
+use std::fs;
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+use ash::vk::{Filter, ImageLayout, ImageView, Sampler};
 
 use util::idx_assigner::{self, IdxAssigner};
-use util::image::ImageData;
+use util::image::{Components, Dimensions, ImageData};
+use vkw::image::sampler::SamplerLod;
 use vkw::prelude::*;
 
 // Texture index
@@ -10,44 +14,90 @@ use vkw::prelude::*;
 #[derive(Default, Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug)]
 pub struct TextureIdx(u16);
 
+impl TextureIdx {
+  /// Layer of this texture in the texture array. Assigned indices start at 1, so layer 0 holds the first texture.
+  #[inline]
+  pub fn layer(self) -> u32 { self.0.saturating_sub(1) as u32 }
+}
+
+// Texture filter
+
+/// How a texture is filtered when sampled. Each filter has its own sampler in the descriptor set of a [TextureDef], at
+/// the array element of its value.
+#[repr(u32)]
+#[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug)]
+pub enum TextureFilter {
+  Nearest = 0,
+  Linear = 1,
+}
+
+impl TextureFilter {
+  /// Number of texture filters, and thus samplers in the descriptor set of a [TextureDef].
+  pub const COUNT: u32 = 2;
+}
+
+impl Default for TextureFilter {
+  #[inline]
+  fn default() -> Self { TextureFilter::Nearest }
+}
+
 // Texture def builder
 
 pub struct TextureDefBuilder {
   assigner: IdxAssigner<TextureIdx, u16>,
   data: Vec<ImageData>,
+  filters: Vec<TextureFilter>,
+  sampler_lod: Option<SamplerLod>,
 }
 
 impl TextureDefBuilder {
   pub fn new() -> Self {
-    Self { assigner: IdxAssigner::new(), data: Vec::new() }
+    Self { assigner: IdxAssigner::new(), data: Vec::new(), filters: Vec::new(), sampler_lod: None }
   }
 
+  /// Sets the level of detail settings of the samplers. If not set, the graphics system picks settings suitable for
+  /// rendering grids.
+  pub fn set_sampler_lod(&mut self, sampler_lod: SamplerLod) {
+    self.sampler_lod = Some(sampler_lod);
+  }
+
+  #[inline]
+  pub fn sampler_lod(&self) -> Option<SamplerLod> { self.sampler_lod }
 
+  /// Adds a texture with the default (nearest) filter, converting `data` to 4 components if needed.
   pub fn add_texture(&mut self, data: ImageData) -> TextureIdx {
+    self.add_texture_with_filter(data, TextureFilter::default())
+  }
+
+  /// Adds a texture that is sampled with `filter`, converting `data` to 4 components if needed.
+  pub fn add_texture_with_filter(&mut self, data: ImageData, filter: TextureFilter) -> TextureIdx {
+    let data = if data.dimensions.components != Components::Components4 { data.to_rgba() } else { data };
     let idx = self.assigner.assign_item();
     self.data.push(data);
+    self.filters.push(filter);
     idx
   }
 
   pub unsafe fn build(self, device: &Device, allocator: &Allocator, transient_command_pool: CommandPool) -> Result<TextureDef> {
     let format = device.find_suitable_format(&[Format::R8G8B8A8_UNORM], ImageTiling::OPTIMAL, FormatFeatureFlags::SAMPLED_IMAGE | FormatFeatureFlags::TRANSFER_DST)?;
+    let dimensions = self.data.first().map(|d| d.dimensions);
+    let layer_count = self.data.len() as u32;
     let texture_array = device.allocate_record_resources_submit_wait(allocator, transient_command_pool, |command_buffer| {
       Ok(std::iter::once(device.allocate_record_copy_texture_array(&self.data, allocator, format, command_buffer)?))
     })?.pop().unwrap();
 
-    let descriptor_set_layout_bindings = &[descriptor_set::sampler_layout_binding(0, 1)];
+    let sampler_lod = self.sampler_lod.unwrap_or_default();
+    let samplers = create_samplers(device, sampler_lod)?;
+
+    let descriptor_set_layout_bindings = &[descriptor_set::sampler_layout_binding(0, TextureFilter::COUNT)];
     let descriptor_set_layout_flags = &[];
     let descriptor_set_layout = device.create_descriptor_set_layout(descriptor_set_layout_bindings, descriptor_set_layout_flags)?;
 
-    let descriptor_pool = device.create_descriptor_pool(1, &[descriptor_set::sampler_pool_size(1)])?;
+    let descriptor_pool = device.create_descriptor_pool(1, &[descriptor_set::sampler_pool_size(TextureFilter::COUNT)], false)?;
 
     let descriptor_set = device.allocate_descriptor_set(descriptor_pool, descriptor_set_layout)?;
-    let mut write_builder = WriteDescriptorSetBuilder::new(descriptor_set, 0, 0, DescriptorType::COMBINED_IMAGE_SAMPLER);
-    write_builder = write_builder.add_image_info(texture_array.sampler, texture_array.view, ImageLayout::SHADER_READ_ONLY_OPTIMAL);
-    DescriptorSetUpdateBuilder::new()
-      .add_write(write_builder)
-      .do_update(device);
-    Ok(TextureDef::new(texture_array, descriptor_set_layout, descriptor_pool, descriptor_set))
+    write_samplers(device, descriptor_set, texture_array.view, &samplers);
+    Ok(TextureDef::new(texture_array, samplers, sampler_lod, self.filters, format, dimensions.unwrap(), layer_count, descriptor_set_layout, descriptor_pool, descriptor_set))
   }
 }
 
@@ -55,6 +105,13 @@ impl TextureDefBuilder {
 
 pub struct TextureDef {
   pub texture_array: Texture,
+  /// Sampler per [TextureFilter], indexed by its value.
+  pub samplers: [Sampler; TextureFilter::COUNT as usize],
+  sampler_lod: SamplerLod,
+  filters: Vec<TextureFilter>,
+  pub format: Format,
+  pub dimensions: Dimensions,
+  pub layer_count: u32,
   pub descriptor_set_layout: DescriptorSetLayout,
   pub descriptor_pool: DescriptorPool,
   pub descriptor_set: DescriptorSet,
@@ -63,25 +120,124 @@ pub struct TextureDef {
 impl TextureDef {
   fn new(
     texture_array: Texture,
+    samplers: [Sampler; TextureFilter::COUNT as usize],
+    sampler_lod: SamplerLod,
+    filters: Vec<TextureFilter>,
+    format: Format,
+    dimensions: Dimensions,
+    layer_count: u32,
     descriptor_set_layout: DescriptorSetLayout,
     descriptor_pool: DescriptorPool,
     descriptor_set: DescriptorSet,
   ) -> Self {
     Self {
       texture_array,
+      samplers,
+      sampler_lod,
+      filters,
+      format,
+      dimensions,
+      layer_count,
       descriptor_set_layout,
       descriptor_pool,
       descriptor_set,
     }
   }
 
+  /// Gets the filter of texture `texture_idx`, or the default filter if it does not exist.
+  #[inline]
+  pub fn filter(&self, texture_idx: TextureIdx) -> TextureFilter {
+    self.filters.get(texture_idx.layer() as usize).copied().unwrap_or_default()
+  }
+
+  #[inline]
+  pub fn sampler_lod(&self) -> SamplerLod { self.sampler_lod }
+
+  /// Recreates the samplers with level of detail settings `sampler_lod`. Waits until the device is idle before
+  /// replacing them, as in-flight frames may still be using them.
+  pub unsafe fn set_sampler_lod(&mut self, device: &Device, sampler_lod: SamplerLod) -> Result<()> {
+    device.device_wait_idle()
+      .with_context(|| "Failed to wait for device idle before recreating samplers")?;
+    let samplers = create_samplers(device, sampler_lod)?;
+    write_samplers(device, self.descriptor_set, self.texture_array.view, &samplers);
+    for sampler in self.samplers.iter() {
+      device.destroy_sampler(*sampler);
+    }
+    self.samplers = samplers;
+    self.sampler_lod = sampler_lod;
+    Ok(())
+  }
+
+  /// Decodes the image at `path` and uploads it into the layer of `texture_idx`, replacing the previous texture. Waits
+  /// until the device is idle before overwriting, as in-flight frames may still be sampling the texture array.
+  pub unsafe fn reload_texture(
+    &self,
+    device: &Device,
+    allocator: &Allocator,
+    transient_command_pool: CommandPool,
+    texture_idx: TextureIdx,
+    path: &Path,
+  ) -> Result<()> {
+    let bytes = fs::read(path)
+      .with_context(|| format!("Failed to read texture file '{}'", path.display()))?;
+    let data = ImageData::from_encoded(&bytes, Some(Components::Components4))
+      .with_context(|| format!("Failed to decode texture file '{}'", path.display()))?;
+    self.reload_texture_from_data(device, allocator, transient_command_pool, texture_idx, &data)
+  }
+
+  /// Uploads `data` into the layer of `texture_idx`, replacing the previous texture. Waits until the device is idle
+  /// before overwriting, as in-flight frames may still be sampling the texture array.
+  pub unsafe fn reload_texture_from_data(
+    &self,
+    device: &Device,
+    allocator: &Allocator,
+    transient_command_pool: CommandPool,
+    texture_idx: TextureIdx,
+    data: &ImageData,
+  ) -> Result<()> {
+    if data.dimensions != self.dimensions {
+      bail!("Cannot reload texture {:?}; dimensions {:?} differ from texture array dimensions {:?}", texture_idx, data.dimensions, self.dimensions);
+    }
+    device.device_wait_idle()
+      .with_context(|| "Failed to wait for device idle before reloading texture")?;
+    let image = self.texture_array.allocation.image;
+    device.allocate_record_resources_submit_wait(allocator, transient_command_pool, |command_buffer| {
+      Ok(std::iter::once(device.allocate_record_copy_texture_array_layer(data, image, texture_idx.layer(), self.layer_count, allocator, self.format, command_buffer)?))
+    }).with_context(|| format!("Failed to reload texture {:?}", texture_idx))?;
+    Ok(())
+  }
+
   pub unsafe fn destroy(&self, device: &Device, allocator: &Allocator) {
     device.destroy_descriptor_pool(self.descriptor_pool);
     device.destroy_descriptor_set_layout(self.descriptor_set_layout);
+    for sampler in self.samplers.iter() {
+      device.destroy_sampler(*sampler);
+    }
     self.texture_array.destroy(device, allocator);
   }
 }
 
+// Samplers
+
+unsafe fn create_samplers(device: &Device, sampler_lod: SamplerLod) -> Result<[Sampler; TextureFilter::COUNT as usize]> {
+  let nearest = device.create_filtered_sampler(Filter::NEAREST, sampler_lod)?;
+  let linear = match device.create_filtered_sampler(Filter::LINEAR, sampler_lod) {
+    Ok(linear) => linear,
+    Err(e) => {
+      device.destroy_sampler(nearest);
+      return Err(e.into());
+    }
+  };
+  Ok([nearest, linear])
+}
+
+unsafe fn write_samplers(device: &Device, descriptor_set: DescriptorSet, view: ImageView, samplers: &[Sampler]) {
+  let images: Vec<_> = samplers.iter().map(|sampler| (*sampler, view, ImageLayout::SHADER_READ_ONLY_OPTIMAL)).collect();
+  DescriptorSetUpdateBuilder::new()
+    .add_image_array_write(descriptor_set, 0, 0, &images)
+    .do_update(device);
+}
+
 // Implementations
 
 impl idx_assigner::Item for TextureIdx {
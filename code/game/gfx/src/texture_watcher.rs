@@ -0,0 +1,56 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver};
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use notify::{DebouncedEvent, RecommendedWatcher, RecursiveMode, Watcher};
+
+use crate::texture_def::TextureIdx;
+
+/// Watches texture files for changes, such that textures can be reloaded with [TextureDef::reload_texture] while the
+/// game is running.
+///
+/// [TextureDef::reload_texture]: crate::texture_def::TextureDef::reload_texture
+pub struct TextureWatcher {
+  watcher: RecommendedWatcher,
+  event_rx: Receiver<DebouncedEvent>,
+  textures: HashMap<PathBuf, TextureIdx>,
+}
+
+impl TextureWatcher {
+  pub fn new() -> Result<Self> {
+    let (event_tx, event_rx) = channel();
+    let watcher = notify::watcher(event_tx, Duration::from_millis(200))
+      .with_context(|| "Failed to create file watcher")?;
+    Ok(Self { watcher, event_rx, textures: HashMap::new() })
+  }
+
+
+  pub fn watch<P: AsRef<Path>>(&mut self, texture_idx: TextureIdx, path: P) -> Result<()> {
+    let path = path.as_ref();
+    let path = path.canonicalize()
+      .with_context(|| format!("Failed to canonicalize texture file path '{}'", path.display()))?;
+    self.watcher.watch(&path, RecursiveMode::NonRecursive)
+      .with_context(|| format!("Failed to watch texture file '{}'", path.display()))?;
+    self.textures.insert(path, texture_idx);
+    Ok(())
+  }
+
+  /// Returns the textures whose files have changed since the last call, without blocking.
+  pub fn changed_textures(&self) -> Vec<(TextureIdx, PathBuf)> {
+    let mut changed: Vec<(TextureIdx, PathBuf)> = Vec::new();
+    for event in self.event_rx.try_iter() {
+      let path = match event {
+        DebouncedEvent::Create(path) | DebouncedEvent::Write(path) | DebouncedEvent::Rename(_, path) => path,
+        _ => continue,
+      };
+      if let Some(texture_idx) = self.textures.get(&path) {
+        if !changed.iter().any(|(idx, _)| idx == texture_idx) {
+          changed.push((*texture_idx, path));
+        }
+      }
+    }
+    changed
+  }
+}
@@ -0,0 +1,161 @@
+use std::mem::size_of;
+
+use anyhow::Result;
+use ash::version::DeviceV1_0;
+use ash::vk::{self, ImageLayout, ImageView, Sampler};
+
+use vkw::image::sampler::SamplerLod;
+use vkw::prelude::*;
+
+use crate::fullscreen_pass::FullscreenPass;
+
+// Tonemap operator
+
+/// Operator that maps linear scene colors with a high dynamic range to displayable colors.
+#[repr(u32)]
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum TonemapOperator {
+  /// Clamps colors, leaving colors in range untouched.
+  Linear = 0,
+  Reinhard = 1,
+  /// Curve fit of the ACES filmic tonemapping curve.
+  AcesApprox = 2,
+}
+
+impl TonemapOperator {
+  /// Gets the operator after this one, wrapping around, for cycling through operators.
+  pub fn next(self) -> Self {
+    match self {
+      TonemapOperator::Linear => TonemapOperator::Reinhard,
+      TonemapOperator::Reinhard => TonemapOperator::AcesApprox,
+      TonemapOperator::AcesApprox => TonemapOperator::Linear,
+    }
+  }
+}
+
+impl Default for TonemapOperator {
+  fn default() -> Self { TonemapOperator::Linear }
+}
+
+// Tonemap system
+
+/// Final post-processing pass that samples the scene color image, applies a [TonemapOperator] and gamma encoding, and
+/// writes the result to the swapchain image.
+pub struct TonemapSys {
+  frag_shader: ShaderModule,
+  descriptor_set_layout: DescriptorSetLayout,
+  pass: FullscreenPass,
+  sampler: Sampler,
+  descriptor_pool: DescriptorPool,
+  descriptor_set: DescriptorSet,
+  operator: TonemapOperator,
+  gamma: f32,
+}
+
+impl TonemapSys {
+  /// Creates the tonemap pass for subpass 0 of `render_pass`. The scene color image to sample must be set with
+  /// [set_scene_view](Self::set_scene_view) before rendering.
+  pub fn new(device: &Device, render_pass: RenderPass, pipeline_cache: PipelineCache) -> Result<Self> {
+    unsafe {
+      // Own the shader and layout until the system is assembled, so that they are destroyed if a later step fails.
+      let frag_shader = device.owned(device.create_shader_module(include_bytes!("../../../../../target/shader/tonemap/tonemap.frag.spv"))?);
+      let descriptor_set_layout = device.owned(FullscreenPass::create_scene_descriptor_set_layout(device)?);
+      let pass = FullscreenPass::new(device, render_pass, pipeline_cache, *frag_shader, &[*descriptor_set_layout], &[TonemapUniformData::push_constant_range()])?;
+      // Linear filtering for upscaling with dynamic resolution; at full resolution, UVs hit texel centers exactly.
+      let sampler = device.create_filtered_sampler(vk::Filter::LINEAR, SamplerLod::default())?;
+      let descriptor_pool = device.create_descriptor_pool(1, &[descriptor_set::sampler_pool_size(1)], false)?;
+      let descriptor_set = device.allocate_descriptor_set(descriptor_pool, *descriptor_set_layout)?;
+      Ok(Self {
+        frag_shader: frag_shader.into_raw(),
+        descriptor_set_layout: descriptor_set_layout.into_raw(),
+        pass,
+        sampler,
+        descriptor_pool,
+        descriptor_set,
+        operator: TonemapOperator::default(),
+        gamma: 1.0,
+      })
+    }
+  }
+
+  /// Points the pass to a new scene color image, for example after it was recreated. The pass must not be in use by
+  /// any frame in flight.
+  pub unsafe fn set_scene_view(&self, device: &Device, scene_view: ImageView) {
+    DescriptorSetUpdateBuilder::new()
+      .add_image_array_write(self.descriptor_set, 0, 0, &[(self.sampler, scene_view, ImageLayout::SHADER_READ_ONLY_OPTIMAL)])
+      .do_update(device);
+  }
+
+  /// Recreates the pass for subpass 0 of `render_pass`, for example after the render pass was recreated for a different
+  /// surface format. The pass must not be in use by any frame in flight.
+  pub unsafe fn set_render_pass(&mut self, device: &Device, render_pass: RenderPass, pipeline_cache: PipelineCache) -> Result<()> {
+    let pass = FullscreenPass::new(device, render_pass, pipeline_cache, self.frag_shader, &[self.descriptor_set_layout], &[TonemapUniformData::push_constant_range()])?;
+    std::mem::replace(&mut self.pass, pass).destroy(device);
+    Ok(())
+  }
+
+  #[inline]
+  pub fn operator(&self) -> TonemapOperator { self.operator }
+
+  #[inline]
+  pub fn set_operator(&mut self, operator: TonemapOperator) { self.operator = operator; }
+
+  #[inline]
+  pub fn gamma(&self) -> f32 { self.gamma }
+
+  /// Sets the gamma to encode output colors with. Defaults to 1.0, as textures are sampled from UNORM images and are
+  /// thus already gamma encoded. Values that are not positive are ignored.
+  pub fn set_gamma(&mut self, gamma: f32) {
+    if gamma > 0.0 {
+      self.gamma = gamma;
+    }
+  }
+
+  /// Records the tonemap pass into `command_buffer`, which must be inside subpass 0 of the render pass that this system
+  /// was created with, with dynamic viewport and scissor state set. Only the top-left region of the scene color image,
+  /// scaled by `scene_uv_scale` in each dimension, is sampled and upscaled to the output, for dynamic resolution. The
+  /// output is rotated clockwise by `pre_rotation` degrees (0, 90, 180, or 270), for pre-rotated swapchains.
+  pub unsafe fn render(&self, device: &Device, command_buffer: CommandBuffer, scene_uv_scale: [f32; 2], pre_rotation: u32) {
+    let uniform_data = TonemapUniformData {
+      operator: self.operator as u32,
+      gamma: self.gamma,
+      scene_uv_scale,
+      quarter_turns: (pre_rotation / 90) % 4,
+    };
+    device.cmd_push_constants(command_buffer, self.pass.pipeline_layout(), ShaderStageFlags::FRAGMENT, 0, uniform_data.as_bytes());
+    self.pass.draw(device, command_buffer, self.descriptor_set);
+  }
+
+  pub fn destroy(&self, device: &Device) {
+    unsafe {
+      device.destroy_descriptor_pool(self.descriptor_pool);
+      device.destroy_sampler(self.sampler);
+      self.pass.destroy(device);
+      device.destroy_descriptor_set_layout(self.descriptor_set_layout);
+      device.destroy_shader_module(self.frag_shader);
+    }
+  }
+}
+
+// Tonemap uniform data (push constant, mutable)
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+struct TonemapUniformData {
+  operator: u32,
+  gamma: f32,
+  scene_uv_scale: [f32; 2],
+  quarter_turns: u32,
+}
+
+impl TonemapUniformData {
+  pub fn push_constant_range() -> PushConstantRange {
+    push_constant::fragment_range(size_of::<Self>() as u32, 0)
+  }
+
+  pub unsafe fn as_bytes(&self) -> &[u8] {
+    let ptr = self as *const Self;
+    let bytes_ptr = ptr as *const u8;
+    std::slice::from_raw_parts(bytes_ptr, size_of::<Self>())
+  }
+}
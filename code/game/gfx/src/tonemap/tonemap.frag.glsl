@@ -0,0 +1,58 @@
+#version 450
+#extension GL_ARB_separate_shader_objects : enable
+
+// Inputs
+layout(location = 0) in vec2 frgUV;
+/// Scene color image, in linear color space with a high dynamic range
+layout(set = 0, binding = 0) uniform sampler2D scene;
+/// Tonemap operator (0 = linear, 1 = Reinhard, 2 = ACES approximation), gamma to encode the output with, scale of the
+/// region of the scene image that was rendered into, for dynamic resolution, and number of clockwise quarter turns
+/// that the output is rotated by, for pre-rotated swapchains
+layout(push_constant) uniform TonemapUniformData { uint tonemapOperator; float gamma; vec2 sceneUVScale; uint quarterTurns; } ud;
+
+// Outputs
+/// Color
+layout(location = 0) out vec4 outCol;
+
+vec3 reinhard(vec3 color) {
+  return color / (1.0 + color);
+}
+
+/// Narkowicz's curve fit of the ACES filmic tonemapping curve
+vec3 acesApprox(vec3 color) {
+  const float a = 2.51;
+  const float b = 0.03;
+  const float c = 2.43;
+  const float d = 0.59;
+  const float e = 0.14;
+  return (color * (a * color + b)) / (color * (c * color + d) + e);
+}
+
+/// Maps UV `uv` of the output image to the UV of the scene as displayed, undoing a clockwise rotation of the scene by
+/// `quarterTurns` quarter turns
+vec2 unrotate(vec2 uv, uint quarterTurns) {
+  if(quarterTurns == 1u) {
+    return vec2(uv.y, 1.0 - uv.x);
+  } else if(quarterTurns == 2u) {
+    return vec2(1.0 - uv.x, 1.0 - uv.y);
+  } else if(quarterTurns == 3u) {
+    return vec2(1.0 - uv.y, uv.x);
+  }
+  return uv;
+}
+
+void main() {
+  vec2 displayUV = unrotate(frgUV, ud.quarterTurns);
+  // Clamp to half a texel inside the rendered region, so that bilinear upscaling does not sample outside of it.
+  vec2 halfTexel = 0.5 / vec2(textureSize(scene, 0));
+  vec2 uv = min(displayUV * ud.sceneUVScale, ud.sceneUVScale - halfTexel);
+  vec4 sceneColor = texture(scene, uv);
+  vec3 color = sceneColor.rgb;
+  if(ud.tonemapOperator == 1u) {
+    color = reinhard(color);
+  } else if(ud.tonemapOperator == 2u) {
+    color = acesApprox(color);
+  }
+  color = pow(clamp(color, 0.0, 1.0), vec3(1.0 / ud.gamma));
+  outCol = vec4(color, sceneColor.a);
+}
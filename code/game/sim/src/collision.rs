@@ -0,0 +1,169 @@
+use std::cmp::Ordering;
+
+use legion::borrow::Ref;
+use legion::prelude::*;
+
+use math::prelude::{Isometry2, Vec2};
+
+use crate::components::WorldTransform;
+
+// Components
+
+#[repr(C)]
+#[derive(Default, Copy, Clone, Debug)]
+/// Component indicating that an entity collides as a box with given half extents, centered on and rotated along with
+/// its [WorldTransform].
+pub struct Collider {
+  pub half_extents: Vec2,
+}
+
+impl Collider {
+  #[inline]
+  pub fn new(half_width: f32, half_height: f32) -> Self { Self { half_extents: Vec2::new(half_width, half_height) } }
+}
+
+// Axis-aligned bounding box
+
+/// Axis-aligned bounding box in world-space.
+#[derive(Default, Copy, Clone, Debug)]
+pub struct Aabb {
+  pub min: Vec2,
+  pub max: Vec2,
+}
+
+impl Aabb {
+  #[inline]
+  pub fn new(min: Vec2, max: Vec2) -> Self { Self { min, max } }
+
+  /// Creates the smallest box that contains `collider` transformed by `isometry`.
+  pub fn from_collider(collider: &Collider, isometry: &Isometry2) -> Self {
+    let x_axis = isometry.rotation * Vec2::unit_x();
+    let y_axis = isometry.rotation * Vec2::unit_y();
+    let half_extents = collider.half_extents;
+    let world_half_extents = Vec2::new(
+      x_axis.x.abs() * half_extents.x + y_axis.x.abs() * half_extents.y,
+      x_axis.y.abs() * half_extents.x + y_axis.y.abs() * half_extents.y,
+    );
+    let center = isometry.translation;
+    Self::new(center - world_half_extents, center + world_half_extents)
+  }
+
+  /// Checks whether this box and `other` overlap. Boxes that only touch are considered overlapping.
+  #[inline]
+  pub fn overlaps(&self, other: &Aabb) -> bool {
+    self.min.x <= other.max.x && other.min.x <= self.max.x && self.min.y <= other.max.y && other.min.y <= self.max.y
+  }
+}
+
+// Broadphase
+
+/// Resource containing the pairs of entities whose [Aabb]s overlapped in the last [update_broadphase], which are
+/// candidates for precise collision checks. Each pair is reported once, in no particular order.
+#[derive(Default, Debug)]
+pub struct BroadphasePairs {
+  pub pairs: Vec<(Entity, Entity)>,
+  aabbs: Vec<(Entity, Aabb)>,
+}
+
+impl BroadphasePairs {
+  #[inline]
+  pub fn iter(&self) -> impl Iterator<Item=&(Entity, Entity)> { self.pairs.iter() }
+
+  #[inline]
+  pub fn len(&self) -> usize { self.pairs.len() }
+
+  #[inline]
+  pub fn is_empty(&self) -> bool { self.pairs.is_empty() }
+}
+
+/// Computes world-space [Aabb]s of all entities with a [Collider] and [WorldTransform], and replaces the pairs in
+/// `broadphase_pairs` with those that overlap, using sort-and-sweep along the x-axis.
+pub fn update_broadphase(world: &World, broadphase_pairs: &mut BroadphasePairs) {
+  let BroadphasePairs { pairs, aabbs } = broadphase_pairs;
+  pairs.clear();
+  aabbs.clear();
+
+  let query = <(Read<Collider>, Read<WorldTransform>)>::query();
+  for i in query.iter_entities(world) {
+    let (entity, (collider, transform)): (_, (Ref<Collider>, Ref<WorldTransform>)) = i;
+    aabbs.push((entity, Aabb::from_collider(&collider, &transform.isometry)));
+  }
+
+  aabbs.sort_unstable_by(|(_, a), (_, b)| a.min.x.partial_cmp(&b.min.x).unwrap_or(Ordering::Equal));
+  for (i, (entity, aabb)) in aabbs.iter().enumerate() {
+    // Boxes after this one start at or after it along the x-axis, so stop at the first one that starts after it ends.
+    for (other_entity, other_aabb) in aabbs[i + 1..].iter().take_while(|(_, other)| other.min.x <= aabb.max.x) {
+      if aabb.overlaps(other_aabb) {
+        pairs.push((*entity, *other_entity));
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::f32::consts::FRAC_PI_2;
+
+  use legion::prelude::*;
+
+  use math::prelude::{Isometry2, Rotor2, Vec2};
+
+  use crate::components::WorldTransform;
+
+  use super::{Aabb, BroadphasePairs, Collider, update_broadphase};
+
+  fn assert_vec2_eq(actual: Vec2, expected: Vec2) {
+    assert!((actual - expected).mag() < 1e-5, "Expected {:?}, got {:?}", expected, actual);
+  }
+
+  #[test]
+  fn aabb_from_rotated_collider() {
+    let isometry = Isometry2::new(Vec2::new(1.0, 2.0), Rotor2::from_angle(FRAC_PI_2));
+    let aabb = Aabb::from_collider(&Collider::new(2.0, 0.5), &isometry);
+    assert_vec2_eq(aabb.min, Vec2::new(0.5, 0.0));
+    assert_vec2_eq(aabb.max, Vec2::new(1.5, 4.0));
+  }
+
+  #[test]
+  fn aabb_overlaps_when_touching() {
+    let a = Aabb::new(Vec2::new(0.0, 0.0), Vec2::new(1.0, 1.0));
+    let b = Aabb::new(Vec2::new(1.0, 0.5), Vec2::new(2.0, 1.5));
+    let c = Aabb::new(Vec2::new(1.1, 0.5), Vec2::new(2.0, 1.5));
+    assert!(a.overlaps(&b) && b.overlaps(&a));
+    assert!(!a.overlaps(&c) && !c.overlaps(&a));
+  }
+
+  #[test]
+  fn update_broadphase_reports_only_overlapping_pairs() {
+    let mut world = World::default();
+    let entities = world.insert((), vec![
+      // Overlapping pair.
+      (Collider::new(1.0, 1.0), WorldTransform::new(0.0, 0.0, 0.0)),
+      (Collider::new(1.0, 1.0), WorldTransform::new(1.5, 0.5, 0.0)),
+      // Overlaps the pair along the x-axis, but not along the y-axis.
+      (Collider::new(1.0, 1.0), WorldTransform::new(0.5, 5.0, 0.0)),
+      // Far away along the x-axis.
+      (Collider::new(1.0, 1.0), WorldTransform::new(10.0, 0.0, 0.0)),
+    ]).to_vec();
+    let mut broadphase_pairs = BroadphasePairs::default();
+    update_broadphase(&world, &mut broadphase_pairs);
+    assert_eq!(broadphase_pairs.len(), 1);
+    let (a, b) = broadphase_pairs.pairs[0];
+    assert!((a, b) == (entities[0], entities[1]) || (a, b) == (entities[1], entities[0]));
+  }
+
+  #[test]
+  fn update_broadphase_replaces_previous_pairs() {
+    let mut world = World::default();
+    let entities = world.insert((), vec![
+      (Collider::new(1.0, 1.0), WorldTransform::new(0.0, 0.0, 0.0)),
+      (Collider::new(1.0, 1.0), WorldTransform::new(1.0, 0.0, 0.0)),
+    ]).to_vec();
+    let mut broadphase_pairs = BroadphasePairs::default();
+    update_broadphase(&world, &mut broadphase_pairs);
+    assert_eq!(broadphase_pairs.len(), 1);
+    world.delete(entities[1]);
+    update_broadphase(&world, &mut broadphase_pairs);
+    assert!(broadphase_pairs.is_empty());
+  }
+}
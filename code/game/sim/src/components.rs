@@ -1,5 +1,6 @@
 use legion::entity::Entity;
-use ultraviolet::{Isometry2, Rotor2, Vec2};
+
+use math::prelude::{Isometry2, Rotor2, rotor2_angle, Vec2};
 
 // World-space components.
 
@@ -19,13 +20,47 @@ impl WorldTransform {
 #[derive(Default, Copy, Clone, Debug)]
 /// Component indicating the dynamics of an entity in world-space.
 pub struct WorldDynamics {
+  /// Translation per second.
   pub linear_velocity: Vec2,
-  pub angular_velocity: Rotor2,
+  /// Counter-clockwise rotation rate in radians per second.
+  pub angular_velocity: f32,
 }
 
 impl WorldDynamics {
   #[inline]
-  pub fn new(x: f32, y: f32, angle: f32) -> Self { Self { linear_velocity: Vec2::new(x, y), angular_velocity: Rotor2::from_angle(angle) } }
+  pub fn new(x: f32, y: f32, angular_velocity: f32) -> Self { Self { linear_velocity: Vec2::new(x, y), angular_velocity } }
+}
+
+#[repr(C)]
+#[derive(Default, Copy, Clone, Debug)]
+/// Component indicating a constant acceleration of an entity in world-space, integrated into its [WorldDynamics] each
+/// tick before velocities are integrated into its [WorldTransform].
+pub struct Acceleration {
+  /// Change in linear velocity per second.
+  pub linear: Vec2,
+  /// Change in angular velocity in radians per second, per second.
+  pub angular: f32,
+}
+
+impl Acceleration {
+  #[inline]
+  pub fn new(x: f32, y: f32, angular: f32) -> Self { Self { linear: Vec2::new(x, y), angular } }
+}
+
+#[repr(C)]
+#[derive(Default, Copy, Clone, Debug)]
+/// Component indicating that the [WorldDynamics] of an entity decay over time. Each tick, velocities are multiplied by
+/// `1 - damping * dt`, clamped to zero. Zero damping (the default) does not decay velocities.
+pub struct Damping {
+  /// Linear damping per second.
+  pub linear: f32,
+  /// Angular damping per second.
+  pub angular: f32,
+}
+
+impl Damping {
+  #[inline]
+  pub fn new(linear: f32, angular: f32) -> Self { Self { linear, angular } }
 }
 
 #[repr(C)]
@@ -68,7 +103,91 @@ pub enum GridOrientation {
   Left,
 }
 
+impl GridOrientation {
+  /// Gets the counter-clockwise angle in radians of this orientation relative to [GridOrientation::Up], in [-π, π],
+  /// where each subsequent orientation is a clockwise quarter turn from the previous one.
+  #[inline]
+  pub fn to_angle(self) -> f32 {
+    use std::f32::consts::{FRAC_PI_2, PI};
+    match self {
+      GridOrientation::Up => 0.0,
+      GridOrientation::Right => -FRAC_PI_2,
+      GridOrientation::Down => PI,
+      GridOrientation::Left => FRAC_PI_2,
+    }
+  }
+
+  /// Gets the rotation of this orientation relative to [GridOrientation::Up].
+  #[inline]
+  pub fn to_rotor2(self) -> Rotor2 { Rotor2::from_angle(self.to_angle()) }
+
+  /// Gets the orientation nearest to counter-clockwise angle `angle` in radians relative to [GridOrientation::Up].
+  pub fn from_angle_snapped(angle: f32) -> Self {
+    use std::f32::consts::FRAC_PI_2;
+    let clockwise_quarter_turns = (-angle / FRAC_PI_2).round() as i32;
+    match clockwise_quarter_turns.rem_euclid(4) {
+      0 => GridOrientation::Up,
+      1 => GridOrientation::Right,
+      2 => GridOrientation::Down,
+      _ => GridOrientation::Left,
+    }
+  }
+
+  /// Gets the orientation nearest to the counter-clockwise rotation `rotation` relative to [GridOrientation::Up].
+  #[inline]
+  pub fn from_rotation(rotation: Rotor2) -> Self { Self::from_angle_snapped(rotor2_angle(rotation)) }
+}
+
 impl Default for GridOrientation {
   #[inline]
   fn default() -> Self { GridOrientation::Up }
 }
+
+#[repr(C)]
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+/// Component indicating whether entities can move through a tile in grid-space. Tiles without this component are
+/// considered impassable by pathfinding.
+pub struct GridTilePassable(pub bool);
+
+impl Default for GridTilePassable {
+  #[inline]
+  fn default() -> Self { GridTilePassable(true) }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::f32::consts::{FRAC_PI_2, PI};
+
+  use super::GridOrientation::{self, *};
+
+  const ORIENTATIONS: [GridOrientation; 4] = [Up, Right, Down, Left];
+
+  #[test]
+  fn grid_orientation_round_trips_through_angle() {
+    for &orientation in &ORIENTATIONS {
+      assert_eq!(GridOrientation::from_angle_snapped(orientation.to_angle()), orientation);
+    }
+  }
+
+  #[test]
+  fn grid_orientation_round_trips_through_rotor() {
+    for &orientation in &ORIENTATIONS {
+      assert_eq!(GridOrientation::from_rotation(orientation.to_rotor2()), orientation);
+    }
+  }
+
+  #[test]
+  fn grid_orientation_snaps_to_nearest() {
+    assert_eq!(GridOrientation::from_angle_snapped(0.3), Up);
+    assert_eq!(GridOrientation::from_angle_snapped(-FRAC_PI_2 + 0.3), Right);
+    assert_eq!(GridOrientation::from_angle_snapped(-PI + 0.1), Down);
+    assert_eq!(GridOrientation::from_angle_snapped(FRAC_PI_2 - 0.3), Left);
+  }
+
+  #[test]
+  fn grid_orientation_snaps_angles_outside_range() {
+    assert_eq!(GridOrientation::from_angle_snapped(2.0 * PI + 0.1), Up);
+    assert_eq!(GridOrientation::from_angle_snapped(-3.0 * FRAC_PI_2), Left);
+    assert_eq!(GridOrientation::from_angle_snapped(3.0 * FRAC_PI_2), Right);
+  }
+}
@@ -0,0 +1,19 @@
+use legion::prelude::*;
+
+use math::prelude::Vec2;
+
+use crate::components::WorldDynamics;
+
+// Impulses
+
+/// Applies an impulse to `entity` by adding `linear` to its linear velocity and `angular` (in radians per second) to its
+/// angular velocity. Returns `false` without applying the impulse if `entity` has no [WorldDynamics].
+pub fn apply_impulse(world: &mut World, entity: Entity, linear: Vec2, angular: f32) -> bool {
+  if let Some(mut dynamics) = world.get_component_mut::<WorldDynamics>(entity) {
+    dynamics.linear_velocity += linear;
+    dynamics.angular_velocity += angular;
+    true
+  } else {
+    false
+  }
+}
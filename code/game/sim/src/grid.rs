@@ -0,0 +1,285 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+
+use legion::prelude::*;
+use legion::storage::Component;
+
+use math::prelude::Vec2;
+
+use crate::components::{GridOrientation, GridPosition, GridTilePassable, InGrid};
+
+// Pivot
+
+/// Point of a tile that its [GridPosition] maps to in grid-space. Grid-space is the space of a grid's
+/// [WorldTransform](crate::components::WorldTransform), with tiles of size 1x1. The grid renderer draws tiles, and
+/// [Sim::update_in_grid_world_transforms](crate::legion_sim::Sim::update_in_grid_world_transforms) places tile
+/// entities, according to the pivot, so both must be configured with the same pivot.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub enum GridPivot {
+  /// Grid position `(x, y)` is the center of the tile, which covers `[x - 0.5, x + 0.5]` on each axis.
+  Center,
+  /// Grid position `(x, y)` is the bottom-left corner of the tile, which covers `[x, x + 1]` on each axis.
+  BottomLeft,
+}
+
+impl Default for GridPivot {
+  fn default() -> Self { GridPivot::Center }
+}
+
+impl GridPivot {
+  /// Gets the offset of the bottom-left corner of a tile from its grid position, on each axis.
+  #[inline]
+  pub fn tile_min_offset(self) -> f32 {
+    match self {
+      GridPivot::Center => -0.5,
+      GridPivot::BottomLeft => 0.0,
+    }
+  }
+
+  /// Gets the bottom-left corner of the tile at `position` in grid-space.
+  #[inline]
+  pub fn tile_min(self, position: GridPosition) -> Vec2 {
+    let offset = self.tile_min_offset();
+    Vec2::new(position.x as f32 + offset, position.y as f32 + offset)
+  }
+
+  /// Gets the center of the tile at `position` in grid-space, around which oriented tiles are rotated.
+  #[inline]
+  pub fn tile_center(self, position: GridPosition) -> Vec2 {
+    self.tile_min(position) + Vec2::new(0.5, 0.5)
+  }
+
+  /// Gets the position of the tile that covers `point` in grid-space, for example for picking tiles.
+  #[inline]
+  pub fn position_at(self, point: Vec2) -> GridPosition {
+    let offset = self.tile_min_offset();
+    GridPosition::new((point.x - offset).floor() as i32, (point.y - offset).floor() as i32)
+  }
+}
+
+// Spawning
+
+/// Spawns tiles into the grid of `in_grid`, with `T` as the additional per-tile component (e.g. how the tile is
+/// rendered). All tiles are inserted with a single batched insert sharing the `in_grid` tag, which is much cheaper than
+/// inserting entities one-by-one through a command buffer. Returns the spawned entities.
+pub fn spawn_grid_tiles<T: Component>(
+  world: &mut World,
+  in_grid: InGrid,
+  tiles: impl IntoIterator<Item=(GridPosition, GridOrientation, T)>,
+) -> &[Entity] {
+  let tiles: Vec<_> = tiles.into_iter().collect();
+  world.insert((in_grid, ), tiles)
+}
+
+// Querying
+
+/// Returns the number of tiles in the grid of `in_grid`.
+pub fn grid_tile_count(world: &World, in_grid: InGrid) -> usize {
+  let query = Read::<GridPosition>::query().filter(tag_value::<InGrid>(&in_grid));
+  query.iter_entities(world).count()
+}
+
+/// Returns the entity, position, and `T` component of all tiles in the grid of `in_grid` that have a `T` component.
+pub fn grid_tiles<T: Component + Copy>(world: &World, in_grid: InGrid) -> impl Iterator<Item=(Entity, GridPosition, T)> {
+  let query = <(Read<GridPosition>, Read<T>)>::query().filter(tag_value::<InGrid>(&in_grid));
+  // Collect, as the query and filter are local and cannot be borrowed by the returned iterator.
+  let tiles: Vec<_> = query.iter_entities(world)
+    .map(|(entity, (position, component))| (entity, *position, *component))
+    .collect();
+  tiles.into_iter()
+}
+
+// Pathfinding
+
+/// Finds a shortest 4-connected path from `start` to `goal` through passable tiles in the grid of `in_grid` with A*,
+/// returning the positions along the path including `start` and `goal`, or `None` if there is no path. Positions
+/// without a tile, and tiles without a [GridTilePassable] component or marked impassable, are blocked. Memory use is
+/// bounded by the number of tiles in the grid, not by the extent of the grid.
+pub fn find_path(world: &World, in_grid: InGrid, start: GridPosition, goal: GridPosition) -> Option<Vec<GridPosition>> {
+  let passable: HashSet<GridPosition> = grid_tiles::<GridTilePassable>(world, in_grid)
+    .filter_map(|(_, position, GridTilePassable(passable))| if passable { Some(position) } else { None })
+    .collect();
+  if !passable.contains(&start) || !passable.contains(&goal) {
+    return None;
+  }
+
+  let heuristic = |p: GridPosition| ((p.x - goal.x).abs() + (p.y - goal.y).abs()) as u32;
+  // Maps visited positions to their cost from `start` and the position they were reached from.
+  let mut visited: HashMap<GridPosition, (u32, GridPosition)> = HashMap::with_capacity(passable.len());
+  // Min-heap of positions ordered by estimated total cost, ties broken by position for determinism.
+  let mut open = BinaryHeap::new();
+  visited.insert(start, (0, start));
+  open.push(Reverse((heuristic(start), start)));
+  while let Some(Reverse((estimate, position))) = open.pop() {
+    let cost = visited[&position].0;
+    if position == goal {
+      let mut path = vec![goal];
+      let mut current = goal;
+      while current != start {
+        current = visited[&current].1;
+        path.push(current);
+      }
+      path.reverse();
+      return Some(path);
+    }
+    if estimate > cost + heuristic(position) {
+      continue; // Stale entry, position was reached more cheaply after this entry was pushed.
+    }
+    let GridPosition { x, y } = position;
+    for neighbour in [GridPosition::new(x, y + 1), GridPosition::new(x + 1, y), GridPosition::new(x, y - 1), GridPosition::new(x - 1, y)].iter().copied() {
+      if !passable.contains(&neighbour) {
+        continue;
+      }
+      let neighbour_cost = cost + 1;
+      if visited.get(&neighbour).map_or(true, |(c, _)| neighbour_cost < *c) {
+        visited.insert(neighbour, (neighbour_cost, position));
+        open.push(Reverse((neighbour_cost + heuristic(neighbour), neighbour)));
+      }
+    }
+  }
+  None
+}
+
+#[cfg(test)]
+mod tests {
+  use legion::prelude::*;
+
+  use math::prelude::Vec2;
+
+  use crate::components::{Grid, GridOrientation, GridPosition, GridTilePassable, InGrid, WorldTransform};
+
+  use super::{find_path, grid_tile_count, grid_tiles, GridPivot, spawn_grid_tiles};
+
+  fn assert_vec2_eq(actual: Vec2, expected: Vec2) {
+    assert!(actual.x == expected.x && actual.y == expected.y, "Expected {:?}, got {:?}", expected, actual);
+  }
+
+  #[test]
+  fn grid_pivot_tile_min() {
+    let position = GridPosition::new(2, -3);
+    assert_vec2_eq(GridPivot::Center.tile_min(position), Vec2::new(1.5, -3.5));
+    assert_vec2_eq(GridPivot::BottomLeft.tile_min(position), Vec2::new(2.0, -3.0));
+    assert_vec2_eq(GridPivot::Center.tile_center(position), Vec2::new(2.0, -3.0));
+    assert_vec2_eq(GridPivot::BottomLeft.tile_center(position), Vec2::new(2.5, -2.5));
+  }
+
+  #[test]
+  fn grid_pivot_position_at() {
+    assert_eq!(GridPivot::Center.position_at(Vec2::new(2.4, -3.4)), GridPosition::new(2, -3));
+    assert_eq!(GridPivot::Center.position_at(Vec2::new(2.6, -3.6)), GridPosition::new(3, -4));
+    assert_eq!(GridPivot::BottomLeft.position_at(Vec2::new(2.9, -2.1)), GridPosition::new(2, -3));
+    assert_eq!(GridPivot::BottomLeft.position_at(Vec2::new(-0.1, 0.0)), GridPosition::new(-1, 0));
+  }
+
+  #[test]
+  fn grid_pivot_position_at_inverts_tile_center() {
+    let position = GridPosition::new(-5, 7);
+    for &pivot in &[GridPivot::Center, GridPivot::BottomLeft] {
+      assert_eq!(pivot.position_at(pivot.tile_center(position)), position);
+    }
+  }
+
+  fn tiles(count: i32) -> impl Iterator<Item=(GridPosition, GridOrientation, GridTilePassable)> {
+    (0..count).map(|i| (GridPosition::new(i % 32, i / 32), GridOrientation::default(), GridTilePassable(true)))
+  }
+
+  #[test]
+  fn spawn_grid_tiles_creates_no_more_archetypes_than_per_entity_inserts() {
+    let mut batched = World::default();
+    let grid = batched.insert((Grid, ), vec![(WorldTransform::default(), )])[0];
+    assert_eq!(spawn_grid_tiles(&mut batched, InGrid::new(grid), tiles(1000)).len(), 1000);
+
+    let mut per_entity = World::default();
+    let grid = per_entity.insert((Grid, ), vec![(WorldTransform::default(), )])[0];
+    let mut command_buffer = legion::command::CommandBuffer::new(&per_entity);
+    for tile in tiles(1000) {
+      command_buffer.insert((InGrid::new(grid), ), vec![tile]);
+    }
+    command_buffer.write(&mut per_entity);
+    assert_eq!(grid_tile_count(&per_entity, InGrid::new(grid)), 1000);
+
+    // One archetype for the grid, and one for all its tiles.
+    assert_eq!(batched.storage().archetypes().len(), 2);
+    assert_eq!(batched.storage().archetypes().len(), per_entity.storage().archetypes().len());
+  }
+
+  #[test]
+  fn grid_tile_count_and_grid_tiles_are_per_grid() {
+    let mut world = World::default();
+    let grids = world.insert((Grid, ), vec![(WorldTransform::default(), ), (WorldTransform::default(), )]).to_vec();
+    let (in_grid, other_in_grid) = (InGrid::new(grids[0]), InGrid::new(grids[1]));
+    let spawned = spawn_grid_tiles(&mut world, in_grid, tiles(40)).to_vec();
+    spawn_grid_tiles(&mut world, other_in_grid, tiles(3));
+    assert_eq!(grid_tile_count(&world, in_grid), 40);
+    assert_eq!(grid_tile_count(&world, other_in_grid), 3);
+
+    let mut enumerated: Vec<_> = grid_tiles::<GridTilePassable>(&world, in_grid).map(|(entity, position, _)| (entity, position)).collect();
+    enumerated.sort_by_key(|(_, position)| (position.y, position.x));
+    let expected: Vec<_> = spawned.iter().copied().zip(tiles(40).map(|(position, _, _)| position)).collect();
+    assert_eq!(enumerated, expected);
+    let mut other_positions: Vec<_> = grid_tiles::<GridTilePassable>(&world, other_in_grid).map(|(_, position, _)| position).collect();
+    other_positions.sort_by_key(|position| position.x);
+    assert_eq!(other_positions, vec![GridPosition::new(0, 0), GridPosition::new(1, 0), GridPosition::new(2, 0)]);
+  }
+
+  /// Creates a grid from `rows`, where row `y` of the grid is `rows[y]`, and `.` is a passable tile, `#` an impassable
+  /// tile, and any other character no tile.
+  fn create_maze(rows: &[&str]) -> (World, InGrid) {
+    let mut world = World::default();
+    let grid = world.insert((Grid, ), vec![(WorldTransform::default(), )])[0];
+    let in_grid = InGrid::new(grid);
+    let tiles: Vec<_> = rows.iter().enumerate().flat_map(|(y, row)| {
+      row.chars().enumerate().filter_map(move |(x, c)| {
+        let passable = match c {
+          '.' => true,
+          '#' => false,
+          _ => return None,
+        };
+        Some((GridPosition::new(x as i32, y as i32), GridOrientation::default(), GridTilePassable(passable)))
+      })
+    }).collect();
+    spawn_grid_tiles(&mut world, in_grid, tiles);
+    (world, in_grid)
+  }
+
+  #[test]
+  fn find_path_finds_shortest_path_through_maze() {
+    let (world, in_grid) = create_maze(&[
+      "....#",
+      "###.#",
+      "....#",
+      ".####",
+      ".....",
+    ]);
+    let path = find_path(&world, in_grid, GridPosition::new(0, 0), GridPosition::new(4, 4)).unwrap();
+    let expected: Vec<_> = [(0, 0), (1, 0), (2, 0), (3, 0), (3, 1), (3, 2), (2, 2), (1, 2), (0, 2), (0, 3), (0, 4), (1, 4), (2, 4), (3, 4), (4, 4)]
+      .iter().map(|&(x, y)| GridPosition::new(x, y)).collect();
+    assert_eq!(path, expected);
+  }
+
+  #[test]
+  fn find_path_does_not_pass_through_impassable_or_missing_tiles() {
+    let (world, in_grid) = create_maze(&[
+      ".#. .",
+    ]);
+    assert_eq!(find_path(&world, in_grid, GridPosition::new(0, 0), GridPosition::new(2, 0)), None);
+    assert_eq!(find_path(&world, in_grid, GridPosition::new(2, 0), GridPosition::new(4, 0)), None);
+  }
+
+  #[test]
+  fn find_path_rejects_blocked_endpoints() {
+    let (world, in_grid) = create_maze(&[
+      "..#",
+    ]);
+    assert_eq!(find_path(&world, in_grid, GridPosition::new(0, 0), GridPosition::new(2, 0)), None);
+    assert_eq!(find_path(&world, in_grid, GridPosition::new(0, 0), GridPosition::new(5, 0)), None);
+  }
+
+  #[test]
+  fn find_path_to_start_is_start() {
+    let (world, in_grid) = create_maze(&[
+      "..",
+    ]);
+    assert_eq!(find_path(&world, in_grid, GridPosition::new(1, 0), GridPosition::new(1, 0)), Some(vec![GridPosition::new(1, 0)]));
+  }
+}
@@ -1,26 +1,298 @@
+use std::collections::HashMap;
+use std::ops::{Deref, DerefMut};
 use std::time::Duration;
 
 use legion::borrow::{Ref, RefMut};
 use legion::prelude::*;
 
-use crate::components::{WorldDynamics, WorldTransform};
+use math::prelude::Rotor2;
+
+use crate::collision::{BroadphasePairs, update_broadphase};
+use crate::components::{Acceleration, Damping, Grid, GridOrientation, GridPosition, InGrid, WorldDynamics, WorldTransform};
+use crate::grid::GridPivot;
+use crate::rng::SimRng;
 
 pub struct Sim {
   pub world: World,
+  /// Simulation resources, which always contain a [SimRng] and [BroadphasePairs].
+  pub resources: Resources,
+  time_scale: f32,
+  grid_pivot: GridPivot,
 }
 
 impl Sim {
   pub fn new() -> Self {
     let world = World::default();
-    Self { world }
+    let mut resources = Resources::default();
+    resources.insert(SimRng::default());
+    resources.insert(BroadphasePairs::default());
+    Self { world, resources, time_scale: 1.0, grid_pivot: GridPivot::default() }
+  }
+
+  /// Gets the deterministic random number generator, which must be used for all gameplay randomness.
+  pub fn rng(&self) -> impl DerefMut<Target=SimRng> + '_ {
+    self.resources.get_mut::<SimRng>().unwrap()
+  }
+
+  /// Reseeds the deterministic random number generator with `seed`.
+  pub fn reseed(&mut self, seed: u64) {
+    *self.rng() = SimRng::new(seed);
   }
 
-  pub fn simulate_tick(&mut self, _time_step: Duration) {
+  #[inline]
+  pub fn grid_pivot(&self) -> GridPivot { self.grid_pivot }
+
+  /// Sets the point of a tile that its [GridPosition] maps to, which must match the pivot of the grid renderer.
+  #[inline]
+  pub fn set_grid_pivot(&mut self, grid_pivot: GridPivot) { self.grid_pivot = grid_pivot; }
+
+  /// Gets the candidate collision pairs found by the broadphase in the last tick.
+  pub fn broadphase_pairs(&self) -> impl Deref<Target=BroadphasePairs> + '_ {
+    self.resources.get::<BroadphasePairs>().unwrap()
+  }
+
+  #[inline]
+  pub fn time_scale(&self) -> f32 { self.time_scale }
+
+  /// Sets the factor that the time step of each tick is multiplied with, for slowing down (below 1) or speeding up
+  /// (above 1) dynamics without changing the tick rate. Zero freezes dynamics. Negative values are clamped to zero.
+  pub fn set_time_scale(&mut self, time_scale: f32) {
+    self.time_scale = time_scale.max(0.0);
+  }
+
+  /// Simulates a single tick of `time_step`, scaled by the [time scale](Self::time_scale).
+  pub fn simulate_tick(&mut self, time_step: Duration) {
+    let dt = time_step.as_secs_f32() * self.time_scale;
+    let acceleration_query = <(Read<Acceleration>, Write<WorldDynamics>)>::query();
+    for i in acceleration_query.iter_mut(&mut self.world) {
+      let (acceleration, mut dynamics): (Ref<Acceleration>, RefMut<WorldDynamics>) = i;
+      dynamics.linear_velocity += acceleration.linear * dt;
+      dynamics.angular_velocity += acceleration.angular * dt;
+    }
+    let damping_query = <(Read<Damping>, Write<WorldDynamics>)>::query();
+    for i in damping_query.iter_mut(&mut self.world) {
+      let (damping, mut dynamics): (Ref<Damping>, RefMut<WorldDynamics>) = i;
+      dynamics.linear_velocity *= (1.0 - damping.linear * dt).max(0.0);
+      dynamics.angular_velocity *= (1.0 - damping.angular * dt).max(0.0);
+    }
     let dynamics_query = <(Read<WorldDynamics>, Write<WorldTransform>)>::query();
     for i in dynamics_query.iter_mut(&mut self.world) {
       let (dynamics, mut transform): (Ref<WorldDynamics>, RefMut<WorldTransform>) = i;
-      transform.isometry.append_translation(dynamics.linear_velocity);
-      transform.isometry.prepend_rotation(dynamics.angular_velocity);
+      transform.isometry.append_translation(dynamics.linear_velocity * dt);
+      transform.isometry.prepend_rotation(Rotor2::from_angle(dynamics.angular_velocity * dt));
+    }
+    self.update_in_grid_world_transforms();
+    update_broadphase(&self.world, &mut self.resources.get_mut::<BroadphasePairs>().unwrap());
+  }
+
+  /// Derives the [WorldTransform] of entities inside a grid that have a [WorldTransform], from the transform of their
+  /// grid and their [GridPosition] and [GridOrientation], matching where the grid renderer draws tiles at those
+  /// positions. Tile entities are placed at the center of their tile, as determined by the
+  /// [grid pivot](Self::grid_pivot). Called every tick after dynamics, and may be called after moving grids to update
+  /// their children immediately. Entities whose grid has no [WorldTransform] are left untouched.
+  pub fn update_in_grid_world_transforms(&mut self) {
+    let grid_query = Read::<WorldTransform>::query().filter(tag::<Grid>());
+    let grid_transforms: HashMap<Entity, WorldTransform> = grid_query.iter_entities(&self.world)
+      .map(|(entity, transform)| (entity, *transform))
+      .collect();
+    let in_grid_query = <(Tagged<InGrid>, Read<GridPosition>, Read<GridOrientation>, Write<WorldTransform>)>::query();
+    let grid_pivot = self.grid_pivot;
+    for i in in_grid_query.iter_mut(&mut self.world) {
+      let (in_grid, position, orientation, mut transform): (&InGrid, Ref<GridPosition>, Ref<GridOrientation>, RefMut<WorldTransform>) = i;
+      if let Some(grid_transform) = grid_transforms.get(&in_grid.grid) {
+        let mut isometry = grid_transform.isometry;
+        isometry.prepend_translation(grid_pivot.tile_center(*position));
+        isometry.prepend_rotation(orientation.to_rotor2());
+        transform.isometry = isometry;
+      }
     }
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use std::f32::consts::FRAC_PI_2;
+  use std::time::Duration;
+
+  use legion::prelude::*;
+  use rand::RngCore;
+
+  use math::prelude::{rotor2_angle, Vec2};
+
+  use crate::components::{Acceleration, Damping, Grid, GridOrientation, GridPosition, InGrid, WorldDynamics, WorldTransform};
+  use crate::dynamics::apply_impulse;
+  use crate::grid::GridPivot;
+
+  use super::Sim;
+
+  const EPSILON: f32 = 1e-5;
+
+  fn transform(sim: &Sim, entity: Entity) -> WorldTransform {
+    *sim.world.get_component::<WorldTransform>(entity).unwrap()
+  }
+
+  fn dynamics(sim: &Sim, entity: Entity) -> WorldDynamics {
+    *sim.world.get_component::<WorldDynamics>(entity).unwrap()
+  }
+
+  fn angle(sim: &Sim, entity: Entity) -> f32 {
+    rotor2_angle(transform(sim, entity).isometry.rotation)
+  }
+
+  #[test]
+  fn angular_velocity_is_integrated_per_second() {
+    let mut sim = Sim::new();
+    let entity = sim.world.insert((), vec![(WorldTransform::default(), WorldDynamics::new(0.0, 0.0, 1.0))])[0];
+    sim.simulate_tick(Duration::from_millis(250));
+    assert!((angle(&sim, entity) - 0.25).abs() < EPSILON);
+  }
+
+  #[test]
+  fn doubling_time_step_doubles_rotation() {
+    let rotation_after_tick = |time_step: Duration| {
+      let mut sim = Sim::new();
+      let entity = sim.world.insert((), vec![(WorldTransform::default(), WorldDynamics::new(0.0, 0.0, 0.5))])[0];
+      sim.simulate_tick(time_step);
+      angle(&sim, entity)
+    };
+    let single = rotation_after_tick(Duration::from_millis(200));
+    let double = rotation_after_tick(Duration::from_millis(400));
+    assert!((double - 2.0 * single).abs() < EPSILON, "Expected {}, got {}", 2.0 * single, double);
+  }
+
+  #[test]
+  fn damping_decays_velocities() {
+    let mut sim = Sim::new();
+    let entity = sim.world.insert((), vec![
+      (WorldTransform::default(), WorldDynamics::new(4.0, 0.0, 2.0), Damping::new(0.5, 0.25)),
+    ])[0];
+    sim.simulate_tick(Duration::from_secs(1));
+    let WorldDynamics { linear_velocity, angular_velocity } = dynamics(&sim, entity);
+    assert!((linear_velocity.x - 2.0).abs() < EPSILON);
+    assert!((angular_velocity - 1.5).abs() < EPSILON);
+    sim.simulate_tick(Duration::from_secs(1));
+    assert!((dynamics(&sim, entity).linear_velocity.x - 1.0).abs() < EPSILON);
+  }
+
+  #[test]
+  fn damping_does_not_reverse_velocities() {
+    let mut sim = Sim::new();
+    let entity = sim.world.insert((), vec![
+      (WorldTransform::default(), WorldDynamics::new(4.0, -4.0, 2.0), Damping::new(3.0, 3.0)),
+    ])[0];
+    sim.simulate_tick(Duration::from_secs(1));
+    let WorldDynamics { linear_velocity, angular_velocity } = dynamics(&sim, entity);
+    assert_eq!((linear_velocity.x, linear_velocity.y, angular_velocity), (0.0, 0.0, 0.0));
+  }
+
+  #[test]
+  fn acceleration_is_integrated_before_moving() {
+    let mut sim = Sim::new();
+    let entity = sim.world.insert((), vec![
+      (WorldTransform::default(), WorldDynamics::default(), Acceleration::new(2.0, 0.0, 1.0)),
+    ])[0];
+    sim.simulate_tick(Duration::from_millis(500));
+    let WorldDynamics { linear_velocity, angular_velocity } = dynamics(&sim, entity);
+    assert!((linear_velocity.x - 1.0).abs() < EPSILON);
+    assert!((angular_velocity - 0.5).abs() < EPSILON);
+    assert!((transform(&sim, entity).isometry.translation.x - 0.5).abs() < EPSILON);
+  }
+
+  #[test]
+  fn impulse_changes_velocity_once() {
+    let mut sim = Sim::new();
+    let entity = sim.world.insert((), vec![(WorldTransform::default(), WorldDynamics::default())])[0];
+    assert!(apply_impulse(&mut sim.world, entity, Vec2::new(0.0, 2.0), 1.0));
+    sim.simulate_tick(Duration::from_secs(1));
+    sim.simulate_tick(Duration::from_secs(1));
+    let WorldDynamics { linear_velocity, angular_velocity } = dynamics(&sim, entity);
+    assert!((linear_velocity.y - 2.0).abs() < EPSILON);
+    assert!((angular_velocity - 1.0).abs() < EPSILON);
+    assert!((transform(&sim, entity).isometry.translation.y - 4.0).abs() < EPSILON);
+  }
+
+  #[test]
+  fn impulse_requires_dynamics() {
+    let mut sim = Sim::new();
+    let entity = sim.world.insert((), vec![(WorldTransform::default(), )])[0];
+    assert!(!apply_impulse(&mut sim.world, entity, Vec2::new(1.0, 0.0), 0.0));
+  }
+
+  #[test]
+  fn linear_velocity_is_integrated_per_second() {
+    let mut sim = Sim::new();
+    let entity = sim.world.insert((), vec![(WorldTransform::default(), WorldDynamics::new(2.0, -1.0, 0.0))])[0];
+    sim.simulate_tick(Duration::from_millis(250));
+    let translation = transform(&sim, entity).isometry.translation;
+    assert!((translation.x - 0.5).abs() < EPSILON && (translation.y + 0.25).abs() < EPSILON);
+  }
+
+  fn assert_transform_eq(actual: WorldTransform, x: f32, y: f32, angle: f32) {
+    let translation = actual.isometry.translation;
+    let actual_angle = rotor2_angle(actual.isometry.rotation);
+    assert!((translation.x - x).abs() < EPSILON && (translation.y - y).abs() < EPSILON && (actual_angle - angle).abs() < EPSILON,
+      "Expected ({}, {}) at angle {}, got {:?} at angle {}", x, y, angle, translation, actual_angle);
+  }
+
+  #[test]
+  fn in_grid_world_transforms_follow_moved_grid() {
+    let mut sim = Sim::new();
+    let grid = sim.world.insert((Grid, ), vec![(WorldTransform::default(), )])[0];
+    let child = sim.world.insert((InGrid::new(grid), ), vec![
+      (GridPosition::new(3, -2), GridOrientation::Right, WorldTransform::default()),
+    ])[0];
+    sim.update_in_grid_world_transforms();
+    assert_transform_eq(transform(&sim, child), 3.0, -2.0, -FRAC_PI_2);
+
+    // Move the grid to (5, 1) and rotate it a counter-clockwise quarter turn, which rotates the tile center (3, -2) to
+    // (2, 3), and cancels out the clockwise quarter turn of the child's orientation.
+    *sim.world.get_component_mut::<WorldTransform>(grid).unwrap() = WorldTransform::new(5.0, 1.0, FRAC_PI_2);
+    sim.update_in_grid_world_transforms();
+    assert_transform_eq(transform(&sim, child), 7.0, 4.0, 0.0);
+
+    // With the bottom-left pivot, the tile center is (3.5, -1.5), rotated to (1.5, 3.5).
+    sim.set_grid_pivot(GridPivot::BottomLeft);
+    sim.update_in_grid_world_transforms();
+    assert_transform_eq(transform(&sim, child), 6.5, 4.5, 0.0);
+  }
+
+  fn draw(sim: &Sim) -> Vec<u64> {
+    let mut rng = sim.rng();
+    (0..8).map(|_| rng.next_u64()).collect()
+  }
+
+  #[test]
+  fn reseeding_with_same_seed_reproduces_rng_sequence() {
+    let mut sim = Sim::new();
+    sim.reseed(42);
+    let first = draw(&sim);
+    sim.reseed(42);
+    assert_eq!(draw(&sim), first);
+    sim.reseed(43);
+    assert_ne!(draw(&sim), first);
+  }
+
+  #[test]
+  fn time_scale_scales_time_step() {
+    let mut sim = Sim::new();
+    sim.set_time_scale(0.5);
+    let entity = sim.world.insert((), vec![
+      (WorldTransform::default(), WorldDynamics::new(2.0, 0.0, 1.0), Acceleration::new(2.0, 0.0, 0.0)),
+    ])[0];
+    sim.simulate_tick(Duration::from_secs(1));
+    assert!((dynamics(&sim, entity).linear_velocity.x - 3.0).abs() < EPSILON);
+    assert!((transform(&sim, entity).isometry.translation.x - 1.5).abs() < EPSILON);
+    assert!((angle(&sim, entity) - 0.5).abs() < EPSILON);
+  }
+
+  #[test]
+  fn zero_time_scale_freezes_dynamics() {
+    let mut sim = Sim::new();
+    sim.set_time_scale(-1.0);
+    assert_eq!(sim.time_scale(), 0.0);
+    let entity = sim.world.insert((), vec![(WorldTransform::default(), WorldDynamics::new(2.0, 0.0, 1.0))])[0];
+    sim.simulate_tick(Duration::from_secs(1));
+    assert_eq!(transform(&sim, entity).isometry.translation.x, 0.0);
+    assert_eq!(angle(&sim, entity), 0.0);
+  }
+}
@@ -1,4 +1,8 @@
 pub mod legion_sim;
+pub mod collision;
 pub mod components;
+pub mod dynamics;
+pub mod grid;
 pub mod prelude;
+pub mod rng;
 
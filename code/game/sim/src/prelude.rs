@@ -1,5 +1,9 @@
 pub use legion::entity::Entity;
 
-pub use crate::components::{Grid, GridOrientation, GridPosition, InGrid, WorldDynamics, WorldTransform};
+pub use crate::collision::{Aabb, BroadphasePairs, Collider};
+pub use crate::components::{Acceleration, Damping, Grid, GridOrientation, GridPosition, GridTilePassable, InGrid, WorldDynamics, WorldTransform};
+pub use crate::dynamics::apply_impulse;
+pub use crate::grid::{find_path, grid_tile_count, grid_tiles, GridPivot, spawn_grid_tiles};
 pub use crate::legion_sim::Sim;
+pub use crate::rng::SimRng;
 
@@ -0,0 +1,33 @@
+use rand::{Error, RngCore, SeedableRng};
+use rand_pcg::Pcg64Mcg;
+
+/// Seed of the [SimRng] when the simulation is created.
+pub const DEFAULT_SEED: u64 = 0xcafef00dd15ea5e5;
+
+/// Deterministic random number generator resource for gameplay randomness. Reseeding with the same seed reproduces the
+/// same sequence of random numbers.
+pub struct SimRng(Pcg64Mcg);
+
+impl SimRng {
+  #[inline]
+  pub fn new(seed: u64) -> Self { Self(Pcg64Mcg::seed_from_u64(seed)) }
+}
+
+impl Default for SimRng {
+  #[inline]
+  fn default() -> Self { Self::new(DEFAULT_SEED) }
+}
+
+impl RngCore for SimRng {
+  #[inline]
+  fn next_u32(&mut self) -> u32 { self.0.next_u32() }
+
+  #[inline]
+  fn next_u64(&mut self) -> u64 { self.0.next_u64() }
+
+  #[inline]
+  fn fill_bytes(&mut self, dest: &mut [u8]) { self.0.fill_bytes(dest) }
+
+  #[inline]
+  fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> { self.0.try_fill_bytes(dest) }
+}